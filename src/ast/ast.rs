@@ -2,6 +2,8 @@ use std::fmt::{self, Display};
 use std::{any::Any, rc::Rc};
 
 use crate::lexer::token::Token;
+use crate::object::integer::Integer;
+use crate::parser::parser::{precedence_of, right_associative, Predecessor};
 
 pub trait Node {
     fn token_literal(&self) -> String;
@@ -55,6 +57,8 @@ impl Program {
 pub struct LetStatement {
     pub token: Token,
     pub name: Rc<Identifier>,
+    // `let x: int = 5`
+    pub annotation: Option<TypeExpr>,
     pub value: Option<Rc<dyn Expression>>,
 }
 
@@ -72,12 +76,416 @@ impl Statement for LetStatement {
     fn statement_node(&self) {}
 }
 
+// A type written in the source, after the name in a let or a parameter, or
+// after `->` in a function literal. Nothing but the type checker reads
+// them; see typeck::typeck for what the names mean.
+#[derive(Debug, Clone)]
+pub enum TypeExpr {
+    // int, float, bool, string, null, or a type variable like T
+    Named(Token),
+    // [int]; the token is the `[`
+    Array(Token, Box<TypeExpr>),
+    // fn(int, int) -> bool; the token is the `fn`
+    Function(Token, Vec<TypeExpr>, Box<TypeExpr>),
+}
+
+impl TypeExpr {
+    pub fn token(&self) -> &Token {
+        match self {
+            TypeExpr::Named(token)
+            | TypeExpr::Array(token, _)
+            | TypeExpr::Function(token, _, _) => token,
+        }
+    }
+}
+
 pub struct Identifier {
     pub token: Token,
-    pub value: String,
+    pub value: String,
+}
+
+impl Node for Identifier {
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl Expression for Identifier {
+    fn expression_node(&self) {}
+}
+
+pub struct ReturnStatements {
+    pub token: Token,
+    pub value: Option<Rc<dyn Expression>>,
+}
+
+impl Statement for ReturnStatements {
+    fn statement_node(&self) {}
+}
+
+impl Node for ReturnStatements {
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+pub struct ExpressionStatement {
+    pub token: Token,
+    pub expr: Option<Rc<dyn Expression>>,
+}
+
+impl Node for ExpressionStatement {
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl Statement for ExpressionStatement {
+    fn statement_node(&self) {}
+}
+pub struct BlockStatement {
+    pub token: Token,
+    pub statements: Vec<Rc<dyn Statement>>,
+}
+
+impl Node for BlockStatement {
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl Statement for BlockStatement {
+    fn statement_node(&self) {}
+}
+
+// throw <expression>;
+pub struct ThrowStatement {
+    pub token: Token,
+    pub value: Option<Rc<dyn Expression>>,
+}
+
+impl Node for ThrowStatement {
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl Statement for ThrowStatement {
+    fn statement_node(&self) {}
+}
+
+// try { } catch (e) { } finally { }
+// At least one of the catch or finally clauses is present.
+pub struct TryStatement {
+    pub token: Token,
+    pub block: Rc<BlockStatement>,
+    pub catch_param: Option<Rc<Identifier>>,
+    pub catch_block: Option<Rc<BlockStatement>>,
+    pub finally_block: Option<Rc<BlockStatement>>,
+}
+
+impl Node for TryStatement {
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl Statement for TryStatement {
+    fn statement_node(&self) {}
+}
+
+// while (<condition>) { }
+pub struct WhileStatement {
+    pub token: Token,
+    pub condition: Rc<dyn Expression>,
+    pub body: Rc<BlockStatement>,
+}
+
+impl Node for WhileStatement {
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl Statement for WhileStatement {
+    fn statement_node(&self) {}
+}
+
+// for (<variable> in <iterable>) { }
+pub struct ForStatement {
+    pub token: Token,
+    pub variable: Rc<Identifier>,
+    pub iterable: Rc<dyn Expression>,
+    pub body: Rc<BlockStatement>,
+}
+
+impl Node for ForStatement {
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl Statement for ForStatement {
+    fn statement_node(&self) {}
+}
+
+pub struct BreakStatement {
+    pub token: Token,
+}
+
+impl Node for BreakStatement {
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl Statement for BreakStatement {
+    fn statement_node(&self) {}
+}
+
+pub struct ContinueStatement {
+    pub token: Token,
+}
+
+impl Node for ContinueStatement {
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl Statement for ContinueStatement {
+    fn statement_node(&self) {}
+}
+
+// import "<path>" as <alias>;
+pub struct ImportStatement {
+    pub token: Token,
+    pub path: String,
+    pub alias: Rc<Identifier>,
+}
+
+impl Node for ImportStatement {
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl Statement for ImportStatement {
+    fn statement_node(&self) {}
+}
+
+// export let <name> = <value>;
+pub struct ExportStatement {
+    pub token: Token,
+    pub statement: Rc<LetStatement>,
+}
+
+impl Node for ExportStatement {
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl Statement for ExportStatement {
+    fn statement_node(&self) {}
+}
+
+pub struct IntegerLiteral {
+    pub token: Token,
+    pub value: Integer,
+}
+
+impl Node for IntegerLiteral {
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl Expression for IntegerLiteral {
+    fn expression_node(&self) {}
+}
+
+pub struct FloatLiteral {
+    pub token: Token,
+    pub value: f64,
+}
+
+impl Node for FloatLiteral {
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl Expression for FloatLiteral {
+    fn expression_node(&self) {}
+}
+
+pub struct StringLiteral {
+    pub token: Token,
+    pub value: String,
+}
+
+impl Node for StringLiteral {
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl Expression for StringLiteral {
+    fn expression_node(&self) {}
+}
+
+pub enum InterpolationPart {
+    Text(String),
+    Expr(Rc<dyn Expression>),
+}
+
+// "Hello ${name}!"
+pub struct InterpolatedString {
+    pub token: Token,
+    pub parts: Vec<InterpolationPart>,
+}
+
+impl Node for InterpolatedString {
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl Expression for InterpolatedString {
+    fn expression_node(&self) {}
+}
+
+pub struct Boolean {
+    pub token: Token,
+    pub value: bool,
+}
+
+impl Node for Boolean {
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl Expression for Boolean {
+    fn expression_node(&self) {}
+}
+
+pub struct PrefixExpression {
+    pub token: Token,
+    pub operator: String,
+    pub right: Rc<dyn Expression>,
+}
+
+impl Node for PrefixExpression {
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl Expression for PrefixExpression {
+    fn expression_node(&self) {}
+}
+
+pub struct InfixExpression {
+    pub token: Token,
+    pub left: Rc<dyn Expression>,
+    pub operator: String,
+    pub right: Rc<dyn Expression>,
+}
+
+impl Node for InfixExpression {
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl Expression for InfixExpression {
+    fn expression_node(&self) {}
+}
+
+pub struct ArrayLiteral {
+    pub token: Token,
+    pub elements: Vec<Rc<dyn Expression>>,
 }
 
-impl Node for Identifier {
+impl Node for ArrayLiteral {
     fn token_literal(&self) -> String {
         self.token.literal.clone()
     }
@@ -87,20 +495,41 @@ impl Node for Identifier {
     }
 }
 
-impl Expression for Identifier {
+impl Expression for ArrayLiteral {
     fn expression_node(&self) {}
 }
 
-pub struct ReturnStatements {
+// <left>[<index>]
+pub struct IndexExpression {
     pub token: Token,
-    pub value: Option<Rc<dyn Expression>>,
+    pub left: Rc<dyn Expression>,
+    pub index: Rc<dyn Expression>,
 }
 
-impl Statement for ReturnStatements {
-    fn statement_node(&self) {}
+impl Node for IndexExpression {
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
 }
 
-impl Node for ReturnStatements {
+impl Expression for IndexExpression {
+    fn expression_node(&self) {}
+}
+
+// <target> = <value>, or a compound form such as <target> += <value>.
+// The target is always an Identifier or an IndexExpression.
+pub struct AssignExpression {
+    pub token: Token,
+    pub target: Rc<dyn Expression>,
+    pub operator: String,
+    pub value: Rc<dyn Expression>,
+}
+
+impl Node for AssignExpression {
     fn token_literal(&self) -> String {
         self.token.literal.clone()
     }
@@ -110,12 +539,18 @@ impl Node for ReturnStatements {
     }
 }
 
-pub struct ExpressionStatement {
+impl Expression for AssignExpression {
+    fn expression_node(&self) {}
+}
+
+// <object>.<property>, e.g. `lib.name`
+pub struct MemberExpression {
     pub token: Token,
-    pub expr: Option<Rc<dyn Expression>>,
+    pub object: Rc<dyn Expression>,
+    pub property: Rc<Identifier>,
 }
 
-impl Node for ExpressionStatement {
+impl Node for MemberExpression {
     fn token_literal(&self) -> String {
         self.token.literal.clone()
     }
@@ -125,9 +560,98 @@ impl Node for ExpressionStatement {
     }
 }
 
-impl Statement for ExpressionStatement {
-    fn statement_node(&self) {}
+impl Expression for MemberExpression {
+    fn expression_node(&self) {}
+}
+
+pub struct IfExpression {
+    pub token: Token,
+    pub condition: Rc<dyn Expression>,
+    pub consequence: Rc<BlockStatement>,
+    pub alternative: Option<Rc<BlockStatement>>,
+}
+
+impl Node for IfExpression {
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl Expression for IfExpression {
+    fn expression_node(&self) {}
+}
+
+pub struct FunctionLiteral {
+    pub token: Token,
+    pub parameters: Vec<Rc<Identifier>>,
+    // one per parameter, None where it has no annotation
+    pub parameter_types: Vec<Option<TypeExpr>>,
+    // after `->`
+    pub return_type: Option<TypeExpr>,
+    pub body: Rc<BlockStatement>,
+}
+
+impl Node for FunctionLiteral {
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl Expression for FunctionLiteral {
+    fn expression_node(&self) {}
+}
+
+// macro(<parameters>) { <body> }
+// Only valid as the value of a top-level let; ast::macros::define_macros
+// lifts those out of the program before anything runs.
+pub struct MacroLiteral {
+    pub token: Token,
+    pub parameters: Vec<Rc<Identifier>>,
+    pub body: Rc<BlockStatement>,
+}
+
+impl Node for MacroLiteral {
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl Expression for MacroLiteral {
+    fn expression_node(&self) {}
+}
+
+pub struct CallExpression {
+    pub token: Token,
+    pub function: Rc<dyn Expression>,
+    pub arguments: Vec<Rc<dyn Expression>>,
+}
+
+impl Node for CallExpression {
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl Expression for CallExpression {
+    fn expression_node(&self) {}
 }
+
 //=======================Display impls =================================
 
 // impl fmt::Display for Program {
@@ -145,6 +669,24 @@ impl fmt::Display for dyn Statement {
             write!(f, "{}", ret_stmt)
         } else if let Some(expr_stmt) = self.as_any().downcast_ref::<ExpressionStatement>() {
             write!(f, "{}", expr_stmt)
+        } else if let Some(block) = self.as_any().downcast_ref::<BlockStatement>() {
+            write!(f, "{}", block)
+        } else if let Some(throw_stmt) = self.as_any().downcast_ref::<ThrowStatement>() {
+            write!(f, "{}", throw_stmt)
+        } else if let Some(try_stmt) = self.as_any().downcast_ref::<TryStatement>() {
+            write!(f, "{}", try_stmt)
+        } else if let Some(while_stmt) = self.as_any().downcast_ref::<WhileStatement>() {
+            write!(f, "{}", while_stmt)
+        } else if let Some(for_stmt) = self.as_any().downcast_ref::<ForStatement>() {
+            write!(f, "{}", for_stmt)
+        } else if let Some(break_stmt) = self.as_any().downcast_ref::<BreakStatement>() {
+            write!(f, "{}", break_stmt)
+        } else if let Some(continue_stmt) = self.as_any().downcast_ref::<ContinueStatement>() {
+            write!(f, "{}", continue_stmt)
+        } else if let Some(import_stmt) = self.as_any().downcast_ref::<ImportStatement>() {
+            write!(f, "{}", import_stmt)
+        } else if let Some(export_stmt) = self.as_any().downcast_ref::<ExportStatement>() {
+            write!(f, "{}", export_stmt)
         } else {
             write!(f, "Unknown statement type")
         }
@@ -182,7 +724,11 @@ impl fmt::Display for Program {
 
 impl fmt::Display for LetStatement {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{} {} = ", self.token.literal, self.name)?;
+        write!(f, "{} {}", self.token.literal, self.name)?;
+        if let Some(annotation) = &self.annotation {
+            write!(f, ": {}", annotation)?;
+        }
+        write!(f, " = ")?;
         if let Some(value) = &self.value {
             write!(f, "{}", value)?;
         }
@@ -190,12 +736,310 @@ impl fmt::Display for LetStatement {
     }
 }
 
+impl fmt::Display for TypeExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TypeExpr::Named(token) => write!(f, "{}", token.literal),
+            TypeExpr::Array(_, element) => write!(f, "[{}]", element),
+            TypeExpr::Function(_, params, result) => {
+                let params: Vec<String> = params.iter().map(|p| p.to_string()).collect();
+                write!(f, "fn({}) -> {}", params.join(", "), result)
+            }
+        }
+    }
+}
+
 impl fmt::Display for Identifier {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.value)
     }
 }
 
+impl fmt::Display for BlockStatement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for stmt in &self.statements {
+            write!(f, "{}", stmt)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ThrowStatement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ", self.token_literal())?;
+        if let Some(value) = &self.value {
+            write!(f, "{}", value)?;
+        }
+        write!(f, ";")
+    }
+}
+
+impl fmt::Display for TryStatement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "try {{ {} }}", self.block)?;
+        if let Some(catch_block) = &self.catch_block {
+            write!(f, " catch ")?;
+            if let Some(param) = &self.catch_param {
+                write!(f, "({}) ", param)?;
+            }
+            write!(f, "{{ {} }}", catch_block)?;
+        }
+        if let Some(finally_block) = &self.finally_block {
+            write!(f, " finally {{ {} }}", finally_block)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for WhileStatement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "while ({}) {{ {} }}", self.condition, self.body)
+    }
+}
+
+impl fmt::Display for ForStatement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "for ({} in {}) {{ {} }}",
+            self.variable, self.iterable, self.body
+        )
+    }
+}
+
+impl fmt::Display for BreakStatement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{};", self.token_literal())
+    }
+}
+
+impl fmt::Display for ContinueStatement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{};", self.token_literal())
+    }
+}
+
+impl fmt::Display for ImportStatement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "import \"{}\" as {};",
+            escape_string(&self.path),
+            self.alias
+        )
+    }
+}
+
+impl fmt::Display for ExportStatement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "export {}", self.statement)
+    }
+}
+
+impl fmt::Display for IntegerLiteral {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.token.literal)
+    }
+}
+
+impl fmt::Display for FloatLiteral {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.token.literal)
+    }
+}
+
+impl fmt::Display for StringLiteral {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "\"{}\"", escape_string(&self.value))
+    }
+}
+
+impl fmt::Display for InterpolatedString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "\"")?;
+        for part in &self.parts {
+            match part {
+                InterpolationPart::Text(text) => write!(f, "{}", escape_string(text))?,
+                InterpolationPart::Expr(expr) => write!(f, "${{{}}}", expr)?,
+            }
+        }
+        write!(f, "\"")
+    }
+}
+
+// The inverse of the lexer's unescaping, so Display output lexes back to
+// the same string.
+pub(crate) fn escape_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '$' if chars.peek() == Some(&'{') => out.push_str("\\$"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+impl fmt::Display for Boolean {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.token.literal)
+    }
+}
+
+impl fmt::Display for PrefixExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.operator)?;
+        if self.brackets_operand() {
+            write!(f, "({})", self.right)
+        } else {
+            write!(f, "{}", self.right)
+        }
+    }
+}
+
+impl PrefixExpression {
+    // Whether printing needs parentheses around the operand.
+    pub(crate) fn brackets_operand(&self) -> bool {
+        binding_of(self.right.as_ref()).is_some_and(|right| right < Predecessor::PREFIX)
+    }
+}
+
+impl fmt::Display for InfixExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (left, right) = self.brackets_operands();
+        if left {
+            write!(f, "({})", self.left)?
+        } else {
+            write!(f, "{}", self.left)?
+        }
+        write!(f, " {} ", self.operator)?;
+        if right {
+            write!(f, "({})", self.right)
+        } else {
+            write!(f, "{}", self.right)
+        }
+    }
+}
+
+impl InfixExpression {
+    // Whether printing needs parentheses around the left and right operands.
+    // Only operands that would otherwise regroup when read back get them.
+    pub(crate) fn brackets_operands(&self) -> (bool, bool) {
+        let prec = precedence_of(&self.token.type_);
+        let right_assoc = right_associative(&self.token.type_);
+        let left = matches!(
+            binding_of(self.left.as_ref()),
+            Some(left) if left < prec || (right_assoc && left == prec)
+        );
+        // a prefix operator on the right is always read back the same way
+        let right_prefix = self.right.as_any().is::<PrefixExpression>();
+        let right = matches!(
+            binding_of(self.right.as_ref()),
+            Some(right) if !right_prefix && (right < prec || (!right_assoc && right == prec))
+        );
+        (left, right)
+    }
+}
+
+pub(crate) fn binding_of(expr: &dyn Expression) -> Option<Predecessor> {
+    if let Some(infix) = expr.as_any().downcast_ref::<InfixExpression>() {
+        Some(precedence_of(&infix.token.type_))
+    } else if expr.as_any().is::<AssignExpression>() {
+        Some(Predecessor::ASSIGN)
+    } else if expr.as_any().is::<PrefixExpression>() {
+        Some(Predecessor::PREFIX)
+    } else {
+        None
+    }
+}
+
+impl fmt::Display for ArrayLiteral {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let elements: Vec<String> = self.elements.iter().map(|e| e.to_string()).collect();
+        write!(f, "[{}]", elements.join(", "))
+    }
+}
+
+impl fmt::Display for IndexExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if binding_of(self.left.as_ref()).is_some() {
+            write!(f, "({})[{}]", self.left, self.index)
+        } else {
+            write!(f, "{}[{}]", self.left, self.index)
+        }
+    }
+}
+
+impl fmt::Display for AssignExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {}", self.target, self.operator, self.value)
+    }
+}
+
+impl fmt::Display for MemberExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if binding_of(self.object.as_ref()).is_some() {
+            write!(f, "({}).{}", self.object, self.property)
+        } else {
+            write!(f, "{}.{}", self.object, self.property)
+        }
+    }
+}
+
+impl fmt::Display for IfExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "if ({}) {{ {} }}", self.condition, self.consequence)?;
+        if let Some(alternative) = &self.alternative {
+            write!(f, " else {{ {} }}", alternative)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for FunctionLiteral {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let params: Vec<String> = self
+            .parameters
+            .iter()
+            .zip(&self.parameter_types)
+            .map(|(p, t)| match t {
+                Some(t) => format!("{}: {}", p, t),
+                None => p.to_string(),
+            })
+            .collect();
+        write!(f, "{}({})", self.token_literal(), params.join(", "))?;
+        if let Some(result) = &self.return_type {
+            write!(f, " -> {}", result)?;
+        }
+        write!(f, " {{ {} }}", self.body)
+    }
+}
+
+impl fmt::Display for MacroLiteral {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let params: Vec<String> = self.parameters.iter().map(|p| p.to_string()).collect();
+        write!(
+            f,
+            "{}({}) {{ {} }}",
+            self.token_literal(),
+            params.join(", "),
+            self.body
+        )
+    }
+}
+
+impl fmt::Display for CallExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let args: Vec<String> = self.arguments.iter().map(|a| a.to_string()).collect();
+        write!(f, "{}({})", self.function, args.join(", "))
+    }
+}
+
 //=======================Debug impls =================================
 
 impl fmt::Debug for Program {
@@ -253,7 +1097,6 @@ impl fmt::Debug for dyn Expression {
     }
 }
 
-
 #[cfg(test)]
 mod tests {
     use crate::lexer::token::TokenType;
@@ -263,22 +1106,14 @@ mod tests {
     fn test_program_string() {
         let program = Program {
             statements: vec![Rc::new(LetStatement {
-                token: Token {
-                    type_: TokenType::Let,
-                    literal: "let".to_string(),
-                },
+                token: Token::new(TokenType::Let, "let"),
                 name: Rc::new(Identifier {
-                    token: Token {
-                        type_: TokenType::Ident,
-                        literal: "myVar".to_string(),
-                    },
+                    token: Token::new(TokenType::Ident, "myVar"),
                     value: "myVar".to_string(),
                 }),
+                annotation: None,
                 value: Some(Rc::new(Identifier {
-                    token: Token {
-                        type_: TokenType::Ident,
-                        literal: "anotherVar".to_string(),
-                    },
+                    token: Token::new(TokenType::Ident, "anotherVar"),
                     value: "anotherVar".to_string(),
                 }) as Rc<dyn Expression>),
             }) as Rc<dyn Statement>],
@@ -0,0 +1,535 @@
+use std::rc::Rc;
+
+use crate::ast::ast::{
+    ArrayLiteral, AssignExpression, BlockStatement, Boolean, CallExpression, ExportStatement,
+    Expression, ExpressionStatement, FloatLiteral, ForStatement, FunctionLiteral, Identifier,
+    IfExpression, IndexExpression, InfixExpression, IntegerLiteral, InterpolatedString,
+    InterpolationPart, LetStatement, MacroLiteral, MemberExpression, PrefixExpression, Program,
+    ReturnStatements, Statement, StringLiteral, ThrowStatement, TryStatement, WhileStatement,
+};
+
+// The rewriting counterpart of visit::Visitor. AST nodes are shared behind
+// Rc and never change in place, so each method returns the node to use
+// instead of the one it was given. The defaults rebuild the node from its
+// folded children; override a method to replace that kind of node, and
+// call the matching fold_* function to keep rewriting below it.
+//
+// Binding names (let names, parameters, the catch and for variables,
+// member properties and import aliases) and type annotations are copied
+// over untouched.
+pub trait Fold {
+    fn fold_program(&mut self, program: Program) -> Program {
+        fold_program(self, program)
+    }
+
+    fn fold_statement(&mut self, stmt: Rc<dyn Statement>) -> Rc<dyn Statement> {
+        fold_statement(self, stmt)
+    }
+
+    fn fold_expression(&mut self, expr: Rc<dyn Expression>) -> Rc<dyn Expression> {
+        fold_expression(self, expr)
+    }
+
+    fn fold_block_statement(&mut self, block: &BlockStatement) -> Rc<BlockStatement> {
+        fold_block_statement(self, block)
+    }
+
+    fn fold_let_statement(&mut self, stmt: &LetStatement) -> Rc<LetStatement> {
+        fold_let_statement(self, stmt)
+    }
+
+    fn fold_return_statement(&mut self, stmt: &ReturnStatements) -> Rc<dyn Statement> {
+        fold_return_statement(self, stmt)
+    }
+
+    fn fold_expression_statement(&mut self, stmt: &ExpressionStatement) -> Rc<dyn Statement> {
+        fold_expression_statement(self, stmt)
+    }
+
+    fn fold_throw_statement(&mut self, stmt: &ThrowStatement) -> Rc<dyn Statement> {
+        fold_throw_statement(self, stmt)
+    }
+
+    fn fold_try_statement(&mut self, stmt: &TryStatement) -> Rc<dyn Statement> {
+        fold_try_statement(self, stmt)
+    }
+
+    fn fold_while_statement(&mut self, stmt: &WhileStatement) -> Rc<dyn Statement> {
+        fold_while_statement(self, stmt)
+    }
+
+    fn fold_for_statement(&mut self, stmt: &ForStatement) -> Rc<dyn Statement> {
+        fold_for_statement(self, stmt)
+    }
+
+    fn fold_export_statement(&mut self, stmt: &ExportStatement) -> Rc<dyn Statement> {
+        fold_export_statement(self, stmt)
+    }
+
+    fn fold_identifier(&mut self, ident: &Identifier) -> Rc<dyn Expression> {
+        Rc::new(Identifier {
+            token: ident.token.clone(),
+            value: ident.value.clone(),
+        })
+    }
+
+    fn fold_integer_literal(&mut self, lit: &IntegerLiteral) -> Rc<dyn Expression> {
+        Rc::new(IntegerLiteral {
+            token: lit.token.clone(),
+            value: lit.value.clone(),
+        })
+    }
+
+    fn fold_float_literal(&mut self, lit: &FloatLiteral) -> Rc<dyn Expression> {
+        Rc::new(FloatLiteral {
+            token: lit.token.clone(),
+            value: lit.value,
+        })
+    }
+
+    fn fold_string_literal(&mut self, lit: &StringLiteral) -> Rc<dyn Expression> {
+        Rc::new(StringLiteral {
+            token: lit.token.clone(),
+            value: lit.value.clone(),
+        })
+    }
+
+    fn fold_boolean(&mut self, lit: &Boolean) -> Rc<dyn Expression> {
+        Rc::new(Boolean {
+            token: lit.token.clone(),
+            value: lit.value,
+        })
+    }
+
+    fn fold_interpolated_string(&mut self, expr: &InterpolatedString) -> Rc<dyn Expression> {
+        fold_interpolated_string(self, expr)
+    }
+
+    fn fold_prefix_expression(&mut self, expr: &PrefixExpression) -> Rc<dyn Expression> {
+        fold_prefix_expression(self, expr)
+    }
+
+    fn fold_infix_expression(&mut self, expr: &InfixExpression) -> Rc<dyn Expression> {
+        fold_infix_expression(self, expr)
+    }
+
+    fn fold_array_literal(&mut self, expr: &ArrayLiteral) -> Rc<dyn Expression> {
+        fold_array_literal(self, expr)
+    }
+
+    fn fold_index_expression(&mut self, expr: &IndexExpression) -> Rc<dyn Expression> {
+        fold_index_expression(self, expr)
+    }
+
+    fn fold_assign_expression(&mut self, expr: &AssignExpression) -> Rc<dyn Expression> {
+        fold_assign_expression(self, expr)
+    }
+
+    fn fold_member_expression(&mut self, expr: &MemberExpression) -> Rc<dyn Expression> {
+        fold_member_expression(self, expr)
+    }
+
+    fn fold_if_expression(&mut self, expr: &IfExpression) -> Rc<dyn Expression> {
+        fold_if_expression(self, expr)
+    }
+
+    fn fold_function_literal(&mut self, expr: &FunctionLiteral) -> Rc<dyn Expression> {
+        fold_function_literal(self, expr)
+    }
+
+    fn fold_macro_literal(&mut self, expr: &MacroLiteral) -> Rc<dyn Expression> {
+        fold_macro_literal(self, expr)
+    }
+
+    fn fold_call_expression(&mut self, expr: &CallExpression) -> Rc<dyn Expression> {
+        fold_call_expression(self, expr)
+    }
+}
+
+pub fn fold_program<F: Fold + ?Sized>(f: &mut F, program: Program) -> Program {
+    Program {
+        statements: program
+            .statements
+            .into_iter()
+            .map(|stmt| f.fold_statement(stmt))
+            .collect(),
+    }
+}
+
+// Dispatches to the fold_* method for the statement's concrete type.
+// Statements without expressions (break, continue, import) are kept as is.
+pub fn fold_statement<F: Fold + ?Sized>(f: &mut F, stmt: Rc<dyn Statement>) -> Rc<dyn Statement> {
+    let any = stmt.as_any();
+    if let Some(s) = any.downcast_ref::<ExpressionStatement>() {
+        f.fold_expression_statement(s)
+    } else if let Some(s) = any.downcast_ref::<LetStatement>() {
+        f.fold_let_statement(s)
+    } else if let Some(s) = any.downcast_ref::<ReturnStatements>() {
+        f.fold_return_statement(s)
+    } else if let Some(s) = any.downcast_ref::<BlockStatement>() {
+        f.fold_block_statement(s)
+    } else if let Some(s) = any.downcast_ref::<ThrowStatement>() {
+        f.fold_throw_statement(s)
+    } else if let Some(s) = any.downcast_ref::<TryStatement>() {
+        f.fold_try_statement(s)
+    } else if let Some(s) = any.downcast_ref::<WhileStatement>() {
+        f.fold_while_statement(s)
+    } else if let Some(s) = any.downcast_ref::<ForStatement>() {
+        f.fold_for_statement(s)
+    } else if let Some(s) = any.downcast_ref::<ExportStatement>() {
+        f.fold_export_statement(s)
+    } else {
+        stmt
+    }
+}
+
+// Dispatches to the fold_* method for the expression's concrete type.
+pub fn fold_expression<F: Fold + ?Sized>(
+    f: &mut F,
+    expr: Rc<dyn Expression>,
+) -> Rc<dyn Expression> {
+    let any = expr.as_any();
+    if let Some(e) = any.downcast_ref::<Identifier>() {
+        f.fold_identifier(e)
+    } else if let Some(e) = any.downcast_ref::<IntegerLiteral>() {
+        f.fold_integer_literal(e)
+    } else if let Some(e) = any.downcast_ref::<FloatLiteral>() {
+        f.fold_float_literal(e)
+    } else if let Some(e) = any.downcast_ref::<StringLiteral>() {
+        f.fold_string_literal(e)
+    } else if let Some(e) = any.downcast_ref::<Boolean>() {
+        f.fold_boolean(e)
+    } else if let Some(e) = any.downcast_ref::<InterpolatedString>() {
+        f.fold_interpolated_string(e)
+    } else if let Some(e) = any.downcast_ref::<PrefixExpression>() {
+        f.fold_prefix_expression(e)
+    } else if let Some(e) = any.downcast_ref::<InfixExpression>() {
+        f.fold_infix_expression(e)
+    } else if let Some(e) = any.downcast_ref::<ArrayLiteral>() {
+        f.fold_array_literal(e)
+    } else if let Some(e) = any.downcast_ref::<IndexExpression>() {
+        f.fold_index_expression(e)
+    } else if let Some(e) = any.downcast_ref::<AssignExpression>() {
+        f.fold_assign_expression(e)
+    } else if let Some(e) = any.downcast_ref::<MemberExpression>() {
+        f.fold_member_expression(e)
+    } else if let Some(e) = any.downcast_ref::<IfExpression>() {
+        f.fold_if_expression(e)
+    } else if let Some(e) = any.downcast_ref::<FunctionLiteral>() {
+        f.fold_function_literal(e)
+    } else if let Some(e) = any.downcast_ref::<MacroLiteral>() {
+        f.fold_macro_literal(e)
+    } else if let Some(e) = any.downcast_ref::<CallExpression>() {
+        f.fold_call_expression(e)
+    } else {
+        expr
+    }
+}
+
+pub fn fold_block_statement<F: Fold + ?Sized>(
+    f: &mut F,
+    block: &BlockStatement,
+) -> Rc<BlockStatement> {
+    Rc::new(BlockStatement {
+        token: block.token.clone(),
+        statements: block
+            .statements
+            .iter()
+            .map(|stmt| f.fold_statement(Rc::clone(stmt)))
+            .collect(),
+    })
+}
+
+pub fn fold_let_statement<F: Fold + ?Sized>(f: &mut F, stmt: &LetStatement) -> Rc<LetStatement> {
+    Rc::new(LetStatement {
+        token: stmt.token.clone(),
+        name: Rc::clone(&stmt.name),
+        annotation: stmt.annotation.clone(),
+        value: fold_optional(f, &stmt.value),
+    })
+}
+
+pub fn fold_return_statement<F: Fold + ?Sized>(
+    f: &mut F,
+    stmt: &ReturnStatements,
+) -> Rc<dyn Statement> {
+    Rc::new(ReturnStatements {
+        token: stmt.token.clone(),
+        value: fold_optional(f, &stmt.value),
+    })
+}
+
+pub fn fold_expression_statement<F: Fold + ?Sized>(
+    f: &mut F,
+    stmt: &ExpressionStatement,
+) -> Rc<dyn Statement> {
+    Rc::new(ExpressionStatement {
+        token: stmt.token.clone(),
+        expr: fold_optional(f, &stmt.expr),
+    })
+}
+
+pub fn fold_throw_statement<F: Fold + ?Sized>(
+    f: &mut F,
+    stmt: &ThrowStatement,
+) -> Rc<dyn Statement> {
+    Rc::new(ThrowStatement {
+        token: stmt.token.clone(),
+        value: fold_optional(f, &stmt.value),
+    })
+}
+
+pub fn fold_try_statement<F: Fold + ?Sized>(f: &mut F, stmt: &TryStatement) -> Rc<dyn Statement> {
+    Rc::new(TryStatement {
+        token: stmt.token.clone(),
+        block: f.fold_block_statement(&stmt.block),
+        catch_param: stmt.catch_param.clone(),
+        catch_block: stmt
+            .catch_block
+            .as_ref()
+            .map(|block| f.fold_block_statement(block)),
+        finally_block: stmt
+            .finally_block
+            .as_ref()
+            .map(|block| f.fold_block_statement(block)),
+    })
+}
+
+pub fn fold_while_statement<F: Fold + ?Sized>(
+    f: &mut F,
+    stmt: &WhileStatement,
+) -> Rc<dyn Statement> {
+    Rc::new(WhileStatement {
+        token: stmt.token.clone(),
+        condition: f.fold_expression(Rc::clone(&stmt.condition)),
+        body: f.fold_block_statement(&stmt.body),
+    })
+}
+
+pub fn fold_for_statement<F: Fold + ?Sized>(f: &mut F, stmt: &ForStatement) -> Rc<dyn Statement> {
+    Rc::new(ForStatement {
+        token: stmt.token.clone(),
+        variable: Rc::clone(&stmt.variable),
+        iterable: f.fold_expression(Rc::clone(&stmt.iterable)),
+        body: f.fold_block_statement(&stmt.body),
+    })
+}
+
+pub fn fold_export_statement<F: Fold + ?Sized>(
+    f: &mut F,
+    stmt: &ExportStatement,
+) -> Rc<dyn Statement> {
+    Rc::new(ExportStatement {
+        token: stmt.token.clone(),
+        statement: f.fold_let_statement(&stmt.statement),
+    })
+}
+
+pub fn fold_interpolated_string<F: Fold + ?Sized>(
+    f: &mut F,
+    expr: &InterpolatedString,
+) -> Rc<dyn Expression> {
+    let parts = expr
+        .parts
+        .iter()
+        .map(|part| match part {
+            InterpolationPart::Text(text) => InterpolationPart::Text(text.clone()),
+            InterpolationPart::Expr(e) => InterpolationPart::Expr(f.fold_expression(Rc::clone(e))),
+        })
+        .collect();
+    Rc::new(InterpolatedString {
+        token: expr.token.clone(),
+        parts,
+    })
+}
+
+pub fn fold_prefix_expression<F: Fold + ?Sized>(
+    f: &mut F,
+    expr: &PrefixExpression,
+) -> Rc<dyn Expression> {
+    Rc::new(PrefixExpression {
+        token: expr.token.clone(),
+        operator: expr.operator.clone(),
+        right: f.fold_expression(Rc::clone(&expr.right)),
+    })
+}
+
+pub fn fold_infix_expression<F: Fold + ?Sized>(
+    f: &mut F,
+    expr: &InfixExpression,
+) -> Rc<dyn Expression> {
+    Rc::new(InfixExpression {
+        token: expr.token.clone(),
+        left: f.fold_expression(Rc::clone(&expr.left)),
+        operator: expr.operator.clone(),
+        right: f.fold_expression(Rc::clone(&expr.right)),
+    })
+}
+
+pub fn fold_array_literal<F: Fold + ?Sized>(f: &mut F, expr: &ArrayLiteral) -> Rc<dyn Expression> {
+    Rc::new(ArrayLiteral {
+        token: expr.token.clone(),
+        elements: fold_all(f, &expr.elements),
+    })
+}
+
+pub fn fold_index_expression<F: Fold + ?Sized>(
+    f: &mut F,
+    expr: &IndexExpression,
+) -> Rc<dyn Expression> {
+    Rc::new(IndexExpression {
+        token: expr.token.clone(),
+        left: f.fold_expression(Rc::clone(&expr.left)),
+        index: f.fold_expression(Rc::clone(&expr.index)),
+    })
+}
+
+pub fn fold_assign_expression<F: Fold + ?Sized>(
+    f: &mut F,
+    expr: &AssignExpression,
+) -> Rc<dyn Expression> {
+    Rc::new(AssignExpression {
+        token: expr.token.clone(),
+        target: f.fold_expression(Rc::clone(&expr.target)),
+        operator: expr.operator.clone(),
+        value: f.fold_expression(Rc::clone(&expr.value)),
+    })
+}
+
+pub fn fold_member_expression<F: Fold + ?Sized>(
+    f: &mut F,
+    expr: &MemberExpression,
+) -> Rc<dyn Expression> {
+    Rc::new(MemberExpression {
+        token: expr.token.clone(),
+        object: f.fold_expression(Rc::clone(&expr.object)),
+        property: Rc::clone(&expr.property),
+    })
+}
+
+pub fn fold_if_expression<F: Fold + ?Sized>(f: &mut F, expr: &IfExpression) -> Rc<dyn Expression> {
+    Rc::new(IfExpression {
+        token: expr.token.clone(),
+        condition: f.fold_expression(Rc::clone(&expr.condition)),
+        consequence: f.fold_block_statement(&expr.consequence),
+        alternative: expr
+            .alternative
+            .as_ref()
+            .map(|block| f.fold_block_statement(block)),
+    })
+}
+
+pub fn fold_function_literal<F: Fold + ?Sized>(
+    f: &mut F,
+    expr: &FunctionLiteral,
+) -> Rc<dyn Expression> {
+    Rc::new(FunctionLiteral {
+        token: expr.token.clone(),
+        parameters: expr.parameters.clone(),
+        parameter_types: expr.parameter_types.clone(),
+        return_type: expr.return_type.clone(),
+        body: f.fold_block_statement(&expr.body),
+    })
+}
+
+pub fn fold_macro_literal<F: Fold + ?Sized>(f: &mut F, expr: &MacroLiteral) -> Rc<dyn Expression> {
+    Rc::new(MacroLiteral {
+        token: expr.token.clone(),
+        parameters: expr.parameters.clone(),
+        body: f.fold_block_statement(&expr.body),
+    })
+}
+
+pub fn fold_call_expression<F: Fold + ?Sized>(
+    f: &mut F,
+    expr: &CallExpression,
+) -> Rc<dyn Expression> {
+    Rc::new(CallExpression {
+        token: expr.token.clone(),
+        function: f.fold_expression(Rc::clone(&expr.function)),
+        arguments: fold_all(f, &expr.arguments),
+    })
+}
+
+fn fold_optional<F: Fold + ?Sized>(
+    f: &mut F,
+    expr: &Option<Rc<dyn Expression>>,
+) -> Option<Rc<dyn Expression>> {
+    expr.as_ref().map(|e| f.fold_expression(Rc::clone(e)))
+}
+
+fn fold_all<F: Fold + ?Sized>(f: &mut F, exprs: &[Rc<dyn Expression>]) -> Vec<Rc<dyn Expression>> {
+    exprs
+        .iter()
+        .map(|e| f.fold_expression(Rc::clone(e)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::token::{Token, TokenType};
+    use crate::parser::parser::parse;
+
+    // Renames every use of one identifier; bindings keep their names.
+    struct Rename<'a>(&'a str, &'a str);
+
+    impl Fold for Rename<'_> {
+        fn fold_identifier(&mut self, ident: &Identifier) -> Rc<dyn Expression> {
+            let value = if ident.value == self.0 {
+                self.1
+            } else {
+                &ident.value
+            };
+            Rc::new(Identifier {
+                token: Token::new(TokenType::Ident, value),
+                value: value.to_string(),
+            })
+        }
+    }
+
+    #[test]
+    fn test_fold_rewrites_only_overridden_nodes() {
+        let input = "let x = fn(x) { x + y }; x(y[x]);";
+        let program = Rename("x", "z").fold_program(parse(input));
+
+        assert_eq!(program.to_string(), "let x = fn(x) { z + y };z(y[z])");
+    }
+
+    // Swaps the operands of every `+`, descending first.
+    struct SwapPlus;
+
+    impl Fold for SwapPlus {
+        fn fold_infix_expression(&mut self, expr: &InfixExpression) -> Rc<dyn Expression> {
+            if expr.operator != "+" {
+                return fold_infix_expression(self, expr);
+            }
+            Rc::new(InfixExpression {
+                token: expr.token.clone(),
+                left: self.fold_expression(Rc::clone(&expr.right)),
+                operator: expr.operator.clone(),
+                right: self.fold_expression(Rc::clone(&expr.left)),
+            })
+        }
+    }
+
+    #[test]
+    fn test_fold_descends_through_statements() {
+        let tests = [
+            ("a + b * (c + d)", "b * (d + c) + a"),
+            (
+                "while (a + b) { for (i in [a + b]) { return a + b; } }",
+                "while (b + a) { for (i in [b + a]) { return b + a; } }",
+            ),
+            (
+                r#"try { throw a + b; } catch (e) { "${a + b}" }"#,
+                r#"try { throw b + a; } catch (e) { "${b + a}" }"#,
+            ),
+        ];
+
+        for (input, expected) in tests {
+            let program = SwapPlus.fold_program(parse(input));
+            assert_eq!(program.to_string(), expected, "input={}", input);
+        }
+    }
+}
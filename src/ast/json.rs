@@ -0,0 +1,995 @@
+// JSON form of the AST, behind the `serde` feature.
+//
+// Every node is an object with these fields:
+//
+//   kind     the node type, e.g. "LetStatement" or "InfixExpression"
+//   span     {start, end, line, column} of the node's first token
+//   token    type of that token, as in the `lex --format json` output
+//   literal  text of that token
+//
+// plus the node's children under the names below. Optional children are
+// `null` when absent. A Program is {"kind": "Program", "statements": [...]}.
+//
+//   LetStatement         name, annotation, value
+//   ReturnStatement      value
+//   ExpressionStatement  expression
+//   BlockStatement       statements
+//   ThrowStatement       value
+//   TryStatement         block, catch_param, catch_block, finally_block
+//   WhileStatement       condition, body
+//   ForStatement         variable, iterable, body
+//   BreakStatement       -
+//   ContinueStatement    -
+//   ImportStatement      path (string), alias
+//   ExportStatement      statement (a LetStatement)
+//   Identifier           value (string)
+//   IntegerLiteral       value (decimal string, any size)
+//   FloatLiteral         value (number)
+//   StringLiteral        value (string)
+//   Boolean              value (bool)
+//   InterpolatedString   parts: [{"text": string} | {"expression": node}]
+//   PrefixExpression     operator, right
+//   InfixExpression      left, operator, right
+//   ArrayLiteral         elements
+//   IndexExpression      left, index
+//   AssignExpression     target, operator, value
+//   MemberExpression     object, property
+//   IfExpression         condition, consequence, alternative
+//   FunctionLiteral      parameters, parameter_types, return_type, body
+//   MacroLiteral         parameters, body
+//   CallExpression       function, arguments
+//
+// Type annotations are objects of their own kinds, with the span, token
+// and literal of their first token:
+//
+//   NamedType            -
+//   ArrayType            element
+//   FunctionType         parameters, result
+//
+// `parameter_types` has one entry per parameter, null where it has no
+// annotation. The annotation fields may be left out when reading.
+//
+// Field names are only ever added to this list, never renamed or removed.
+
+use std::rc::Rc;
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::ast::ast::{
+    ArrayLiteral, AssignExpression, BlockStatement, Boolean, BreakStatement, CallExpression,
+    ContinueStatement, ExportStatement, Expression, ExpressionStatement, FloatLiteral,
+    ForStatement, FunctionLiteral, Identifier, IfExpression, ImportStatement, IndexExpression,
+    InfixExpression, IntegerLiteral, InterpolatedString, InterpolationPart, LetStatement,
+    MacroLiteral, MemberExpression, PrefixExpression, Program, ReturnStatements, Statement,
+    StringLiteral, ThrowStatement, TryStatement, TypeExpr, WhileStatement,
+};
+use crate::lexer::token::{Span, Token, TokenType, TokenValue};
+use crate::object::integer::Integer;
+
+impl Serialize for Program {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ProgramNode {
+            statements: self
+                .statements
+                .iter()
+                .map(|s| stmt_node(s.as_ref()))
+                .collect(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Program {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let node = ProgramNode::deserialize(deserializer)?;
+        let statements = node
+            .statements
+            .into_iter()
+            .map(StmtNode::into_ast)
+            .collect::<Result<_, _>>()
+            .map_err(de::Error::custom)?;
+        Ok(Program { statements })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "kind", rename = "Program")]
+struct ProgramNode {
+    statements: Vec<StmtNode>,
+}
+
+// The first token of a node, flattened into the node's object.
+#[derive(Serialize, Deserialize)]
+struct Head {
+    span: Span,
+    token: TokenType,
+    literal: String,
+}
+
+impl Head {
+    fn of(token: &Token) -> Self {
+        Head {
+            span: token.span,
+            token: token.type_,
+            literal: token.literal.clone(),
+        }
+    }
+
+    fn into_token(self) -> Token {
+        Token::new(self.token, &self.literal).with_span(self.span)
+    }
+}
+
+// Variant names are the `kind` strings, so they match the AST types.
+#[allow(clippy::enum_variant_names)]
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "kind")]
+enum StmtNode {
+    LetStatement {
+        #[serde(flatten)]
+        head: Head,
+        name: Box<ExprNode>,
+        #[serde(default)]
+        annotation: Option<TypeNode>,
+        value: Option<Box<ExprNode>>,
+    },
+    ReturnStatement {
+        #[serde(flatten)]
+        head: Head,
+        value: Option<Box<ExprNode>>,
+    },
+    ExpressionStatement {
+        #[serde(flatten)]
+        head: Head,
+        expression: Option<Box<ExprNode>>,
+    },
+    BlockStatement {
+        #[serde(flatten)]
+        head: Head,
+        statements: Vec<StmtNode>,
+    },
+    ThrowStatement {
+        #[serde(flatten)]
+        head: Head,
+        value: Option<Box<ExprNode>>,
+    },
+    TryStatement {
+        #[serde(flatten)]
+        head: Head,
+        block: Box<StmtNode>,
+        catch_param: Option<Box<ExprNode>>,
+        catch_block: Option<Box<StmtNode>>,
+        finally_block: Option<Box<StmtNode>>,
+    },
+    WhileStatement {
+        #[serde(flatten)]
+        head: Head,
+        condition: Box<ExprNode>,
+        body: Box<StmtNode>,
+    },
+    ForStatement {
+        #[serde(flatten)]
+        head: Head,
+        variable: Box<ExprNode>,
+        iterable: Box<ExprNode>,
+        body: Box<StmtNode>,
+    },
+    BreakStatement {
+        #[serde(flatten)]
+        head: Head,
+    },
+    ContinueStatement {
+        #[serde(flatten)]
+        head: Head,
+    },
+    ImportStatement {
+        #[serde(flatten)]
+        head: Head,
+        path: String,
+        alias: Box<ExprNode>,
+    },
+    ExportStatement {
+        #[serde(flatten)]
+        head: Head,
+        statement: Box<StmtNode>,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "kind")]
+enum ExprNode {
+    Identifier {
+        #[serde(flatten)]
+        head: Head,
+        value: String,
+    },
+    IntegerLiteral {
+        #[serde(flatten)]
+        head: Head,
+        value: Integer,
+    },
+    FloatLiteral {
+        #[serde(flatten)]
+        head: Head,
+        value: f64,
+    },
+    StringLiteral {
+        #[serde(flatten)]
+        head: Head,
+        value: String,
+    },
+    Boolean {
+        #[serde(flatten)]
+        head: Head,
+        value: bool,
+    },
+    InterpolatedString {
+        #[serde(flatten)]
+        head: Head,
+        parts: Vec<PartNode>,
+    },
+    PrefixExpression {
+        #[serde(flatten)]
+        head: Head,
+        operator: String,
+        right: Box<ExprNode>,
+    },
+    InfixExpression {
+        #[serde(flatten)]
+        head: Head,
+        left: Box<ExprNode>,
+        operator: String,
+        right: Box<ExprNode>,
+    },
+    ArrayLiteral {
+        #[serde(flatten)]
+        head: Head,
+        elements: Vec<ExprNode>,
+    },
+    IndexExpression {
+        #[serde(flatten)]
+        head: Head,
+        left: Box<ExprNode>,
+        index: Box<ExprNode>,
+    },
+    AssignExpression {
+        #[serde(flatten)]
+        head: Head,
+        target: Box<ExprNode>,
+        operator: String,
+        value: Box<ExprNode>,
+    },
+    MemberExpression {
+        #[serde(flatten)]
+        head: Head,
+        object: Box<ExprNode>,
+        property: Box<ExprNode>,
+    },
+    IfExpression {
+        #[serde(flatten)]
+        head: Head,
+        condition: Box<ExprNode>,
+        consequence: Box<StmtNode>,
+        alternative: Option<Box<StmtNode>>,
+    },
+    FunctionLiteral {
+        #[serde(flatten)]
+        head: Head,
+        parameters: Vec<ExprNode>,
+        #[serde(default)]
+        parameter_types: Vec<Option<TypeNode>>,
+        #[serde(default)]
+        return_type: Option<TypeNode>,
+        body: Box<StmtNode>,
+    },
+    MacroLiteral {
+        #[serde(flatten)]
+        head: Head,
+        parameters: Vec<ExprNode>,
+        body: Box<StmtNode>,
+    },
+    CallExpression {
+        #[serde(flatten)]
+        head: Head,
+        function: Box<ExprNode>,
+        arguments: Vec<ExprNode>,
+    },
+}
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "kind")]
+enum TypeNode {
+    NamedType {
+        #[serde(flatten)]
+        head: Head,
+    },
+    ArrayType {
+        #[serde(flatten)]
+        head: Head,
+        element: Box<TypeNode>,
+    },
+    FunctionType {
+        #[serde(flatten)]
+        head: Head,
+        parameters: Vec<TypeNode>,
+        result: Box<TypeNode>,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum PartNode {
+    Text(String),
+    Expression(ExprNode),
+}
+
+//======================= AST -> JSON nodes =================================
+
+fn stmt_node(stmt: &dyn Statement) -> StmtNode {
+    let any = stmt.as_any();
+    if let Some(s) = any.downcast_ref::<LetStatement>() {
+        let_node(s)
+    } else if let Some(s) = any.downcast_ref::<ReturnStatements>() {
+        StmtNode::ReturnStatement {
+            head: Head::of(&s.token),
+            value: s.value.as_deref().map(boxed_expr),
+        }
+    } else if let Some(s) = any.downcast_ref::<ExpressionStatement>() {
+        StmtNode::ExpressionStatement {
+            head: Head::of(&s.token),
+            expression: s.expr.as_deref().map(boxed_expr),
+        }
+    } else if let Some(s) = any.downcast_ref::<BlockStatement>() {
+        block_node(s)
+    } else if let Some(s) = any.downcast_ref::<ThrowStatement>() {
+        StmtNode::ThrowStatement {
+            head: Head::of(&s.token),
+            value: s.value.as_deref().map(boxed_expr),
+        }
+    } else if let Some(s) = any.downcast_ref::<TryStatement>() {
+        StmtNode::TryStatement {
+            head: Head::of(&s.token),
+            block: Box::new(block_node(&s.block)),
+            catch_param: s.catch_param.as_deref().map(|p| Box::new(ident_node(p))),
+            catch_block: s.catch_block.as_deref().map(|b| Box::new(block_node(b))),
+            finally_block: s.finally_block.as_deref().map(|b| Box::new(block_node(b))),
+        }
+    } else if let Some(s) = any.downcast_ref::<WhileStatement>() {
+        StmtNode::WhileStatement {
+            head: Head::of(&s.token),
+            condition: boxed_expr(s.condition.as_ref()),
+            body: Box::new(block_node(&s.body)),
+        }
+    } else if let Some(s) = any.downcast_ref::<ForStatement>() {
+        StmtNode::ForStatement {
+            head: Head::of(&s.token),
+            variable: Box::new(ident_node(&s.variable)),
+            iterable: boxed_expr(s.iterable.as_ref()),
+            body: Box::new(block_node(&s.body)),
+        }
+    } else if let Some(s) = any.downcast_ref::<BreakStatement>() {
+        StmtNode::BreakStatement {
+            head: Head::of(&s.token),
+        }
+    } else if let Some(s) = any.downcast_ref::<ContinueStatement>() {
+        StmtNode::ContinueStatement {
+            head: Head::of(&s.token),
+        }
+    } else if let Some(s) = any.downcast_ref::<ImportStatement>() {
+        StmtNode::ImportStatement {
+            head: Head::of(&s.token),
+            path: s.path.clone(),
+            alias: Box::new(ident_node(&s.alias)),
+        }
+    } else if let Some(s) = any.downcast_ref::<ExportStatement>() {
+        StmtNode::ExportStatement {
+            head: Head::of(&s.token),
+            statement: Box::new(let_node(&s.statement)),
+        }
+    } else {
+        unreachable!("statement {} has no JSON form", stmt.token_literal())
+    }
+}
+
+fn let_node(s: &LetStatement) -> StmtNode {
+    StmtNode::LetStatement {
+        head: Head::of(&s.token),
+        name: Box::new(ident_node(&s.name)),
+        annotation: s.annotation.as_ref().map(type_node),
+        value: s.value.as_deref().map(boxed_expr),
+    }
+}
+
+fn type_node(t: &TypeExpr) -> TypeNode {
+    match t {
+        TypeExpr::Named(token) => TypeNode::NamedType {
+            head: Head::of(token),
+        },
+        TypeExpr::Array(token, element) => TypeNode::ArrayType {
+            head: Head::of(token),
+            element: Box::new(type_node(element)),
+        },
+        TypeExpr::Function(token, params, result) => TypeNode::FunctionType {
+            head: Head::of(token),
+            parameters: params.iter().map(type_node).collect(),
+            result: Box::new(type_node(result)),
+        },
+    }
+}
+
+fn block_node(block: &BlockStatement) -> StmtNode {
+    StmtNode::BlockStatement {
+        head: Head::of(&block.token),
+        statements: block
+            .statements
+            .iter()
+            .map(|s| stmt_node(s.as_ref()))
+            .collect(),
+    }
+}
+
+fn ident_node(ident: &Identifier) -> ExprNode {
+    ExprNode::Identifier {
+        head: Head::of(&ident.token),
+        value: ident.value.clone(),
+    }
+}
+
+fn boxed_expr(expr: &dyn Expression) -> Box<ExprNode> {
+    Box::new(expr_node(expr))
+}
+
+fn expr_nodes(exprs: &[Rc<dyn Expression>]) -> Vec<ExprNode> {
+    exprs.iter().map(|e| expr_node(e.as_ref())).collect()
+}
+
+fn expr_node(expr: &dyn Expression) -> ExprNode {
+    let any = expr.as_any();
+    if let Some(e) = any.downcast_ref::<Identifier>() {
+        ident_node(e)
+    } else if let Some(e) = any.downcast_ref::<IntegerLiteral>() {
+        ExprNode::IntegerLiteral {
+            head: Head::of(&e.token),
+            value: e.value.clone(),
+        }
+    } else if let Some(e) = any.downcast_ref::<FloatLiteral>() {
+        ExprNode::FloatLiteral {
+            head: Head::of(&e.token),
+            value: e.value,
+        }
+    } else if let Some(e) = any.downcast_ref::<StringLiteral>() {
+        ExprNode::StringLiteral {
+            head: Head::of(&e.token),
+            value: e.value.clone(),
+        }
+    } else if let Some(e) = any.downcast_ref::<Boolean>() {
+        ExprNode::Boolean {
+            head: Head::of(&e.token),
+            value: e.value,
+        }
+    } else if let Some(e) = any.downcast_ref::<InterpolatedString>() {
+        let parts = e
+            .parts
+            .iter()
+            .map(|part| match part {
+                InterpolationPart::Text(text) => PartNode::Text(text.clone()),
+                InterpolationPart::Expr(expr) => PartNode::Expression(expr_node(expr.as_ref())),
+            })
+            .collect();
+        ExprNode::InterpolatedString {
+            head: Head::of(&e.token),
+            parts,
+        }
+    } else if let Some(e) = any.downcast_ref::<PrefixExpression>() {
+        ExprNode::PrefixExpression {
+            head: Head::of(&e.token),
+            operator: e.operator.clone(),
+            right: boxed_expr(e.right.as_ref()),
+        }
+    } else if let Some(e) = any.downcast_ref::<InfixExpression>() {
+        ExprNode::InfixExpression {
+            head: Head::of(&e.token),
+            left: boxed_expr(e.left.as_ref()),
+            operator: e.operator.clone(),
+            right: boxed_expr(e.right.as_ref()),
+        }
+    } else if let Some(e) = any.downcast_ref::<ArrayLiteral>() {
+        ExprNode::ArrayLiteral {
+            head: Head::of(&e.token),
+            elements: expr_nodes(&e.elements),
+        }
+    } else if let Some(e) = any.downcast_ref::<IndexExpression>() {
+        ExprNode::IndexExpression {
+            head: Head::of(&e.token),
+            left: boxed_expr(e.left.as_ref()),
+            index: boxed_expr(e.index.as_ref()),
+        }
+    } else if let Some(e) = any.downcast_ref::<AssignExpression>() {
+        ExprNode::AssignExpression {
+            head: Head::of(&e.token),
+            target: boxed_expr(e.target.as_ref()),
+            operator: e.operator.clone(),
+            value: boxed_expr(e.value.as_ref()),
+        }
+    } else if let Some(e) = any.downcast_ref::<MemberExpression>() {
+        ExprNode::MemberExpression {
+            head: Head::of(&e.token),
+            object: boxed_expr(e.object.as_ref()),
+            property: Box::new(ident_node(&e.property)),
+        }
+    } else if let Some(e) = any.downcast_ref::<IfExpression>() {
+        ExprNode::IfExpression {
+            head: Head::of(&e.token),
+            condition: boxed_expr(e.condition.as_ref()),
+            consequence: Box::new(block_node(&e.consequence)),
+            alternative: e.alternative.as_deref().map(|b| Box::new(block_node(b))),
+        }
+    } else if let Some(e) = any.downcast_ref::<FunctionLiteral>() {
+        ExprNode::FunctionLiteral {
+            head: Head::of(&e.token),
+            parameters: e.parameters.iter().map(|p| ident_node(p)).collect(),
+            parameter_types: e
+                .parameter_types
+                .iter()
+                .map(|t| t.as_ref().map(type_node))
+                .collect(),
+            return_type: e.return_type.as_ref().map(type_node),
+            body: Box::new(block_node(&e.body)),
+        }
+    } else if let Some(e) = any.downcast_ref::<MacroLiteral>() {
+        ExprNode::MacroLiteral {
+            head: Head::of(&e.token),
+            parameters: e.parameters.iter().map(|p| ident_node(p)).collect(),
+            body: Box::new(block_node(&e.body)),
+        }
+    } else if let Some(e) = any.downcast_ref::<CallExpression>() {
+        ExprNode::CallExpression {
+            head: Head::of(&e.token),
+            function: boxed_expr(e.function.as_ref()),
+            arguments: expr_nodes(&e.arguments),
+        }
+    } else {
+        unreachable!("expression {} has no JSON form", expr)
+    }
+}
+
+//======================= JSON nodes -> AST =================================
+
+impl StmtNode {
+    fn into_ast(self) -> Result<Rc<dyn Statement>, String> {
+        Ok(match self {
+            node @ StmtNode::LetStatement { .. } => Rc::new(node.into_let()?),
+            node @ StmtNode::BlockStatement { .. } => Rc::new(node.into_block()?),
+            StmtNode::ReturnStatement { head, value } => Rc::new(ReturnStatements {
+                token: head.into_token(),
+                value: value.map(|v| v.into_ast()).transpose()?,
+            }),
+            StmtNode::ExpressionStatement { head, expression } => Rc::new(ExpressionStatement {
+                token: head.into_token(),
+                expr: expression.map(|e| e.into_ast()).transpose()?,
+            }),
+            StmtNode::ThrowStatement { head, value } => Rc::new(ThrowStatement {
+                token: head.into_token(),
+                value: value.map(|v| v.into_ast()).transpose()?,
+            }),
+            StmtNode::TryStatement {
+                head,
+                block,
+                catch_param,
+                catch_block,
+                finally_block,
+            } => Rc::new(TryStatement {
+                token: head.into_token(),
+                block: Rc::new(block.into_block()?),
+                catch_param: catch_param
+                    .map(|p| p.into_identifier().map(Rc::new))
+                    .transpose()?,
+                catch_block: catch_block
+                    .map(|b| b.into_block().map(Rc::new))
+                    .transpose()?,
+                finally_block: finally_block
+                    .map(|b| b.into_block().map(Rc::new))
+                    .transpose()?,
+            }),
+            StmtNode::WhileStatement {
+                head,
+                condition,
+                body,
+            } => Rc::new(WhileStatement {
+                token: head.into_token(),
+                condition: condition.into_ast()?,
+                body: Rc::new(body.into_block()?),
+            }),
+            StmtNode::ForStatement {
+                head,
+                variable,
+                iterable,
+                body,
+            } => Rc::new(ForStatement {
+                token: head.into_token(),
+                variable: Rc::new(variable.into_identifier()?),
+                iterable: iterable.into_ast()?,
+                body: Rc::new(body.into_block()?),
+            }),
+            StmtNode::BreakStatement { head } => Rc::new(BreakStatement {
+                token: head.into_token(),
+            }),
+            StmtNode::ContinueStatement { head } => Rc::new(ContinueStatement {
+                token: head.into_token(),
+            }),
+            StmtNode::ImportStatement { head, path, alias } => Rc::new(ImportStatement {
+                token: head.into_token(),
+                path,
+                alias: Rc::new(alias.into_identifier()?),
+            }),
+            StmtNode::ExportStatement { head, statement } => Rc::new(ExportStatement {
+                token: head.into_token(),
+                statement: Rc::new(statement.into_let()?),
+            }),
+        })
+    }
+
+    fn into_let(self) -> Result<LetStatement, String> {
+        match self {
+            StmtNode::LetStatement {
+                head,
+                name,
+                annotation,
+                value,
+            } => Ok(LetStatement {
+                token: head.into_token(),
+                name: Rc::new(name.into_identifier()?),
+                annotation: annotation.map(TypeNode::into_type),
+                value: value.map(|v| v.into_ast()).transpose()?,
+            }),
+            other => Err(format!("expected LetStatement, got {}", other.kind())),
+        }
+    }
+
+    fn into_block(self) -> Result<BlockStatement, String> {
+        match self {
+            StmtNode::BlockStatement { head, statements } => Ok(BlockStatement {
+                token: head.into_token(),
+                statements: statements
+                    .into_iter()
+                    .map(StmtNode::into_ast)
+                    .collect::<Result<_, _>>()?,
+            }),
+            other => Err(format!("expected BlockStatement, got {}", other.kind())),
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            StmtNode::LetStatement { .. } => "LetStatement",
+            StmtNode::ReturnStatement { .. } => "ReturnStatement",
+            StmtNode::ExpressionStatement { .. } => "ExpressionStatement",
+            StmtNode::BlockStatement { .. } => "BlockStatement",
+            StmtNode::ThrowStatement { .. } => "ThrowStatement",
+            StmtNode::TryStatement { .. } => "TryStatement",
+            StmtNode::WhileStatement { .. } => "WhileStatement",
+            StmtNode::ForStatement { .. } => "ForStatement",
+            StmtNode::BreakStatement { .. } => "BreakStatement",
+            StmtNode::ContinueStatement { .. } => "ContinueStatement",
+            StmtNode::ImportStatement { .. } => "ImportStatement",
+            StmtNode::ExportStatement { .. } => "ExportStatement",
+        }
+    }
+}
+
+impl ExprNode {
+    fn into_ast(self) -> Result<Rc<dyn Expression>, String> {
+        Ok(match self {
+            node @ ExprNode::Identifier { .. } => Rc::new(node.into_identifier()?),
+            ExprNode::IntegerLiteral { head, value } => {
+                let mut token = head.into_token();
+                token.value = Some(TokenValue::Int(value.clone()));
+                Rc::new(IntegerLiteral { token, value })
+            }
+            ExprNode::FloatLiteral { head, value } => Rc::new(FloatLiteral {
+                token: head.into_token(),
+                value,
+            }),
+            ExprNode::StringLiteral { head, value } => Rc::new(StringLiteral {
+                token: head.into_token(),
+                value,
+            }),
+            ExprNode::Boolean { head, value } => Rc::new(Boolean {
+                token: head.into_token(),
+                value,
+            }),
+            ExprNode::InterpolatedString { head, parts } => Rc::new(InterpolatedString {
+                token: head.into_token(),
+                parts: parts
+                    .into_iter()
+                    .map(|part| match part {
+                        PartNode::Text(text) => Ok(InterpolationPart::Text(text)),
+                        PartNode::Expression(e) => e.into_ast().map(InterpolationPart::Expr),
+                    })
+                    .collect::<Result<_, String>>()?,
+            }),
+            ExprNode::PrefixExpression {
+                head,
+                operator,
+                right,
+            } => Rc::new(PrefixExpression {
+                token: head.into_token(),
+                operator,
+                right: right.into_ast()?,
+            }),
+            ExprNode::InfixExpression {
+                head,
+                left,
+                operator,
+                right,
+            } => Rc::new(InfixExpression {
+                token: head.into_token(),
+                left: left.into_ast()?,
+                operator,
+                right: right.into_ast()?,
+            }),
+            ExprNode::ArrayLiteral { head, elements } => Rc::new(ArrayLiteral {
+                token: head.into_token(),
+                elements: into_asts(elements)?,
+            }),
+            ExprNode::IndexExpression { head, left, index } => Rc::new(IndexExpression {
+                token: head.into_token(),
+                left: left.into_ast()?,
+                index: index.into_ast()?,
+            }),
+            ExprNode::AssignExpression {
+                head,
+                target,
+                operator,
+                value,
+            } => Rc::new(AssignExpression {
+                token: head.into_token(),
+                target: target.into_ast()?,
+                operator,
+                value: value.into_ast()?,
+            }),
+            ExprNode::MemberExpression {
+                head,
+                object,
+                property,
+            } => Rc::new(MemberExpression {
+                token: head.into_token(),
+                object: object.into_ast()?,
+                property: Rc::new(property.into_identifier()?),
+            }),
+            ExprNode::IfExpression {
+                head,
+                condition,
+                consequence,
+                alternative,
+            } => Rc::new(IfExpression {
+                token: head.into_token(),
+                condition: condition.into_ast()?,
+                consequence: Rc::new(consequence.into_block()?),
+                alternative: alternative
+                    .map(|b| b.into_block().map(Rc::new))
+                    .transpose()?,
+            }),
+            ExprNode::FunctionLiteral {
+                head,
+                parameters,
+                parameter_types,
+                return_type,
+                body,
+            } => Rc::new(FunctionLiteral {
+                token: head.into_token(),
+                parameter_types: into_parameter_types(parameter_types, parameters.len())?,
+                parameters: into_identifiers(parameters)?,
+                return_type: return_type.map(TypeNode::into_type),
+                body: Rc::new(body.into_block()?),
+            }),
+            ExprNode::MacroLiteral {
+                head,
+                parameters,
+                body,
+            } => Rc::new(MacroLiteral {
+                token: head.into_token(),
+                parameters: into_identifiers(parameters)?,
+                body: Rc::new(body.into_block()?),
+            }),
+            ExprNode::CallExpression {
+                head,
+                function,
+                arguments,
+            } => Rc::new(CallExpression {
+                token: head.into_token(),
+                function: function.into_ast()?,
+                arguments: into_asts(arguments)?,
+            }),
+        })
+    }
+
+    fn into_identifier(self) -> Result<Identifier, String> {
+        match self {
+            ExprNode::Identifier { head, value } => Ok(Identifier {
+                token: head.into_token(),
+                value,
+            }),
+            other => Err(format!("expected Identifier, got {}", other.kind())),
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            ExprNode::Identifier { .. } => "Identifier",
+            ExprNode::IntegerLiteral { .. } => "IntegerLiteral",
+            ExprNode::FloatLiteral { .. } => "FloatLiteral",
+            ExprNode::StringLiteral { .. } => "StringLiteral",
+            ExprNode::Boolean { .. } => "Boolean",
+            ExprNode::InterpolatedString { .. } => "InterpolatedString",
+            ExprNode::PrefixExpression { .. } => "PrefixExpression",
+            ExprNode::InfixExpression { .. } => "InfixExpression",
+            ExprNode::ArrayLiteral { .. } => "ArrayLiteral",
+            ExprNode::IndexExpression { .. } => "IndexExpression",
+            ExprNode::AssignExpression { .. } => "AssignExpression",
+            ExprNode::MemberExpression { .. } => "MemberExpression",
+            ExprNode::IfExpression { .. } => "IfExpression",
+            ExprNode::FunctionLiteral { .. } => "FunctionLiteral",
+            ExprNode::MacroLiteral { .. } => "MacroLiteral",
+            ExprNode::CallExpression { .. } => "CallExpression",
+        }
+    }
+}
+
+fn into_asts(nodes: Vec<ExprNode>) -> Result<Vec<Rc<dyn Expression>>, String> {
+    nodes.into_iter().map(ExprNode::into_ast).collect()
+}
+
+fn into_identifiers(nodes: Vec<ExprNode>) -> Result<Vec<Rc<Identifier>>, String> {
+    nodes
+        .into_iter()
+        .map(|n| n.into_identifier().map(Rc::new))
+        .collect()
+}
+
+// Left out, the annotations are all absent.
+fn into_parameter_types(
+    nodes: Vec<Option<TypeNode>>,
+    parameters: usize,
+) -> Result<Vec<Option<TypeExpr>>, String> {
+    if nodes.is_empty() {
+        return Ok(vec![None; parameters]);
+    }
+    if nodes.len() != parameters {
+        return Err(format!(
+            "{} parameter types for {} parameters",
+            nodes.len(),
+            parameters
+        ));
+    }
+    Ok(nodes
+        .into_iter()
+        .map(|t| t.map(TypeNode::into_type))
+        .collect())
+}
+
+impl TypeNode {
+    fn into_type(self) -> TypeExpr {
+        match self {
+            TypeNode::NamedType { head } => TypeExpr::Named(head.into_token()),
+            TypeNode::ArrayType { head, element } => {
+                TypeExpr::Array(head.into_token(), Box::new(element.into_type()))
+            }
+            TypeNode::FunctionType {
+                head,
+                parameters,
+                result,
+            } => TypeExpr::Function(
+                head.into_token(),
+                parameters.into_iter().map(TypeNode::into_type).collect(),
+                Box::new(result.into_type()),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::parser::parser::parse;
+
+    #[test]
+    fn test_program_json_shape() {
+        let value = serde_json::to_value(parse("let x = -1 + y;")).unwrap();
+
+        let span =
+            |start, end, column| json!({"start": start, "end": end, "line": 1, "column": column});
+        assert_eq!(
+            value,
+            json!({
+                "kind": "Program",
+                "statements": [{
+                    "kind": "LetStatement",
+                    "span": span(0, 3, 1),
+                    "token": "Let",
+                    "literal": "let",
+                    "name": {
+                        "kind": "Identifier",
+                        "span": span(4, 5, 5),
+                        "token": "Ident",
+                        "literal": "x",
+                        "value": "x",
+                    },
+                    "annotation": null,
+                    "value": {
+                        "kind": "InfixExpression",
+                        "span": span(11, 12, 12),
+                        "token": "Plus",
+                        "literal": "+",
+                        "left": {
+                            "kind": "PrefixExpression",
+                            "span": span(8, 9, 9),
+                            "token": "MINUS",
+                            "literal": "-",
+                            "operator": "-",
+                            "right": {
+                                "kind": "IntegerLiteral",
+                                "span": span(9, 10, 10),
+                                "token": "Int",
+                                "literal": "1",
+                                "value": "1",
+                            },
+                        },
+                        "operator": "+",
+                        "right": {
+                            "kind": "Identifier",
+                            "span": span(13, 14, 14),
+                            "token": "Ident",
+                            "literal": "y",
+                            "value": "y",
+                        },
+                    },
+                }],
+            })
+        );
+    }
+
+    #[test]
+    fn test_program_json_round_trip() {
+        let input = r#"import "lib.mk" as lib;
+export let f = fn(a, b) { return a ** b; };
+let big = 0xFFFF_FFFF_FFFF_FFFF_FF;
+let m = macro(x) { quote(unquote(x) * 2.5) };
+for (i in [1, 2][0]) { if (!i) { break; } else { continue; } }
+while (true) { xs[0] += lib.n; }
+try { throw "no ${f(1, 2)}"; } catch (e) { e } finally { 1 }
+let g: fn(int, [T]) -> [T] = fn(n: int, xs) -> [T] { xs };"#;
+        let program = parse(input);
+
+        let json = serde_json::to_string(&program).unwrap();
+        let back: Program = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(back.to_string(), program.to_string());
+        assert_eq!(serde_json::to_string(&back).unwrap(), json);
+    }
+
+    #[test]
+    fn test_program_json_rejects_misplaced_nodes() {
+        let json = r#"{"kind": "Program", "statements": [{
+            "kind": "LetStatement",
+            "span": {"start": 0, "end": 3, "line": 1, "column": 1},
+            "token": "Let", "literal": "let",
+            "name": {
+                "kind": "Boolean",
+                "span": {"start": 4, "end": 8, "line": 1, "column": 5},
+                "token": "True", "literal": "true", "value": true
+            },
+            "value": null
+        }]}"#;
+
+        let err = serde_json::from_str::<Program>(json).unwrap_err();
+        assert!(
+            err.to_string().contains("expected Identifier, got Boolean"),
+            "{}",
+            err
+        );
+    }
+}
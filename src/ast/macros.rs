@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::ast::ast::{LetStatement, MacroLiteral, Program};
+
+// Removes every top-level `let name = macro(...) { ... };` from the program
+// and returns the macros by name. Macros defined inside blocks or function
+// bodies are left in place; like the book, only top-level definitions are
+// visible to expansion.
+pub fn define_macros(program: &mut Program) -> HashMap<String, Rc<MacroLiteral>> {
+    let mut macros = HashMap::new();
+    program.statements.retain(|stmt| {
+        let Some(let_stmt) = stmt.as_any().downcast_ref::<LetStatement>() else {
+            return true;
+        };
+        match macro_definition(let_stmt) {
+            Some(macro_lit) => {
+                macros.insert(let_stmt.name.value.clone(), macro_lit);
+                false
+            }
+            None => true,
+        }
+    });
+    macros
+}
+
+fn macro_definition(let_stmt: &LetStatement) -> Option<Rc<MacroLiteral>> {
+    let value = let_stmt.value.as_ref()?;
+    let macro_lit = value.as_any().downcast_ref::<MacroLiteral>()?;
+    Some(Rc::new(MacroLiteral {
+        token: macro_lit.token.clone(),
+        parameters: macro_lit.parameters.clone(),
+        body: Rc::clone(&macro_lit.body),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::lexer::{Lexer, LexerTrait};
+    use crate::parser::parser::Parser;
+
+    #[test]
+    fn test_define_macros() {
+        let input = "let number = 1;
+let function = fn(x, y) { x + y };
+let mymacro = macro(x, y) { x + y; };
+fn() { let inner = macro() { 1 }; };";
+        let mut p = Parser::new(Lexer::new(input));
+        let mut program = p.parse_program();
+        assert!(p.errors().is_empty());
+
+        let macros = define_macros(&mut program);
+
+        assert_eq!(program.statements.len(), 3);
+        assert!(program
+            .to_string()
+            .starts_with("let number = 1;let function = "));
+        assert_eq!(macros.len(), 1);
+
+        let mymacro = macros.get("mymacro").expect("macro not defined");
+        let params: Vec<&str> = mymacro
+            .parameters
+            .iter()
+            .map(|p| p.value.as_str())
+            .collect();
+        assert_eq!(params, ["x", "y"]);
+        assert_eq!(mymacro.body.to_string(), "x + y");
+    }
+}
@@ -1 +1,9 @@
 pub mod ast;
+pub mod fold;
+#[cfg(feature = "serde")]
+pub mod json;
+pub mod macros;
+pub mod modify;
+pub mod optimize;
+pub mod printer;
+pub mod visit;
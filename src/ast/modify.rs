@@ -0,0 +1,93 @@
+use std::rc::Rc;
+
+use crate::ast::ast::{Expression, Program, Statement};
+use crate::ast::fold::{self, Fold};
+
+pub type Modifier<'a> = dyn FnMut(Rc<dyn Expression>) -> Rc<dyn Expression> + 'a;
+
+// Rewrites every expression in the program bottom-up: the children of an
+// expression are modified first and the modifier then sees the rebuilt
+// node. Binding names are not expressions and are left alone; see
+// fold::Fold.
+pub fn modify(program: Program, modifier: &mut Modifier) -> Program {
+    Modify(modifier).fold_program(program)
+}
+
+pub fn modify_statement(stmt: Rc<dyn Statement>, modifier: &mut Modifier) -> Rc<dyn Statement> {
+    Modify(modifier).fold_statement(stmt)
+}
+
+pub fn modify_expression(expr: Rc<dyn Expression>, modifier: &mut Modifier) -> Rc<dyn Expression> {
+    Modify(modifier).fold_expression(expr)
+}
+
+struct Modify<'a, 'b>(&'a mut Modifier<'b>);
+
+impl Fold for Modify<'_, '_> {
+    fn fold_expression(&mut self, expr: Rc<dyn Expression>) -> Rc<dyn Expression> {
+        let rebuilt = fold::fold_expression(self, expr);
+        (self.0)(rebuilt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::ast::IntegerLiteral;
+    use crate::lexer::token::{Token, TokenType};
+    use crate::object::integer::Integer;
+    use crate::parser::parser::parse;
+
+    fn turn_one_into_two(expr: Rc<dyn Expression>) -> Rc<dyn Expression> {
+        match expr.as_any().downcast_ref::<IntegerLiteral>() {
+            Some(int) if int.value == Integer::Small(1) => Rc::new(IntegerLiteral {
+                token: Token::new(TokenType::Int, "2"),
+                value: Integer::Small(2),
+            }),
+            _ => expr,
+        }
+    }
+
+    #[test]
+    fn test_modify() {
+        let tests = [
+            ("1", "2"),
+            ("1 + 2", "2 + 2"),
+            ("-1", "-2"),
+            ("x = 1", "x = 2"),
+            ("a[1]", "a[2]"),
+            ("[1, 1]", "[2, 2]"),
+            ("f(1)(1)", "f(2)(2)"),
+            ("a[1].x", "a[2].x"),
+            ("if (1) { 1 } else { 1 }", "if (2) { 2 } else { 2 }"),
+            ("fn(x) { return 1; }", "fn(x) { return 2; }"),
+            ("macro(x) { 1 }", "macro(x) { 2 }"),
+            ("\"n=${1}\"", "\"n=${2}\""),
+            ("let x = 1;", "let x = 2;"),
+            ("export let x = 1;", "export let x = 2;"),
+            ("throw 1;", "throw 2;"),
+            (
+                "try { 1 } catch (e) { 1 } finally { 1 }",
+                "try { 2 } catch (e) { 2 } finally { 2 }",
+            ),
+            ("while (1) { 1 }", "while (2) { 2 }"),
+            ("for (x in [1]) { 1 }", "for (x in [2]) { 2 }"),
+        ];
+
+        for (input, expected) in tests {
+            let modified = modify(parse(input), &mut turn_one_into_two);
+            assert_eq!(modified.to_string(), expected, "input={}", input);
+        }
+    }
+
+    #[test]
+    fn test_modify_visits_children_first() {
+        let mut seen = Vec::new();
+        modify(parse("f(a + b, -c)"), &mut |expr| {
+            seen.push(expr.to_string());
+            expr
+        });
+
+        assert_eq!(seen, ["f", "a", "b", "a + b", "c", "-c", "f(a + b, -c)"]);
+    }
+}
@@ -0,0 +1,945 @@
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use crate::ast::ast::{
+    AssignExpression, BlockStatement, Boolean, BreakStatement, CallExpression, ContinueStatement,
+    ExportStatement, Expression, ExpressionStatement, ForStatement, FunctionLiteral, Identifier,
+    IfExpression, ImportStatement, InfixExpression, IntegerLiteral, InterpolatedString,
+    InterpolationPart, LetStatement, MacroLiteral, PrefixExpression, Program, ReturnStatements,
+    Statement, StringLiteral, ThrowStatement, TryStatement, WhileStatement,
+};
+use crate::ast::fold::{self, Fold};
+use crate::ast::printer;
+use crate::ast::visit::{self, Visitor};
+use crate::lexer::token::{Span, Token, TokenType, TokenValue};
+use crate::object::integer::Integer;
+
+// Which rewrites optimize() runs. Every pass keeps the program's meaning:
+// the same output and the same value, only with less work left to do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Passes {
+    // `60 * 60 * 24` becomes `86400`, `"a" + "b"` becomes `"ab"`
+    pub fold_constants: bool,
+    // uses of `let n = 3;` become `3` when nothing else binds or assigns n
+    pub inline_constants: bool,
+    // `if (true) { a } else { b }` becomes `a`
+    pub dead_branches: bool,
+    // statements after return, throw, break or continue are dropped
+    pub unreachable_code: bool,
+}
+
+impl Default for Passes {
+    fn default() -> Self {
+        Passes {
+            fold_constants: true,
+            inline_constants: true,
+            dead_branches: true,
+            unreachable_code: true,
+        }
+    }
+}
+
+impl Passes {
+    pub fn none() -> Self {
+        Passes {
+            fold_constants: false,
+            inline_constants: false,
+            dead_branches: false,
+            unreachable_code: false,
+        }
+    }
+}
+
+// One pass can make work for another: inlining hands folding literal
+// operands, and folding hands dead branch elimination literal conditions.
+// The passes run in rounds until none of them changes anything.
+const MAX_ROUNDS: usize = 16;
+
+// Macro bodies and quoted code are data until they are expanded, so none of
+// the passes look inside them.
+pub fn optimize(program: Program, passes: Passes) -> Program {
+    let mut program = program;
+    for _ in 0..MAX_ROUNDS {
+        let mut changed = false;
+        if passes.inline_constants {
+            let mut pass = InlineConstants::new(&program);
+            program = pass.fold_program(program);
+            changed |= pass.changed;
+        }
+        if passes.fold_constants {
+            let mut pass = FoldConstants::default();
+            program = pass.fold_program(program);
+            changed |= pass.changed;
+        }
+        if passes.dead_branches {
+            let mut pass = DeadBranches::default();
+            program = pass.fold_program(program);
+            changed |= pass.changed;
+        }
+        if passes.unreachable_code {
+            let mut pass = UnreachableCode::default();
+            program = pass.fold_program(program);
+            changed |= pass.changed;
+        }
+        if !changed {
+            break;
+        }
+    }
+    program
+}
+
+fn is_opaque(expr: &dyn Expression) -> bool {
+    let any = expr.as_any();
+    if any.is::<MacroLiteral>() {
+        return true;
+    }
+    any.downcast_ref::<CallExpression>().is_some_and(|call| {
+        call.function
+            .as_any()
+            .downcast_ref::<Identifier>()
+            .is_some_and(|ident| ident.value == "quote")
+    })
+}
+
+// The value of a literal, or of an operator applied to literals.
+#[derive(Debug, Clone, PartialEq)]
+enum Constant {
+    Int(Integer),
+    Bool(bool),
+    Str(String),
+}
+
+impl Constant {
+    fn of(expr: &dyn Expression) -> Option<Constant> {
+        let any = expr.as_any();
+        if let Some(lit) = any.downcast_ref::<IntegerLiteral>() {
+            Some(Constant::Int(lit.value.clone()))
+        } else if let Some(lit) = any.downcast_ref::<Boolean>() {
+            Some(Constant::Bool(lit.value))
+        } else {
+            any.downcast_ref::<StringLiteral>()
+                .map(|lit| Constant::Str(lit.value.clone()))
+        }
+    }
+
+    // How the value reads inside an interpolated string.
+    fn text(&self) -> String {
+        match self {
+            Constant::Int(n) => n.to_string(),
+            Constant::Bool(b) => b.to_string(),
+            Constant::Str(s) => s.clone(),
+        }
+    }
+
+    fn expression(self, span: Span) -> Rc<dyn Expression> {
+        match self {
+            Constant::Int(value) => {
+                let mut token = Token::new(TokenType::Int, &value.to_string()).with_span(span);
+                token.value = Some(TokenValue::Int(value.clone()));
+                Rc::new(IntegerLiteral { token, value })
+            }
+            Constant::Bool(value) => {
+                let (type_, literal) = if value {
+                    (TokenType::True, "true")
+                } else {
+                    (TokenType::False, "false")
+                };
+                Rc::new(Boolean {
+                    token: Token::new(type_, literal).with_span(span),
+                    value,
+                })
+            }
+            Constant::Str(value) => Rc::new(StringLiteral {
+                token: Token::new(TokenType::String, &value).with_span(span),
+                value,
+            }),
+        }
+    }
+}
+
+fn unary(operator: &str, right: Constant) -> Option<Constant> {
+    match (operator, right) {
+        ("-", Constant::Int(n)) => Some(Constant::Int(n.neg())),
+        ("!", Constant::Bool(b)) => Some(Constant::Bool(!b)),
+        _ => None,
+    }
+}
+
+// Folded shifts and powers stay below this many bits; a program that
+// means to compute `2 ** 100000` can do it when it runs.
+const MAX_BITS: u64 = 4096;
+
+// Only the operators whose result is certain: division by zero is left
+// for the program to fail on, as are negative shift amounts and
+// exponents, and floats keep their spelling.
+fn binary(operator: &str, left: Constant, right: Constant) -> Option<Constant> {
+    use Constant::{Bool, Int, Str};
+    let value = match (left, right) {
+        (Int(a), Int(b)) => match operator {
+            "+" => Int(a.add(&b)),
+            "-" => Int(a.sub(&b)),
+            "*" => Int(a.mul(&b)),
+            "/" => Int(a.div(&b)?),
+            "%" => Int(a.rem(&b)?),
+            "&" => Int(a.bitand(&b)),
+            "|" => Int(a.bitor(&b)),
+            "^" => Int(a.bitxor(&b)),
+            "<<" => {
+                let by = amount(&b)?;
+                (a.bits() + u64::from(by) <= MAX_BITS).then(|| Int(a.shl(by)))?
+            }
+            ">>" => Int(a.shr(amount(&b)?)),
+            "**" => {
+                let exponent = amount(&b)?;
+                (a.bits() * u64::from(exponent) <= MAX_BITS).then(|| Int(a.pow(exponent)))?
+            }
+            "<" => Bool(a < b),
+            ">" => Bool(a > b),
+            "<=" => Bool(a <= b),
+            ">=" => Bool(a >= b),
+            "==" => Bool(a == b),
+            "!=" => Bool(a != b),
+            _ => return None,
+        },
+        (Bool(a), Bool(b)) => match operator {
+            "==" => Bool(a == b),
+            "!=" => Bool(a != b),
+            "&&" => Bool(a && b),
+            "||" => Bool(a || b),
+            _ => return None,
+        },
+        (Str(a), Str(b)) => match operator {
+            "+" => Str(a + &b),
+            "==" => Bool(a == b),
+            "!=" => Bool(a != b),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    Some(value)
+}
+
+// A shift amount or exponent, if it is one: not negative, and small.
+fn amount(n: &Integer) -> Option<u32> {
+    match n {
+        Integer::Small(n) => u32::try_from(*n).ok(),
+        Integer::Big(_) => None,
+    }
+}
+
+// The constant an operator expression computes, if its operands are all
+// literals. Literals themselves give None: there is nothing to fold.
+fn evaluate(expr: &dyn Expression) -> Option<Constant> {
+    let any = expr.as_any();
+    if let Some(e) = any.downcast_ref::<PrefixExpression>() {
+        unary(&e.operator, Constant::of(e.right.as_ref())?)
+    } else if let Some(e) = any.downcast_ref::<InfixExpression>() {
+        let left = Constant::of(e.left.as_ref())?;
+        binary(&e.operator, left, Constant::of(e.right.as_ref())?)
+    } else if let Some(e) = any.downcast_ref::<InterpolatedString>() {
+        let mut text = String::new();
+        for part in &e.parts {
+            match part {
+                InterpolationPart::Text(t) => text.push_str(t),
+                InterpolationPart::Expr(e) => text.push_str(&Constant::of(e.as_ref())?.text()),
+            }
+        }
+        Some(Constant::Str(text))
+    } else {
+        None
+    }
+}
+
+#[derive(Default)]
+struct FoldConstants {
+    changed: bool,
+}
+
+impl Fold for FoldConstants {
+    fn fold_expression(&mut self, expr: Rc<dyn Expression>) -> Rc<dyn Expression> {
+        if is_opaque(expr.as_ref()) {
+            return expr;
+        }
+        let expr = fold::fold_expression(self, expr);
+        match evaluate(expr.as_ref()) {
+            Some(value) => {
+                self.changed = true;
+                value.expression(printer::start_span(expr.as_ref()))
+            }
+            None => expr,
+        }
+    }
+}
+
+// Counts how often each name is bound anywhere in the program, and which
+// names are ever assigned to. A name bound once and never assigned means
+// the same value wherever it is visible.
+#[derive(Default)]
+struct Bindings {
+    counts: HashMap<String, usize>,
+    assigned: HashSet<String>,
+}
+
+impl Bindings {
+    fn bind(&mut self, name: &Identifier) {
+        *self.counts.entry(name.value.clone()).or_default() += 1;
+    }
+}
+
+impl Visitor for Bindings {
+    fn visit_let_statement(&mut self, stmt: &LetStatement) {
+        self.bind(&stmt.name);
+        visit::walk_let_statement(self, stmt);
+    }
+
+    fn visit_function_literal(&mut self, expr: &FunctionLiteral) {
+        expr.parameters.iter().for_each(|p| self.bind(p));
+        visit::walk_function_literal(self, expr);
+    }
+
+    fn visit_macro_literal(&mut self, expr: &MacroLiteral) {
+        expr.parameters.iter().for_each(|p| self.bind(p));
+        visit::walk_macro_literal(self, expr);
+    }
+
+    fn visit_for_statement(&mut self, stmt: &ForStatement) {
+        self.bind(&stmt.variable);
+        visit::walk_for_statement(self, stmt);
+    }
+
+    fn visit_try_statement(&mut self, stmt: &TryStatement) {
+        if let Some(param) = &stmt.catch_param {
+            self.bind(param);
+        }
+        visit::walk_try_statement(self, stmt);
+    }
+
+    fn visit_import_statement(&mut self, stmt: &ImportStatement) {
+        self.bind(&stmt.alias);
+    }
+
+    fn visit_assign_expression(&mut self, expr: &AssignExpression) {
+        if let Some(target) = expr.target.as_any().downcast_ref::<Identifier>() {
+            self.assigned.insert(target.value.clone());
+        }
+        visit::walk_assign_expression(self, expr);
+    }
+}
+
+// Replaces uses of let-bound literals with the literal. Only uses after the
+// let and inside its block are replaced; the let itself stays, since an
+// importing module or a later REPL line may still refer to the name.
+struct InlineConstants {
+    candidates: HashSet<String>,
+    scopes: Vec<HashMap<String, Constant>>,
+    changed: bool,
+}
+
+impl InlineConstants {
+    fn new(program: &Program) -> Self {
+        let mut bindings = Bindings::default();
+        bindings.visit_program(program);
+        let candidates = bindings
+            .counts
+            .into_iter()
+            .filter(|(name, count)| *count == 1 && !bindings.assigned.contains(name))
+            .map(|(name, _)| name)
+            .collect();
+        InlineConstants {
+            candidates,
+            scopes: Vec::new(),
+            changed: false,
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Constant> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+}
+
+impl Fold for InlineConstants {
+    fn fold_program(&mut self, program: Program) -> Program {
+        self.scopes.push(HashMap::new());
+        let program = fold::fold_program(self, program);
+        self.scopes.pop();
+        program
+    }
+
+    fn fold_block_statement(&mut self, block: &BlockStatement) -> Rc<BlockStatement> {
+        self.scopes.push(HashMap::new());
+        let block = fold::fold_block_statement(self, block);
+        self.scopes.pop();
+        block
+    }
+
+    fn fold_let_statement(&mut self, stmt: &LetStatement) -> Rc<LetStatement> {
+        let stmt = fold::fold_let_statement(self, stmt);
+        let value = stmt.value.as_ref().and_then(|v| Constant::of(v.as_ref()));
+        if let Some(value) = value {
+            if self.candidates.contains(&stmt.name.value) {
+                let scope = self.scopes.last_mut().expect("no scope");
+                scope.insert(stmt.name.value.clone(), value);
+            }
+        }
+        stmt
+    }
+
+    fn fold_expression(&mut self, expr: Rc<dyn Expression>) -> Rc<dyn Expression> {
+        if is_opaque(expr.as_ref()) {
+            return expr;
+        }
+        fold::fold_expression(self, expr)
+    }
+
+    fn fold_identifier(&mut self, ident: &Identifier) -> Rc<dyn Expression> {
+        match self.lookup(&ident.value).cloned() {
+            Some(value) => {
+                self.changed = true;
+                value.expression(ident.token.span)
+            }
+            None => Rc::new(Identifier {
+                token: ident.token.clone(),
+                value: ident.value.clone(),
+            }),
+        }
+    }
+}
+
+fn condition(expr: &dyn Expression) -> Option<bool> {
+    expr.as_any().downcast_ref::<Boolean>().map(|b| b.value)
+}
+
+// The block's single expression, if that is all it holds.
+fn sole_expression(block: &BlockStatement) -> Option<Rc<dyn Expression>> {
+    match block.statements.as_slice() {
+        [stmt] => stmt
+            .as_any()
+            .downcast_ref::<ExpressionStatement>()?
+            .expr
+            .clone(),
+        _ => None,
+    }
+}
+
+// What is left of a statement whose condition is a literal.
+enum Taken {
+    Branch(Rc<BlockStatement>),
+    Nothing,
+}
+
+fn taken(stmt: &dyn Statement) -> Option<Taken> {
+    let any = stmt.as_any();
+    if let Some(s) = any.downcast_ref::<WhileStatement>() {
+        return match condition(s.condition.as_ref())? {
+            true => None,
+            false => Some(Taken::Nothing),
+        };
+    }
+    let expr = any.downcast_ref::<ExpressionStatement>()?.expr.as_ref()?;
+    let e = expr.as_any().downcast_ref::<IfExpression>()?;
+    Some(match (condition(e.condition.as_ref())?, &e.alternative) {
+        (true, _) => Taken::Branch(Rc::clone(&e.consequence)),
+        (false, Some(alternative)) => Taken::Branch(Rc::clone(alternative)),
+        (false, None) => Taken::Nothing,
+    })
+}
+
+// A branch can take the place of its if statement unless that would move
+// one of its lets into the enclosing scope, or change the value of the
+// enclosing block when the if was its last statement.
+fn can_splice(block: &BlockStatement, last: bool) -> bool {
+    let binds = block.statements.iter().any(|stmt| {
+        let any = stmt.as_any();
+        any.is::<LetStatement>() || any.is::<ExportStatement>()
+    });
+    let keeps_value = !last
+        || block
+            .statements
+            .last()
+            .is_some_and(|stmt| stmt.as_any().is::<ExpressionStatement>());
+    !binds && keeps_value
+}
+
+#[derive(Default)]
+struct DeadBranches {
+    changed: bool,
+}
+
+impl DeadBranches {
+    fn statements(&mut self, stmts: &[Rc<dyn Statement>]) -> Vec<Rc<dyn Statement>> {
+        let mut out = Vec::with_capacity(stmts.len());
+        for (i, stmt) in stmts.iter().enumerate() {
+            let stmt = self.fold_statement(Rc::clone(stmt));
+            let last = i + 1 == stmts.len();
+            match taken(stmt.as_ref()) {
+                Some(Taken::Branch(block)) if can_splice(&block, last) => {
+                    self.changed = true;
+                    out.extend(block.statements.iter().cloned());
+                }
+                Some(Taken::Nothing) if !last => self.changed = true,
+                _ => out.push(stmt),
+            }
+        }
+        out
+    }
+}
+
+impl Fold for DeadBranches {
+    fn fold_program(&mut self, program: Program) -> Program {
+        Program {
+            statements: self.statements(&program.statements),
+        }
+    }
+
+    fn fold_block_statement(&mut self, block: &BlockStatement) -> Rc<BlockStatement> {
+        Rc::new(BlockStatement {
+            token: block.token.clone(),
+            statements: self.statements(&block.statements),
+        })
+    }
+
+    fn fold_expression(&mut self, expr: Rc<dyn Expression>) -> Rc<dyn Expression> {
+        if is_opaque(expr.as_ref()) {
+            return expr;
+        }
+        fold::fold_expression(self, expr)
+    }
+
+    // In expression position a branch can only replace the if when it is a
+    // single expression; otherwise the dead branch is dropped.
+    fn fold_if_expression(&mut self, expr: &IfExpression) -> Rc<dyn Expression> {
+        let rebuilt = fold::fold_if_expression(self, expr);
+        let e = rebuilt
+            .as_any()
+            .downcast_ref::<IfExpression>()
+            .expect("folding an if gives an if");
+        let chosen = match condition(e.condition.as_ref()) {
+            Some(true) => Some(&e.consequence),
+            Some(false) => e.alternative.as_ref(),
+            None => return rebuilt,
+        };
+        if let Some(value) = chosen.and_then(|block| sole_expression(block)) {
+            self.changed = true;
+            return value;
+        }
+        match (condition(e.condition.as_ref()), &e.alternative) {
+            (Some(true), Some(_)) => {
+                self.changed = true;
+                Rc::new(IfExpression {
+                    token: e.token.clone(),
+                    condition: Rc::clone(&e.condition),
+                    consequence: Rc::clone(&e.consequence),
+                    alternative: None,
+                })
+            }
+            _ => rebuilt,
+        }
+    }
+}
+
+#[derive(Default)]
+struct UnreachableCode {
+    changed: bool,
+}
+
+impl UnreachableCode {
+    fn statements(&mut self, stmts: &[Rc<dyn Statement>]) -> Vec<Rc<dyn Statement>> {
+        let mut out = Vec::with_capacity(stmts.len());
+        for stmt in stmts {
+            let stmt = self.fold_statement(Rc::clone(stmt));
+            let any = stmt.as_any();
+            let jumps = any.is::<ReturnStatements>()
+                || any.is::<ThrowStatement>()
+                || any.is::<BreakStatement>()
+                || any.is::<ContinueStatement>();
+            out.push(stmt);
+            if jumps {
+                self.changed |= out.len() < stmts.len();
+                break;
+            }
+        }
+        out
+    }
+}
+
+impl Fold for UnreachableCode {
+    fn fold_program(&mut self, program: Program) -> Program {
+        Program {
+            statements: self.statements(&program.statements),
+        }
+    }
+
+    fn fold_block_statement(&mut self, block: &BlockStatement) -> Rc<BlockStatement> {
+        Rc::new(BlockStatement {
+            token: block.token.clone(),
+            statements: self.statements(&block.statements),
+        })
+    }
+
+    fn fold_expression(&mut self, expr: Rc<dyn Expression>) -> Rc<dyn Expression> {
+        if is_opaque(expr.as_ref()) {
+            return expr;
+        }
+        fold::fold_expression(self, expr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::format::format_program;
+    use crate::parser::parser::parse;
+
+    // A reference evaluator for the part of the language the passes touch,
+    // so each rewrite can be checked to leave the program's output and
+    // value as they were. Null is None.
+    #[derive(Default)]
+    struct Eval {
+        scopes: Vec<HashMap<String, Option<Constant>>>,
+        output: Vec<String>,
+        jump: Option<Flow>,
+    }
+
+    enum Flow {
+        Next(Option<Constant>),
+        Return(Option<Constant>),
+        Break,
+        Continue,
+    }
+
+    impl Eval {
+        fn run(program: &Program) -> (Option<Constant>, Vec<String>) {
+            let mut eval = Eval::default();
+            let value = match eval.block(&program.statements) {
+                Flow::Next(value) | Flow::Return(value) => value,
+                Flow::Break | Flow::Continue => panic!("jump outside a loop"),
+            };
+            (value, eval.output)
+        }
+
+        fn block(&mut self, stmts: &[Rc<dyn Statement>]) -> Flow {
+            self.scopes.push(HashMap::new());
+            let mut flow = Flow::Next(None);
+            for stmt in stmts {
+                flow = self.statement(stmt.as_ref());
+                if let Some(jump) = self.jump.take() {
+                    flow = jump;
+                }
+                if !matches!(flow, Flow::Next(_)) {
+                    break;
+                }
+            }
+            self.scopes.pop();
+            flow
+        }
+
+        fn statement(&mut self, stmt: &dyn Statement) -> Flow {
+            let any = stmt.as_any();
+            if let Some(s) = any.downcast_ref::<LetStatement>() {
+                let value = self.expression(s.value.as_ref().unwrap().as_ref());
+                let scope = self.scopes.last_mut().unwrap();
+                scope.insert(s.name.value.clone(), value);
+                Flow::Next(None)
+            } else if let Some(s) = any.downcast_ref::<ExpressionStatement>() {
+                Flow::Next(self.expression(s.expr.as_ref().unwrap().as_ref()))
+            } else if let Some(s) = any.downcast_ref::<ReturnStatements>() {
+                Flow::Return(s.value.as_ref().and_then(|v| self.expression(v.as_ref())))
+            } else if let Some(s) = any.downcast_ref::<WhileStatement>() {
+                while truthy(&self.expression(s.condition.as_ref())) {
+                    match self.block(&s.body.statements) {
+                        Flow::Return(value) => return Flow::Return(value),
+                        Flow::Break => break,
+                        Flow::Next(_) | Flow::Continue => {}
+                    }
+                }
+                Flow::Next(None)
+            } else if any.is::<BreakStatement>() {
+                Flow::Break
+            } else if any.is::<ContinueStatement>() {
+                Flow::Continue
+            } else {
+                panic!("unsupported statement: {}", stmt.token_literal())
+            }
+        }
+
+        fn expression(&mut self, expr: &dyn Expression) -> Option<Constant> {
+            let any = expr.as_any();
+            if let Some(value) = Constant::of(expr) {
+                Some(value)
+            } else if let Some(ident) = any.downcast_ref::<Identifier>() {
+                let scope = self.scopes.iter().rev().find_map(|s| s.get(&ident.value));
+                scope.expect("unbound name").clone()
+            } else if let Some(e) = any.downcast_ref::<PrefixExpression>() {
+                let right = self.expression(e.right.as_ref()).unwrap();
+                unary(&e.operator, right)
+            } else if let Some(e) = any.downcast_ref::<InfixExpression>() {
+                let left = self.expression(e.left.as_ref()).unwrap();
+                let right = self.expression(e.right.as_ref()).unwrap();
+                binary(&e.operator, left, right)
+            } else if let Some(e) = any.downcast_ref::<InterpolatedString>() {
+                let mut text = String::new();
+                for part in &e.parts {
+                    match part {
+                        InterpolationPart::Text(t) => text.push_str(t),
+                        InterpolationPart::Expr(e) => {
+                            text.push_str(&self.expression(e.as_ref()).unwrap().text())
+                        }
+                    }
+                }
+                Some(Constant::Str(text))
+            } else if let Some(e) = any.downcast_ref::<IfExpression>() {
+                if truthy(&self.expression(e.condition.as_ref())) {
+                    self.value(&e.consequence)
+                } else {
+                    e.alternative.as_ref().and_then(|block| self.value(block))
+                }
+            } else if let Some(e) = any.downcast_ref::<AssignExpression>() {
+                let target = e.target.as_any().downcast_ref::<Identifier>().unwrap();
+                let value = self.expression(e.value.as_ref());
+                let mut scopes = self.scopes.iter_mut().rev();
+                let slot = scopes.find_map(|s| s.get_mut(&target.value)).unwrap();
+                *slot = value.clone();
+                value
+            } else if let Some(e) = any.downcast_ref::<CallExpression>() {
+                assert_eq!(e.function.to_string(), "puts");
+                let value = self.expression(e.arguments[0].as_ref());
+                self.output
+                    .push(value.map_or("null".to_string(), |v| v.text()));
+                None
+            } else {
+                panic!("unsupported expression: {}", expr.token_literal())
+            }
+        }
+
+        // A jump out of an if is held until its statement finishes.
+        fn value(&mut self, block: &BlockStatement) -> Option<Constant> {
+            match self.block(&block.statements) {
+                Flow::Next(value) => value,
+                jump => {
+                    self.jump = Some(jump);
+                    None
+                }
+            }
+        }
+    }
+
+    fn truthy(value: &Option<Constant>) -> bool {
+        !matches!(value, None | Some(Constant::Bool(false)))
+    }
+
+    const PROGRAMS: &[&str] = &[
+        "let x = 2 * 3; let y = x + 1; y * y",
+        "let n = 10; let total = 0; let i = 0; while (i < n) { total = total + i; i = i + 1; } total",
+        "if (1 < 2) { \"yes\" } else { \"no\" }",
+        "let a = 5; if (a > 3) { puts(\"big\"); } let b = a - 1; b",
+        "let f = 7 % 3; if (false) { puts(\"never\"); } f",
+        "\"n=${1 + 2}, ok=${!false}\"",
+        "let s = \"a\" + \"b\"; puts(s == \"ab\"); return s + \"!\"; puts(\"after\");",
+        "if (true) { let hidden = 1; puts(hidden); } 2",
+        "-(3 - 10) * 2 / 4",
+        "let i = 0; while (i < 5) { i = i + 1; if (i == 3) { break; puts(i); } puts(i); } i",
+        "let flag = true; if (flag && 1 == 1) { puts(1); puts(2); } else { puts(3); } 4",
+        "let m = 12; let n = if (m > 10) { m - 10 } else { m }; n",
+        "let deep = if (2 > 1) { puts(0); 1 } else { 2 }; deep",
+        "while (false) { puts(1); } if (false) { puts(2) }",
+    ];
+
+    #[test]
+    fn test_passes_keep_meaning() {
+        let single = [
+            Passes {
+                fold_constants: true,
+                ..Passes::none()
+            },
+            Passes {
+                inline_constants: true,
+                ..Passes::none()
+            },
+            Passes {
+                dead_branches: true,
+                ..Passes::none()
+            },
+            Passes {
+                unreachable_code: true,
+                ..Passes::none()
+            },
+        ];
+        for input in PROGRAMS {
+            let expected = Eval::run(&parse(input));
+            for passes in single.iter().chain([&Passes::default()]) {
+                let optimized = optimize(parse(input), *passes);
+                assert_eq!(
+                    Eval::run(&optimized),
+                    expected,
+                    "input={} passes={:?} optimized={}",
+                    input,
+                    passes,
+                    optimized
+                );
+            }
+        }
+    }
+
+    // The expected code is parsed too, and both sides are compared as the
+    // formatter prints them, one statement per line.
+    fn assert_optimized(passes: Passes, tests: &[(&str, &str)]) {
+        for (input, expected) in tests {
+            let optimized = optimize(parse(input), passes);
+            assert_eq!(
+                format_program(&optimized),
+                format_program(&parse(expected)),
+                "input={}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_fold_constants() {
+        let passes = Passes {
+            fold_constants: true,
+            ..Passes::none()
+        };
+        assert_optimized(
+            passes,
+            &[
+                ("60 * 60 * 24", "86400"),
+                ("1 + 2 * 3 - 4", "3"),
+                ("-(2 - 5)", "3"),
+                ("9223372036854775807 + 1", "9223372036854775808"),
+                ("7 / 2; 7 % 2; 2 <= 3", "3; 1; true"),
+                ("7 / 0; 7 % 0", "7 / 0; 7 % 0"),
+                ("!true == false && (1 < 2 || false)", "true"),
+                ("\"a\" + \"b\" == \"ab\"", "true"),
+                ("\"${1 + 1} is ${\"two\"}\"", "\"2 is two\""),
+                ("\"${x}!\"", "\"${x}!\""),
+                ("x + 2 * 3", "x + 6"),
+                (
+                    "1.5 + 1.5; 2.0 ** 3; 1 + true",
+                    "1.5 + 1.5; 2.0 ** 3; 1 + true",
+                ),
+                ("6 & 3; 6 | 3; 6 ^ 3; -6 & 3", "2; 7; 5; 2"),
+                (
+                    "1 << 3; 1 << 64; -9 >> 1; 2 ** 10; 3 ** 0",
+                    "8; 18446744073709551616; -5; 1024; 1",
+                ),
+                (
+                    "1 << -1; 2 ** -1; 1 << 5000; 2 ** 5000; 1 >> 99999999999",
+                    "1 << -1; 2 ** -1; 1 << 5000; 2 ** 5000; 1 >> 99999999999",
+                ),
+                (
+                    "let f = fn() { return 1 + 1; }",
+                    "let f = fn() { return 2; };",
+                ),
+                (
+                    "let m = macro() { 1 + 1 }; quote(1 + 1)",
+                    "let m = macro() { 1 + 1 }; quote(1 + 1)",
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_inline_constants() {
+        let passes = Passes {
+            inline_constants: true,
+            ..Passes::none()
+        };
+        assert_optimized(
+            passes,
+            &[
+                ("let n = 3; n * n", "let n = 3; 3 * 3"),
+                (
+                    "let s = \"hi\"; let f = fn() { s }",
+                    "let s = \"hi\"; let f = fn() { \"hi\" };",
+                ),
+                // not a literal
+                ("let n = f(); n", "let n = f(); n"),
+                // bound twice, or assigned to
+                (
+                    "let n = 1; let g = fn(n) { n }; n",
+                    "let n = 1; let g = fn(n) { n }; n",
+                ),
+                ("let n = 1; n = 2; n", "let n = 1; n = 2; n"),
+                // used before the let, or outside its block
+                (
+                    "let f = fn() { n }; let n = 1;",
+                    "let f = fn() { n }; let n = 1;",
+                ),
+                ("if (c) { let n = 1; n } n", "if (c) { let n = 1; 1 } n"),
+                ("let n = 1; quote(n)", "let n = 1; quote(n)"),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_dead_branches() {
+        let passes = Passes {
+            dead_branches: true,
+            ..Passes::none()
+        };
+        assert_optimized(
+            passes,
+            &[
+                ("if (true) { 1 } else { 2 }", "1"),
+                ("if (false) { 1 } else { 2 }", "2"),
+                ("if (false) { 1 }", "if (false) { 1 }"),
+                ("if (false) { 1 } x", "x"),
+                ("if (true) { a(); b() } x", "a(); b(); x"),
+                ("if (false) { a() } else { b(); c() }", "b(); c()"),
+                (
+                    "let v = if (true) { a(); b } else { c }",
+                    "let v = if (true) { a(); b };",
+                ),
+                (
+                    "if (true) { let n = 1; n } x",
+                    "if (true) { let n = 1; n } x",
+                ),
+                ("while (false) { a() } x", "x"),
+                ("if (c) { 1 } else { 2 }", "if (c) { 1 } else { 2 }"),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_unreachable_code() {
+        let passes = Passes {
+            unreachable_code: true,
+            ..Passes::none()
+        };
+        assert_optimized(
+            passes,
+            &[
+                ("fn() { return 1; a(); }", "fn() { return 1; }"),
+                ("fn() { throw \"e\"; a(); }", "fn() { throw \"e\"; }"),
+                ("while (c) { break; a(); }", "while (c) { break; }"),
+                (
+                    "while (c) { a(); continue; b() }",
+                    "while (c) { a(); continue; }",
+                ),
+                (
+                    "fn() { if (c) { return 1; } a() }",
+                    "fn() { if (c) { return 1; } a() }",
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_passes_feed_each_other() {
+        assert_optimized(
+            Passes::default(),
+            &[
+                (
+                    "let debug = false; let limit = 2 * 50; if (debug) { puts(limit) } limit - 1",
+                    "let debug = false; let limit = 100; 99",
+                ),
+                (
+                    "let f = fn() { if (1 < 2) { return 1; } return 2; }",
+                    "let f = fn() { return 1; };",
+                ),
+            ],
+        );
+        assert_optimized(Passes::none(), &[("let n = 1 + 1; n", "let n = 1 + 1; n")]);
+    }
+}
@@ -0,0 +1,511 @@
+use std::fmt::Write;
+use std::rc::Rc;
+
+use crate::ast::ast::{
+    escape_string, ArrayLiteral, AssignExpression, BlockStatement, Boolean, BreakStatement,
+    CallExpression, ContinueStatement, ExportStatement, Expression, ExpressionStatement,
+    FloatLiteral, ForStatement, FunctionLiteral, Identifier, IfExpression, ImportStatement,
+    IndexExpression, InfixExpression, IntegerLiteral, InterpolatedString, InterpolationPart,
+    LetStatement, MacroLiteral, MemberExpression, Node, PrefixExpression, Program,
+    ReturnStatements, Statement, StringLiteral, ThrowStatement, TryStatement, WhileStatement,
+};
+use crate::ast::visit::{self, Visitor};
+use crate::lexer::token::{Span, Token};
+
+// Prints the program like Display, but with every operator application
+// wrapped in parentheses so the parsed grouping is visible:
+// `a + b * c - d` prints as `((a + (b * c)) - d)`.
+pub fn parenthesize(program: &Program) -> String {
+    let mut p = Parenthesize::default();
+    p.visit_program(program);
+    p.out
+}
+
+// One line per node, indented by depth, with the node kind, what the node
+// names or holds, and where its first token starts:
+//
+//   LetStatement x @1:1
+//     InfixExpression + @1:11
+//       IntegerLiteral 1 @1:9
+//       IntegerLiteral 2 @1:13
+pub fn tree(program: &Program) -> String {
+    let mut t = Tree::default();
+    t.visit_program(program);
+    t.out
+}
+
+#[derive(Default)]
+struct Parenthesize {
+    out: String,
+}
+
+impl Parenthesize {
+    fn expr(&mut self, expr: &dyn Expression) {
+        self.visit_expression(expr);
+    }
+
+    fn list(&mut self, exprs: &[Rc<dyn Expression>]) {
+        for (i, expr) in exprs.iter().enumerate() {
+            if i > 0 {
+                self.out.push_str(", ");
+            }
+            self.expr(expr.as_ref());
+        }
+    }
+
+    fn names(&mut self, names: &[Rc<Identifier>]) {
+        let names: Vec<&str> = names.iter().map(|n| n.value.as_str()).collect();
+        self.out.push_str(&names.join(", "));
+    }
+
+    fn braced(&mut self, block: &BlockStatement) {
+        self.out.push_str("{ ");
+        self.visit_block_statement(block);
+        self.out.push_str(" }");
+    }
+}
+
+impl Visitor for Parenthesize {
+    fn visit_let_statement(&mut self, stmt: &LetStatement) {
+        write!(self.out, "let {}", stmt.name.value).unwrap();
+        if let Some(annotation) = &stmt.annotation {
+            write!(self.out, ": {}", annotation).unwrap();
+        }
+        self.out.push_str(" = ");
+        visit::walk_let_statement(self, stmt);
+        self.out.push(';');
+    }
+
+    fn visit_return_statement(&mut self, stmt: &ReturnStatements) {
+        self.out.push_str("return ");
+        visit::walk_return_statement(self, stmt);
+        self.out.push(';');
+    }
+
+    fn visit_throw_statement(&mut self, stmt: &ThrowStatement) {
+        self.out.push_str("throw ");
+        visit::walk_throw_statement(self, stmt);
+        self.out.push(';');
+    }
+
+    fn visit_try_statement(&mut self, stmt: &TryStatement) {
+        self.out.push_str("try ");
+        self.braced(&stmt.block);
+        if let Some(block) = &stmt.catch_block {
+            self.out.push_str(" catch ");
+            if let Some(param) = &stmt.catch_param {
+                write!(self.out, "({}) ", param.value).unwrap();
+            }
+            self.braced(block);
+        }
+        if let Some(block) = &stmt.finally_block {
+            self.out.push_str(" finally ");
+            self.braced(block);
+        }
+    }
+
+    fn visit_while_statement(&mut self, stmt: &WhileStatement) {
+        self.out.push_str("while (");
+        self.expr(stmt.condition.as_ref());
+        self.out.push_str(") ");
+        self.braced(&stmt.body);
+    }
+
+    fn visit_for_statement(&mut self, stmt: &ForStatement) {
+        write!(self.out, "for ({} in ", stmt.variable.value).unwrap();
+        self.expr(stmt.iterable.as_ref());
+        self.out.push_str(") ");
+        self.braced(&stmt.body);
+    }
+
+    fn visit_break_statement(&mut self, _stmt: &BreakStatement) {
+        self.out.push_str("break;");
+    }
+
+    fn visit_continue_statement(&mut self, _stmt: &ContinueStatement) {
+        self.out.push_str("continue;");
+    }
+
+    fn visit_import_statement(&mut self, stmt: &ImportStatement) {
+        write!(self.out, "{}", stmt).unwrap();
+    }
+
+    fn visit_export_statement(&mut self, stmt: &ExportStatement) {
+        self.out.push_str("export ");
+        visit::walk_export_statement(self, stmt);
+    }
+
+    fn visit_identifier(&mut self, ident: &Identifier) {
+        self.out.push_str(&ident.value);
+    }
+
+    fn visit_integer_literal(&mut self, lit: &IntegerLiteral) {
+        write!(self.out, "{}", lit).unwrap();
+    }
+
+    fn visit_float_literal(&mut self, lit: &FloatLiteral) {
+        write!(self.out, "{}", lit).unwrap();
+    }
+
+    fn visit_string_literal(&mut self, lit: &StringLiteral) {
+        write!(self.out, "{}", lit).unwrap();
+    }
+
+    fn visit_boolean(&mut self, lit: &Boolean) {
+        write!(self.out, "{}", lit).unwrap();
+    }
+
+    fn visit_interpolated_string(&mut self, expr: &InterpolatedString) {
+        self.out.push('"');
+        for part in &expr.parts {
+            match part {
+                InterpolationPart::Text(text) => self.out.push_str(&escape_string(text)),
+                InterpolationPart::Expr(e) => {
+                    self.out.push_str("${");
+                    self.expr(e.as_ref());
+                    self.out.push('}');
+                }
+            }
+        }
+        self.out.push('"');
+    }
+
+    fn visit_prefix_expression(&mut self, expr: &PrefixExpression) {
+        write!(self.out, "({}", expr.operator).unwrap();
+        self.expr(expr.right.as_ref());
+        self.out.push(')');
+    }
+
+    fn visit_infix_expression(&mut self, expr: &InfixExpression) {
+        self.out.push('(');
+        self.expr(expr.left.as_ref());
+        write!(self.out, " {} ", expr.operator).unwrap();
+        self.expr(expr.right.as_ref());
+        self.out.push(')');
+    }
+
+    fn visit_array_literal(&mut self, expr: &ArrayLiteral) {
+        self.out.push('[');
+        self.list(&expr.elements);
+        self.out.push(']');
+    }
+
+    fn visit_index_expression(&mut self, expr: &IndexExpression) {
+        self.out.push('(');
+        self.expr(expr.left.as_ref());
+        self.out.push('[');
+        self.expr(expr.index.as_ref());
+        self.out.push_str("])");
+    }
+
+    fn visit_assign_expression(&mut self, expr: &AssignExpression) {
+        self.out.push('(');
+        self.expr(expr.target.as_ref());
+        write!(self.out, " {} ", expr.operator).unwrap();
+        self.expr(expr.value.as_ref());
+        self.out.push(')');
+    }
+
+    fn visit_member_expression(&mut self, expr: &MemberExpression) {
+        self.out.push('(');
+        self.expr(expr.object.as_ref());
+        write!(self.out, ".{})", expr.property.value).unwrap();
+    }
+
+    fn visit_if_expression(&mut self, expr: &IfExpression) {
+        self.out.push_str("if (");
+        self.expr(expr.condition.as_ref());
+        self.out.push_str(") ");
+        self.braced(&expr.consequence);
+        if let Some(alternative) = &expr.alternative {
+            self.out.push_str(" else ");
+            self.braced(alternative);
+        }
+    }
+
+    fn visit_function_literal(&mut self, expr: &FunctionLiteral) {
+        self.out.push_str("fn(");
+        for (i, (name, annotation)) in expr
+            .parameters
+            .iter()
+            .zip(&expr.parameter_types)
+            .enumerate()
+        {
+            if i > 0 {
+                self.out.push_str(", ");
+            }
+            self.out.push_str(&name.value);
+            if let Some(annotation) = annotation {
+                write!(self.out, ": {}", annotation).unwrap();
+            }
+        }
+        self.out.push_str(") ");
+        if let Some(result) = &expr.return_type {
+            write!(self.out, "-> {} ", result).unwrap();
+        }
+        self.braced(&expr.body);
+    }
+
+    fn visit_macro_literal(&mut self, expr: &MacroLiteral) {
+        self.out.push_str("macro(");
+        self.names(&expr.parameters);
+        self.out.push_str(") ");
+        self.braced(&expr.body);
+    }
+
+    fn visit_call_expression(&mut self, expr: &CallExpression) {
+        self.expr(expr.function.as_ref());
+        self.out.push('(');
+        self.list(&expr.arguments);
+        self.out.push(')');
+    }
+}
+
+#[derive(Default)]
+struct Tree {
+    out: String,
+    depth: usize,
+}
+
+impl Tree {
+    fn line(&mut self, node: &dyn Node, span: Span) {
+        let (kind, detail) = describe(node);
+        let indent = "  ".repeat(self.depth);
+        if detail.is_empty() {
+            writeln!(self.out, "{}{} @{}", indent, kind, span).unwrap();
+        } else {
+            writeln!(self.out, "{}{} {} @{}", indent, kind, detail, span).unwrap();
+        }
+    }
+
+    fn nested(&mut self, f: impl FnOnce(&mut Self)) {
+        self.depth += 1;
+        f(self);
+        self.depth -= 1;
+    }
+}
+
+impl Visitor for Tree {
+    fn visit_program(&mut self, program: &Program) {
+        self.out.push_str("Program\n");
+        self.nested(|t| visit::walk_program(t, program));
+    }
+
+    fn visit_statement(&mut self, stmt: &dyn Statement) {
+        // blocks print their own line in visit_block_statement
+        if let Some(block) = stmt.as_any().downcast_ref::<BlockStatement>() {
+            return self.visit_block_statement(block);
+        }
+        self.line(stmt, span_of(stmt));
+        self.nested(|t| visit::walk_statement(t, stmt));
+    }
+
+    fn visit_block_statement(&mut self, block: &BlockStatement) {
+        self.line(block, block.token.span);
+        self.nested(|t| visit::walk_block_statement(t, block));
+    }
+
+    fn visit_expression(&mut self, expr: &dyn Expression) {
+        self.line(expr, span_of(expr));
+        self.nested(|t| visit::walk_expression(t, expr));
+    }
+}
+
+fn span_of(node: &dyn Node) -> Span {
+    token_of(node).map(|t| t.span).unwrap_or_default()
+}
+
+// Where an expression starts. Operators and calls have their operator or
+// parenthesis as their token, so those look to their left operand.
+pub(crate) fn start_span(expr: &dyn Expression) -> Span {
+    let any = expr.as_any();
+    if let Some(e) = any.downcast_ref::<InfixExpression>() {
+        start_span(e.left.as_ref())
+    } else if let Some(e) = any.downcast_ref::<CallExpression>() {
+        start_span(e.function.as_ref())
+    } else if let Some(e) = any.downcast_ref::<IndexExpression>() {
+        start_span(e.left.as_ref())
+    } else if let Some(e) = any.downcast_ref::<AssignExpression>() {
+        start_span(e.target.as_ref())
+    } else if let Some(e) = any.downcast_ref::<MemberExpression>() {
+        start_span(e.object.as_ref())
+    } else {
+        span_of(expr)
+    }
+}
+
+// The token every AST node keeps, found by downcasting.
+pub(crate) fn token_of(node: &dyn Node) -> Option<&Token> {
+    let any = node.as_any();
+    macro_rules! token_of {
+        ($($ty:ty),*) => {
+            $(if let Some(n) = any.downcast_ref::<$ty>() {
+                return Some(&n.token);
+            })*
+        };
+    }
+    token_of!(
+        LetStatement,
+        ReturnStatements,
+        ExpressionStatement,
+        BlockStatement,
+        ThrowStatement,
+        TryStatement,
+        WhileStatement,
+        ForStatement,
+        BreakStatement,
+        ContinueStatement,
+        ImportStatement,
+        ExportStatement,
+        Identifier,
+        IntegerLiteral,
+        FloatLiteral,
+        StringLiteral,
+        Boolean,
+        InterpolatedString,
+        PrefixExpression,
+        InfixExpression,
+        ArrayLiteral,
+        IndexExpression,
+        AssignExpression,
+        MemberExpression,
+        IfExpression,
+        FunctionLiteral,
+        MacroLiteral,
+        CallExpression
+    );
+    None
+}
+
+// The node's kind and a short description of what it names or holds.
+fn describe(node: &dyn Node) -> (&'static str, String) {
+    let any = node.as_any();
+    let names = |ids: &[Rc<Identifier>]| {
+        let ids: Vec<&str> = ids.iter().map(|id| id.value.as_str()).collect();
+        format!("({})", ids.join(", "))
+    };
+    if let Some(n) = any.downcast_ref::<LetStatement>() {
+        ("LetStatement", n.name.value.clone())
+    } else if any.is::<ReturnStatements>() {
+        ("ReturnStatement", String::new())
+    } else if any.is::<ExpressionStatement>() {
+        ("ExpressionStatement", String::new())
+    } else if any.is::<BlockStatement>() {
+        ("BlockStatement", String::new())
+    } else if any.is::<ThrowStatement>() {
+        ("ThrowStatement", String::new())
+    } else if let Some(n) = any.downcast_ref::<TryStatement>() {
+        let detail = match &n.catch_param {
+            Some(param) => format!("catch {}", param.value),
+            None => String::new(),
+        };
+        ("TryStatement", detail)
+    } else if any.is::<WhileStatement>() {
+        ("WhileStatement", String::new())
+    } else if let Some(n) = any.downcast_ref::<ForStatement>() {
+        ("ForStatement", n.variable.value.clone())
+    } else if any.is::<BreakStatement>() {
+        ("BreakStatement", String::new())
+    } else if any.is::<ContinueStatement>() {
+        ("ContinueStatement", String::new())
+    } else if let Some(n) = any.downcast_ref::<ImportStatement>() {
+        (
+            "ImportStatement",
+            format!("{:?} as {}", n.path, n.alias.value),
+        )
+    } else if let Some(n) = any.downcast_ref::<ExportStatement>() {
+        ("ExportStatement", n.statement.name.value.clone())
+    } else if let Some(n) = any.downcast_ref::<Identifier>() {
+        ("Identifier", n.value.clone())
+    } else if let Some(n) = any.downcast_ref::<IntegerLiteral>() {
+        ("IntegerLiteral", n.value.to_string())
+    } else if let Some(n) = any.downcast_ref::<FloatLiteral>() {
+        ("FloatLiteral", n.to_string())
+    } else if let Some(n) = any.downcast_ref::<StringLiteral>() {
+        ("StringLiteral", n.to_string())
+    } else if let Some(n) = any.downcast_ref::<Boolean>() {
+        ("Boolean", n.value.to_string())
+    } else if any.is::<InterpolatedString>() {
+        ("InterpolatedString", String::new())
+    } else if let Some(n) = any.downcast_ref::<PrefixExpression>() {
+        ("PrefixExpression", n.operator.clone())
+    } else if let Some(n) = any.downcast_ref::<InfixExpression>() {
+        ("InfixExpression", n.operator.clone())
+    } else if any.is::<ArrayLiteral>() {
+        ("ArrayLiteral", String::new())
+    } else if any.is::<IndexExpression>() {
+        ("IndexExpression", String::new())
+    } else if let Some(n) = any.downcast_ref::<AssignExpression>() {
+        ("AssignExpression", n.operator.clone())
+    } else if let Some(n) = any.downcast_ref::<MemberExpression>() {
+        ("MemberExpression", format!(".{}", n.property.value))
+    } else if any.is::<IfExpression>() {
+        ("IfExpression", String::new())
+    } else if let Some(n) = any.downcast_ref::<FunctionLiteral>() {
+        ("FunctionLiteral", names(&n.parameters))
+    } else if let Some(n) = any.downcast_ref::<MacroLiteral>() {
+        ("MacroLiteral", names(&n.parameters))
+    } else if any.is::<CallExpression>() {
+        ("CallExpression", String::new())
+    } else {
+        ("Unknown", node.token_literal())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parser::parse;
+
+    #[test]
+    fn test_parenthesize_statements() {
+        let tests = [
+            ("let x = a + b * c - d;", "let x = ((a + (b * c)) - d);"),
+            ("return -a[i].b;", "return (-((a[i]).b));"),
+            ("x += f(a + b)(c)", "(x += f((a + b))(c))"),
+            (
+                "if (a < b) { [a * 2, \"n=${n + 1}\"] } else { !c }",
+                "if ((a < b)) { [(a * 2), \"n=${(n + 1)}\"] } else { (!c) }",
+            ),
+            (
+                "for (i in xs) { while (i > 0) { break; } }",
+                "for (i in xs) { while ((i > 0)) { break; } }",
+            ),
+            (
+                "export let f = fn(a, b) { try { throw a ** b; } catch (e) { e } };",
+                "export let f = fn(a, b) { try { throw (a ** b); } catch (e) { e } };",
+            ),
+            (
+                "let f: fn(int) -> int = fn(n: int) -> int { n * 2 };",
+                "let f: fn(int) -> int = fn(n: int) -> int { (n * 2) };",
+            ),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(parenthesize(&parse(input)), expected, "input={}", input);
+        }
+    }
+
+    #[test]
+    fn test_tree() {
+        let program = parse("let x = 1 + f(y);\nfor (i in xs) { -i }");
+
+        assert_eq!(
+            tree(&program),
+            "Program
+  LetStatement x @1:1
+    InfixExpression + @1:11
+      IntegerLiteral 1 @1:9
+      CallExpression @1:14
+        Identifier f @1:13
+        Identifier y @1:15
+  ForStatement i @2:1
+    Identifier xs @2:11
+    BlockStatement @2:15
+      ExpressionStatement @2:17
+        PrefixExpression - @2:17
+          Identifier i @2:18
+"
+        );
+    }
+}
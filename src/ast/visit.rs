@@ -0,0 +1,367 @@
+use crate::ast::ast::{
+    ArrayLiteral, AssignExpression, BlockStatement, Boolean, BreakStatement, CallExpression,
+    ContinueStatement, ExportStatement, Expression, ExpressionStatement, FloatLiteral,
+    ForStatement, FunctionLiteral, Identifier, IfExpression, ImportStatement, IndexExpression,
+    InfixExpression, IntegerLiteral, InterpolatedString, InterpolationPart, LetStatement,
+    MacroLiteral, MemberExpression, PrefixExpression, Program, ReturnStatements, Statement,
+    StringLiteral, ThrowStatement, TryStatement, WhileStatement,
+};
+
+// Read-only traversal of the AST. Every method defaults to walking the
+// node's children, so an analysis only overrides the nodes it cares about
+// and calls the matching walk_* function when it still wants to descend.
+//
+// visit_identifier only sees identifiers used as expressions. Names that
+// are being bound (let names, parameters, the catch and for variables),
+// member properties and import aliases are reachable through their parent
+// node instead.
+pub trait Visitor {
+    fn visit_program(&mut self, program: &Program) {
+        walk_program(self, program)
+    }
+
+    fn visit_statement(&mut self, stmt: &dyn Statement) {
+        walk_statement(self, stmt)
+    }
+
+    fn visit_expression(&mut self, expr: &dyn Expression) {
+        walk_expression(self, expr)
+    }
+
+    fn visit_block_statement(&mut self, block: &BlockStatement) {
+        walk_block_statement(self, block)
+    }
+
+    fn visit_let_statement(&mut self, stmt: &LetStatement) {
+        walk_let_statement(self, stmt)
+    }
+
+    fn visit_return_statement(&mut self, stmt: &ReturnStatements) {
+        walk_return_statement(self, stmt)
+    }
+
+    fn visit_expression_statement(&mut self, stmt: &ExpressionStatement) {
+        walk_expression_statement(self, stmt)
+    }
+
+    fn visit_throw_statement(&mut self, stmt: &ThrowStatement) {
+        walk_throw_statement(self, stmt)
+    }
+
+    fn visit_try_statement(&mut self, stmt: &TryStatement) {
+        walk_try_statement(self, stmt)
+    }
+
+    fn visit_while_statement(&mut self, stmt: &WhileStatement) {
+        walk_while_statement(self, stmt)
+    }
+
+    fn visit_for_statement(&mut self, stmt: &ForStatement) {
+        walk_for_statement(self, stmt)
+    }
+
+    fn visit_break_statement(&mut self, _stmt: &BreakStatement) {}
+
+    fn visit_continue_statement(&mut self, _stmt: &ContinueStatement) {}
+
+    fn visit_import_statement(&mut self, _stmt: &ImportStatement) {}
+
+    fn visit_export_statement(&mut self, stmt: &ExportStatement) {
+        walk_export_statement(self, stmt)
+    }
+
+    fn visit_identifier(&mut self, _ident: &Identifier) {}
+
+    fn visit_integer_literal(&mut self, _lit: &IntegerLiteral) {}
+
+    fn visit_float_literal(&mut self, _lit: &FloatLiteral) {}
+
+    fn visit_string_literal(&mut self, _lit: &StringLiteral) {}
+
+    fn visit_boolean(&mut self, _lit: &Boolean) {}
+
+    fn visit_interpolated_string(&mut self, expr: &InterpolatedString) {
+        walk_interpolated_string(self, expr)
+    }
+
+    fn visit_prefix_expression(&mut self, expr: &PrefixExpression) {
+        walk_prefix_expression(self, expr)
+    }
+
+    fn visit_infix_expression(&mut self, expr: &InfixExpression) {
+        walk_infix_expression(self, expr)
+    }
+
+    fn visit_array_literal(&mut self, expr: &ArrayLiteral) {
+        walk_array_literal(self, expr)
+    }
+
+    fn visit_index_expression(&mut self, expr: &IndexExpression) {
+        walk_index_expression(self, expr)
+    }
+
+    fn visit_assign_expression(&mut self, expr: &AssignExpression) {
+        walk_assign_expression(self, expr)
+    }
+
+    fn visit_member_expression(&mut self, expr: &MemberExpression) {
+        walk_member_expression(self, expr)
+    }
+
+    fn visit_if_expression(&mut self, expr: &IfExpression) {
+        walk_if_expression(self, expr)
+    }
+
+    fn visit_function_literal(&mut self, expr: &FunctionLiteral) {
+        walk_function_literal(self, expr)
+    }
+
+    fn visit_macro_literal(&mut self, expr: &MacroLiteral) {
+        walk_macro_literal(self, expr)
+    }
+
+    fn visit_call_expression(&mut self, expr: &CallExpression) {
+        walk_call_expression(self, expr)
+    }
+}
+
+pub fn walk_program<V: Visitor + ?Sized>(v: &mut V, program: &Program) {
+    for stmt in &program.statements {
+        v.visit_statement(stmt.as_ref());
+    }
+}
+
+// Dispatches to the visit_* method for the statement's concrete type.
+pub fn walk_statement<V: Visitor + ?Sized>(v: &mut V, stmt: &dyn Statement) {
+    let any = stmt.as_any();
+    if let Some(s) = any.downcast_ref::<ExpressionStatement>() {
+        v.visit_expression_statement(s)
+    } else if let Some(s) = any.downcast_ref::<LetStatement>() {
+        v.visit_let_statement(s)
+    } else if let Some(s) = any.downcast_ref::<ReturnStatements>() {
+        v.visit_return_statement(s)
+    } else if let Some(s) = any.downcast_ref::<BlockStatement>() {
+        v.visit_block_statement(s)
+    } else if let Some(s) = any.downcast_ref::<ThrowStatement>() {
+        v.visit_throw_statement(s)
+    } else if let Some(s) = any.downcast_ref::<TryStatement>() {
+        v.visit_try_statement(s)
+    } else if let Some(s) = any.downcast_ref::<WhileStatement>() {
+        v.visit_while_statement(s)
+    } else if let Some(s) = any.downcast_ref::<ForStatement>() {
+        v.visit_for_statement(s)
+    } else if let Some(s) = any.downcast_ref::<BreakStatement>() {
+        v.visit_break_statement(s)
+    } else if let Some(s) = any.downcast_ref::<ContinueStatement>() {
+        v.visit_continue_statement(s)
+    } else if let Some(s) = any.downcast_ref::<ImportStatement>() {
+        v.visit_import_statement(s)
+    } else if let Some(s) = any.downcast_ref::<ExportStatement>() {
+        v.visit_export_statement(s)
+    }
+}
+
+// Dispatches to the visit_* method for the expression's concrete type.
+pub fn walk_expression<V: Visitor + ?Sized>(v: &mut V, expr: &dyn Expression) {
+    let any = expr.as_any();
+    if let Some(e) = any.downcast_ref::<Identifier>() {
+        v.visit_identifier(e)
+    } else if let Some(e) = any.downcast_ref::<IntegerLiteral>() {
+        v.visit_integer_literal(e)
+    } else if let Some(e) = any.downcast_ref::<FloatLiteral>() {
+        v.visit_float_literal(e)
+    } else if let Some(e) = any.downcast_ref::<StringLiteral>() {
+        v.visit_string_literal(e)
+    } else if let Some(e) = any.downcast_ref::<Boolean>() {
+        v.visit_boolean(e)
+    } else if let Some(e) = any.downcast_ref::<InterpolatedString>() {
+        v.visit_interpolated_string(e)
+    } else if let Some(e) = any.downcast_ref::<PrefixExpression>() {
+        v.visit_prefix_expression(e)
+    } else if let Some(e) = any.downcast_ref::<InfixExpression>() {
+        v.visit_infix_expression(e)
+    } else if let Some(e) = any.downcast_ref::<ArrayLiteral>() {
+        v.visit_array_literal(e)
+    } else if let Some(e) = any.downcast_ref::<IndexExpression>() {
+        v.visit_index_expression(e)
+    } else if let Some(e) = any.downcast_ref::<AssignExpression>() {
+        v.visit_assign_expression(e)
+    } else if let Some(e) = any.downcast_ref::<MemberExpression>() {
+        v.visit_member_expression(e)
+    } else if let Some(e) = any.downcast_ref::<IfExpression>() {
+        v.visit_if_expression(e)
+    } else if let Some(e) = any.downcast_ref::<FunctionLiteral>() {
+        v.visit_function_literal(e)
+    } else if let Some(e) = any.downcast_ref::<MacroLiteral>() {
+        v.visit_macro_literal(e)
+    } else if let Some(e) = any.downcast_ref::<CallExpression>() {
+        v.visit_call_expression(e)
+    }
+}
+
+pub fn walk_block_statement<V: Visitor + ?Sized>(v: &mut V, block: &BlockStatement) {
+    for stmt in &block.statements {
+        v.visit_statement(stmt.as_ref());
+    }
+}
+
+pub fn walk_let_statement<V: Visitor + ?Sized>(v: &mut V, stmt: &LetStatement) {
+    if let Some(value) = &stmt.value {
+        v.visit_expression(value.as_ref());
+    }
+}
+
+pub fn walk_return_statement<V: Visitor + ?Sized>(v: &mut V, stmt: &ReturnStatements) {
+    if let Some(value) = &stmt.value {
+        v.visit_expression(value.as_ref());
+    }
+}
+
+pub fn walk_expression_statement<V: Visitor + ?Sized>(v: &mut V, stmt: &ExpressionStatement) {
+    if let Some(expr) = &stmt.expr {
+        v.visit_expression(expr.as_ref());
+    }
+}
+
+pub fn walk_throw_statement<V: Visitor + ?Sized>(v: &mut V, stmt: &ThrowStatement) {
+    if let Some(value) = &stmt.value {
+        v.visit_expression(value.as_ref());
+    }
+}
+
+pub fn walk_try_statement<V: Visitor + ?Sized>(v: &mut V, stmt: &TryStatement) {
+    v.visit_block_statement(&stmt.block);
+    if let Some(block) = &stmt.catch_block {
+        v.visit_block_statement(block);
+    }
+    if let Some(block) = &stmt.finally_block {
+        v.visit_block_statement(block);
+    }
+}
+
+pub fn walk_while_statement<V: Visitor + ?Sized>(v: &mut V, stmt: &WhileStatement) {
+    v.visit_expression(stmt.condition.as_ref());
+    v.visit_block_statement(&stmt.body);
+}
+
+pub fn walk_for_statement<V: Visitor + ?Sized>(v: &mut V, stmt: &ForStatement) {
+    v.visit_expression(stmt.iterable.as_ref());
+    v.visit_block_statement(&stmt.body);
+}
+
+pub fn walk_export_statement<V: Visitor + ?Sized>(v: &mut V, stmt: &ExportStatement) {
+    v.visit_let_statement(&stmt.statement);
+}
+
+pub fn walk_interpolated_string<V: Visitor + ?Sized>(v: &mut V, expr: &InterpolatedString) {
+    for part in &expr.parts {
+        if let InterpolationPart::Expr(e) = part {
+            v.visit_expression(e.as_ref());
+        }
+    }
+}
+
+pub fn walk_prefix_expression<V: Visitor + ?Sized>(v: &mut V, expr: &PrefixExpression) {
+    v.visit_expression(expr.right.as_ref());
+}
+
+pub fn walk_infix_expression<V: Visitor + ?Sized>(v: &mut V, expr: &InfixExpression) {
+    v.visit_expression(expr.left.as_ref());
+    v.visit_expression(expr.right.as_ref());
+}
+
+pub fn walk_array_literal<V: Visitor + ?Sized>(v: &mut V, expr: &ArrayLiteral) {
+    for element in &expr.elements {
+        v.visit_expression(element.as_ref());
+    }
+}
+
+pub fn walk_index_expression<V: Visitor + ?Sized>(v: &mut V, expr: &IndexExpression) {
+    v.visit_expression(expr.left.as_ref());
+    v.visit_expression(expr.index.as_ref());
+}
+
+pub fn walk_assign_expression<V: Visitor + ?Sized>(v: &mut V, expr: &AssignExpression) {
+    v.visit_expression(expr.target.as_ref());
+    v.visit_expression(expr.value.as_ref());
+}
+
+pub fn walk_member_expression<V: Visitor + ?Sized>(v: &mut V, expr: &MemberExpression) {
+    v.visit_expression(expr.object.as_ref());
+}
+
+pub fn walk_if_expression<V: Visitor + ?Sized>(v: &mut V, expr: &IfExpression) {
+    v.visit_expression(expr.condition.as_ref());
+    v.visit_block_statement(&expr.consequence);
+    if let Some(alternative) = &expr.alternative {
+        v.visit_block_statement(alternative);
+    }
+}
+
+pub fn walk_function_literal<V: Visitor + ?Sized>(v: &mut V, expr: &FunctionLiteral) {
+    v.visit_block_statement(&expr.body);
+}
+
+pub fn walk_macro_literal<V: Visitor + ?Sized>(v: &mut V, expr: &MacroLiteral) {
+    v.visit_block_statement(&expr.body);
+}
+
+pub fn walk_call_expression<V: Visitor + ?Sized>(v: &mut V, expr: &CallExpression) {
+    v.visit_expression(expr.function.as_ref());
+    for arg in &expr.arguments {
+        v.visit_expression(arg.as_ref());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parser::parse;
+
+    #[derive(Default)]
+    struct IdentifierUses(Vec<String>);
+
+    impl Visitor for IdentifierUses {
+        fn visit_identifier(&mut self, ident: &Identifier) {
+            self.0.push(ident.value.clone());
+        }
+    }
+
+    #[test]
+    fn test_visitor_reaches_every_expression() {
+        let input = r#"let f = fn(x) { return x + a; };
+export let g = [b, -c][d];
+for (i in e) { if (i) { h(i) } else { break; } }
+while (j) { k[m] = l.x + "${n}"; }
+try { throw o; } catch (err) { p } finally { q }"#;
+
+        let mut uses = IdentifierUses::default();
+        uses.visit_program(&parse(input));
+
+        assert_eq!(
+            uses.0,
+            ["x", "a", "b", "c", "d", "e", "i", "h", "i", "j", "k", "m", "l", "n", "o", "p", "q"]
+        );
+    }
+
+    // Stops at function bodies by not calling walk_function_literal.
+    #[derive(Default)]
+    struct TopLevelCalls(usize);
+
+    impl Visitor for TopLevelCalls {
+        fn visit_call_expression(&mut self, expr: &CallExpression) {
+            self.0 += 1;
+            walk_call_expression(self, expr)
+        }
+
+        fn visit_function_literal(&mut self, _expr: &FunctionLiteral) {}
+    }
+
+    #[test]
+    fn test_visitor_override_controls_descent() {
+        let mut calls = TopLevelCalls::default();
+        calls.visit_program(&parse("f(g(1), fn() { h() });"));
+
+        assert_eq!(calls.0, 2);
+    }
+}
@@ -0,0 +1,2 @@
+pub mod signatures;
+pub mod string;
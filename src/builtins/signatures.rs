@@ -0,0 +1,125 @@
+use std::fmt;
+
+// What the builtin functions take and do, for tools that describe them
+// without a runtime to ask: hover and completion in the language server.
+// `len`, `first`, `last`, `rest`, `push` and `puts` are the core set the
+// prelude relies on; the rest is the `string` set from builtins::string.
+
+pub struct Signature {
+    pub name: &'static str,
+    pub params: &'static [&'static str],
+    pub doc: &'static str,
+}
+
+pub const BUILTINS: &[Signature] = &[
+    Signature {
+        name: "len",
+        params: &["value"],
+        doc: "Length of a string in chars, or of an array.",
+    },
+    Signature {
+        name: "first",
+        params: &["arr"],
+        doc: "First element of an array, or null when it is empty.",
+    },
+    Signature {
+        name: "last",
+        params: &["arr"],
+        doc: "Last element of an array, or null when it is empty.",
+    },
+    Signature {
+        name: "rest",
+        params: &["arr"],
+        doc: "A new array without the first element.",
+    },
+    Signature {
+        name: "push",
+        params: &["arr", "value"],
+        doc: "A new array with `value` appended; `arr` is unchanged.",
+    },
+    Signature {
+        name: "puts",
+        params: &["...values"],
+        doc: "Prints each value on its own line.",
+    },
+    Signature {
+        name: "split",
+        params: &["s", "sep"],
+        doc: "Splits `s` at every `sep`, which must not be empty.",
+    },
+    Signature {
+        name: "join",
+        params: &["parts", "sep"],
+        doc: "Joins an array of strings with `sep` between them.",
+    },
+    Signature {
+        name: "trim",
+        params: &["s"],
+        doc: "`s` without leading and trailing whitespace.",
+    },
+    Signature {
+        name: "upper",
+        params: &["s"],
+        doc: "`s` in upper case.",
+    },
+    Signature {
+        name: "lower",
+        params: &["s"],
+        doc: "`s` in lower case.",
+    },
+    Signature {
+        name: "contains",
+        params: &["s", "needle"],
+        doc: "Whether `needle` occurs in `s`.",
+    },
+    Signature {
+        name: "starts_with",
+        params: &["s", "prefix"],
+        doc: "Whether `s` starts with `prefix`.",
+    },
+    Signature {
+        name: "ends_with",
+        params: &["s", "suffix"],
+        doc: "Whether `s` ends with `suffix`.",
+    },
+    Signature {
+        name: "replace",
+        params: &["s", "from", "to"],
+        doc: "`s` with every `from` replaced by `to`.",
+    },
+    Signature {
+        name: "substr",
+        params: &["s", "start", "len?"],
+        doc: "`len` chars of `s` from char `start`, or the rest without `len`.",
+    },
+    Signature {
+        name: "chars",
+        params: &["s"],
+        doc: "The chars of `s` as an array of one-char strings.",
+    },
+    Signature {
+        name: "ord",
+        params: &["c"],
+        doc: "Code point of a one-char string.",
+    },
+    Signature {
+        name: "chr",
+        params: &["code"],
+        doc: "One-char string for a code point.",
+    },
+    Signature {
+        name: "format",
+        params: &["template", "...args"],
+        doc: "`template` with each `{}` replaced by the next argument.",
+    },
+];
+
+pub fn lookup(name: &str) -> Option<&'static Signature> {
+    BUILTINS.iter().find(|s| s.name == name)
+}
+
+impl fmt::Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}({})", self.name, self.params.join(", "))
+    }
+}
@@ -0,0 +1,216 @@
+// The `string` builtin set. These work on plain Rust values so they can be
+// wrapped by whichever runtime calls them; errors are returned as messages.
+// All indexing is by char, never by byte.
+
+pub fn split(s: &str, sep: &str) -> Result<Vec<String>, String> {
+    if sep.is_empty() {
+        return Err("split: separator must not be empty, use chars".to_string());
+    }
+    Ok(s.split(sep).map(str::to_string).collect())
+}
+
+pub fn join(parts: &[String], sep: &str) -> String {
+    parts.join(sep)
+}
+
+pub fn trim(s: &str) -> String {
+    s.trim().to_string()
+}
+
+pub fn upper(s: &str) -> String {
+    s.to_uppercase()
+}
+
+pub fn lower(s: &str) -> String {
+    s.to_lowercase()
+}
+
+pub fn contains(s: &str, needle: &str) -> bool {
+    s.contains(needle)
+}
+
+pub fn starts_with(s: &str, prefix: &str) -> bool {
+    s.starts_with(prefix)
+}
+
+pub fn ends_with(s: &str, suffix: &str) -> bool {
+    s.ends_with(suffix)
+}
+
+pub fn replace(s: &str, from: &str, to: &str) -> Result<String, String> {
+    if from.is_empty() {
+        return Err("replace: pattern must not be empty".to_string());
+    }
+    Ok(s.replace(from, to))
+}
+
+// `len` chars starting at char `start`; without `len`, the rest of the string.
+// A `len` running past the end is cut short.
+pub fn substr(s: &str, start: i64, len: Option<i64>) -> Result<String, String> {
+    let count = s.chars().count();
+    if start < 0 || start as usize > count {
+        return Err(format!(
+            "substr: start {} out of range for string of length {}",
+            start, count
+        ));
+    }
+    let take = match len {
+        Some(len) if len < 0 => {
+            return Err(format!("substr: length must not be negative, got {}", len))
+        }
+        Some(len) => len as usize,
+        None => count,
+    };
+    Ok(s.chars().skip(start as usize).take(take).collect())
+}
+
+pub fn chars(s: &str) -> Vec<String> {
+    s.chars().map(String::from).collect()
+}
+
+pub fn ord(s: &str) -> Result<i64, String> {
+    let mut it = s.chars();
+    match (it.next(), it.next()) {
+        (Some(c), None) => Ok(c as i64),
+        _ => Err(format!(
+            "ord: expected a single character, got string of length {}",
+            s.chars().count()
+        )),
+    }
+}
+
+pub fn chr(code: i64) -> Result<String, String> {
+    u32::try_from(code)
+        .ok()
+        .and_then(char::from_u32)
+        .map(String::from)
+        .ok_or_else(|| format!("chr: {} is not a valid code point", code))
+}
+
+// Replaces each `{}` with the next argument in order. `{{` and `}}` stand
+// for literal braces.
+pub fn format(template: &str, args: &[String]) -> Result<String, String> {
+    let mut out = String::with_capacity(template.len());
+    let mut args = args.iter();
+    let mut used = 0;
+    let mut chars = template.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('{', Some('{')) | ('}', Some('}')) => {
+                out.push(c);
+                chars.next();
+            }
+            ('{', Some('}')) => {
+                chars.next();
+                match args.next() {
+                    Some(arg) => out.push_str(arg),
+                    None => {
+                        return Err(format!("format: no argument for placeholder {}", used + 1))
+                    }
+                }
+                used += 1;
+            }
+            ('{', _) | ('}', _) => {
+                return Err(format!("format: unmatched '{}' in template", c));
+            }
+            _ => out.push(c),
+        }
+    }
+
+    let extra = args.count();
+    if extra > 0 {
+        return Err(format!(
+            "format: {} placeholders but {} arguments",
+            used,
+            used + extra
+        ));
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_split_and_join() {
+        assert_eq!(split("a,b,,c", ",").unwrap(), strings(&["a", "b", "", "c"]));
+        assert_eq!(
+            split("naïve→café→日本", "→").unwrap(),
+            strings(&["naïve", "café", "日本"])
+        );
+        assert!(split("abc", "").is_err());
+        assert_eq!(join(&strings(&["日", "本"]), "·"), "日·本");
+        assert_eq!(join(&[], ","), "");
+    }
+
+    #[test]
+    fn test_case_and_trim() {
+        assert_eq!(trim("\t  héllo \n"), "héllo");
+        assert_eq!(upper("straße"), "STRASSE");
+        assert_eq!(lower("ÀÉÎ"), "àéî");
+    }
+
+    #[test]
+    fn test_search_and_replace() {
+        assert!(contains("crème brûlée", "brû"));
+        assert!(!contains("crème", "e\u{301}"));
+        assert!(starts_with("🐒 monkey", "🐒"));
+        assert!(ends_with("🐒 monkey", "key"));
+        assert_eq!(replace("a-b-c", "-", "→").unwrap(), "a→b→c");
+        assert!(replace("abc", "", "x").is_err());
+    }
+
+    #[test]
+    fn test_substr_counts_chars() {
+        assert_eq!(substr("héllo wörld", 6, Some(5)).unwrap(), "wörld");
+        assert_eq!(substr("日本語", 1, None).unwrap(), "本語");
+        assert_eq!(substr("日本語", 2, Some(10)).unwrap(), "語");
+        assert_eq!(substr("日本語", 3, None).unwrap(), "");
+        assert_eq!(
+            substr("日本語", 4, None).unwrap_err(),
+            "substr: start 4 out of range for string of length 3"
+        );
+        assert!(substr("abc", -1, None).is_err());
+        assert!(substr("abc", 0, Some(-1)).is_err());
+    }
+
+    #[test]
+    fn test_chars_ord_chr() {
+        assert_eq!(chars("añ🐒"), strings(&["a", "ñ", "🐒"]));
+        assert_eq!(ord("🐒").unwrap(), 0x1F412);
+        assert_eq!(ord("é").unwrap(), 233);
+        assert!(ord("ab").is_err());
+        assert!(ord("").is_err());
+        assert_eq!(chr(0x1F412).unwrap(), "🐒");
+        assert_eq!(
+            chr(0xD800).unwrap_err(),
+            "chr: 55296 is not a valid code point"
+        );
+        assert!(chr(-1).is_err());
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(
+            format("{} is {} years", &strings(&["Zoë", "7"])).unwrap(),
+            "Zoë is 7 years"
+        );
+        assert_eq!(format("{{}} {}", &strings(&["ok"])).unwrap(), "{} ok");
+        assert_eq!(format("none", &[]).unwrap(), "none");
+        assert_eq!(
+            format("{} {}", &strings(&["one"])).unwrap_err(),
+            "format: no argument for placeholder 2"
+        );
+        assert_eq!(
+            format("{}", &strings(&["a", "b"])).unwrap_err(),
+            "format: 1 placeholders but 2 arguments"
+        );
+        assert!(format("{x}", &[]).is_err());
+    }
+}
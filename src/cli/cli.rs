@@ -0,0 +1,829 @@
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use crate::ast::optimize::{self, Passes};
+use crate::ast::printer;
+use crate::cst;
+use crate::format::format::{format_program, format_source};
+use crate::lexer::lexer::{Lexer, LexerTrait};
+use crate::lexer::token::TokenType;
+use crate::lint::config::Config;
+use crate::lint::lint::{fix_source, lint_source};
+#[cfg(feature = "serde")]
+use crate::lsp;
+use crate::parser::parser::Parser;
+use crate::resolver::diagnostics::Severity;
+use crate::typeck::typeck;
+
+const USAGE: &str = "usage: monkey <command> [--format <format>] <file>
+       monkey parse [--optimize] [--format <format>] <file>
+       monkey fmt [--check | --write] <file>...
+       monkey lint [--fix] [--config <monkey.toml>] [--no-prelude] <file>...
+       monkey check [--no-prelude] <file>...
+       monkey lsp [--no-prelude]
+
+commands:
+    lex      print the tokens of a file (formats: text, json)
+    parse    print the syntax tree of a file
+             (formats: text, json, parens, tree, cst), after folding
+             constants and dropping dead code if --optimize is given
+    fmt      print a file formatted, list the files that are not
+             formatted (--check) or format them in place (--write)
+    lint     report likely mistakes and style problems, fixing the ones
+             that can be fixed safely (--fix); rules are set in the
+             nearest monkey.toml unless --config names one
+    check    infer the types of a file and report where they conflict
+    lsp      run the language server on standard input and output
+
+lint, check and lsp take the prelude's functions as defined unless
+--no-prelude is given. Use - as the file to read standard input.";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Text,
+    Json,
+    // every expression parenthesized, see ast::printer::parenthesize
+    Parens,
+    // one node per line, see ast::printer::tree
+    Tree,
+    // the lossless syntax tree, see cst::cst::SyntaxNode::dump
+    Cst,
+}
+
+// Runs one command line (without the program name) and returns the process
+// exit code: 0 on success, 1 when the source has errors, 2 on bad usage.
+pub fn run<W: Write, E: Write>(args: &[String], out: &mut W, err: &mut E) -> i32 {
+    let Some((command, rest)) = args.split_first() else {
+        let _ = writeln!(err, "{}", USAGE);
+        return 2;
+    };
+    if command == "fmt" {
+        return fmt(rest, out, err);
+    }
+    if command == "lint" {
+        return lint(rest, out, err);
+    }
+    if command == "check" {
+        return check(rest, out, err);
+    }
+    if command == "lsp" {
+        let prelude = match rest {
+            [] => true,
+            [flag] if flag == "--no-prelude" => false,
+            _ => {
+                let _ = writeln!(err, "lsp takes no arguments but --no-prelude\n\n{}", USAGE);
+                return 2;
+            }
+        };
+        return lsp(prelude, out, err);
+    }
+    if command != "lex" && command != "parse" {
+        let _ = writeln!(err, "unknown command {:?}\n\n{}", command, USAGE);
+        return 2;
+    }
+
+    let Options {
+        format,
+        optimize,
+        path,
+    } = match parse_options(rest) {
+        Ok(options) => options,
+        Err(msg) => {
+            let _ = writeln!(err, "{}\n\n{}", msg, USAGE);
+            return 2;
+        }
+    };
+    if command == "lex" && !matches!(format, Format::Text | Format::Json) {
+        let _ = writeln!(err, "lex only prints text or json\n\n{}", USAGE);
+        return 2;
+    }
+    if optimize && (command == "lex" || format == Format::Cst) {
+        let _ = writeln!(err, "--optimize only applies to the AST\n\n{}", USAGE);
+        return 2;
+    }
+
+    let source = match read_source(&path) {
+        Ok(source) => source,
+        Err(e) => {
+            let _ = writeln!(err, "cannot read {}: {}", path, e);
+            return 1;
+        }
+    };
+
+    let result = if command == "lex" {
+        lex(&source, &path, format, out, err)
+    } else {
+        parse(&source, &path, format, optimize, out, err)
+    };
+
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            let _ = writeln!(err, "{}", e);
+            1
+        }
+    }
+}
+
+struct Options {
+    format: Format,
+    // run ast::optimize before printing
+    optimize: bool,
+    path: String,
+}
+
+fn parse_options(args: &[String]) -> Result<Options, String> {
+    let mut format = Format::Text;
+    let mut optimize = false;
+    let mut path = None;
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => {
+                format = match args.next().map(String::as_str) {
+                    Some("text") => Format::Text,
+                    Some("json") => Format::Json,
+                    Some("parens") => Format::Parens,
+                    Some("tree") => Format::Tree,
+                    Some("cst") => Format::Cst,
+                    Some(other) => return Err(format!("unknown format {:?}", other)),
+                    None => return Err("--format needs a value".to_string()),
+                }
+            }
+            "--optimize" => optimize = true,
+            flag if flag.starts_with("--") => return Err(format!("unknown option {}", flag)),
+            file if path.is_none() => path = Some(file.to_string()),
+            extra => return Err(format!("unexpected argument {:?}", extra)),
+        }
+    }
+
+    match path {
+        Some(path) => Ok(Options {
+            format,
+            optimize,
+            path,
+        }),
+        None => Err("missing file".to_string()),
+    }
+}
+
+fn read_source(path: &str) -> io::Result<String> {
+    if path == "-" {
+        let mut source = String::new();
+        io::stdin().read_to_string(&mut source)?;
+        Ok(source)
+    } else {
+        fs::read_to_string(path)
+    }
+}
+
+fn lex<W: Write, E: Write>(
+    source: &str,
+    path: &str,
+    format: Format,
+    out: &mut W,
+    err: &mut E,
+) -> io::Result<i32> {
+    let mut lexer = Lexer::new(source);
+    let mut tokens = Vec::new();
+    loop {
+        let token = lexer.next_token();
+        if token.type_ == TokenType::Eof {
+            break;
+        }
+        tokens.push(token);
+    }
+
+    match format {
+        Format::Text => {
+            for token in &tokens {
+                writeln!(out, "{}\t{:?}\t{}", token.span, token.type_, token.literal)?;
+            }
+        }
+        Format::Json => write_json(&tokens, out)?,
+        Format::Parens | Format::Tree | Format::Cst => unreachable!("rejected in run"),
+    }
+
+    for e in lexer.errors() {
+        writeln!(err, "{}:{}", path, e)?;
+    }
+    Ok(if lexer.errors().is_empty() { 0 } else { 1 })
+}
+
+fn parse<W: Write, E: Write>(
+    source: &str,
+    path: &str,
+    format: Format,
+    optimize: bool,
+    out: &mut W,
+    err: &mut E,
+) -> io::Result<i32> {
+    if format == Format::Cst {
+        // the syntax tree has room for broken code, so it is printed as is
+        write!(out, "{}", cst::parser::parse(source).dump())?;
+        return Ok(0);
+    }
+
+    let mut p = Parser::new(Lexer::new(source));
+    let program = p.parse_program();
+    let errors = p.errors();
+    if !errors.is_empty() {
+        for e in &errors {
+            writeln!(err, "{}:{}: {}", path, e.span, e)?;
+        }
+        return Ok(1);
+    }
+    let program = if optimize {
+        optimize::optimize(program, Passes::default())
+    } else {
+        program
+    };
+
+    match format {
+        Format::Text => write!(out, "{}", format_program(&program))?,
+        Format::Json => write_json(&program, out)?,
+        Format::Parens => writeln!(out, "{}", printer::parenthesize(&program))?,
+        Format::Tree => write!(out, "{}", printer::tree(&program))?,
+        Format::Cst => unreachable!("printed above"),
+    }
+    Ok(0)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FmtMode {
+    Print,
+    Check,
+    Write,
+}
+
+fn fmt<W: Write, E: Write>(args: &[String], out: &mut W, err: &mut E) -> i32 {
+    let mut mode = FmtMode::Print;
+    let mut paths = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "--check" if mode == FmtMode::Print => mode = FmtMode::Check,
+            "--write" if mode == FmtMode::Print => mode = FmtMode::Write,
+            "--check" | "--write" => {
+                let _ = writeln!(err, "--check and --write go alone\n\n{}", USAGE);
+                return 2;
+            }
+            flag if flag.starts_with("--") => {
+                let _ = writeln!(err, "unknown option {}\n\n{}", flag, USAGE);
+                return 2;
+            }
+            path => paths.push(path.to_string()),
+        }
+    }
+    let usage = match paths.len() {
+        0 => Some("missing file"),
+        1 => None,
+        _ if mode == FmtMode::Print => Some("fmt prints one file at a time"),
+        _ => None,
+    };
+    if let Some(msg) = usage {
+        let _ = writeln!(err, "{}\n\n{}", msg, USAGE);
+        return 2;
+    }
+    if mode == FmtMode::Write && paths.iter().any(|p| p == "-") {
+        let _ = writeln!(err, "cannot write back to standard input\n\n{}", USAGE);
+        return 2;
+    }
+
+    let mut code = 0;
+    for path in &paths {
+        if let Err(e) = fmt_file(path, mode, out, err, &mut code) {
+            let _ = writeln!(err, "{}: {}", path, e);
+            code = 1;
+        }
+    }
+    code
+}
+
+// Formats one file. Files with syntax errors are reported and left alone;
+// with --check, so are files that would change.
+fn fmt_file<W: Write, E: Write>(
+    path: &str,
+    mode: FmtMode,
+    out: &mut W,
+    err: &mut E,
+    code: &mut i32,
+) -> io::Result<()> {
+    let source = read_source(path)?;
+    let formatted = match format_source(&source) {
+        Ok(formatted) => formatted,
+        Err(errors) => {
+            for e in &errors {
+                writeln!(err, "{}:{}: {}", path, e.span, e)?;
+            }
+            *code = 1;
+            return Ok(());
+        }
+    };
+
+    match mode {
+        FmtMode::Print => write!(out, "{}", formatted)?,
+        FmtMode::Check if formatted != source => {
+            writeln!(out, "{}", path)?;
+            *code = 1;
+        }
+        FmtMode::Write if formatted != source => fs::write(path, formatted)?,
+        FmtMode::Check | FmtMode::Write => {}
+    }
+    Ok(())
+}
+
+fn lint<W: Write, E: Write>(args: &[String], out: &mut W, err: &mut E) -> i32 {
+    let mut fix = false;
+    let mut prelude = true;
+    let mut config_path = None;
+    let mut paths = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--fix" => fix = true,
+            "--no-prelude" => prelude = false,
+            "--config" => match args.next() {
+                Some(path) => config_path = Some(path.clone()),
+                None => {
+                    let _ = writeln!(err, "--config needs a value\n\n{}", USAGE);
+                    return 2;
+                }
+            },
+            flag if flag.starts_with("--") => {
+                let _ = writeln!(err, "unknown option {}\n\n{}", flag, USAGE);
+                return 2;
+            }
+            path => paths.push(path.to_string()),
+        }
+    }
+    if paths.is_empty() {
+        let _ = writeln!(err, "missing file\n\n{}", USAGE);
+        return 2;
+    }
+    if fix && paths.iter().any(|p| p == "-") {
+        let _ = writeln!(err, "cannot write back to standard input\n\n{}", USAGE);
+        return 2;
+    }
+
+    let mut code = 0;
+    for path in &paths {
+        let config = match lint_config(path, config_path.as_deref()) {
+            Ok(mut config) => {
+                config.prelude = prelude;
+                config
+            }
+            Err(e) => {
+                let _ = writeln!(err, "{}", e);
+                code = 1;
+                continue;
+            }
+        };
+        if let Err(e) = lint_file(path, &config, fix, out, err, &mut code) {
+            let _ = writeln!(err, "{}: {}", path, e);
+            code = 1;
+        }
+    }
+    code
+}
+
+// The config named on the command line, or else the nearest monkey.toml
+// above the file, or else the defaults.
+fn lint_config(path: &str, named: Option<&str>) -> Result<Config, String> {
+    if let Some(named) = named {
+        return Config::load(Path::new(named));
+    }
+    let dir = if path == "-" {
+        std::env::current_dir().map_err(|e| e.to_string())?
+    } else {
+        let file = fs::canonicalize(path).map_err(|e| format!("{}: {}", path, e))?;
+        file.parent().map(Path::to_path_buf).unwrap_or_default()
+    };
+    match Config::find(&dir) {
+        Some(found) => Config::load(&found),
+        None => Ok(Config::default()),
+    }
+}
+
+// Lints one file, after fixing it in place with --fix. Only errors fail
+// the run; warnings are reported and let it pass.
+fn lint_file<W: Write, E: Write>(
+    path: &str,
+    config: &Config,
+    fix: bool,
+    out: &mut W,
+    err: &mut E,
+    code: &mut i32,
+) -> io::Result<()> {
+    let mut source = read_source(path)?;
+    let mut result = lint_source(&source, config);
+    if fix && result.is_ok() {
+        let fixed = fix_source(&source, config).expect("the source parsed");
+        if fixed != source {
+            fs::write(path, &fixed)?;
+            source = fixed;
+            result = lint_source(&source, config);
+        }
+    }
+
+    let lints = match result {
+        Ok(lints) => lints,
+        Err(errors) => {
+            for e in &errors {
+                writeln!(err, "{}:{}: {}", path, e.span, e)?;
+            }
+            *code = 1;
+            return Ok(());
+        }
+    };
+    for lint in &lints {
+        writeln!(out, "{}:{}: {}", path, lint.span, lint)?;
+        match &lint.fix {
+            Some(fix) if fix.automatic => writeln!(out, "    fix: {}", fix.msg)?,
+            Some(fix) => writeln!(out, "    suggestion: {}", fix.msg)?,
+            None => {}
+        }
+        if lint.severity == Severity::Error {
+            *code = 1;
+        }
+    }
+    Ok(())
+}
+
+fn check<W: Write, E: Write>(args: &[String], out: &mut W, err: &mut E) -> i32 {
+    let mut prelude = true;
+    let mut paths = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "--no-prelude" => prelude = false,
+            flag if flag.starts_with("--") => {
+                let _ = writeln!(err, "unknown option {}\n\n{}", flag, USAGE);
+                return 2;
+            }
+            path => paths.push(path),
+        }
+    }
+    if paths.is_empty() {
+        let _ = writeln!(err, "missing file\n\n{}", USAGE);
+        return 2;
+    }
+
+    let mut code = 0;
+    for path in paths {
+        if let Err(e) = check_file(path, prelude, out, err, &mut code) {
+            let _ = writeln!(err, "{}: {}", path, e);
+            code = 1;
+        }
+    }
+    code
+}
+
+// Type checks one file. Each error gets a note for the other side of the
+// conflict when there is one.
+fn check_file<W: Write, E: Write>(
+    path: &str,
+    prelude: bool,
+    out: &mut W,
+    err: &mut E,
+    code: &mut i32,
+) -> io::Result<()> {
+    let source = read_source(path)?;
+    let mut p = Parser::new(Lexer::new(&source));
+    let program = p.parse_program();
+    let errors = p.errors();
+    if !errors.is_empty() {
+        for e in &errors {
+            writeln!(err, "{}:{}: {}", path, e.span, e)?;
+        }
+        *code = 1;
+        return Ok(());
+    }
+
+    for e in typeck::check_with_prelude(&program, prelude).errors {
+        writeln!(out, "{}:{}: error: {}", path, e.span, e)?;
+        if let Some((span, note)) = &e.related {
+            writeln!(out, "    note: {}:{}: {}", path, span, note)?;
+        }
+        *code = 1;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "serde")]
+fn lsp<W: Write, E: Write>(prelude: bool, out: &mut W, err: &mut E) -> i32 {
+    match lsp::server::run(io::stdin().lock(), out, prelude) {
+        Ok(code) => code,
+        Err(e) => {
+            let _ = writeln!(err, "lsp: {}", e);
+            1
+        }
+    }
+}
+
+#[cfg(not(feature = "serde"))]
+fn lsp<W: Write, E: Write>(_prelude: bool, _out: &mut W, err: &mut E) -> i32 {
+    let _ = writeln!(
+        err,
+        "the language server needs monkey built with the `serde` feature"
+    );
+    1
+}
+
+#[cfg(feature = "serde")]
+fn write_json<T: serde::Serialize + ?Sized, W: Write>(value: &T, out: &mut W) -> io::Result<()> {
+    serde_json::to_writer_pretty(&mut *out, value)?;
+    writeln!(out)
+}
+
+#[cfg(not(feature = "serde"))]
+fn write_json<T: ?Sized, W: Write>(_value: &T, _out: &mut W) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "JSON output needs monkey built with the `serde` feature",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn source_file(name: &str, source: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("monkey-cli-{}-{}", std::process::id(), name));
+        fs::write(&path, source).unwrap();
+        path
+    }
+
+    fn run_args(args: &[&str]) -> (i32, String, String) {
+        let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        let code = run(&args, &mut out, &mut err);
+        (
+            code,
+            String::from_utf8(out).unwrap(),
+            String::from_utf8(err).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_lex_text() {
+        let path = source_file("lex.mk", "let x = 5;\n");
+        let (code, out, err) = run_args(&["lex", path.to_str().unwrap()]);
+        fs::remove_file(path).unwrap();
+
+        assert_eq!(code, 0, "{}", err);
+        assert_eq!(
+            out,
+            "1:1\tLet\tlet\n1:5\tIdent\tx\n1:7\tAssign\t=\n1:9\tInt\t5\n1:10\tSemicolon\t;\n"
+        );
+    }
+
+    #[test]
+    fn test_parse_text_and_errors() {
+        let path = source_file("parse.mk", "let x = 1 + 2 * 3;");
+        let (code, out, _) = run_args(&["parse", path.to_str().unwrap()]);
+        fs::remove_file(path).unwrap();
+        assert_eq!(code, 0);
+        assert_eq!(out, "let x = 1 + 2 * 3;\n");
+
+        let path = source_file("statements.mk", "x += 1; arr[0] = 2;");
+        let (code, out, _) = run_args(&["parse", path.to_str().unwrap()]);
+        fs::remove_file(path).unwrap();
+        assert_eq!(code, 0);
+        assert_eq!(out, "x += 1;\narr[0] = 2;\n");
+
+        let path = source_file("broken.mk", "let = 1;");
+        let (code, out, err) = run_args(&["parse", path.to_str().unwrap()]);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(code, 1);
+        assert!(out.is_empty());
+        assert!(
+            err.starts_with(&format!("{}:1:5: Unexpected Token: ", path.display())),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_parens_and_tree_output() {
+        let path = source_file("grouping.mk", "a + b * c");
+        let file = path.to_str().unwrap();
+
+        let (code, out, _) = run_args(&["parse", "--format", "parens", file]);
+        assert_eq!(code, 0);
+        assert_eq!(out, "(a + (b * c))\n");
+
+        let (code, out, _) = run_args(&["parse", "--format", "tree", file]);
+        assert_eq!(code, 0);
+        assert!(
+            out.starts_with("Program\n  ExpressionStatement @1:1\n"),
+            "{}",
+            out
+        );
+
+        let (code, out, _) = run_args(&["parse", "--format", "cst", file]);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(code, 0);
+        assert!(
+            out.starts_with("Program@0..9\n  ExpressionStatement@0..9\n"),
+            "{}",
+            out
+        );
+    }
+
+    #[test]
+    fn test_parse_optimize() {
+        let source = "let day = 60 * 60 * 24; if (false) { puts(day) } day * 2";
+        let path = source_file("optimize.mk", source);
+        let (code, out, err) = run_args(&["parse", "--optimize", path.to_str().unwrap()]);
+        fs::remove_file(path).unwrap();
+
+        assert_eq!(code, 0, "{}", err);
+        assert_eq!(out, "let day = 86400;\n172800;\n");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_json_output() {
+        let path = source_file("json.mk", "f(1)");
+        let file = path.to_str().unwrap();
+
+        let (code, out, _) = run_args(&["lex", "--format", "json", file]);
+        assert_eq!(code, 0);
+        let tokens: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(tokens[0]["type"], "Ident");
+        assert_eq!(tokens[2]["value"]["Int"], "1");
+        assert_eq!(tokens.as_array().unwrap().len(), 4);
+
+        let (code, out, _) = run_args(&["parse", "--format", "json", file]);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(code, 0);
+        let program: crate::ast::ast::Program = serde_json::from_str(&out).unwrap();
+        assert_eq!(program.to_string(), "f(1)");
+    }
+
+    #[test]
+    fn test_fmt() {
+        let messy = source_file("messy.mk", "let x=1;  // one\nputs( x )");
+        let tidy = source_file("tidy.mk", "let y = 2;\n");
+        let (messy_file, tidy_file) = (messy.to_str().unwrap(), tidy.to_str().unwrap());
+
+        let (code, out, _) = run_args(&["fmt", messy_file]);
+        assert_eq!(code, 0);
+        assert_eq!(out, "let x = 1; // one\nputs(x);\n");
+
+        let (code, out, _) = run_args(&["fmt", "--check", messy_file, tidy_file]);
+        assert_eq!(code, 1);
+        assert_eq!(out, format!("{}\n", messy_file));
+
+        let (code, out, _) = run_args(&["fmt", "--write", messy_file, tidy_file]);
+        assert_eq!((code, out.as_str()), (0, ""));
+        assert_eq!(
+            fs::read_to_string(&messy).unwrap(),
+            "let x = 1; // one\nputs(x);\n"
+        );
+        let (code, _, _) = run_args(&["fmt", "--check", messy_file, tidy_file]);
+        assert_eq!(code, 0);
+
+        fs::write(&messy, "let = 1;").unwrap();
+        let (code, _, err) = run_args(&["fmt", "--write", messy_file]);
+        assert_eq!(code, 1);
+        assert!(err.contains("Unexpected Token"), "{}", err);
+        assert_eq!(fs::read_to_string(&messy).unwrap(), "let = 1;");
+
+        fs::remove_file(messy).unwrap();
+        fs::remove_file(tidy).unwrap();
+    }
+
+    #[test]
+    fn test_lint() {
+        let dir = std::env::temp_dir().join(format!("monkey-cli-{}-lint", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("main.mk");
+        let path = file.to_str().unwrap();
+        fs::write(&file, "let isOk = 1 == true;\nputs(isOk, missing);\n").unwrap();
+
+        let (code, out, _) = run_args(&["lint", path]);
+        assert_eq!(code, 1);
+        assert_eq!(
+            out,
+            format!(
+                "{0}:1:5: warning[naming]: isOk should be snake_case\n    fix: rename to is_ok\n\
+                 {0}:1:12: warning[bool-comparison]: comparison to true\n    suggestion: replace with 1\n\
+                 {0}:2:12: error[undefined-name]: missing is not defined\n",
+                path
+            )
+        );
+
+        // the nearest monkey.toml sets the rules
+        fs::write(
+            dir.join("monkey.toml"),
+            "[lint.rules]\nundefined-name = \"off\"\nbool-comparison = \"off\"\n",
+        )
+        .unwrap();
+        let (code, out, _) = run_args(&["lint", "--fix", path]);
+        assert_eq!((code, out.as_str()), (0, ""));
+        assert_eq!(
+            fs::read_to_string(&file).unwrap(),
+            "let is_ok = 1 == true;\nputs(is_ok, missing);\n"
+        );
+
+        fs::write(dir.join("monkey.toml"), "[lint.rules]\nnaming = \"loud\"\n").unwrap();
+        let (code, _, err) = run_args(&["lint", path]);
+        assert_eq!(code, 1);
+        assert!(
+            err.contains("monkey.toml: line 2: naming must be"),
+            "{}",
+            err
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_check() {
+        let good = source_file(
+            "check-good.mk",
+            "let inc = fn(n: int) -> int { n + 1 };\nputs(inc(1));\n",
+        );
+        let bad = source_file(
+            "check-bad.mk",
+            "let n: int = \"one\";\nlet s = n + \"a\";\n",
+        );
+        let (good_path, bad_path) = (good.to_str().unwrap(), bad.to_str().unwrap());
+
+        let (code, out, _) = run_args(&["check", good_path]);
+        assert_eq!((code, out.as_str()), (0, ""));
+
+        let (code, out, _) = run_args(&["check", good_path, bad_path]);
+        assert_eq!(code, 1);
+        assert_eq!(
+            out,
+            format!(
+                "{0}:1:14: error: expected int, found string\n    \
+                 note: {0}:1:8: annotated as int here\n\
+                 {0}:2:13: error: expected int, found string\n    \
+                 note: {0}:2:9: the left operand is int\n",
+                bad_path
+            )
+        );
+
+        fs::remove_file(good).unwrap();
+        fs::remove_file(bad).unwrap();
+    }
+
+    #[test]
+    fn test_no_prelude() {
+        let file = source_file("no-prelude.mk", "puts(map([1], fn(v) { v }) - 1);\n");
+        let path = file.to_str().unwrap();
+
+        let (code, out, _) = run_args(&["check", path]);
+        assert_eq!(code, 1, "{}", out);
+        let (code, out, _) = run_args(&["check", "--no-prelude", path]);
+        assert_eq!((code, out.as_str()), (0, ""));
+
+        let (code, out, _) = run_args(&["lint", "--config", "/dev/null", path]);
+        assert_eq!((code, out.as_str()), (0, ""));
+        let (code, out, _) = run_args(&["lint", "--config", "/dev/null", "--no-prelude", path]);
+        assert_eq!(code, 1);
+        assert_eq!(
+            out,
+            format!("{}:1:6: error[undefined-name]: map is not defined\n", path)
+        );
+
+        fs::remove_file(file).unwrap();
+    }
+
+    #[test]
+    fn test_usage_errors() {
+        for args in [
+            &[][..],
+            &["run", "x.mk"],
+            &["lex"],
+            &["lex", "--format", "xml", "x.mk"],
+            &["lex", "--format", "tree", "x.mk"],
+            &["lex", "--format", "cst", "x.mk"],
+            &["parse", "a.mk", "b.mk"],
+            &["lex", "--optimize", "x.mk"],
+            &["parse", "--optimize", "--format", "cst", "x.mk"],
+            &["fmt"],
+            &["fmt", "a.mk", "b.mk"],
+            &["fmt", "--check", "--write", "a.mk"],
+            &["fmt", "--write", "-"],
+            &["lsp", "x.mk"],
+            &["lsp", "--no-prelude", "x.mk"],
+            &["lint"],
+            &["lint", "--config"],
+            &["lint", "--strict", "a.mk"],
+            &["lint", "--fix", "-"],
+            &["check"],
+            &["check", "--strict", "a.mk"],
+        ] {
+            let (code, _, err) = run_args(args);
+            assert_eq!(code, 2, "args={:?}", args);
+            assert!(err.contains("usage: monkey"), "args={:?}", args);
+        }
+    }
+}
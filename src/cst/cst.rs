@@ -0,0 +1,428 @@
+use std::fmt;
+use std::ops::Range;
+use std::rc::Rc;
+
+use crate::lexer::token::TokenType;
+
+// A lossless syntax tree: every byte of the source, whitespace and comments
+// included, is the text of exactly one token, so printing the tree gives
+// the source back. It comes in two layers, as in red-green trees:
+//
+// - green nodes only know their kind, their children and their length.
+//   They are immutable and can be shared between trees.
+// - SyntaxNode/SyntaxToken (the red layer) wrap a green node together with
+//   its absolute offset and its parent, and are built on the way down.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SyntaxKind {
+    // a token from the lexer
+    Token(TokenType),
+    // trivia: a run of whitespace, or a `//` comment without its newline
+    Whitespace,
+    Comment,
+
+    Program,
+    LetStatement,
+    ReturnStatement,
+    ThrowStatement,
+    ExpressionStatement,
+    BlockStatement,
+    TryStatement,
+    CatchClause,
+    FinallyClause,
+    WhileStatement,
+    ForStatement,
+    BreakStatement,
+    ContinueStatement,
+    ImportStatement,
+    ExportStatement,
+
+    Name,
+    Literal,
+    PrefixExpression,
+    InfixExpression,
+    AssignExpression,
+    ParenExpression,
+    ArrayLiteral,
+    IndexExpression,
+    MemberExpression,
+    CallExpression,
+    ArgumentList,
+    IfExpression,
+    ElseClause,
+    FunctionLiteral,
+    MacroLiteral,
+    ParameterList,
+    // `: T` after a let name or a parameter, `-> T` after a parameter list
+    TypeAnnotation,
+
+    // tokens the parser could not place, or text the lexer made no token of
+    Error,
+}
+
+impl SyntaxKind {
+    pub fn is_trivia(self) -> bool {
+        matches!(self, SyntaxKind::Whitespace | SyntaxKind::Comment)
+    }
+}
+
+impl fmt::Display for SyntaxKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SyntaxKind::Token(t) => write!(f, "{:?}", t),
+            kind => write!(f, "{:?}", kind),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct GreenToken {
+    pub kind: SyntaxKind,
+    pub text: String,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct GreenNode {
+    pub kind: SyntaxKind,
+    pub children: Vec<GreenElement>,
+    len: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GreenElement {
+    Node(Rc<GreenNode>),
+    Token(Rc<GreenToken>),
+}
+
+impl GreenNode {
+    pub fn new(kind: SyntaxKind, children: Vec<GreenElement>) -> Self {
+        let len = children.iter().map(GreenElement::len).sum();
+        GreenNode {
+            kind,
+            children,
+            len,
+        }
+    }
+
+    // Length of the node's text in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl GreenElement {
+    pub fn kind(&self) -> SyntaxKind {
+        match self {
+            GreenElement::Node(node) => node.kind,
+            GreenElement::Token(token) => token.kind,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            GreenElement::Node(node) => node.len(),
+            GreenElement::Token(token) => token.text.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl fmt::Display for GreenNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for child in &self.children {
+            match child {
+                GreenElement::Node(node) => write!(f, "{}", node)?,
+                GreenElement::Token(token) => write!(f, "{}", token.text)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+// Builds a green tree bottom-up, the way a parser visits it. Checkpoints
+// let the parser wrap already finished children in a new node, which is
+// how infix expressions get their left operand.
+#[derive(Default)]
+pub struct GreenBuilder {
+    parents: Vec<(SyntaxKind, Vec<GreenElement>)>,
+    children: Vec<GreenElement>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Checkpoint(usize);
+
+impl GreenBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn token(&mut self, kind: SyntaxKind, text: &str) {
+        self.children.push(GreenElement::Token(Rc::new(GreenToken {
+            kind,
+            text: text.to_string(),
+        })));
+    }
+
+    pub fn start_node(&mut self, kind: SyntaxKind) {
+        let children = std::mem::take(&mut self.children);
+        self.parents.push((kind, children));
+    }
+
+    pub fn finish_node(&mut self) {
+        let (kind, parent_children) = self.parents.pop().expect("finish_node without start_node");
+        let children = std::mem::replace(&mut self.children, parent_children);
+        self.children
+            .push(GreenElement::Node(Rc::new(GreenNode::new(kind, children))));
+    }
+
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint(self.children.len())
+    }
+
+    // Starts a node that takes every child added since the checkpoint.
+    pub fn start_node_at(&mut self, checkpoint: Checkpoint, kind: SyntaxKind) {
+        let Checkpoint(at) = checkpoint;
+        assert!(at <= self.children.len(), "checkpoint is no longer valid");
+        let wrapped = self.children.split_off(at);
+        let children = std::mem::replace(&mut self.children, wrapped);
+        self.parents.push((kind, children));
+    }
+
+    // The finished tree. Exactly one node must be left open.
+    pub fn finish(mut self) -> Rc<GreenNode> {
+        assert!(self.parents.is_empty(), "unfinished nodes");
+        match self.children.pop() {
+            Some(GreenElement::Node(node)) if self.children.is_empty() => node,
+            _ => panic!("the builder must hold a single root node"),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct SyntaxNode(Rc<NodeData>);
+
+struct NodeData {
+    green: Rc<GreenNode>,
+    offset: usize,
+    parent: Option<SyntaxNode>,
+}
+
+#[derive(Clone)]
+pub struct SyntaxToken {
+    green: Rc<GreenToken>,
+    offset: usize,
+    parent: SyntaxNode,
+}
+
+#[derive(Clone)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+impl SyntaxNode {
+    pub fn new_root(green: Rc<GreenNode>) -> Self {
+        SyntaxNode(Rc::new(NodeData {
+            green,
+            offset: 0,
+            parent: None,
+        }))
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.0.green.kind
+    }
+
+    pub fn green(&self) -> &Rc<GreenNode> {
+        &self.0.green
+    }
+
+    // Byte range of the node in the source.
+    pub fn range(&self) -> Range<usize> {
+        self.0.offset..self.0.offset + self.0.green.len()
+    }
+
+    pub fn parent(&self) -> Option<SyntaxNode> {
+        self.0.parent.clone()
+    }
+
+    // The node itself, then its parent and so on up to the root.
+    pub fn ancestors(&self) -> impl Iterator<Item = SyntaxNode> {
+        std::iter::successors(Some(self.clone()), SyntaxNode::parent)
+    }
+
+    pub fn children_with_tokens(&self) -> Vec<SyntaxElement> {
+        let mut offset = self.0.offset;
+        let mut children = Vec::with_capacity(self.0.green.children.len());
+        for child in &self.0.green.children {
+            children.push(match child {
+                GreenElement::Node(green) => SyntaxElement::Node(SyntaxNode(Rc::new(NodeData {
+                    green: green.clone(),
+                    offset,
+                    parent: Some(self.clone()),
+                }))),
+                GreenElement::Token(green) => SyntaxElement::Token(SyntaxToken {
+                    green: green.clone(),
+                    offset,
+                    parent: self.clone(),
+                }),
+            });
+            offset += child.len();
+        }
+        children
+    }
+
+    pub fn children(&self) -> Vec<SyntaxNode> {
+        self.children_with_tokens()
+            .into_iter()
+            .filter_map(|child| match child {
+                SyntaxElement::Node(node) => Some(node),
+                SyntaxElement::Token(_) => None,
+            })
+            .collect()
+    }
+
+    // Every token under the node, trivia included, in source order.
+    pub fn tokens(&self) -> Vec<SyntaxToken> {
+        let mut tokens = Vec::new();
+        for child in self.children_with_tokens() {
+            match child {
+                SyntaxElement::Node(node) => tokens.extend(node.tokens()),
+                SyntaxElement::Token(token) => tokens.push(token),
+            }
+        }
+        tokens
+    }
+
+    // The token covering `offset`. At the boundary between two tokens the
+    // one starting there wins, and the last token holds the end of input.
+    pub fn token_at_offset(&self, offset: usize) -> Option<SyntaxToken> {
+        let mut node = self.clone();
+        'down: loop {
+            let children = node.children_with_tokens();
+            let last = children.len().checked_sub(1)?;
+            for (i, child) in children.into_iter().enumerate() {
+                let range = child.range();
+                if range.contains(&offset) || (i == last && range.end == offset) {
+                    match child {
+                        SyntaxElement::Node(child) => {
+                            node = child;
+                            continue 'down;
+                        }
+                        SyntaxElement::Token(token) => return Some(token),
+                    }
+                }
+            }
+            return None;
+        }
+    }
+
+    // An indented listing of the tree with the byte range of every element
+    // and the text of every token, for tests and `monkey parse --format cst`.
+    pub fn dump(&self) -> String {
+        let mut out = String::new();
+        self.dump_into(&mut out, 0);
+        out
+    }
+
+    fn dump_into(&self, out: &mut String, depth: usize) {
+        let range = self.range();
+        out.push_str(&format!(
+            "{}{}@{}..{}\n",
+            "  ".repeat(depth),
+            self.kind(),
+            range.start,
+            range.end
+        ));
+        for child in self.children_with_tokens() {
+            match child {
+                SyntaxElement::Node(node) => node.dump_into(out, depth + 1),
+                SyntaxElement::Token(token) => {
+                    let range = token.range();
+                    out.push_str(&format!(
+                        "{}{}@{}..{} {:?}\n",
+                        "  ".repeat(depth + 1),
+                        token.kind(),
+                        range.start,
+                        range.end,
+                        token.text()
+                    ));
+                }
+            }
+        }
+    }
+}
+
+impl SyntaxToken {
+    pub fn kind(&self) -> SyntaxKind {
+        self.green.kind
+    }
+
+    pub fn text(&self) -> &str {
+        &self.green.text
+    }
+
+    pub fn range(&self) -> Range<usize> {
+        self.offset..self.offset + self.green.text.len()
+    }
+
+    pub fn parent(&self) -> SyntaxNode {
+        self.parent.clone()
+    }
+}
+
+impl SyntaxElement {
+    pub fn kind(&self) -> SyntaxKind {
+        match self {
+            SyntaxElement::Node(node) => node.kind(),
+            SyntaxElement::Token(token) => token.kind(),
+        }
+    }
+
+    pub fn range(&self) -> Range<usize> {
+        match self {
+            SyntaxElement::Node(node) => node.range(),
+            SyntaxElement::Token(token) => token.range(),
+        }
+    }
+}
+
+impl fmt::Display for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.green)
+    }
+}
+
+impl fmt::Display for SyntaxToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.green.text)
+    }
+}
+
+impl fmt::Debug for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let range = self.range();
+        write!(f, "{}@{}..{}", self.kind(), range.start, range.end)
+    }
+}
+
+impl fmt::Debug for SyntaxToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let range = self.range();
+        write!(
+            f,
+            "{}@{}..{} {:?}",
+            self.kind(),
+            range.start,
+            range.end,
+            self.text()
+        )
+    }
+}
@@ -0,0 +1,645 @@
+use std::rc::Rc;
+
+use crate::cst::cst::{Checkpoint, GreenBuilder, GreenNode, SyntaxKind, SyntaxNode};
+use crate::lexer::lexer::{Lexer, LexerTrait};
+use crate::lexer::token::{Token, TokenType};
+use crate::parser::parser::{precedence_of, right_associative, Predecessor};
+
+// Parses `source` into a lossless syntax tree. It follows the grammar of
+// parser::parser::Parser, with the same precedences, but never gives up:
+// missing tokens are simply absent from their node and tokens that fit
+// nowhere are wrapped in Error nodes. Diagnostics are the AST parser's job.
+pub fn parse(source: &str) -> SyntaxNode {
+    SyntaxNode::new_root(parse_green(source))
+}
+
+pub fn parse_green(source: &str) -> Rc<GreenNode> {
+    let mut lexer = Lexer::new(source);
+    let mut tokens = Vec::new();
+    loop {
+        let token = lexer.next_token();
+        if token.type_ == TokenType::Eof {
+            break;
+        }
+        tokens.push(token);
+    }
+
+    let mut p = CstParser {
+        source,
+        tokens,
+        pos: 0,
+        offset: 0,
+        builder: GreenBuilder::new(),
+    };
+    p.builder.start_node(SyntaxKind::Program);
+    while p.current().is_some() {
+        p.statement();
+    }
+    p.trivia();
+    p.builder.finish_node();
+    p.builder.finish()
+}
+
+struct CstParser<'s> {
+    source: &'s str,
+    tokens: Vec<Token>,
+    pos: usize,
+    // how much of the source is in the tree so far
+    offset: usize,
+    builder: GreenBuilder,
+}
+
+impl CstParser<'_> {
+    fn current(&self) -> Option<TokenType> {
+        self.tokens.get(self.pos).map(|t| t.type_)
+    }
+
+    fn at(&self, t: TokenType) -> bool {
+        self.current() == Some(t)
+    }
+
+    // Adds the whitespace and comments before the next token (or the end
+    // of input) to the node being built.
+    fn trivia(&mut self) {
+        let end = self
+            .tokens
+            .get(self.pos)
+            .map_or(self.source.len(), |t| t.span.start)
+            .max(self.offset);
+        let end = self.char_boundary(end);
+        let mut rest = &self.source[self.offset..end];
+        while !rest.is_empty() {
+            let (kind, len) = if rest.starts_with("//") {
+                (SyntaxKind::Comment, rest.find('\n').unwrap_or(rest.len()))
+            } else if rest.starts_with(char::is_whitespace) {
+                let len = rest
+                    .find(|c: char| !c.is_whitespace())
+                    .unwrap_or(rest.len());
+                (SyntaxKind::Whitespace, len)
+            } else {
+                // left behind by a lexer error
+                (
+                    SyntaxKind::Error,
+                    rest.chars().next().map_or(1, char::len_utf8),
+                )
+            };
+            self.builder.token(kind, &rest[..len]);
+            rest = &rest[len..];
+        }
+        self.offset = end;
+    }
+
+    fn bump(&mut self) {
+        self.trivia();
+        let token = &self.tokens[self.pos];
+        let end = self.char_boundary(token.span.end.max(self.offset));
+        self.builder.token(
+            SyntaxKind::Token(token.type_),
+            &self.source[self.offset..end],
+        );
+        self.offset = end;
+        self.pos += 1;
+    }
+
+    // The lexer works on bytes, so the tokens it makes of non-ASCII input
+    // can end inside a character. Those get the rest of the character.
+    fn char_boundary(&self, mut offset: usize) -> usize {
+        while !self.source.is_char_boundary(offset) {
+            offset += 1;
+        }
+        offset
+    }
+
+    fn eat(&mut self, t: TokenType) -> bool {
+        if self.at(t) {
+            self.bump();
+            true
+        } else {
+            false
+        }
+    }
+
+    // Nodes start at their first token, so trivia before it goes to the
+    // parent.
+    fn start(&mut self, kind: SyntaxKind) {
+        self.trivia();
+        self.builder.start_node(kind);
+    }
+
+    fn finish(&mut self) {
+        self.builder.finish_node();
+    }
+
+    fn checkpoint(&mut self) -> Checkpoint {
+        self.trivia();
+        self.builder.checkpoint()
+    }
+
+    fn error(&mut self) {
+        self.start(SyntaxKind::Error);
+        self.bump();
+        self.finish();
+    }
+
+    fn statement(&mut self) {
+        let before = self.pos;
+        match self.current() {
+            Some(TokenType::Let) => self.let_statement(),
+            Some(TokenType::Return) => self.value_statement(SyntaxKind::ReturnStatement),
+            Some(TokenType::Throw) => self.value_statement(SyntaxKind::ThrowStatement),
+            Some(TokenType::Try) => self.try_statement(),
+            Some(TokenType::While) => {
+                self.start(SyntaxKind::WhileStatement);
+                self.bump();
+                self.eat(TokenType::LParen);
+                self.expression(Predecessor::LOWEST);
+                self.eat(TokenType::RParen);
+                self.block();
+                self.eat(TokenType::Semicolon);
+                self.finish();
+            }
+            Some(TokenType::For) => {
+                self.start(SyntaxKind::ForStatement);
+                self.bump();
+                self.eat(TokenType::LParen);
+                self.eat(TokenType::Ident);
+                self.eat(TokenType::In);
+                self.expression(Predecessor::LOWEST);
+                self.eat(TokenType::RParen);
+                self.block();
+                self.eat(TokenType::Semicolon);
+                self.finish();
+            }
+            Some(TokenType::Break) => self.keyword_statement(SyntaxKind::BreakStatement),
+            Some(TokenType::Continue) => self.keyword_statement(SyntaxKind::ContinueStatement),
+            Some(TokenType::Import) => {
+                self.start(SyntaxKind::ImportStatement);
+                self.bump();
+                self.eat(TokenType::String);
+                self.eat(TokenType::As);
+                self.eat(TokenType::Ident);
+                self.eat(TokenType::Semicolon);
+                self.finish();
+            }
+            Some(TokenType::Export) => {
+                self.start(SyntaxKind::ExportStatement);
+                self.bump();
+                if self.at(TokenType::Let) {
+                    self.let_statement();
+                }
+                self.finish();
+            }
+            Some(_) => {
+                let checkpoint = self.checkpoint();
+                if self.expression(Predecessor::LOWEST) {
+                    self.builder
+                        .start_node_at(checkpoint, SyntaxKind::ExpressionStatement);
+                    self.eat(TokenType::Semicolon);
+                    self.finish();
+                }
+            }
+            None => return,
+        }
+        // every statement takes at least one token, so parsing moves on
+        if self.pos == before {
+            self.error();
+        }
+    }
+
+    fn let_statement(&mut self) {
+        self.start(SyntaxKind::LetStatement);
+        self.bump();
+        self.eat(TokenType::Ident);
+        self.annotation(TokenType::Colon);
+        self.eat(TokenType::Assign);
+        self.expression(Predecessor::LOWEST);
+        self.eat(TokenType::Semicolon);
+        self.finish();
+    }
+
+    // return and throw
+    fn value_statement(&mut self, kind: SyntaxKind) {
+        self.start(kind);
+        self.bump();
+        self.expression(Predecessor::LOWEST);
+        self.eat(TokenType::Semicolon);
+        self.finish();
+    }
+
+    // break and continue
+    fn keyword_statement(&mut self, kind: SyntaxKind) {
+        self.start(kind);
+        self.bump();
+        self.eat(TokenType::Semicolon);
+        self.finish();
+    }
+
+    fn try_statement(&mut self) {
+        self.start(SyntaxKind::TryStatement);
+        self.bump();
+        self.block();
+        if self.at(TokenType::Catch) {
+            self.start(SyntaxKind::CatchClause);
+            self.bump();
+            if self.eat(TokenType::LParen) {
+                self.eat(TokenType::Ident);
+                self.eat(TokenType::RParen);
+            }
+            self.block();
+            self.finish();
+        }
+        if self.at(TokenType::Finally) {
+            self.start(SyntaxKind::FinallyClause);
+            self.bump();
+            self.block();
+            self.finish();
+        }
+        self.eat(TokenType::Semicolon);
+        self.finish();
+    }
+
+    fn block(&mut self) {
+        if !self.at(TokenType::LBrace) {
+            return;
+        }
+        self.start(SyntaxKind::BlockStatement);
+        self.bump();
+        while !self.at(TokenType::RBrace) && self.current().is_some() {
+            self.statement();
+        }
+        self.eat(TokenType::RBrace);
+        self.finish();
+    }
+
+    // Parses an expression binding tighter than `precedence`. Returns false,
+    // having consumed nothing, when no expression starts here.
+    fn expression(&mut self, precedence: Predecessor) -> bool {
+        let checkpoint = self.checkpoint();
+        if !self.prefix() {
+            return false;
+        }
+
+        while let Some(t) = self.current() {
+            let next = precedence_of(&t);
+            if t == TokenType::Semicolon || precedence >= next {
+                break;
+            }
+            match t {
+                TokenType::LParen => {
+                    self.builder
+                        .start_node_at(checkpoint, SyntaxKind::CallExpression);
+                    self.list(SyntaxKind::ArgumentList, TokenType::RParen);
+                }
+                TokenType::LBracket => {
+                    self.builder
+                        .start_node_at(checkpoint, SyntaxKind::IndexExpression);
+                    self.bump();
+                    self.expression(Predecessor::LOWEST);
+                    self.eat(TokenType::RBracket);
+                }
+                TokenType::Dot => {
+                    self.builder
+                        .start_node_at(checkpoint, SyntaxKind::MemberExpression);
+                    self.bump();
+                    self.eat(TokenType::Ident);
+                }
+                _ if next == Predecessor::ASSIGN => {
+                    self.builder
+                        .start_node_at(checkpoint, SyntaxKind::AssignExpression);
+                    self.bump();
+                    self.expression(Predecessor::LOWEST);
+                }
+                _ => {
+                    self.builder
+                        .start_node_at(checkpoint, SyntaxKind::InfixExpression);
+                    self.bump();
+                    if right_associative(&t) {
+                        self.expression(Predecessor::PREFIX);
+                    } else {
+                        self.expression(next);
+                    }
+                }
+            }
+            self.finish();
+        }
+        true
+    }
+
+    fn prefix(&mut self) -> bool {
+        let Some(t) = self.current() else {
+            return false;
+        };
+        match t {
+            TokenType::Ident => {
+                self.start(SyntaxKind::Name);
+                self.bump();
+            }
+            TokenType::Int
+            | TokenType::Float
+            | TokenType::String
+            | TokenType::InterpolatedString
+            | TokenType::True
+            | TokenType::False => {
+                self.start(SyntaxKind::Literal);
+                self.bump();
+            }
+            TokenType::Illegal => {
+                self.start(SyntaxKind::Error);
+                self.bump();
+            }
+            TokenType::BANG | TokenType::MINUS => {
+                self.start(SyntaxKind::PrefixExpression);
+                self.bump();
+                self.expression(Predecessor::PREFIX);
+            }
+            TokenType::LParen => {
+                self.start(SyntaxKind::ParenExpression);
+                self.bump();
+                self.expression(Predecessor::LOWEST);
+                self.eat(TokenType::RParen);
+            }
+            TokenType::LBracket => {
+                self.start(SyntaxKind::ArrayLiteral);
+                self.list(SyntaxKind::Error, TokenType::RBracket);
+            }
+            TokenType::If => {
+                self.start(SyntaxKind::IfExpression);
+                self.bump();
+                self.eat(TokenType::LParen);
+                self.expression(Predecessor::LOWEST);
+                self.eat(TokenType::RParen);
+                self.block();
+                if self.at(TokenType::Else) {
+                    self.start(SyntaxKind::ElseClause);
+                    self.bump();
+                    self.block();
+                    self.finish();
+                }
+            }
+            TokenType::Function | TokenType::Macro => {
+                let kind = if t == TokenType::Function {
+                    SyntaxKind::FunctionLiteral
+                } else {
+                    SyntaxKind::MacroLiteral
+                };
+                self.start(kind);
+                self.bump();
+                if self.at(TokenType::LParen) {
+                    self.list(SyntaxKind::ParameterList, TokenType::RParen);
+                }
+                self.annotation(TokenType::Arrow);
+                self.block();
+            }
+            _ => return false,
+        }
+        self.finish();
+        true
+    }
+
+    // A type annotation starting with `marker`, the type's tokens kept flat
+    // in the TypeAnnotation node.
+    fn annotation(&mut self, marker: TokenType) {
+        if !self.at(marker) {
+            return;
+        }
+        self.start(SyntaxKind::TypeAnnotation);
+        self.bump();
+        self.type_tokens();
+        self.finish();
+    }
+
+    // Returns false, having consumed nothing, when no type starts here.
+    fn type_tokens(&mut self) -> bool {
+        match self.current() {
+            Some(TokenType::Ident) => self.bump(),
+            Some(TokenType::LBracket) => {
+                self.bump();
+                self.type_tokens();
+                self.eat(TokenType::RBracket);
+            }
+            Some(TokenType::Function) => {
+                self.bump();
+                if self.eat(TokenType::LParen) {
+                    while self.type_tokens() && self.eat(TokenType::Comma) {}
+                    self.eat(TokenType::RParen);
+                }
+                if self.eat(TokenType::Arrow) {
+                    self.type_tokens();
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    // A comma separated list of expressions from the current opening token
+    // up to `close`. The items go in a node of their own unless `kind` is
+    // Error, which array literals use to keep them as direct children.
+    fn list(&mut self, kind: SyntaxKind, close: TokenType) {
+        let own_node = kind != SyntaxKind::Error;
+        if own_node {
+            self.start(kind);
+        }
+        self.bump();
+        while !self.at(close) && self.current().is_some() {
+            if !self.expression(Predecessor::LOWEST) {
+                if self.at(TokenType::RBrace) {
+                    break;
+                }
+                self.error();
+            }
+            if kind == SyntaxKind::ParameterList {
+                self.annotation(TokenType::Colon);
+            }
+            if !self.eat(TokenType::Comma) && !self.at(close) {
+                break;
+            }
+        }
+        self.eat(close);
+        if own_node {
+            self.finish();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cst::cst::SyntaxElement;
+
+    fn kinds(node: &SyntaxNode) -> Vec<SyntaxKind> {
+        node.children().iter().map(SyntaxNode::kind).collect()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let corpus = [
+            crate::prelude::prelude::SOURCE,
+            include_str!("../format/corpus/comments.mk"),
+            include_str!("../format/corpus/layout.mk"),
+            include_str!("../format/corpus/operators.mk"),
+        ];
+        let broken = [
+            "",
+            "   \n\t// only a comment",
+            "let = ;",
+            "let x = \"unterminated",
+            "fn(a, { }",
+            "if (x { y } else",
+            "}}}) ] let a = [1, 2,, 3]",
+            "a @ b # c",
+            "\"${1 + \"",
+            "let café = \"naïve\";\r\nputs(café)\r\n",
+            "x.1 = 2",
+            "foo(1 2)",
+            "let f: fn(int, [T]) -> [T] = fn(n: int, xs) -> [T] { xs };",
+            "let x: = 1; fn(a: [, b: fn(int ->) -> {}",
+        ];
+        for source in corpus.iter().chain(broken.iter()) {
+            let cst = parse(source);
+            assert_eq!(cst.to_string(), *source);
+            assert_eq!(cst.range(), 0..source.len());
+        }
+    }
+
+    #[test]
+    fn test_loop_and_try_take_a_semicolon() {
+        let cst = parse("while (x) { 1 }; for (a in b) { }; try { } finally { };");
+        assert_eq!(
+            kinds(&cst),
+            vec![
+                SyntaxKind::WhileStatement,
+                SyntaxKind::ForStatement,
+                SyntaxKind::TryStatement,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tree_shape() {
+        let cst = parse("let x = 1 + 2 * 3; // note\nf(a)[0]");
+        assert_eq!(
+            kinds(&cst),
+            vec![SyntaxKind::LetStatement, SyntaxKind::ExpressionStatement]
+        );
+        assert_eq!(
+            cst.dump(),
+            r#"Program@0..34
+  LetStatement@0..18
+    Let@0..3 "let"
+    Whitespace@3..4 " "
+    Ident@4..5 "x"
+    Whitespace@5..6 " "
+    Assign@6..7 "="
+    Whitespace@7..8 " "
+    InfixExpression@8..17
+      Literal@8..9
+        Int@8..9 "1"
+      Whitespace@9..10 " "
+      Plus@10..11 "+"
+      Whitespace@11..12 " "
+      InfixExpression@12..17
+        Literal@12..13
+          Int@12..13 "2"
+        Whitespace@13..14 " "
+        ASTERISK@14..15 "*"
+        Whitespace@15..16 " "
+        Literal@16..17
+          Int@16..17 "3"
+    Semicolon@17..18 ";"
+  Whitespace@18..19 " "
+  Comment@19..26 "// note"
+  Whitespace@26..27 "\n"
+  ExpressionStatement@27..34
+    IndexExpression@27..34
+      CallExpression@27..31
+        Name@27..28
+          Ident@27..28 "f"
+        ArgumentList@28..31
+          LParen@28..29 "("
+          Name@29..30
+            Ident@29..30 "a"
+          RParen@30..31 ")"
+      LBracket@31..32 "["
+      Literal@32..33
+        Int@32..33 "0"
+      RBracket@33..34 "]"
+"#
+        );
+    }
+
+    #[test]
+    fn test_type_annotations() {
+        let cst = parse("let f: fn(int) -> int = fn(n: int) -> int { n };");
+        let let_stmt = &cst.children()[0];
+        assert_eq!(
+            kinds(let_stmt),
+            vec![SyntaxKind::TypeAnnotation, SyntaxKind::FunctionLiteral]
+        );
+        assert_eq!(let_stmt.children()[0].to_string(), ": fn(int) -> int");
+
+        let function = &let_stmt.children()[1];
+        assert_eq!(
+            kinds(function),
+            vec![
+                SyntaxKind::ParameterList,
+                SyntaxKind::TypeAnnotation,
+                SyntaxKind::BlockStatement
+            ]
+        );
+        assert_eq!(function.children()[1].to_string(), "-> int");
+        let params = &function.children()[0];
+        assert_eq!(
+            kinds(params),
+            vec![SyntaxKind::Name, SyntaxKind::TypeAnnotation]
+        );
+        assert_eq!(params.children()[1].to_string(), ": int");
+    }
+
+    #[test]
+    fn test_precedence_matches_parser() {
+        let cst = parse("a ** b ** c - -d");
+        let stmt = &cst.children()[0];
+        let minus = &stmt.children()[0];
+        assert_eq!(minus.kind(), SyntaxKind::InfixExpression);
+        let power = &minus.children()[0];
+        assert_eq!(power.to_string(), "a ** b ** c");
+        assert_eq!(power.children()[1].to_string(), "b ** c");
+        assert_eq!(minus.children()[1].kind(), SyntaxKind::PrefixExpression);
+    }
+
+    #[test]
+    fn test_navigation() {
+        let source = "let f = fn(x) {\n    x // id\n};";
+        let cst = parse(source);
+
+        let token = cst.token_at_offset(source.find("id").unwrap()).unwrap();
+        assert_eq!(token.kind(), SyntaxKind::Comment);
+        let token = cst.token_at_offset(source.find("x ").unwrap()).unwrap();
+        assert_eq!(token.text(), "x");
+        let path: Vec<SyntaxKind> = token.parent().ancestors().map(|n| n.kind()).collect();
+        assert_eq!(
+            path,
+            vec![
+                SyntaxKind::Name,
+                SyntaxKind::ExpressionStatement,
+                SyntaxKind::BlockStatement,
+                SyntaxKind::FunctionLiteral,
+                SyntaxKind::LetStatement,
+                SyntaxKind::Program,
+            ]
+        );
+        assert_eq!(
+            cst.token_at_offset(source.len())
+                .map(|t| t.text().to_string()),
+            Some(";".to_string())
+        );
+
+        let texts: String = cst.tokens().iter().map(|t| t.text()).collect();
+        assert_eq!(texts, source);
+        assert!(cst
+            .children_with_tokens()
+            .iter()
+            .all(|e| matches!(e, SyntaxElement::Node(_))));
+    }
+}
@@ -0,0 +1,44 @@
+// Comments in every place the formatter has to keep them.
+
+import "lib/math.mk" as math; // trailing after an import
+
+// a comment before a let
+let limit = 10;   // spaced trailing comment
+
+
+// blank lines above collapse to one
+let counter = fn(start) {
+    // leading comment inside a body
+    let n = start; // trailing inside a body
+
+    // before a loop
+    while (n < limit) {
+        // inside a loop
+        n += 1;
+    }
+    // before the value
+    n // the value
+};
+
+let calls = add(1, // first argument
+    // own line inside the arguments
+    2);
+
+try {
+    risky(); // may throw
+} catch (e) {
+    // ignored
+} finally {
+    // always runs
+}
+
+if (limit > 5) { // after the brace
+    puts("big");
+} else {
+    puts("small"); // small
+}
+
+let empty = fn() {
+    // nothing here yet
+};
+// the end
@@ -0,0 +1,17 @@
+let add=fn(a,b){a+b};
+let twice   =   fn(f) {  fn(x) { f(f(x)) }  };
+let pick = fn(c) { if (c) { "yes" } else { "no" } };
+let long_name_for_a_function = fn(first_parameter, second_parameter, third_parameter) { first_parameter + second_parameter + third_parameter };
+let words = ["a string that is fairly long", "another string that is long", "and a third one"];
+let nested = [[1, 2, 3], [4, 5, 6], [7, 8, 9]];
+let result = reduce(map(filter(range(0, 100), fn(x) { x % 2 == 0 }), fn(x) { x * x }), 0, fn(acc, x) { acc + x });
+each([1, 2, 3], fn(x) { puts(x); puts(x * 2); });
+for (word in words) { if (len(word) > 20) { continue; } puts(word) }
+while (true) { break }
+let greeting = "Hello ${name}, you are ${age + 1} next year";
+export let exported = fn() { 1 };
+let m = macro(a, b) { quote(unquote(b) - unquote(a)) };
+if (a) { b } else { c };
+[1, 2][0]
+let empty = fn() {};
+throw "done"
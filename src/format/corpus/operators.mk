@@ -0,0 +1,17 @@
+let a=1+2*3-4/5%6;
+let b=(1+2)*3;
+let c=-(a+b);
+let d=!true==false;
+let e=a**b**c;
+let f=(a**b)**c;
+let g=a&b|c^d<<1>>2;
+let h=a<b&&b<=c||c>d&&d>=e;
+let i=a!=b==(c==d);
+x=y=z;
+x+=1;x-=1;x*=2;x/=2;
+let j=(-a)**2;
+let k=arr[i+1][j];
+let l=(a+b)[0];
+let n=math.pi*(r**2);
+let o=f(a)(b)(c);
+let p=1.5e3+0.25;
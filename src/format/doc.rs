@@ -0,0 +1,180 @@
+// A small Wadler-style pretty-printing document. The formatter describes
+// the layout it wants with Group and the line variants, and render picks,
+// group by group from the outside in, whether each fits flat on the rest
+// of the line or has to break.
+
+const INDENT: usize = 4;
+
+pub enum Doc {
+    Text(String),
+    // a space when the enclosing group is flat, a newline when it breaks
+    Line,
+    // nothing when flat, a newline when broken
+    SoftLine,
+    // always a newline
+    HardLine,
+    Concat(Vec<Doc>),
+    // indents the lines started inside by one more level
+    Nest(Box<Doc>),
+    // lays its content out flat if that fits, broken otherwise
+    Group(Box<Doc>),
+    // lays its content out broken even inside a flat group
+    Broken(Box<Doc>),
+}
+
+pub fn text(s: impl Into<String>) -> Doc {
+    Doc::Text(s.into())
+}
+
+pub fn concat(docs: Vec<Doc>) -> Doc {
+    Doc::Concat(docs)
+}
+
+pub fn nest(doc: Doc) -> Doc {
+    Doc::Nest(Box::new(doc))
+}
+
+pub fn group(doc: Doc) -> Doc {
+    Doc::Group(Box::new(doc))
+}
+
+pub fn broken(doc: Doc) -> Doc {
+    Doc::Broken(Box::new(doc))
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Flat,
+    Break,
+}
+
+pub fn render(doc: &Doc, width: usize) -> String {
+    let mut out = String::new();
+    let mut column = 0;
+    // indentation owed to the current line, written with its first text so
+    // that blank lines carry no trailing spaces
+    let mut pending_indent: Option<usize> = None;
+    let mut stack = vec![(0, Mode::Break, doc)];
+
+    while let Some((indent, mode, doc)) = stack.pop() {
+        match doc {
+            Doc::Text(s) => {
+                if let Some(n) = pending_indent.take() {
+                    out.extend(std::iter::repeat_n(' ', n));
+                }
+                out.push_str(s);
+                column += s.chars().count();
+            }
+            Doc::Line | Doc::SoftLine if mode == Mode::Flat => {
+                if matches!(doc, Doc::Line) {
+                    out.push(' ');
+                    column += 1;
+                }
+            }
+            Doc::Line | Doc::SoftLine | Doc::HardLine => {
+                out.push('\n');
+                pending_indent = Some(indent);
+                column = indent;
+            }
+            Doc::Concat(docs) => {
+                for d in docs.iter().rev() {
+                    stack.push((indent, mode, d));
+                }
+            }
+            // a flat group keeps the indentation it started at, so a hard
+            // line inside it (the body of a trailing fn argument) lines up
+            // with the statement rather than with the group's own content
+            Doc::Nest(d) if mode == Mode::Flat => stack.push((indent, mode, d)),
+            Doc::Nest(d) => stack.push((indent + INDENT, mode, d)),
+            Doc::Group(d) => {
+                let mode = if mode == Mode::Flat || fits(width.saturating_sub(column), d, &stack) {
+                    Mode::Flat
+                } else {
+                    Mode::Break
+                };
+                stack.push((indent, mode, d));
+            }
+            Doc::Broken(d) => stack.push((indent, Mode::Break, d)),
+        }
+    }
+    out
+}
+
+// Whether `doc` laid out flat, followed by what is already queued, reaches
+// the end of the line within `room` columns. A hard line inside `doc` ends
+// the measurement early: whatever comes after it starts a fresh line.
+fn fits(room: usize, doc: &Doc, rest: &[(usize, Mode, &Doc)]) -> bool {
+    let mut room = room as isize;
+    let mut todo: Vec<(Mode, &Doc)> = vec![(Mode::Flat, doc)];
+    let mut rest = rest.iter().rev();
+
+    loop {
+        let (mode, doc) = match todo.pop() {
+            Some(next) => next,
+            None => match rest.next() {
+                Some((_, mode, doc)) => (*mode, *doc),
+                None => return true,
+            },
+        };
+        match doc {
+            Doc::Text(s) => {
+                room -= s.chars().count() as isize;
+                if room < 0 {
+                    return false;
+                }
+            }
+            Doc::Line if mode == Mode::Flat => room -= 1,
+            Doc::SoftLine if mode == Mode::Flat => {}
+            Doc::Line | Doc::SoftLine | Doc::HardLine => return true,
+            Doc::Concat(docs) => todo.extend(docs.iter().rev().map(|d| (mode, d))),
+            Doc::Nest(d) => todo.push((mode, d)),
+            // groups still waiting in `rest` are measured flat
+            Doc::Group(d) => todo.push((Mode::Flat, d)),
+            Doc::Broken(d) => todo.push((Mode::Break, d)),
+        }
+        if room < 0 {
+            return false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(name: &str, args: &[&str]) -> Doc {
+        let mut items = Vec::new();
+        for (i, arg) in args.iter().enumerate() {
+            if i > 0 {
+                items.push(text(","));
+                items.push(Doc::Line);
+            }
+            items.push(text(*arg));
+        }
+        group(concat(vec![
+            text(format!("{}(", name)),
+            nest(concat(vec![Doc::SoftLine, concat(items)])),
+            Doc::SoftLine,
+            text(")"),
+        ]))
+    }
+
+    #[test]
+    fn test_group_breaks_only_when_too_wide() {
+        let doc = call("f", &["alpha", "beta", "gamma"]);
+        assert_eq!(render(&doc, 80), "f(alpha, beta, gamma)");
+        assert_eq!(render(&doc, 20), "f(\n    alpha,\n    beta,\n    gamma\n)");
+    }
+
+    #[test]
+    fn test_hard_line_ends_measurement() {
+        let doc = concat(vec![
+            text("a"),
+            nest(concat(vec![Doc::HardLine, text("b")])),
+            Doc::HardLine,
+            Doc::HardLine,
+            call("g", &["x"]),
+        ]);
+        assert_eq!(render(&doc, 80), "a\n    b\n\ng(x)");
+    }
+}
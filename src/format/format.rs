@@ -0,0 +1,673 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::ast::ast::*;
+use crate::ast::printer::token_of;
+use crate::format::doc::{broken, concat, group, nest, render, text, Doc};
+use crate::lexer::lexer::{Lexer, LexerTrait};
+use crate::lexer::token::{Comment, Token, TokenType};
+use crate::parser::errors::ParseErrors;
+use crate::parser::parser::{precedence_of, Parser, Predecessor};
+
+const WIDTH: usize = 80;
+
+// Formats a whole source file. The layout comes from the AST alone, so
+// formatting twice gives the same result as formatting once. Comments are
+// the only thing taken from the original text, together with whether a
+// blank line separated two statements.
+pub fn format_source(source: &str) -> Result<String, ParseErrors> {
+    let mut p = Parser::new(Lexer::new(source));
+    let program = p.parse_program();
+    let errors = p.errors();
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    Ok(Formatter::new(source).program(&program))
+}
+
+// Lays out a program that has no source text of its own, such as one the
+// optimizer rewrote, the same way format_source would. There are no
+// comments to keep; blank lines still follow the spans of the tokens.
+pub fn format_program(program: &Program) -> String {
+    Formatter::new("").program(program)
+}
+
+struct Formatter<'a> {
+    source: &'a str,
+    tokens: Vec<Token>,
+    // offset of the matching `}` for the offset of every `{`
+    closing: HashMap<usize, usize>,
+    // the comments directly inside each block, keyed by the offset of its
+    // `{`; None holds the top level
+    comments: HashMap<Option<usize>, Vec<Comment>>,
+}
+
+// Where a comment inside a statement list ends up.
+enum Place<'c> {
+    // on its own line before the statement
+    Before(usize, &'c Comment),
+    // belongs to the statement: either written inside it or after it on
+    // its last line. The flag tells whether code precedes it on its line.
+    Attached(usize, &'c Comment, bool),
+}
+
+impl<'a> Formatter<'a> {
+    fn new(source: &'a str) -> Self {
+        let mut lexer = Lexer::new(source);
+        let mut tokens = Vec::new();
+        loop {
+            let token = lexer.next_token();
+            if token.type_ == TokenType::Eof {
+                break;
+            }
+            tokens.push(token);
+        }
+
+        let mut closing = HashMap::new();
+        let mut open = Vec::new();
+        for token in &tokens {
+            match token.type_ {
+                TokenType::LBrace => open.push(token.span.start),
+                TokenType::RBrace => {
+                    if let Some(start) = open.pop() {
+                        closing.insert(start, token.span.start);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut comments: HashMap<Option<usize>, Vec<Comment>> = HashMap::new();
+        for comment in lexer.comments() {
+            // the innermost block around the comment is the last one opened
+            // before it that is still open there
+            let block = closing
+                .iter()
+                .filter(|(&open, &close)| open < comment.span.start && comment.span.start < close)
+                .map(|(&open, _)| open)
+                .max();
+            comments.entry(block).or_default().push(comment.clone());
+        }
+
+        Formatter {
+            source,
+            tokens,
+            closing,
+            comments,
+        }
+    }
+
+    fn program(&self, program: &Program) -> String {
+        let doc = self.statements(&program.statements, None, self.source.len(), false);
+        let mut out = render(&doc, WIDTH);
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out
+    }
+
+    fn last_token_before(&self, offset: usize) -> Option<&Token> {
+        let i = self.tokens.partition_point(|t| t.span.start < offset);
+        i.checked_sub(1).map(|i| &self.tokens[i])
+    }
+
+    fn has_code_before(&self, comment: &Comment) -> bool {
+        let line_start = self.source[..comment.span.start]
+            .rfind('\n')
+            .map_or(0, |i| i + 1);
+        !self.source[line_start..comment.span.start]
+            .trim()
+            .is_empty()
+    }
+
+    // Sorts the comments of one statement list between its statements.
+    fn place(&self, starts: &[usize], block: Option<usize>, end: usize) -> Vec<Place<'_>> {
+        let Some(comments) = self.comments.get(&block) else {
+            return Vec::new();
+        };
+        let mut places = Vec::new();
+        for comment in comments {
+            let k = starts.partition_point(|&s| s <= comment.span.start);
+            if k == 0 {
+                places.push(Place::Before(0, comment));
+                continue;
+            }
+            let k = k - 1;
+            let limit = starts.get(k + 1).copied().unwrap_or(end);
+            let last = self.last_token_before(limit);
+            if last.is_some_and(|t| comment.span.start < t.span.end) {
+                places.push(Place::Attached(k, comment, self.has_code_before(comment)));
+            } else if last.is_some_and(|t| t.span.line == comment.span.line) {
+                places.push(Place::Attached(k, comment, true));
+            } else {
+                places.push(Place::Before(k + 1, comment));
+            }
+        }
+        places
+    }
+
+    // The statements of the program or of one block, one per line.
+    // `value` says whether the last statement is the value of the block.
+    fn statements(
+        &self,
+        statements: &[Rc<dyn Statement>],
+        block: Option<usize>,
+        end: usize,
+        value: bool,
+    ) -> Doc {
+        let starts: Vec<usize> = statements
+            .iter()
+            .map(|s| first_token(s).span.start)
+            .collect();
+        let places = self.place(&starts, block, end);
+        let mut lines = Lines::default();
+        let count = statements.len();
+        let mut docs: Vec<Option<Doc>> = statements
+            .iter()
+            .enumerate()
+            .map(|(k, stmt)| Some(self.statement(stmt, value && k + 1 == count)))
+            .collect();
+
+        for k in 0..=statements.len() {
+            for place in &places {
+                if let Place::Before(i, c) = place {
+                    if *i == k {
+                        lines.push(c.span.line, c.span.line, text(&c.text));
+                    }
+                }
+            }
+            let Some(stmt) = statements.get(k) else {
+                break;
+            };
+
+            let mut attached: Vec<(&Comment, bool)> = places
+                .iter()
+                .filter_map(|place| match place {
+                    Place::Attached(i, c, trailing) if *i == k => Some((*c, *trailing)),
+                    _ => None,
+                })
+                .collect();
+            // the last comment stays at the end of the statement's line if it
+            // was written after code; everything else moves above it
+            let trailing = match attached.last() {
+                Some((_, true)) => attached.pop().map(|(c, _)| c),
+                _ => None,
+            };
+
+            let first = first_token(stmt).span.line;
+            let last = self
+                .last_token_before(starts.get(k + 1).copied().unwrap_or(end))
+                .map_or(first, |t| t.span.line);
+            for (c, _) in attached {
+                lines.push(first, first, text(&c.text));
+            }
+
+            let mut doc = docs[k].take().expect("each statement is laid out once");
+            // the closing brace of an if ends the statement, unless what is
+            // printed next would be read as an operator applied to the if
+            let merges = docs
+                .get(k + 1)
+                .and_then(Option::as_ref)
+                .and_then(first_text)
+                .is_some_and(continues_expression);
+            if merges && is_if_statement(stmt) && !(value && k + 1 == count) {
+                doc = concat(vec![doc, text(";")]);
+            }
+            if let Some(c) = trailing {
+                doc = concat(vec![doc, text(format!(" {}", c.text))]);
+            }
+            lines.push(first, last, doc);
+        }
+        concat(lines.docs)
+    }
+
+    fn statement(&self, stmt: &Rc<dyn Statement>, tail: bool) -> Doc {
+        let any = stmt.as_any();
+        if let Some(s) = any.downcast_ref::<LetStatement>() {
+            self.let_statement(s)
+        } else if let Some(s) = any.downcast_ref::<ExportStatement>() {
+            concat(vec![text("export "), self.let_statement(&s.statement)])
+        } else if let Some(s) = any.downcast_ref::<ReturnStatements>() {
+            self.keyword_statement("return", s.value.as_ref())
+        } else if let Some(s) = any.downcast_ref::<ThrowStatement>() {
+            self.keyword_statement("throw", s.value.as_ref())
+        } else if let Some(s) = any.downcast_ref::<ExpressionStatement>() {
+            let Some(expr) = &s.expr else {
+                return concat(vec![]);
+            };
+            if let Some(if_expr) = expr.as_any().downcast_ref::<IfExpression>() {
+                // whether a `;` follows is decided in statements()
+                return self.if_expression(if_expr, tail);
+            }
+            let doc = self.expression(expr.as_ref());
+            if tail {
+                doc
+            } else {
+                concat(vec![doc, text(";")])
+            }
+        } else if let Some(s) = any.downcast_ref::<WhileStatement>() {
+            concat(vec![
+                text("while ("),
+                self.expression(s.condition.as_ref()),
+                text(") "),
+                self.block(&s.body, false),
+            ])
+        } else if let Some(s) = any.downcast_ref::<ForStatement>() {
+            concat(vec![
+                text(format!("for ({} in ", s.variable)),
+                self.expression(s.iterable.as_ref()),
+                text(") "),
+                self.block(&s.body, false),
+            ])
+        } else if let Some(s) = any.downcast_ref::<TryStatement>() {
+            let mut docs = vec![text("try "), self.block(&s.block, false)];
+            if let Some(catch_block) = &s.catch_block {
+                match &s.catch_param {
+                    Some(param) => docs.push(text(format!(" catch ({}) ", param))),
+                    None => docs.push(text(" catch ")),
+                }
+                docs.push(self.block(catch_block, false));
+            }
+            if let Some(finally_block) = &s.finally_block {
+                docs.push(text(" finally "));
+                docs.push(self.block(finally_block, false));
+            }
+            concat(docs)
+        } else if let Some(s) = any.downcast_ref::<BlockStatement>() {
+            self.block(s, false)
+        } else {
+            // break, continue and import print the same as in Display
+            text(stmt.to_string())
+        }
+    }
+
+    fn let_statement(&self, s: &LetStatement) -> Doc {
+        let mut docs = vec![match &s.annotation {
+            Some(annotation) => text(format!("let {}: {} = ", s.name, annotation)),
+            None => text(format!("let {} = ", s.name)),
+        }];
+        if let Some(value) = &s.value {
+            docs.push(self.expression(value.as_ref()));
+        }
+        docs.push(text(";"));
+        concat(docs)
+    }
+
+    fn keyword_statement(&self, keyword: &str, value: Option<&Rc<dyn Expression>>) -> Doc {
+        match value {
+            Some(value) => concat(vec![
+                text(format!("{} ", keyword)),
+                self.expression(value.as_ref()),
+                text(";"),
+            ]),
+            None => text(format!("{};", keyword)),
+        }
+    }
+
+    // A block with its braces. Blocks holding statements are written one
+    // statement per line, except that a value block made of a single short
+    // expression stays on the line it opens, as in `fn(x) { x * 2 }`.
+    fn block(&self, block: &BlockStatement, value: bool) -> Doc {
+        let open = block.token.span.start;
+        let end = self
+            .closing
+            .get(&open)
+            .copied()
+            .unwrap_or(self.source.len());
+        let has_comments = self.comments.contains_key(&Some(open));
+        if block.statements.is_empty() && !has_comments {
+            return text("{}");
+        }
+
+        let body = self.statements(&block.statements, Some(open), end, value);
+        let single = value
+            && !has_comments
+            && block.statements.len() == 1
+            && block.statements[0].as_any().is::<ExpressionStatement>()
+            && !has_hard_line(&body);
+        if single {
+            return group(concat(vec![
+                text("{"),
+                nest(concat(vec![Doc::Line, body])),
+                Doc::Line,
+                text("}"),
+            ]));
+        }
+        broken(concat(vec![
+            text("{"),
+            nest(concat(vec![Doc::HardLine, body])),
+            Doc::HardLine,
+            text("}"),
+        ]))
+    }
+
+    fn if_expression(&self, e: &IfExpression, value: bool) -> Doc {
+        let mut docs = vec![
+            text("if ("),
+            self.expression(e.condition.as_ref()),
+            text(") "),
+            self.block(&e.consequence, value),
+        ];
+        if let Some(alternative) = &e.alternative {
+            docs.push(text(" else "));
+            docs.push(self.block(alternative, value));
+        }
+        concat(docs)
+    }
+
+    fn expression(&self, expr: &dyn Expression) -> Doc {
+        let any = expr.as_any();
+        if let Some(e) = any.downcast_ref::<PrefixExpression>() {
+            concat(vec![
+                text(e.operator.clone()),
+                self.operand(e.right.as_ref(), e.brackets_operand()),
+            ])
+        } else if let Some(e) = any.downcast_ref::<InfixExpression>() {
+            let (left, right) = e.brackets_operands();
+            concat(vec![
+                self.operand(e.left.as_ref(), left),
+                text(format!(" {} ", e.operator)),
+                self.operand(e.right.as_ref(), right),
+            ])
+        } else if let Some(e) = any.downcast_ref::<AssignExpression>() {
+            concat(vec![
+                self.expression(e.target.as_ref()),
+                text(format!(" {} ", e.operator)),
+                self.expression(e.value.as_ref()),
+            ])
+        } else if let Some(e) = any.downcast_ref::<IndexExpression>() {
+            concat(vec![
+                self.operand(e.left.as_ref(), binding_of(e.left.as_ref()).is_some()),
+                text("["),
+                self.expression(e.index.as_ref()),
+                text("]"),
+            ])
+        } else if let Some(e) = any.downcast_ref::<MemberExpression>() {
+            concat(vec![
+                self.operand(e.object.as_ref(), binding_of(e.object.as_ref()).is_some()),
+                text(format!(".{}", e.property)),
+            ])
+        } else if let Some(e) = any.downcast_ref::<CallExpression>() {
+            concat(vec![
+                self.expression(e.function.as_ref()),
+                list("(", self.expressions(&e.arguments), ")"),
+            ])
+        } else if let Some(e) = any.downcast_ref::<ArrayLiteral>() {
+            list("[", self.expressions(&e.elements), "]")
+        } else if let Some(e) = any.downcast_ref::<IfExpression>() {
+            self.if_expression(e, true)
+        } else if let Some(e) = any.downcast_ref::<FunctionLiteral>() {
+            self.function(
+                "fn",
+                &e.parameters,
+                &e.parameter_types,
+                &e.return_type,
+                &e.body,
+            )
+        } else if let Some(e) = any.downcast_ref::<MacroLiteral>() {
+            self.function("macro", &e.parameters, &[], &None, &e.body)
+        } else {
+            // identifiers, literals and strings
+            text(expr.to_string())
+        }
+    }
+
+    fn operand(&self, expr: &dyn Expression, parenthesize: bool) -> Doc {
+        if parenthesize {
+            concat(vec![text("("), self.expression(expr), text(")")])
+        } else {
+            self.expression(expr)
+        }
+    }
+
+    fn function(
+        &self,
+        keyword: &str,
+        parameters: &[Rc<Identifier>],
+        types: &[Option<TypeExpr>],
+        result: &Option<TypeExpr>,
+        body: &BlockStatement,
+    ) -> Doc {
+        let params = parameters
+            .iter()
+            .enumerate()
+            .map(|(i, p)| match types.get(i).and_then(Option::as_ref) {
+                Some(t) => text(format!("{}: {}", p, t)),
+                None => text(p.to_string()),
+            })
+            .collect();
+        let result = match result {
+            Some(t) => text(format!(" -> {} ", t)),
+            None => text(" "),
+        };
+        concat(vec![
+            text(keyword),
+            list("(", params, ")"),
+            result,
+            self.block(body, true),
+        ])
+    }
+
+    fn expressions(&self, expressions: &[Rc<dyn Expression>]) -> Vec<Doc> {
+        expressions
+            .iter()
+            .map(|e| self.expression(e.as_ref()))
+            .collect()
+    }
+}
+
+// Parameters, call arguments and array elements: all on one line if they
+// fit, otherwise one per line. The parser takes no trailing comma, so none
+// is added when broken.
+fn list(open: &str, items: Vec<Doc>, close: &str) -> Doc {
+    if items.is_empty() {
+        return text(format!("{}{}", open, close));
+    }
+    let mut docs = Vec::new();
+    for (i, item) in items.into_iter().enumerate() {
+        if i > 0 {
+            docs.push(text(","));
+            docs.push(Doc::Line);
+        }
+        docs.push(item);
+    }
+    group(concat(vec![
+        text(open),
+        nest(concat(vec![Doc::SoftLine, concat(docs)])),
+        Doc::SoftLine,
+        text(close),
+    ]))
+}
+
+fn first_token(stmt: &Rc<dyn Statement>) -> &Token {
+    token_of(stmt.as_ref()).expect("every statement keeps its first token")
+}
+
+fn is_if_statement(stmt: &Rc<dyn Statement>) -> bool {
+    stmt.as_any()
+        .downcast_ref::<ExpressionStatement>()
+        .and_then(|s| s.expr.as_ref())
+        .is_some_and(|expr| expr.as_any().is::<IfExpression>())
+}
+
+// The first text a laid out statement prints.
+fn first_text(doc: &Doc) -> Option<&str> {
+    match doc {
+        Doc::Text(s) if !s.is_empty() => Some(s),
+        Doc::Concat(docs) => docs.iter().find_map(first_text),
+        Doc::Nest(d) | Doc::Group(d) | Doc::Broken(d) => first_text(d),
+        _ => None,
+    }
+}
+
+// Whether code starting with `text` continues the expression before it,
+// as `(` or `-` would after an if.
+fn continues_expression(text: &str) -> bool {
+    let token = Lexer::new(text).next_token();
+    precedence_of(&token.type_) > Predecessor::LOWEST
+}
+
+fn has_hard_line(doc: &Doc) -> bool {
+    match doc {
+        Doc::HardLine => true,
+        Doc::Concat(docs) => docs.iter().any(has_hard_line),
+        Doc::Nest(d) | Doc::Group(d) | Doc::Broken(d) => has_hard_line(d),
+        _ => false,
+    }
+}
+
+// Statement and comment lines with at most one blank line kept between
+// them, where the source had at least one.
+#[derive(Default)]
+struct Lines {
+    docs: Vec<Doc>,
+    last_line: Option<usize>,
+}
+
+impl Lines {
+    fn push(&mut self, first_line: usize, last_line: usize, doc: Doc) {
+        if let Some(previous) = self.last_line {
+            self.docs.push(Doc::HardLine);
+            if first_line > previous + 1 {
+                self.docs.push(Doc::HardLine);
+            }
+        }
+        self.docs.push(doc);
+        self.last_line = Some(last_line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::printer::parenthesize;
+    use crate::parser::parser::parse;
+    use crate::prelude::prelude;
+
+    const CORPUS: &[(&str, &str)] = &[
+        ("prelude.mk", prelude::SOURCE),
+        ("comments.mk", include_str!("corpus/comments.mk")),
+        ("layout.mk", include_str!("corpus/layout.mk")),
+        ("operators.mk", include_str!("corpus/operators.mk")),
+    ];
+
+    fn format(source: &str) -> String {
+        format_source(source).unwrap_or_else(|errors| panic!("{:?}", errors))
+    }
+
+    fn comments(source: &str) -> Vec<String> {
+        let mut lexer = Lexer::new(source);
+        while lexer.next_token().type_ != TokenType::Eof {}
+        lexer.comments().iter().map(|c| c.text.clone()).collect()
+    }
+
+    fn parens(source: &str) -> String {
+        let mut p = Parser::new(Lexer::new(source));
+        parenthesize(&p.parse_program())
+    }
+
+    #[test]
+    fn test_corpus() {
+        for (name, source) in CORPUS {
+            let once = format(source);
+            let twice = format(&once);
+            assert_eq!(once, twice, "{} is not formatted idempotently", name);
+            assert_eq!(parens(source), parens(&once), "{} changed meaning", name);
+            assert_eq!(comments(source), comments(&once), "{} lost comments", name);
+        }
+    }
+
+    #[test]
+    fn test_prelude_is_formatted() {
+        assert_eq!(format(prelude::SOURCE), prelude::SOURCE);
+    }
+
+    #[test]
+    fn test_spacing_and_indentation() {
+        let tests = vec![
+            ("let x=1+2*3", "let x = 1 + 2 * 3;\n"),
+            ("let f=fn(a,b){a+b};", "let f = fn(a, b) { a + b };\n"),
+            (
+                "let f:fn(int,[T])->[T]=fn(n:int,xs)->[T]{xs};",
+                "let f: fn(int, [T]) -> [T] = fn(n: int, xs) -> [T] { xs };\n",
+            ),
+            (
+                "if(x){y}else{z}\nw",
+                "if (x) {\n    y;\n} else {\n    z;\n}\nw;\n",
+            ),
+            ("while(i<3){i+=1;}", "while (i < 3) {\n    i += 1;\n}\n"),
+            ("let a=[1,2];a[0]=-a[1]", "let a = [1, 2];\na[0] = -a[1];\n"),
+            ("\n\nx;\n\n\n\ny;\n\n", "x;\n\ny;\n"),
+            ("", ""),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(format(input), expected, "input={:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_long_lines_break() {
+        let input = "let total = add(first_long_argument_name, second_long_argument_name, third_long_argument_name);";
+        assert_eq!(
+            format(input),
+            "let total = add(\n    first_long_argument_name,\n    second_long_argument_name,\n    third_long_argument_name\n);\n"
+        );
+
+        let input = "let xs = [\"alpha\", \"beta\", \"gamma\", \"delta\", \"epsilon\", \"zeta\", \"eta\", \"theta\", \"iota\"];";
+        assert_eq!(
+            format(input),
+            "let xs = [\n    \"alpha\",\n    \"beta\",\n    \"gamma\",\n    \"delta\",\n    \"epsilon\",\n    \"zeta\",\n    \"eta\",\n    \"theta\",\n    \"iota\"\n];\n"
+        );
+
+        // a trailing function stays attached to the call
+        let input = "each(items, fn(x) { puts(x); puts(x); });";
+        assert_eq!(
+            format(input),
+            "each(items, fn(x) {\n    puts(x);\n    puts(x)\n});\n"
+        );
+    }
+
+    #[test]
+    fn test_comments_are_kept() {
+        let input = "// header\nlet x = 1; // one\n\n\n// about y\nlet y = fn() {\n  // inside\n  x  // value\n};\nlet z = add(1, // first\n  2);\n// end\n";
+        let expected = "// header\nlet x = 1; // one\n\n// about y\nlet y = fn() {\n    // inside\n    x // value\n};\nlet z = add(1, 2); // first\n// end\n";
+        assert_eq!(format(input), expected);
+
+        assert_eq!(format("fn() {\n// only\n}"), "fn() {\n    // only\n};\n");
+    }
+
+    #[test]
+    fn test_if_statement_keeps_needed_semicolon() {
+        let tests = [
+            // the parentheses go, so nothing merges with the if any more
+            ("if (a) { b };\n(c)", "if (a) {\n    b;\n}\nc;\n"),
+            ("if (x) { }; (1 + 2);", "if (x) {}\n1 + 2;\n"),
+            ("if (a) { b }; c", "if (a) {\n    b;\n}\nc;\n"),
+            (
+                "if (a) { b }; (c + d) * e",
+                "if (a) {\n    b;\n};\n(c + d) * e;\n",
+            ),
+            ("if (a) { b }; -c", "if (a) {\n    b;\n};\n-c;\n"),
+            ("if (a) { b }; [c]", "if (a) {\n    b;\n};\n[c];\n"),
+        ];
+        for (input, expected) in tests {
+            let formatted = format(input);
+            assert_eq!(formatted, expected, "input={}", input);
+            assert_eq!(format(&formatted), formatted, "input={}", input);
+        }
+    }
+
+    #[test]
+    fn test_format_program_without_source() {
+        let source = "let f = fn(x) {\n    x += 1;\n    arr[0] = x;\n};\n\nf(1);\n";
+        let program = parse(source);
+        assert_eq!(format_program(&program), format(source));
+        assert_eq!(format(&format_program(&program)), format_program(&program));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(format_source("let = 1;").is_err());
+    }
+}
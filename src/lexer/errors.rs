@@ -0,0 +1,23 @@
+use std::fmt::Display;
+
+use super::token::Span;
+
+// A malformed token, e.g. a bad digit in `0b102`. The lexer still emits an
+// Illegal token for it so the parser can report it in place.
+#[derive(Debug, Clone)]
+pub struct LexError {
+    pub msg: String,
+    pub span: Span,
+}
+
+impl LexError {
+    pub fn new(msg: String, span: Span) -> Self {
+        LexError { msg, span }
+    }
+}
+
+impl Display for LexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.span, self.msg)
+    }
+}
@@ -1,10 +1,19 @@
-use super::token::{lookup_ident, Token, TokenType};
+use num_bigint::BigInt;
+
+use super::errors::LexError;
+use super::token::{lookup_ident, Comment, Span, TemplatePart, Token, TokenType, TokenValue};
+use crate::object::integer::Integer;
 
 pub struct Lexer {
     input: String,
     position: usize,
     read_position: usize,
     ch: u8,
+    // 1-based line/column of `ch`
+    line: usize,
+    column: usize,
+    errors: Vec<LexError>,
+    comments: Vec<Comment>,
 }
 
 pub trait LexerTrait {
@@ -17,6 +26,217 @@ pub trait LexerTrait {
     fn peek_char(&self) -> u8;
 }
 
+impl Lexer {
+    fn span_from(&self, start: usize, line: usize, column: usize) -> Span {
+        let len = self.input.len();
+        Span::new(start.min(len), self.position.min(len), line, column)
+    }
+
+    fn read_digits(&mut self) {
+        while self.ch.is_ascii_digit() || self.ch == b'_' {
+            self.read_char();
+        }
+    }
+
+    // Turns the text from read_number into an Int (carrying its value), a
+    // Float, or an Illegal token with a recorded error.
+    fn number_token(&mut self, literal: &str, span: Span) -> Token {
+        let parsed = if literal.contains(['x', 'X', 'o', 'O', 'b', 'B']) {
+            parse_radix_literal(literal).map(Some)
+        } else if literal
+            .split(['.', 'e', 'E', '+', '-'])
+            .any(|run| run.ends_with('_'))
+        {
+            Err(format!("trailing underscore in number literal {}", literal))
+        } else if literal.contains(['.', 'e', 'E']) {
+            Ok(None)
+        } else {
+            Ok(Integer::parse(&literal.replace('_', "")))
+        };
+
+        match parsed {
+            Ok(Some(value)) => {
+                let mut tok = Token::new(TokenType::Int, literal).with_span(span);
+                tok.value = Some(TokenValue::Int(value));
+                tok
+            }
+            Ok(None) => Token::new(TokenType::Float, literal).with_span(span),
+            Err(msg) => {
+                self.errors.push(LexError::new(msg, span));
+                Token::new(TokenType::Illegal, literal).with_span(span)
+            }
+        }
+    }
+
+    // Called on the opening quote. A string with no `${` becomes a String
+    // token holding the unescaped text; otherwise an InterpolatedString whose
+    // value lists the text pieces and the tokens of each embedded expression.
+    fn read_string(&mut self, start: usize, line: usize, column: usize) -> Token {
+        let mut parts = Vec::new();
+        let mut text: Vec<u8> = Vec::new();
+        let mut interpolated = false;
+        let mut error = None;
+        self.read_char();
+
+        loop {
+            match self.ch {
+                b'"' => {
+                    self.read_char();
+                    break;
+                }
+                0 if self.position >= self.input.len() => {
+                    error = Some("unterminated string".to_string());
+                    break;
+                }
+                b'\\' => {
+                    let escaped = match self.peek_char() {
+                        b'n' => b'\n',
+                        b't' => b'\t',
+                        b'"' => b'"',
+                        b'\\' => b'\\',
+                        b'$' => b'$',
+                        other => {
+                            error.get_or_insert(format!(
+                                "unknown escape sequence \\{}",
+                                other as char
+                            ));
+                            other
+                        }
+                    };
+                    text.push(escaped);
+                    self.read_char();
+                    self.read_char();
+                }
+                b'$' if self.peek_char() == b'{' => {
+                    interpolated = true;
+                    if !text.is_empty() {
+                        let piece = String::from_utf8_lossy(&text).to_string();
+                        parts.push(TemplatePart::Text(piece));
+                        text.clear();
+                    }
+                    self.read_char();
+                    self.read_char();
+                    match self.read_interpolation() {
+                        Ok(tokens) => parts.push(TemplatePart::Expr(tokens)),
+                        Err(msg) => {
+                            error = Some(msg);
+                            break;
+                        }
+                    }
+                }
+                c => {
+                    text.push(c);
+                    self.read_char();
+                }
+            }
+        }
+
+        let span = self.span_from(start, line, column);
+        if let Some(msg) = error {
+            self.errors.push(LexError::new(msg, span));
+            let raw = self.input[span.start..span.end].to_string();
+            return Token::new(TokenType::Illegal, &raw).with_span(span);
+        }
+
+        if !interpolated {
+            let literal = String::from_utf8_lossy(&text);
+            return Token::new(TokenType::String, &literal).with_span(span);
+        }
+        if !text.is_empty() {
+            parts.push(TemplatePart::Text(
+                String::from_utf8_lossy(&text).to_string(),
+            ));
+        }
+        // keep the source spelling, minus the quotes
+        let raw = &self.input[span.start + 1..span.end - 1];
+        let mut tok = Token::new(TokenType::InterpolatedString, raw).with_span(span);
+        tok.value = Some(TokenValue::Template(parts));
+        tok
+    }
+
+    // Lexes the tokens after `${` up to its matching `}`, which is consumed.
+    fn read_interpolation(&mut self) -> Result<Vec<Token>, String> {
+        let mut tokens = Vec::new();
+        let mut depth = 0;
+        loop {
+            let tok = self.next_token();
+            match tok.type_ {
+                TokenType::LBrace => depth += 1,
+                TokenType::RBrace if depth == 0 => return Ok(tokens),
+                TokenType::RBrace => depth -= 1,
+                TokenType::Eof => return Err("unterminated ${ in string".to_string()),
+                _ => {}
+            }
+            tokens.push(tok);
+        }
+    }
+
+    pub fn errors(&self) -> &[LexError] {
+        &self.errors
+    }
+
+    // Every comment skipped so far, in source order.
+    pub fn comments(&self) -> &[Comment] {
+        &self.comments
+    }
+
+    pub fn error_at(&self, span: Span) -> Option<&LexError> {
+        self.errors.iter().find(|err| err.span == span)
+    }
+
+    fn read_comment(&mut self) {
+        let (start, line, column) = (self.position, self.line, self.column);
+        while self.ch != b'\n' && self.position < self.input.len() {
+            self.read_char();
+        }
+        let span = self.span_from(start, line, column);
+        let text = self.input[span.start..span.end].trim_end().to_string();
+        self.comments.push(Comment { text, span });
+    }
+
+    fn byte_at(&self, index: usize) -> u8 {
+        self.input.as_bytes().get(index).copied().unwrap_or(0)
+    }
+
+    // Builds `double` when the next byte is `next` (consuming it), `single` otherwise.
+    fn either(&mut self, next: u8, double: (TokenType, &str), single: (TokenType, &str)) -> Token {
+        if self.peek_char() == next {
+            self.read_char();
+            Token::new(double.0, double.1)
+        } else {
+            Token::new(single.0, single.1)
+        }
+    }
+}
+
+// 0x, 0o and 0b literals. `literal` still has its prefix and any underscores.
+fn parse_radix_literal(literal: &str) -> Result<Integer, String> {
+    let (radix, name) = match literal.as_bytes()[1] {
+        b'x' | b'X' => (16, "hexadecimal"),
+        b'o' | b'O' => (8, "octal"),
+        _ => (2, "binary"),
+    };
+    let digits = &literal[2..];
+
+    if digits.is_empty() {
+        return Err(format!("missing digits in {} literal {}", name, literal));
+    }
+    if digits.ends_with('_') {
+        return Err(format!("trailing underscore in number literal {}", literal));
+    }
+    if let Some(bad) = digits.chars().find(|c| *c != '_' && !c.is_digit(radix)) {
+        return Err(format!(
+            "invalid digit '{}' in {} literal {}",
+            bad, name, literal
+        ));
+    }
+
+    let digits = digits.replace('_', "");
+    BigInt::parse_bytes(digits.as_bytes(), radix)
+        .map(Integer::from)
+        .ok_or_else(|| format!("could not parse {} as integer", literal))
+}
+
 fn is_letter(ch: u8) -> bool {
     ch.is_ascii_alphanumeric() || ch == b'_'
 }
@@ -24,8 +244,35 @@ fn is_letter(ch: u8) -> bool {
 impl LexerTrait for Lexer {
     fn read_number(&mut self) -> String {
         let pos = self.position;
-        while self.ch.is_ascii_digit() {
+        if self.ch == b'0' && matches!(self.peek_char(), b'x' | b'X' | b'o' | b'O' | b'b' | b'B') {
             self.read_char();
+            self.read_char();
+            // take every alphanumeric so `0b102` is one bad literal, not `0b10` then `2`
+            while self.ch.is_ascii_alphanumeric() || self.ch == b'_' {
+                self.read_char();
+            }
+            return self.input[pos..self.position].to_string();
+        }
+        self.read_digits();
+        // a fraction needs a digit after the dot: `1.` is not a float
+        if self.ch == b'.' && self.peek_char().is_ascii_digit() {
+            self.read_char();
+            self.read_digits();
+        }
+        if self.ch == b'e' || self.ch == b'E' {
+            let signed = matches!(self.peek_char(), b'+' | b'-');
+            let first = if signed {
+                self.byte_at(self.read_position + 1)
+            } else {
+                self.peek_char()
+            };
+            if first.is_ascii_digit() {
+                self.read_char();
+                if signed {
+                    self.read_char();
+                }
+                self.read_digits();
+            }
         }
         // dbg!(&self.input[pos..self.position]);
         self.input[pos..self.position].to_string()
@@ -37,12 +284,22 @@ impl LexerTrait for Lexer {
             position: 0,
             read_position: 0,
             ch: 0,
+            line: 1,
+            column: 0,
+            errors: vec![],
+            comments: vec![],
         };
         lexer.read_char();
         lexer
     }
 
     fn read_char(&mut self) {
+        if self.ch == b'\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
         if self.read_position >= self.input.len() {
             self.ch = 0;
         } else {
@@ -55,6 +312,7 @@ impl LexerTrait for Lexer {
 
     fn next_token(&mut self) -> Token {
         self.skip_whitespace();
+        let (start, line, column) = (self.position, self.line, self.column);
         let tok = match self.ch {
             b'=' => {
                 if self.peek_char() == b'=' {
@@ -62,18 +320,22 @@ impl LexerTrait for Lexer {
                     // let literal = "==".to_string();
                     Token::new(TokenType::EQ, "==")
                 } else {
-                    dbg!("inside =");
                     Token::new(TokenType::Assign, "=")
                 }
             }
-            b';' => {
-                println!("inside semi");
-                Token::new(TokenType::Semicolon, ";")
-            }
+            b';' => Token::new(TokenType::Semicolon, ";"),
             b'(' => Token::new(TokenType::LParen, "("),
             b')' => Token::new(TokenType::RParen, ")"),
             b',' => Token::new(TokenType::Comma, ","),
-            b'+' => Token::new(TokenType::Plus, "+"),
+            b'.' => Token::new(TokenType::Dot, "."),
+            b':' => Token::new(TokenType::Colon, ":"),
+            b'+' => self.either(b'=', (TokenType::PlusAssign, "+="), (TokenType::Plus, "+")),
+            b'&' => self.either(b'&', (TokenType::And, "&&"), (TokenType::BitAnd, "&")),
+            b'|' => self.either(b'|', (TokenType::Or, "||"), (TokenType::BitOr, "|")),
+            b'^' => Token::new(TokenType::BitXor, "^"),
+            b'%' => Token::new(TokenType::Percent, "%"),
+            b'[' => Token::new(TokenType::LBracket, "["),
+            b']' => Token::new(TokenType::RBracket, "]"),
             b'{' => Token::new(TokenType::LBrace, "{"),
             b'}' => Token::new(TokenType::RBrace, "}"),
             b'!' => {
@@ -87,14 +349,61 @@ impl LexerTrait for Lexer {
                     Token::new(TokenType::BANG, "!")
                 }
             }
-            b'*' => Token::new(TokenType::ASTERISK, "*"),
-            b'<' => Token::new(TokenType::LT, "<"),
-            b'>' => Token::new(TokenType::GT, ">"),
-            b'/' => Token::new(TokenType::SLASH, "/"),
-            b'-' => Token::new(TokenType::MINUS, "-"),
+            b'*' => match self.peek_char() {
+                b'=' => {
+                    self.read_char();
+                    Token::new(TokenType::AsteriskAssign, "*=")
+                }
+                b'*' => {
+                    self.read_char();
+                    Token::new(TokenType::Power, "**")
+                }
+                _ => Token::new(TokenType::ASTERISK, "*"),
+            },
+            b'<' => match self.peek_char() {
+                b'=' => {
+                    self.read_char();
+                    Token::new(TokenType::LtEq, "<=")
+                }
+                b'<' => {
+                    self.read_char();
+                    Token::new(TokenType::ShiftLeft, "<<")
+                }
+                _ => Token::new(TokenType::LT, "<"),
+            },
+            b'>' => match self.peek_char() {
+                b'=' => {
+                    self.read_char();
+                    Token::new(TokenType::GtEq, ">=")
+                }
+                b'>' => {
+                    self.read_char();
+                    Token::new(TokenType::ShiftRight, ">>")
+                }
+                _ => Token::new(TokenType::GT, ">"),
+            },
+            b'/' => self.either(
+                b'=',
+                (TokenType::SlashAssign, "/="),
+                (TokenType::SLASH, "/"),
+            ),
+            b'-' => match self.peek_char() {
+                b'=' => {
+                    self.read_char();
+                    Token::new(TokenType::MinusAssign, "-=")
+                }
+                b'>' => {
+                    self.read_char();
+                    Token::new(TokenType::Arrow, "->")
+                }
+                _ => Token::new(TokenType::MINUS, "-"),
+            },
+            b'"' => return self.read_string(start, line, column),
             b'0'..=b'9' => {
+                // read_number already moved past the last digit
                 let literal = self.read_number();
-                Token::new(TokenType::Int, &literal)
+                let span = self.span_from(start, line, column);
+                return self.number_token(&literal, span);
             }
             _c if is_letter(self.ch) => {
                 let literal = self.read_identifier();
@@ -108,14 +417,14 @@ impl LexerTrait for Lexer {
                 //     "fn" => Token::new(TokenType::Function, &literal),
                 //     _ => Token::new(TokenType::Ident, &literal),
                 // }
-                return Token::new(_type, &literal);
+                return Token::new(_type, &literal).with_span(self.span_from(start, line, column));
             }
             // _ch if is_digit(self.ch) => {
             b'\0' => Token::new(TokenType::Eof, ""),
             _ => Token::new(TokenType::Illegal, "ILLEGAL"),
         };
         self.read_char();
-        tok
+        tok.with_span(self.span_from(start, line, column))
     }
 
     fn read_identifier(&mut self) -> String {
@@ -123,15 +432,15 @@ impl LexerTrait for Lexer {
         while is_letter(self.ch) || self.ch.is_ascii_digit() {
             self.read_char();
         }
-        dbg!(&self.input[pos..self.position]);
-        String::from_utf8_lossy(self.input[pos..self.position].as_bytes()).to_string()
+        String::from_utf8_lossy(&self.input.as_bytes()[pos..self.position]).to_string()
     }
 
     fn skip_whitespace(&mut self) {
         loop {
             if self.ch == b' ' || self.ch == b'\t' || self.ch == b'\n' || self.ch == b'\r' {
-                println!("skipping whitespace");
                 self.read_char();
+            } else if self.ch == b'/' && self.peek_char() == b'/' {
+                self.read_comment();
             } else {
                 break;
             }
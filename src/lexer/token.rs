@@ -1,11 +1,77 @@
 use std::collections::HashMap;
 
+use crate::object::integer::Integer;
+
 pub type TokenString = String;
 
+/// Where a token sits in the source. `start`/`end` are byte offsets,
+/// `line`/`column` are 1-based and point at the first byte.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize, line: usize, column: usize) -> Self {
+        Self {
+            start,
+            end,
+            line,
+            column,
+        }
+    }
+}
+
+impl std::fmt::Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// A `//` comment, running to the end of its line. `text` includes the
+/// slashes. Comments never become tokens; the lexer keeps them on the side
+/// for tools that put them back, like the formatter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Comment {
+    pub text: String,
+    pub span: Span,
+}
+
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Token {
+    #[cfg_attr(feature = "serde", serde(rename = "type"))]
     pub type_: TokenType,
     pub literal: String,
+    pub span: Span,
+    // extra data for Int and InterpolatedString tokens
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub value: Option<TokenValue>,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TokenValue {
+    // parsed value of an Int; `literal` keeps the spelling (0xFF, 1_000)
+    Int(Integer),
+    // pieces of "a ${b} c" in source order
+    Template(Vec<TemplatePart>),
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TemplatePart {
+    Text(String),
+    // tokens between `${` and `}`, spans pointing into the string
+    Expr(Vec<Token>),
 }
 
 pub fn keywords() -> HashMap<String, TokenType> {
@@ -17,13 +83,26 @@ pub fn keywords() -> HashMap<String, TokenType> {
     keywords.insert("if".to_string(), TokenType::If);
     keywords.insert("else".to_string(), TokenType::Else);
     keywords.insert("return".to_string(), TokenType::Return);
+    keywords.insert("try".to_string(), TokenType::Try);
+    keywords.insert("catch".to_string(), TokenType::Catch);
+    keywords.insert("finally".to_string(), TokenType::Finally);
+    keywords.insert("throw".to_string(), TokenType::Throw);
+    keywords.insert("while".to_string(), TokenType::While);
+    keywords.insert("for".to_string(), TokenType::For);
+    keywords.insert("in".to_string(), TokenType::In);
+    keywords.insert("break".to_string(), TokenType::Break);
+    keywords.insert("continue".to_string(), TokenType::Continue);
+    keywords.insert("import".to_string(), TokenType::Import);
+    keywords.insert("export".to_string(), TokenType::Export);
+    keywords.insert("as".to_string(), TokenType::As);
+    keywords.insert("macro".to_string(), TokenType::Macro);
     keywords
 }
 
 pub fn lookup_ident(ident: &str) -> TokenType {
     let kw = keywords();
     if let Some(token_type) = kw.get(ident) {
-        token_type.clone()
+        *token_type
     } else {
         TokenType::Ident
     }
@@ -34,17 +113,35 @@ impl Token {
         Self {
             type_,
             literal: literal.to_string(),
+            span: Span::default(),
+            value: None,
+        }
+    }
+
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = span;
+        self
+    }
+
+    pub fn int_value(&self) -> Option<&Integer> {
+        match &self.value {
+            Some(TokenValue::Int(value)) => Some(value),
+            _ => None,
         }
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TokenType {
     Illegal,
     Eof,
     // Identifiers + literals
     Ident,
-    Int, // 1343456
+    Int,   // 1343456
+    Float, // 3.14, 1e-3
+    String,
+    InterpolatedString, // "a ${b} c"
     // Operators
     Assign,
     Plus,
@@ -54,8 +151,23 @@ pub enum TokenType {
     SLASH,
     LT,
     GT,
+    LtEq,
+    GtEq,
+    Percent,
+    Power,
+    BitAnd,
+    BitOr,
+    BitXor,
+    ShiftLeft,
+    ShiftRight,
     EQ,
     NotEq,
+    And,
+    Or,
+    PlusAssign,
+    MinusAssign,
+    AsteriskAssign,
+    SlashAssign,
     // Delimiters
     Comma,
     Semicolon,
@@ -63,6 +175,11 @@ pub enum TokenType {
     RParen,
     LBrace,
     RBrace,
+    LBracket,
+    RBracket,
+    Dot,
+    Colon, // `let x: int`, only in type annotations
+    Arrow, // `fn(a: int) -> int`
     // Keywords
     Function,
     Let,
@@ -71,6 +188,19 @@ pub enum TokenType {
     If,
     Else,
     Return,
+    Try,
+    Catch,
+    Finally,
+    Throw,
+    While,
+    For,
+    In,
+    Break,
+    Continue,
+    Import,
+    Export,
+    As,
+    Macro,
 }
 
 impl std::fmt::Display for TokenType {
@@ -80,6 +210,9 @@ impl std::fmt::Display for TokenType {
             TokenType::Eof => write!(f, "EOF"),
             TokenType::Ident => write!(f, "Ident"),
             TokenType::Int => write!(f, "int"),
+            TokenType::Float => write!(f, "float"),
+            TokenType::String => write!(f, "string"),
+            TokenType::InterpolatedString => write!(f, "interpolated string"),
             TokenType::Assign => write!(f, "="),
             TokenType::Plus => write!(f, "+"),
             TokenType::Comma => write!(f, ","),
@@ -96,13 +229,46 @@ impl std::fmt::Display for TokenType {
             TokenType::SLASH => write!(f, "/"),
             TokenType::LT => write!(f, "<"),
             TokenType::GT => write!(f, ">"),
+            TokenType::LtEq => write!(f, "<="),
+            TokenType::GtEq => write!(f, ">="),
+            TokenType::Percent => write!(f, "%"),
+            TokenType::Power => write!(f, "**"),
+            TokenType::BitAnd => write!(f, "&"),
+            TokenType::BitOr => write!(f, "|"),
+            TokenType::BitXor => write!(f, "^"),
+            TokenType::ShiftLeft => write!(f, "<<"),
+            TokenType::ShiftRight => write!(f, ">>"),
             TokenType::True => write!(f, "true"),
             TokenType::False => write!(f, "false"),
             TokenType::If => write!(f, "if"),
             TokenType::Else => write!(f, "else"),
             TokenType::Return => write!(f, "return"),
+            TokenType::Try => write!(f, "try"),
+            TokenType::Catch => write!(f, "catch"),
+            TokenType::Finally => write!(f, "finally"),
+            TokenType::Throw => write!(f, "throw"),
+            TokenType::While => write!(f, "while"),
+            TokenType::For => write!(f, "for"),
+            TokenType::In => write!(f, "in"),
+            TokenType::Break => write!(f, "break"),
+            TokenType::Continue => write!(f, "continue"),
+            TokenType::Import => write!(f, "import"),
+            TokenType::Export => write!(f, "export"),
+            TokenType::As => write!(f, "as"),
+            TokenType::Macro => write!(f, "macro"),
+            TokenType::Dot => write!(f, "."),
+            TokenType::Colon => write!(f, ":"),
+            TokenType::Arrow => write!(f, "->"),
             TokenType::EQ => write!(f, "=="),
             TokenType::NotEq => write!(f, "!="),
+            TokenType::And => write!(f, "&&"),
+            TokenType::Or => write!(f, "||"),
+            TokenType::PlusAssign => write!(f, "+="),
+            TokenType::MinusAssign => write!(f, "-="),
+            TokenType::AsteriskAssign => write!(f, "*="),
+            TokenType::SlashAssign => write!(f, "/="),
+            TokenType::LBracket => write!(f, "["),
+            TokenType::RBracket => write!(f, "]"),
         }
     }
 }
@@ -217,4 +383,484 @@ return false;
             );
         }
     }
+
+    #[test]
+    fn test_assign_operators() {
+        let input = "x += 1; x -= 2; x *= 3; x /= 4; a[0] = x / y;";
+
+        let tests = vec![
+            (TokenType::Ident, "x"),
+            (TokenType::PlusAssign, "+="),
+            (TokenType::Int, "1"),
+            (TokenType::Semicolon, ";"),
+            (TokenType::Ident, "x"),
+            (TokenType::MinusAssign, "-="),
+            (TokenType::Int, "2"),
+            (TokenType::Semicolon, ";"),
+            (TokenType::Ident, "x"),
+            (TokenType::AsteriskAssign, "*="),
+            (TokenType::Int, "3"),
+            (TokenType::Semicolon, ";"),
+            (TokenType::Ident, "x"),
+            (TokenType::SlashAssign, "/="),
+            (TokenType::Int, "4"),
+            (TokenType::Semicolon, ";"),
+            (TokenType::Ident, "a"),
+            (TokenType::LBracket, "["),
+            (TokenType::Int, "0"),
+            (TokenType::RBracket, "]"),
+            (TokenType::Assign, "="),
+            (TokenType::Ident, "x"),
+            (TokenType::SLASH, "/"),
+            (TokenType::Ident, "y"),
+            (TokenType::Semicolon, ";"),
+            (TokenType::Eof, ""),
+        ];
+
+        let mut lexer = Lexer::new(input);
+
+        for (i, (expected_token, expected_literal)) in tests.iter().enumerate() {
+            let token = lexer.next_token();
+            assert_eq!(
+                token.type_, *expected_token,
+                "tests[{}] - tokentype wrong. expected={:?} , got ={:?}",
+                i, expected_token, token.type_
+            );
+            assert_eq!(
+                &token.literal, *expected_literal,
+                "tests[{}] - literal wrong. expected={:?} , got ={:?}",
+                i, expected_literal, token.literal
+            );
+        }
+    }
+
+    #[test]
+    fn test_logical_operators() {
+        let input = "a && b || !c; a & b | c ^ d;";
+
+        let tests = vec![
+            (TokenType::Ident, "a"),
+            (TokenType::And, "&&"),
+            (TokenType::Ident, "b"),
+            (TokenType::Or, "||"),
+            (TokenType::BANG, "!"),
+            (TokenType::Ident, "c"),
+            (TokenType::Semicolon, ";"),
+            (TokenType::Ident, "a"),
+            (TokenType::BitAnd, "&"),
+            (TokenType::Ident, "b"),
+            (TokenType::BitOr, "|"),
+            (TokenType::Ident, "c"),
+            (TokenType::BitXor, "^"),
+            (TokenType::Ident, "d"),
+            (TokenType::Semicolon, ";"),
+            (TokenType::Eof, ""),
+        ];
+
+        let mut lexer = Lexer::new(input);
+
+        for (i, (expected_token, expected_literal)) in tests.iter().enumerate() {
+            let token = lexer.next_token();
+            assert_eq!(
+                token.type_, *expected_token,
+                "tests[{}] - tokentype wrong. expected={:?} , got ={:?}",
+                i, expected_token, token.type_
+            );
+            assert_eq!(
+                &token.literal, *expected_literal,
+                "tests[{}] - literal wrong. expected={:?} , got ={:?}",
+                i, expected_literal, token.literal
+            );
+        }
+    }
+
+    #[test]
+    fn test_arithmetic_and_comparison_operators() {
+        let input = "a <= b >= c < d > e; x % 2 ** 3 * 4; 1 << 2 >> 3;";
+
+        let tests = vec![
+            (TokenType::Ident, "a"),
+            (TokenType::LtEq, "<="),
+            (TokenType::Ident, "b"),
+            (TokenType::GtEq, ">="),
+            (TokenType::Ident, "c"),
+            (TokenType::LT, "<"),
+            (TokenType::Ident, "d"),
+            (TokenType::GT, ">"),
+            (TokenType::Ident, "e"),
+            (TokenType::Semicolon, ";"),
+            (TokenType::Ident, "x"),
+            (TokenType::Percent, "%"),
+            (TokenType::Int, "2"),
+            (TokenType::Power, "**"),
+            (TokenType::Int, "3"),
+            (TokenType::ASTERISK, "*"),
+            (TokenType::Int, "4"),
+            (TokenType::Semicolon, ";"),
+            (TokenType::Int, "1"),
+            (TokenType::ShiftLeft, "<<"),
+            (TokenType::Int, "2"),
+            (TokenType::ShiftRight, ">>"),
+            (TokenType::Int, "3"),
+            (TokenType::Semicolon, ";"),
+            (TokenType::Eof, ""),
+        ];
+
+        let mut lexer = Lexer::new(input);
+
+        for (i, (expected_token, expected_literal)) in tests.iter().enumerate() {
+            let token = lexer.next_token();
+            assert_eq!(
+                token.type_, *expected_token,
+                "tests[{}] - tokentype wrong. expected={:?} , got ={:?}",
+                i, expected_token, token.type_
+            );
+            assert_eq!(
+                &token.literal, *expected_literal,
+                "tests[{}] - literal wrong. expected={:?} , got ={:?}",
+                i, expected_literal, token.literal
+            );
+        }
+    }
+
+    #[test]
+    fn test_float_literals() {
+        let input = "3.14 1e-3 2.5E+10 7e2 10. 4e;";
+
+        let tests = vec![
+            (TokenType::Float, "3.14"),
+            (TokenType::Float, "1e-3"),
+            (TokenType::Float, "2.5E+10"),
+            (TokenType::Float, "7e2"),
+            (TokenType::Int, "10"),
+            (TokenType::Dot, "."),
+            (TokenType::Int, "4"),
+            (TokenType::Ident, "e"),
+            (TokenType::Semicolon, ";"),
+            (TokenType::Eof, ""),
+        ];
+
+        let mut lexer = Lexer::new(input);
+
+        for (i, (expected_token, expected_literal)) in tests.iter().enumerate() {
+            let token = lexer.next_token();
+            assert_eq!(
+                token.type_, *expected_token,
+                "tests[{}] - tokentype wrong. expected={:?} , got ={:?}",
+                i, expected_token, token.type_
+            );
+            assert_eq!(
+                &token.literal, *expected_literal,
+                "tests[{}] - literal wrong. expected={:?} , got ={:?}",
+                i, expected_literal, token.literal
+            );
+        }
+    }
+
+    #[test]
+    fn test_number_literal_bases() {
+        let input = "0xFF 0Xff 0o755 0b1010 1_000_000 0xDEAD_BEEF 0b102 1_;";
+
+        let tests = vec![
+            (TokenType::Int, "0xFF", Some(255)),
+            (TokenType::Int, "0Xff", Some(255)),
+            (TokenType::Int, "0o755", Some(493)),
+            (TokenType::Int, "0b1010", Some(10)),
+            (TokenType::Int, "1_000_000", Some(1_000_000)),
+            (TokenType::Int, "0xDEAD_BEEF", Some(0xDEAD_BEEF)),
+            (TokenType::Illegal, "0b102", None),
+            (TokenType::Illegal, "1_", None),
+            (TokenType::Semicolon, ";", None),
+        ];
+
+        let mut lexer = Lexer::new(input);
+
+        for (i, (expected_token, expected_literal, expected_value)) in tests.iter().enumerate() {
+            let token = lexer.next_token();
+            assert_eq!(
+                token.type_, *expected_token,
+                "tests[{}] - tokentype wrong. expected={:?} , got ={:?}",
+                i, expected_token, token.type_
+            );
+            assert_eq!(
+                &token.literal, *expected_literal,
+                "tests[{}] - literal wrong. expected={:?} , got ={:?}",
+                i, expected_literal, token.literal
+            );
+            assert_eq!(
+                token.int_value(),
+                expected_value.map(Integer::from).as_ref(),
+                "tests[{}] - value wrong",
+                i
+            );
+        }
+
+        let errs = lexer.errors();
+        assert_eq!(errs.len(), 2);
+        assert_eq!(errs[0].msg, "invalid digit '2' in binary literal 0b102");
+        assert_eq!(errs[0].span, Span::new(45, 50, 1, 46));
+        assert_eq!(errs[1].msg, "trailing underscore in number literal 1_");
+    }
+
+    #[test]
+    fn test_string_literals() {
+        let input = r#""foobar" "foo bar" "a\"b\n" "" "open"#;
+
+        let tests = [
+            (TokenType::String, "foobar"),
+            (TokenType::String, "foo bar"),
+            (TokenType::String, "a\"b\n"),
+            (TokenType::String, ""),
+            (TokenType::Illegal, r#""open"#),
+            (TokenType::Eof, ""),
+        ];
+
+        let mut lexer = Lexer::new(input);
+
+        for (i, (expected_token, expected_literal)) in tests.iter().enumerate() {
+            let token = lexer.next_token();
+            assert_eq!(
+                token.type_, *expected_token,
+                "tests[{}] - tokentype wrong. expected={:?} , got ={:?}",
+                i, expected_token, token.type_
+            );
+            assert_eq!(
+                &token.literal, *expected_literal,
+                "tests[{}] - literal wrong. expected={:?} , got ={:?}",
+                i, expected_literal, token.literal
+            );
+        }
+        assert_eq!(lexer.errors()[0].msg, "unterminated string");
+    }
+
+    #[test]
+    fn test_interpolated_string() {
+        let input = r#""Hi ${name}, ${age + 1}!""#;
+        let mut lexer = Lexer::new(input);
+        let token = lexer.next_token();
+
+        assert_eq!(token.type_, TokenType::InterpolatedString);
+        assert_eq!(token.literal, "Hi ${name}, ${age + 1}!");
+        assert_eq!(token.span, Span::new(0, 25, 1, 1));
+
+        let Some(TokenValue::Template(parts)) = &token.value else {
+            panic!("interpolated string has no parts. got={:?}", token.value);
+        };
+        assert_eq!(parts.len(), 5);
+        assert!(matches!(&parts[0], TemplatePart::Text(t) if t == "Hi "));
+        assert!(matches!(&parts[2], TemplatePart::Text(t) if t == ", "));
+        assert!(matches!(&parts[4], TemplatePart::Text(t) if t == "!"));
+
+        let TemplatePart::Expr(tokens) = &parts[3] else {
+            panic!("parts[3] not an expression");
+        };
+        let types: Vec<_> = tokens.iter().map(|t| t.type_).collect();
+        assert_eq!(
+            types,
+            vec![TokenType::Ident, TokenType::Plus, TokenType::Int]
+        );
+        // embedded tokens keep their position inside the string
+        assert_eq!(tokens[0].span, Span::new(15, 18, 1, 16));
+
+        assert_eq!(lexer.next_token().type_, TokenType::Eof);
+    }
+
+    #[test]
+    fn test_module_tokens() {
+        let input = r#"import "lib/math.mk" as math; export let x = math.pi; 1.5;"#;
+
+        let tests = [
+            (TokenType::Import, "import"),
+            (TokenType::String, "lib/math.mk"),
+            (TokenType::As, "as"),
+            (TokenType::Ident, "math"),
+            (TokenType::Semicolon, ";"),
+            (TokenType::Export, "export"),
+            (TokenType::Let, "let"),
+            (TokenType::Ident, "x"),
+            (TokenType::Assign, "="),
+            (TokenType::Ident, "math"),
+            (TokenType::Dot, "."),
+            (TokenType::Ident, "pi"),
+            (TokenType::Semicolon, ";"),
+            (TokenType::Float, "1.5"),
+            (TokenType::Semicolon, ";"),
+            (TokenType::Eof, ""),
+        ];
+
+        let mut lexer = Lexer::new(input);
+
+        for (i, (expected_token, expected_literal)) in tests.iter().enumerate() {
+            let token = lexer.next_token();
+            assert_eq!(
+                token.type_, *expected_token,
+                "tests[{}] - tokentype wrong. expected={:?} , got ={:?}",
+                i, expected_token, token.type_
+            );
+            assert_eq!(
+                &token.literal, *expected_literal,
+                "tests[{}] - literal wrong. expected={:?} , got ={:?}",
+                i, expected_literal, token.literal
+            );
+        }
+    }
+
+    #[test]
+    fn test_annotation_tokens() {
+        let input = "let f: fn(int) -> [int] = fn(a:int)->int { a - 1 };";
+
+        let expected = [
+            TokenType::Let,
+            TokenType::Ident,
+            TokenType::Colon,
+            TokenType::Function,
+            TokenType::LParen,
+            TokenType::Ident,
+            TokenType::RParen,
+            TokenType::Arrow,
+            TokenType::LBracket,
+            TokenType::Ident,
+            TokenType::RBracket,
+            TokenType::Assign,
+            TokenType::Function,
+            TokenType::LParen,
+            TokenType::Ident,
+            TokenType::Colon,
+            TokenType::Ident,
+            TokenType::RParen,
+            TokenType::Arrow,
+            TokenType::Ident,
+            TokenType::LBrace,
+            TokenType::Ident,
+            TokenType::MINUS,
+            TokenType::Int,
+            TokenType::RBrace,
+            TokenType::Semicolon,
+            TokenType::Eof,
+        ];
+
+        let mut lexer = Lexer::new(input);
+        for (i, expected) in expected.iter().enumerate() {
+            assert_eq!(lexer.next_token().type_, *expected, "tests[{}]", i);
+        }
+    }
+
+    #[test]
+    fn test_macro_token() {
+        let input = "macro(x, y) { x + y; };";
+
+        let tests = [
+            (TokenType::Macro, "macro"),
+            (TokenType::LParen, "("),
+            (TokenType::Ident, "x"),
+            (TokenType::Comma, ","),
+            (TokenType::Ident, "y"),
+            (TokenType::RParen, ")"),
+            (TokenType::LBrace, "{"),
+            (TokenType::Ident, "x"),
+            (TokenType::Plus, "+"),
+            (TokenType::Ident, "y"),
+            (TokenType::Semicolon, ";"),
+            (TokenType::RBrace, "}"),
+            (TokenType::Semicolon, ";"),
+            (TokenType::Eof, ""),
+        ];
+
+        let mut lexer = Lexer::new(input);
+
+        for (i, (expected_token, expected_literal)) in tests.iter().enumerate() {
+            let token = lexer.next_token();
+            assert_eq!(
+                token.type_, *expected_token,
+                "tests[{}] - tokentype wrong",
+                i
+            );
+            assert_eq!(
+                &token.literal, *expected_literal,
+                "tests[{}] - literal wrong",
+                i
+            );
+        }
+    }
+
+    #[test]
+    fn test_comments_are_skipped_and_kept() {
+        let input = "// header\nlet x = 10 / 2; // half\n//\nx";
+
+        let mut lexer = Lexer::new(input);
+        let mut types = Vec::new();
+        loop {
+            let token = lexer.next_token();
+            if token.type_ == TokenType::Eof {
+                break;
+            }
+            types.push(token.type_);
+        }
+
+        assert_eq!(
+            types,
+            [
+                TokenType::Let,
+                TokenType::Ident,
+                TokenType::Assign,
+                TokenType::Int,
+                TokenType::SLASH,
+                TokenType::Int,
+                TokenType::Semicolon,
+                TokenType::Ident,
+            ]
+        );
+        assert_eq!(
+            lexer.comments(),
+            [
+                Comment {
+                    text: "// header".to_string(),
+                    span: Span::new(0, 9, 1, 1),
+                },
+                Comment {
+                    text: "// half".to_string(),
+                    span: Span::new(26, 33, 2, 17),
+                },
+                Comment {
+                    text: "//".to_string(),
+                    span: Span::new(34, 36, 3, 1),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_token_spans() {
+        let input = "let x = 10;
+  x == y;";
+
+        let tests = vec![
+            (TokenType::Let, Span::new(0, 3, 1, 1)),
+            (TokenType::Ident, Span::new(4, 5, 1, 5)),
+            (TokenType::Assign, Span::new(6, 7, 1, 7)),
+            (TokenType::Int, Span::new(8, 10, 1, 9)),
+            (TokenType::Semicolon, Span::new(10, 11, 1, 11)),
+            (TokenType::Ident, Span::new(14, 15, 2, 3)),
+            (TokenType::EQ, Span::new(16, 18, 2, 5)),
+            (TokenType::Ident, Span::new(19, 20, 2, 8)),
+            (TokenType::Semicolon, Span::new(20, 21, 2, 9)),
+            (TokenType::Eof, Span::new(21, 21, 2, 10)),
+        ];
+
+        let mut lexer = Lexer::new(input);
+
+        for (i, (expected_type, expected_span)) in tests.iter().enumerate() {
+            let token = lexer.next_token();
+            assert_eq!(
+                token.type_, *expected_type,
+                "tests[{}] - tokentype wrong. expected={:?} , got ={:?}",
+                i, expected_type, token.type_
+            );
+            assert_eq!(
+                token.span, *expected_span,
+                "tests[{}] - span wrong. expected={:?} , got ={:?}",
+                i, expected_span, token.span
+            );
+        }
+    }
 }
@@ -0,0 +1,17 @@
+#![allow(clippy::module_inception)]
+
+pub mod ast;
+pub mod builtins;
+pub mod cli;
+pub mod cst;
+pub mod format;
+pub mod lexer;
+pub mod lint;
+pub mod lsp;
+pub mod module;
+pub mod object;
+pub mod parser;
+pub mod prelude;
+pub mod repl;
+pub mod resolver;
+pub mod typeck;
@@ -0,0 +1,240 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Display;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use toml::{Spanned, Value};
+
+use crate::lint::rules::{self, Rule};
+use crate::resolver::diagnostics::Severity;
+
+// Lint settings, read from the `[lint]` table of a monkey.toml:
+//
+//     [lint]
+//     max-params = 4
+//
+//     [lint.rules]
+//     bool-comparison = "off"
+//     naming = "error"
+//
+// Rules are "off", "warning" or "error"; the ones not listed keep their
+// default severity. The rest of the file is any TOML at all, so it can
+// hold more later.
+
+pub const FILE_NAME: &str = "monkey.toml";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Config {
+    // None turns a rule off
+    levels: HashMap<&'static str, Option<Severity>>,
+    pub max_params: usize,
+    // false with --no-prelude: the prelude's names are not defined
+    pub prelude: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigError {
+    pub line: usize,
+    pub msg: String,
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.msg)
+    }
+}
+
+// The parts of monkey.toml read here; values keep where they were written
+// so errors can point at them.
+#[derive(Deserialize)]
+struct File {
+    #[serde(default)]
+    lint: Lint,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Lint {
+    #[serde(rename = "max-params")]
+    max_params: Option<Spanned<Value>>,
+    #[serde(default)]
+    rules: BTreeMap<Spanned<String>, Spanned<Value>>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            levels: HashMap::new(),
+            max_params: 5,
+            prelude: true,
+        }
+    }
+}
+
+impl Config {
+    pub fn parse(source: &str) -> Result<Config, ConfigError> {
+        let error = |offset: usize, msg: String| ConfigError {
+            line: source[..offset.min(source.len())].matches('\n').count() + 1,
+            msg,
+        };
+        let file: File = toml::from_str(source).map_err(|e| {
+            let offset = e.span().map_or(0, |span| span.start);
+            error(offset, e.message().trim().to_string())
+        })?;
+
+        let mut config = Config::default();
+        if let Some(value) = file.lint.max_params {
+            let offset = value.span().start;
+            config
+                .set_max_params(value.into_inner())
+                .map_err(|msg| error(offset, msg))?;
+        }
+        for (key, value) in file.lint.rules {
+            let offset = key.span().start;
+            config
+                .set_level(key.get_ref(), value.into_inner())
+                .map_err(|msg| error(offset, msg))?;
+        }
+        Ok(config)
+    }
+
+    pub fn load(path: &Path) -> Result<Config, String> {
+        let source = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Config::parse(&source).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    // The nearest monkey.toml in `dir` or one of its parents.
+    pub fn find(dir: &Path) -> Option<PathBuf> {
+        dir.ancestors()
+            .map(|d| d.join(FILE_NAME))
+            .find(|path| path.is_file())
+    }
+
+    // The severity the rule reports with, None if it is off.
+    pub fn level(&self, rule: &Rule) -> Option<Severity> {
+        match self.levels.get(rule.id) {
+            Some(level) => *level,
+            None => Some(rule.severity),
+        }
+    }
+
+    fn set_max_params(&mut self, value: Value) -> Result<(), String> {
+        match value {
+            Value::Integer(n) if n >= 0 => self.max_params = n as usize,
+            _ => return Err("max-params must be a whole number".to_string()),
+        }
+        Ok(())
+    }
+
+    fn set_level(&mut self, key: &str, value: Value) -> Result<(), String> {
+        let Some(rule) = rules::lookup(key) else {
+            return Err(format!("unknown lint rule {}", key));
+        };
+        let level = match value {
+            Value::String(s) if s == "off" => None,
+            Value::String(s) if s == "warning" || s == "warn" => Some(Severity::Warning),
+            Value::String(s) if s == "error" => Some(Severity::Error),
+            _ => return Err(format!("{} must be \"off\", \"warning\" or \"error\"", key)),
+        };
+        self.levels.insert(rule.id, level);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let config = Config::parse(
+            "# project settings
+[package]
+name = \"demo # not a comment\"
+
+[lint]
+max-params = 3 # fewer than the default
+
+[lint.rules]
+bool-comparison = \"off\"
+naming = \"error\"
+",
+        )
+        .unwrap();
+        assert_eq!(config.max_params, 3);
+        let level = |id| config.level(rules::lookup(id).unwrap());
+        assert_eq!(level("bool-comparison"), None);
+        assert_eq!(level("naming"), Some(Severity::Error));
+        assert_eq!(level("empty-block"), Some(Severity::Warning));
+        assert_eq!(level("undefined-name"), Some(Severity::Error));
+    }
+
+    #[test]
+    fn test_other_tables_take_any_value() {
+        let config = Config::parse(
+            "deps = [\n  \"x\",\n]
+
+[package]
+deps = [
+  \"x\", # ] not the end
+  [\"y]\", 'z'],
+]
+notes = \"\"\"
+[lint]
+max-params = 1
+\"\"\"
+literal = '''
+\\'''
+
+[lint]
+max-params = 3
+",
+        )
+        .unwrap();
+        assert_eq!(config.max_params, 3);
+    }
+
+    #[test]
+    fn test_dotted_keys_and_inline_tables() {
+        for source in [
+            "[lint]\nmax-params = 2\nrules.naming = \"off\"\n",
+            "lint.max-params = 2\nlint.rules = { naming = \"off\" }\n",
+            "lint = { max-params = 2, rules = { \"naming\" = \"off\" } }\n",
+        ] {
+            let config = Config::parse(source).unwrap();
+            assert_eq!(config.max_params, 2, "{}", source);
+            assert_eq!(config.level(rules::lookup("naming").unwrap()), None);
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        for (source, msg) in [
+            ("[lint\n", "line 1: invalid table header\nexpected `.`, `]`"),
+            ("[lint]\nmax-params\n", "line 2: expected `.`, `=`"),
+            (
+                "[lint]\nmax-params = \"4\"\n",
+                "line 2: max-params must be a whole number",
+            ),
+            (
+                "[lint]\nstrict = true\n",
+                "line 2: unknown field `strict`, expected `max-params` or `rules`",
+            ),
+            (
+                "[lint.rules]\nno-such-rule = \"off\"\n",
+                "line 2: unknown lint rule no-such-rule",
+            ),
+            (
+                "[lint.rules]\nnaming = \"loud\"\n",
+                "line 2: naming must be \"off\", \"warning\" or \"error\"",
+            ),
+            (
+                "[lint.rules]\nbool-comparison = \"off\"\nnaming = off\n",
+                "line 3: invalid string\nexpected `\"`, `'`",
+            ),
+        ] {
+            assert_eq!(Config::parse(source).unwrap_err().to_string(), msg);
+        }
+    }
+}
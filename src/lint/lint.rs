@@ -0,0 +1,408 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::ops::Range;
+
+use crate::cst::cst::{SyntaxKind, SyntaxNode};
+use crate::cst::parser;
+use crate::lexer::lexer::{Lexer, LexerTrait};
+use crate::lexer::token::Span;
+use crate::lint::config::Config;
+use crate::lint::rules::{self, Context, Finding, Rule, RULES};
+use crate::lsp::analysis::Analysis;
+use crate::parser::errors::ParseErrors;
+use crate::parser::parser::Parser;
+use crate::resolver::diagnostics::Severity;
+use crate::resolver::resolver::resolve_with_prelude;
+
+// Runs the lint rules over a file. Only files that parse are linted, like
+// the formatter only formats those. A comment like
+//
+//     // lint:allow(naming, empty-block)
+//
+// silences the rules it names on its own line and the line below.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edit {
+    pub range: Range<usize>,
+    pub text: String,
+}
+
+// A change that makes a lint go away: one or more edits to the source,
+// applied together or not at all.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fix {
+    pub msg: String,
+    pub edits: Vec<Edit>,
+    // whether --fix applies it; the others are only suggested, since they
+    // keep the meaning only in the cases the lint cannot tell apart
+    pub automatic: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Lint {
+    pub rule: &'static str,
+    pub severity: Severity,
+    pub msg: String,
+    pub span: Span,
+    pub fix: Option<Fix>,
+}
+
+impl Fix {
+    pub fn replace(range: Range<usize>, text: String) -> Fix {
+        let msg = if text.is_empty() {
+            "remove it".to_string()
+        } else {
+            format!("replace with {}", text)
+        };
+        Fix {
+            msg,
+            edits: vec![Edit { range, text }],
+            automatic: true,
+        }
+    }
+
+    // A replacement that is right for the usual case only; --fix leaves it.
+    pub fn suggest(range: Range<usize>, text: String) -> Fix {
+        Fix {
+            automatic: false,
+            ..Fix::replace(range, text)
+        }
+    }
+}
+
+impl Display for Lint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}[{}]: {}", self.severity, self.rule, self.msg)
+    }
+}
+
+pub fn lint_source(source: &str, config: &Config) -> Result<Vec<Lint>, ParseErrors> {
+    let mut p = Parser::new(Lexer::new(source));
+    let program = p.parse_program();
+    let errors = p.errors();
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    let root = parser::parse(source);
+    let allowed = allow_comments(source, &root);
+    let mut lints = Vec::new();
+    let mut report = |rule: &Rule, span: Span, msg: String, fix: Option<Fix>| {
+        let Some(severity) = config.level(rule) else {
+            return;
+        };
+        let silenced = |line: usize| {
+            allowed
+                .get(&line)
+                .is_some_and(|rules| rules.iter().any(|r| r == rule.id))
+        };
+        if silenced(span.line) || silenced(span.line.saturating_sub(1)) {
+            return;
+        }
+        lints.push(Lint {
+            rule: rule.id,
+            severity,
+            msg,
+            span,
+            fix,
+        });
+    };
+
+    for d in resolve_with_prelude(&program, config.prelude).diagnostics {
+        report(rules::for_diagnostic(d.kind), d.span, d.msg, None);
+    }
+
+    let cx = Context {
+        config,
+        analysis: Analysis::with_prelude(&root, config.prelude),
+    };
+    let mut findings = Vec::new();
+    let mut nodes = vec![root.clone()];
+    while let Some(node) = nodes.pop() {
+        for rule in RULES {
+            findings.clear();
+            rule.check(&cx, &node, &mut findings);
+            for Finding { range, msg, fix } in findings.drain(..) {
+                report(rule, span_of(source, range), msg, fix);
+            }
+        }
+        nodes.extend(node.children().into_iter().rev());
+    }
+
+    lints.sort_by_key(|l| (l.span.start, l.span.end));
+    Ok(lints)
+}
+
+// Applies the automatic fixes that do not overlap an earlier one and
+// returns the new source with how many fixes went in.
+pub fn apply_fixes(source: &str, lints: &[Lint]) -> (String, usize) {
+    let mut fixes: Vec<&Fix> = lints
+        .iter()
+        .filter_map(|l| l.fix.as_ref())
+        .filter(|f| f.automatic)
+        .collect();
+    fixes.sort_by_key(|f| f.edits.iter().map(|e| e.range.start).min());
+
+    let mut taken: Vec<Range<usize>> = Vec::new();
+    let mut edits: Vec<&Edit> = Vec::new();
+    let mut applied = 0;
+    for fix in fixes {
+        let overlaps = fix
+            .edits
+            .iter()
+            .any(|e| taken.iter().any(|t| overlap(&e.range, t)));
+        if overlaps {
+            continue;
+        }
+        taken.extend(fix.edits.iter().map(|e| e.range.clone()));
+        edits.extend(&fix.edits);
+        applied += 1;
+    }
+
+    edits.sort_by_key(|e| e.range.start);
+    let mut out = String::with_capacity(source.len());
+    let mut at = 0;
+    for edit in edits {
+        out.push_str(&source[at..edit.range.start]);
+        out.push_str(&edit.text);
+        at = edit.range.end;
+    }
+    out.push_str(&source[at..]);
+    (out, applied)
+}
+
+// Two edits at the same place would be applied in no particular order,
+// so touching empty ranges count as overlapping too.
+fn overlap(a: &Range<usize>, b: &Range<usize>) -> bool {
+    (a.start < b.end && b.start < a.end) || a.start == b.start
+}
+
+// Applies fixes until none are left, since one fix can uncover another.
+// A round whose result no longer parses is thrown away.
+pub fn fix_source(source: &str, config: &Config) -> Result<String, ParseErrors> {
+    let mut source = source.to_string();
+    let mut lints = lint_source(&source, config)?;
+    for _ in 0..10 {
+        let (fixed, applied) = apply_fixes(&source, &lints);
+        if applied == 0 {
+            break;
+        }
+        match lint_source(&fixed, config) {
+            Ok(next) => {
+                source = fixed;
+                lints = next;
+            }
+            Err(_) => break,
+        }
+    }
+    Ok(source)
+}
+
+// The rules each line's `lint:allow(...)` comment names.
+fn allow_comments(source: &str, root: &SyntaxNode) -> HashMap<usize, Vec<String>> {
+    let mut allowed: HashMap<usize, Vec<String>> = HashMap::new();
+    for token in root.tokens() {
+        if token.kind() != SyntaxKind::Comment {
+            continue;
+        }
+        let Some((_, rest)) = token.text().split_once("lint:allow(") else {
+            continue;
+        };
+        let Some((list, _)) = rest.split_once(')') else {
+            continue;
+        };
+        let line = span_of(source, token.range()).line;
+        allowed
+            .entry(line)
+            .or_default()
+            .extend(list.split(',').map(|r| r.trim().to_string()));
+    }
+    allowed
+}
+
+// The span of a byte range, with the 1-based line and column of its start
+// counted the way the lexer counts them.
+fn span_of(source: &str, range: Range<usize>) -> Span {
+    let before = &source[..range.start];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let column = before[line_start..].chars().count() + 1;
+    Span::new(range.start, range.end, line, column)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lints(source: &str) -> Vec<String> {
+        lint_source(source, &Config::default())
+            .unwrap()
+            .iter()
+            .map(|l| format!("{} {}", l.span, l))
+            .collect()
+    }
+
+    fn fixed(source: &str) -> String {
+        fix_source(source, &Config::default()).unwrap()
+    }
+
+    #[test]
+    fn test_rules() {
+        let source = "let isReady = fn(a, b, c, d, e, f) {
+    if (a == true) { puts(b) } else { }
+    if (false) { puts(c, d, e, f) }
+    while (isReady(1, 2, 3, 4, 5, 6)) {}
+    return 1;
+};
+";
+        assert_eq!(
+            lints(source),
+            vec![
+                "1:5 warning[naming]: isReady should be snake_case",
+                "1:17 warning[too-many-params]: 6 parameters, more than the 5 allowed",
+                "2:9 warning[bool-comparison]: comparison to true",
+                "2:37 warning[empty-block]: empty block",
+                "3:9 warning[constant-condition]: the condition is always false",
+                "4:39 warning[empty-block]: empty block",
+                "5:5 warning[redundant-return]: return is redundant at the end of a function",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resolver_rules() {
+        assert_eq!(
+            lints("let x = 1;\nputs(y);\n"),
+            vec![
+                "1:5 warning[unused-binding]: x is never used",
+                "2:6 error[undefined-name]: y is not defined",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_config_and_allow_comments() {
+        let source = "// lint:allow(unused-binding)
+let someName = 1;
+let other = [1] == true; // lint:allow(bool-comparison, unused-binding)
+";
+        let config = Config::parse("[lint.rules]\nnaming = \"error\"\n").unwrap();
+        let lints: Vec<String> = lint_source(source, &config)
+            .unwrap()
+            .iter()
+            .map(|l| l.to_string())
+            .collect();
+        assert_eq!(lints, vec!["error[naming]: someName should be snake_case"]);
+
+        let config = Config::parse("[lint.rules]\nnaming = \"off\"\n").unwrap();
+        assert!(lint_source(source, &config).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_fixes() {
+        for (source, expected) in [
+            (
+                "if (len([])) { puts(1) } else {}",
+                "if (len([])) { puts(1) }",
+            ),
+            (
+                "try { puts(1) } catch (e) { puts(e) } finally {\n}",
+                "try { puts(1) } catch (e) { puts(e) }",
+            ),
+            (
+                "let f = fn() {\n    puts(1);\n    return 2;\n};\nf();",
+                "let f = fn() {\n    puts(1);\n    2\n};\nf();",
+            ),
+            (
+                "let addOne = fn(xValue) { xValue + 1 };\nputs(addOne(1));",
+                "let add_one = fn(x_value) { x_value + 1 };\nputs(add_one(1));",
+            ),
+            (
+                "let f = fn(xValue: int) -> int { xValue };\nputs(f(1));",
+                "let f = fn(x_value: int) -> int { x_value };\nputs(f(1));",
+            ),
+            // uses inside interpolations are renamed too
+            (
+                "let myVar = 1; puts(\"v=${myVar}\");",
+                "let my_var = 1; puts(\"v=${my_var}\");",
+            ),
+            (
+                "let myVar = 1; puts(\"${\"${myVar}\" + \"!\"}\", myVar);",
+                "let my_var = 1; puts(\"${\"${my_var}\" + \"!\"}\", my_var);",
+            ),
+            // fixes that uncover or overlap each other take several rounds
+            (
+                "let f = fn(aB) { if (aB) { puts(1) } else {}\n return aB; };\nf(1);",
+                "let f = fn(a_b) { if (a_b) { puts(1) }\n a_b };\nf(1);",
+            ),
+        ] {
+            assert_eq!(fixed(source), expected, "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_bool_comparisons_are_only_suggested() {
+        // `n == true` is false for n = 5, while `n` is 5
+        for (source, suggestion) in [
+            ("let n = 5;\nputs(n == true);", "n"),
+            ("puts(false == len([]));", "!len([])"),
+            ("puts(1 + 1 != true);", "!(1 + 1)"),
+            ("puts(1 != false);", "1"),
+        ] {
+            let lints = lint_source(source, &Config::default()).unwrap();
+            let fix = lints
+                .iter()
+                .find(|l| l.rule == "bool-comparison")
+                .and_then(|l| l.fix.clone())
+                .unwrap();
+            assert!(!fix.automatic, "{}", source);
+            assert_eq!(fix.edits[0].text, suggestion, "{}", source);
+            assert_eq!(fixed(source), source);
+        }
+    }
+
+    #[test]
+    fn test_unsafe_renames_are_not_fixed() {
+        // the new name is taken, or the binding is exported
+        for source in [
+            "let fooBar = 1;\nlet foo_bar = 2;\nputs(fooBar, foo_bar);",
+            "let Map = 1;\nputs(Map);",
+            "export let myApi = 1;",
+        ] {
+            let lints = lint_source(source, &Config::default()).unwrap();
+            assert!(lints.iter().any(|l| l.rule == "naming"), "{}", source);
+            assert_eq!(fixed(source), source);
+        }
+    }
+
+    #[test]
+    fn test_return_value_is_not_joined_to_the_statement_before() {
+        // without the return, `(x)` would call the result of puts(x)
+        for source in [
+            "let f = fn(x) { puts(x)\n return (x); };\nf(1);",
+            "let f = fn(x) { puts(x)\n return -x; };\nf(1);",
+            "let f = fn(x) { if (x) { puts(x) }\n return [x]; };\nf(1);",
+        ] {
+            let lints = lint_source(source, &Config::default()).unwrap();
+            assert!(
+                lints.iter().any(|l| l.rule == "redundant-return"),
+                "{}",
+                source
+            );
+            assert_eq!(fixed(source), source);
+        }
+        assert_eq!(
+            fixed("let f = fn(x) { puts(x);\n return (x); };\nf(1);"),
+            "let f = fn(x) { puts(x);\n (x) };\nf(1);"
+        );
+        assert_eq!(
+            fixed("let f = fn(x) { puts(x)\n return x; };\nf(1);"),
+            "let f = fn(x) { puts(x)\n x };\nf(1);"
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(lint_source("let = 1;", &Config::default()).is_err());
+    }
+}
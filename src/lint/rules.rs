@@ -0,0 +1,478 @@
+use std::ops::Range;
+
+use crate::cst::cst::{SyntaxElement, SyntaxKind, SyntaxNode, SyntaxToken};
+use crate::lexer::token::TokenType;
+use crate::lint::config::Config;
+use crate::lint::lint::{Edit, Fix};
+use crate::lsp::analysis::{self, Analysis};
+use crate::parser::parser::{precedence_of, Predecessor};
+use crate::resolver::diagnostics::{DiagnosticKind, Severity};
+
+// The lint rules. Most look at one node of the lossless syntax tree at a
+// time, so their fixes can quote and replace source text exactly; the
+// rest report what the resolver finds.
+
+pub struct Rule {
+    pub id: &'static str,
+    // the severity unless monkey.toml says otherwise
+    pub severity: Severity,
+    pub doc: &'static str,
+    // None for the rules the resolver checks
+    check: Option<Check>,
+}
+
+type Check = fn(&Context, &SyntaxNode, &mut Vec<Finding>);
+
+// What the rules see besides the node at hand.
+pub struct Context<'a> {
+    pub config: &'a Config,
+    pub analysis: Analysis,
+}
+
+// A problem a rule found, before it gets its rule's id and severity.
+pub struct Finding {
+    pub range: Range<usize>,
+    pub msg: String,
+    pub fix: Option<Fix>,
+}
+
+pub const RULES: &[Rule] = &[
+    Rule {
+        id: "undefined-name",
+        severity: Severity::Error,
+        doc: "a name that is not defined anywhere in scope",
+        check: None,
+    },
+    Rule {
+        id: "unused-binding",
+        severity: Severity::Warning,
+        doc: "a binding that is never used",
+        check: None,
+    },
+    Rule {
+        id: "shadowed-binding",
+        severity: Severity::Warning,
+        doc: "a binding that hides another one of the same name",
+        check: None,
+    },
+    Rule {
+        id: "unreachable-code",
+        severity: Severity::Warning,
+        doc: "a statement after return, throw, break or continue",
+        check: None,
+    },
+    Rule {
+        id: "bool-comparison",
+        severity: Severity::Warning,
+        doc: "`x == true` and friends, which say `x` or `!x` the long way",
+        check: Some(bool_comparison),
+    },
+    Rule {
+        id: "constant-condition",
+        severity: Severity::Warning,
+        doc: "an if whose condition is a literal",
+        check: Some(constant_condition),
+    },
+    Rule {
+        id: "empty-block",
+        severity: Severity::Warning,
+        doc: "a block with neither statements nor comments, outside function bodies",
+        check: Some(empty_block),
+    },
+    Rule {
+        id: "too-many-params",
+        severity: Severity::Warning,
+        doc: "a function with more parameters than `max-params` (5)",
+        check: Some(too_many_params),
+    },
+    Rule {
+        id: "redundant-return",
+        severity: Severity::Warning,
+        doc: "a return as the last statement of a function body",
+        check: Some(redundant_return),
+    },
+    Rule {
+        id: "naming",
+        severity: Severity::Warning,
+        doc: "a binding whose name is not snake_case",
+        check: Some(naming),
+    },
+];
+
+pub fn lookup(id: &str) -> Option<&'static Rule> {
+    RULES.iter().find(|r| r.id == id)
+}
+
+// The rule a resolver diagnostic is reported under.
+pub fn for_diagnostic(kind: DiagnosticKind) -> &'static Rule {
+    let id = match kind {
+        DiagnosticKind::UndefinedName => "undefined-name",
+        DiagnosticKind::UnusedBinding => "unused-binding",
+        DiagnosticKind::ShadowedBinding => "shadowed-binding",
+        DiagnosticKind::UnreachableCode => "unreachable-code",
+    };
+    lookup(id).expect("every diagnostic kind has a rule")
+}
+
+impl Rule {
+    pub fn check(&self, cx: &Context, node: &SyntaxNode, findings: &mut Vec<Finding>) {
+        if let Some(check) = self.check {
+            check(cx, node, findings);
+        }
+    }
+}
+
+fn bool_comparison(_: &Context, node: &SyntaxNode, findings: &mut Vec<Finding>) {
+    if node.kind() != SyntaxKind::InfixExpression {
+        return;
+    }
+    let negated = match operator(node).map(|t| t.kind()) {
+        Some(SyntaxKind::Token(TokenType::EQ)) => false,
+        Some(SyntaxKind::Token(TokenType::NotEq)) => true,
+        _ => return,
+    };
+    let [left, right] = &node.children()[..] else {
+        return;
+    };
+    let (operand, literal) = match (bool_literal(left), bool_literal(right)) {
+        (_, Some(value)) => (left, value),
+        (Some(value), None) => (right, value),
+        (None, None) => return,
+    };
+
+    let replacement = if literal != negated {
+        operand.to_string()
+    } else if is_primary(operand) {
+        format!("!{}", operand)
+    } else {
+        format!("!({})", operand)
+    };
+    findings.push(Finding {
+        range: node.range(),
+        msg: format!("comparison to {}", literal),
+        // only right when the operand is a bool: `5 == true` is false
+        fix: Some(Fix::suggest(node.range(), replacement)),
+    });
+}
+
+fn constant_condition(_: &Context, node: &SyntaxNode, findings: &mut Vec<Finding>) {
+    if node.kind() != SyntaxKind::IfExpression {
+        return;
+    }
+    let Some(mut condition) = node.children().into_iter().next() else {
+        return;
+    };
+    while condition.kind() == SyntaxKind::ParenExpression {
+        match condition.children().into_iter().next() {
+            Some(inner) => condition = inner,
+            None => return,
+        }
+    }
+    if condition.kind() == SyntaxKind::Literal {
+        findings.push(Finding {
+            range: condition.range(),
+            msg: format!("the condition is always {}", condition),
+            fix: None,
+        });
+    }
+}
+
+fn empty_block(_: &Context, node: &SyntaxNode, findings: &mut Vec<Finding>) {
+    if node.kind() != SyntaxKind::BlockStatement {
+        return;
+    }
+    let empty = node.children_with_tokens().iter().all(|child| {
+        matches!(
+            child.kind(),
+            SyntaxKind::Whitespace
+                | SyntaxKind::Token(TokenType::LBrace)
+                | SyntaxKind::Token(TokenType::RBrace)
+        )
+    });
+    let Some(parent) = node.parent() else {
+        return;
+    };
+    if !empty
+        || matches!(
+            parent.kind(),
+            SyntaxKind::FunctionLiteral | SyntaxKind::MacroLiteral
+        )
+    {
+        return;
+    }
+
+    // an empty else or finally can go; a try needs a catch without it
+    let removable = match parent.kind() {
+        SyntaxKind::ElseClause => true,
+        SyntaxKind::FinallyClause => parent.parent().is_some_and(|t| {
+            t.children()
+                .iter()
+                .any(|c| c.kind() == SyntaxKind::CatchClause)
+        }),
+        _ => false,
+    };
+    let fix = removable.then(|| {
+        let start = previous_sibling_end(&parent).unwrap_or(parent.range().start);
+        Fix::replace(start..parent.range().end, String::new())
+    });
+    findings.push(Finding {
+        range: node.range(),
+        msg: "empty block".to_string(),
+        fix,
+    });
+}
+
+fn too_many_params(cx: &Context, node: &SyntaxNode, findings: &mut Vec<Finding>) {
+    if node.kind() != SyntaxKind::ParameterList {
+        return;
+    }
+    let count = node
+        .children()
+        .iter()
+        .filter(|c| c.kind() == SyntaxKind::Name)
+        .count();
+    if count > cx.config.max_params {
+        findings.push(Finding {
+            range: node.range(),
+            msg: format!(
+                "{} parameters, more than the {} allowed",
+                count, cx.config.max_params
+            ),
+            fix: None,
+        });
+    }
+}
+
+fn redundant_return(_: &Context, node: &SyntaxNode, findings: &mut Vec<Finding>) {
+    if node.kind() != SyntaxKind::ReturnStatement {
+        return;
+    }
+    let Some(block) = node.parent() else {
+        return;
+    };
+    let in_body = block.parent().is_some_and(|f| {
+        matches!(
+            f.kind(),
+            SyntaxKind::FunctionLiteral | SyntaxKind::MacroLiteral
+        )
+    });
+    let last = block.children().last().map(SyntaxNode::range) == Some(node.range());
+    if !in_body || !last {
+        return;
+    }
+
+    // a block's last expression is its value already
+    let Some(value) = node.children().into_iter().next() else {
+        return;
+    };
+    // ...but bare, a value starting with `(`, `[` or `-` continues the
+    // statement before it unless a `;` or the block's `{` ends that one
+    let previous = block
+        .tokens()
+        .into_iter()
+        .filter(|t| !t.kind().is_trivia() && t.range().end <= node.range().start)
+        .last();
+    let separated = previous.is_none_or(|t| {
+        matches!(
+            t.kind(),
+            SyntaxKind::Token(TokenType::Semicolon | TokenType::LBrace)
+        )
+    });
+    let continues = value
+        .tokens()
+        .into_iter()
+        .find(|t| !t.kind().is_trivia())
+        .is_some_and(|t| match t.kind() {
+            SyntaxKind::Token(kind) => precedence_of(&kind) > Predecessor::LOWEST,
+            _ => false,
+        });
+    findings.push(Finding {
+        range: node.range(),
+        msg: "return is redundant at the end of a function".to_string(),
+        fix: (separated || !continues).then(|| Fix::replace(node.range(), value.to_string())),
+    });
+}
+
+fn naming(cx: &Context, node: &SyntaxNode, findings: &mut Vec<Finding>) {
+    let names: Vec<SyntaxToken> = match node.kind() {
+        SyntaxKind::LetStatement
+        | SyntaxKind::ForStatement
+        | SyntaxKind::CatchClause
+        | SyntaxKind::ImportStatement => own_tokens(node)
+            .into_iter()
+            .filter(|t| t.kind() == SyntaxKind::Token(TokenType::Ident))
+            .take(1)
+            .collect(),
+        SyntaxKind::ParameterList => node
+            .children()
+            .iter()
+            .filter(|c| c.kind() == SyntaxKind::Name)
+            .flat_map(own_tokens)
+            .collect(),
+        _ => return,
+    };
+
+    for name in names {
+        let snake = snake_case(name.text());
+        if snake == name.text() {
+            continue;
+        }
+        let exported = node
+            .parent()
+            .is_some_and(|p| p.kind() == SyntaxKind::ExportStatement);
+        findings.push(Finding {
+            range: name.range(),
+            msg: format!("{} should be snake_case", name.text()),
+            fix: if exported {
+                // other modules use the name as it is
+                None
+            } else {
+                rename(cx, &name, &snake)
+            },
+        });
+    }
+}
+
+// Renames a binding and all its uses, unless the new name is taken.
+fn rename(cx: &Context, name: &SyntaxToken, to: &str) -> Option<Fix> {
+    let taken = analysis::KEYWORDS.contains(&to)
+        || analysis::globals(cx.config.prelude)
+            .iter()
+            .any(|(g, _)| g == to)
+        || cx.analysis.definitions.iter().any(|d| d.name == to)
+        || cx.analysis.references.iter().any(|r| r.name == to);
+    if taken {
+        return None;
+    }
+    let definition = cx
+        .analysis
+        .definitions
+        .iter()
+        .position(|d| d.range == name.range())?;
+    let mut edits = vec![Edit {
+        range: name.range(),
+        text: to.to_string(),
+    }];
+    for range in cx.analysis.references_to(definition) {
+        edits.push(Edit {
+            range,
+            text: to.to_string(),
+        });
+    }
+    Some(Fix {
+        msg: format!("rename to {}", to),
+        edits,
+        automatic: true,
+    })
+}
+
+// fooBar and FooBar become foo_bar, HTTPServer http_server. Leading
+// underscores stay.
+pub fn snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut out = String::with_capacity(name.len() + 2);
+    for (i, &ch) in chars.iter().enumerate() {
+        if ch.is_uppercase() {
+            let prev = i.checked_sub(1).map(|p| chars[p]);
+            let next = chars.get(i + 1);
+            let boundary = match prev {
+                Some(p) if p.is_lowercase() || p.is_ascii_digit() => true,
+                Some(p) if p.is_uppercase() => next.is_some_and(|n| n.is_lowercase()),
+                _ => false,
+            };
+            if boundary {
+                out.push('_');
+            }
+            out.extend(ch.to_lowercase());
+        } else {
+            out.push(ch);
+        }
+    }
+    out
+}
+
+// The tokens that are children of the node itself, trivia left out.
+fn own_tokens(node: &SyntaxNode) -> Vec<SyntaxToken> {
+    node.children_with_tokens()
+        .into_iter()
+        .filter_map(|child| match child {
+            SyntaxElement::Token(t) if !t.kind().is_trivia() => Some(t),
+            _ => None,
+        })
+        .collect()
+}
+
+fn operator(node: &SyntaxNode) -> Option<SyntaxToken> {
+    own_tokens(node).into_iter().next()
+}
+
+fn bool_literal(node: &SyntaxNode) -> Option<bool> {
+    if node.kind() != SyntaxKind::Literal {
+        return None;
+    }
+    match own_tokens(node).first()?.kind() {
+        SyntaxKind::Token(TokenType::True) => Some(true),
+        SyntaxKind::Token(TokenType::False) => Some(false),
+        _ => None,
+    }
+}
+
+// Whether `!` can go in front of the expression without parentheses.
+fn is_primary(node: &SyntaxNode) -> bool {
+    matches!(
+        node.kind(),
+        SyntaxKind::Name
+            | SyntaxKind::Literal
+            | SyntaxKind::ParenExpression
+            | SyntaxKind::ArrayLiteral
+            | SyntaxKind::IndexExpression
+            | SyntaxKind::MemberExpression
+            | SyntaxKind::CallExpression
+            | SyntaxKind::PrefixExpression
+    )
+}
+
+// Where the element before the node ends, skipping whitespace but not
+// comments, so deleting up to the node takes its indentation with it.
+fn previous_sibling_end(node: &SyntaxNode) -> Option<usize> {
+    let parent = node.parent()?;
+    parent
+        .children_with_tokens()
+        .into_iter()
+        .take_while(|c| c.range().start < node.range().start)
+        .filter(|c| c.kind() != SyntaxKind::Whitespace)
+        .last()
+        .map(|c| c.range().end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snake_case() {
+        for (name, snake) in [
+            ("total", "total"),
+            ("_unused", "_unused"),
+            ("fooBar", "foo_bar"),
+            ("FooBar", "foo_bar"),
+            ("HTTPServer", "http_server"),
+            ("parseV2Json", "parse_v2_json"),
+            ("MAX", "max"),
+            ("already_snake", "already_snake"),
+        ] {
+            assert_eq!(snake_case(name), snake, "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_rule_ids_are_unique() {
+        for (i, rule) in RULES.iter().enumerate() {
+            assert!(
+                RULES[..i].iter().all(|r| r.id != rule.id),
+                "{} is listed twice",
+                rule.id
+            );
+        }
+    }
+}
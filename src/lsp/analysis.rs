@@ -0,0 +1,730 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use crate::builtins::signatures;
+use crate::cst::cst::{SyntaxElement, SyntaxKind, SyntaxNode, SyntaxToken};
+use crate::cst::parser;
+use crate::lexer::lexer::{Lexer, LexerTrait};
+use crate::lexer::token::{TemplatePart, TokenType, TokenValue};
+use crate::prelude::prelude;
+
+// Name resolution over the syntax tree, for the language server. It works
+// on the lossless tree, so a file that does not parse still gets answers
+// for the parts that do.
+//
+// Scopes follow the evaluator: the program and every block open one,
+// functions bind their parameters, for loops their variable and catch
+// clauses their error. A `let` is visible after its statement, except that
+// a function can see its own name so it can recurse.
+
+pub(crate) const KEYWORDS: &[&str] = &[
+    "let", "fn", "macro", "return", "if", "else", "while", "for", "in", "break", "continue", "try",
+    "catch", "finally", "throw", "import", "export", "as", "true", "false",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DefinitionKind {
+    Let,
+    // a let bound to a function or macro literal
+    Function,
+    Parameter,
+    LoopVariable,
+    CatchParameter,
+    Import,
+}
+
+#[derive(Debug, Clone)]
+pub struct Definition {
+    pub name: String,
+    pub kind: DefinitionKind,
+    // the binding identifier
+    pub range: Range<usize>,
+    // where the name can be used: from where it is bound to the end of
+    // its scope
+    pub scope: Range<usize>,
+    // one line describing it, shown on hover
+    pub detail: String,
+    // bound in the program's own scope
+    pub top_level: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Reference {
+    pub name: String,
+    pub range: Range<usize>,
+    // None for builtins, prelude functions and unknown names
+    pub definition: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompletionKind {
+    Keyword,
+    Function,
+    Variable,
+    Module,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+    pub label: String,
+    pub kind: CompletionKind,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Function,
+    Variable,
+    Module,
+}
+
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    // the whole statement, and the name within it
+    pub range: Range<usize>,
+    pub selection: Range<usize>,
+    pub children: Vec<Symbol>,
+}
+
+#[derive(Default)]
+pub struct Analysis {
+    pub definitions: Vec<Definition>,
+    pub references: Vec<Reference>,
+    // whether the prelude's functions are defined, see globals()
+    prelude: bool,
+}
+
+impl Analysis {
+    pub fn new(root: &SyntaxNode) -> Self {
+        Analysis::with_prelude(root, true)
+    }
+
+    // Without the prelude (--no-prelude) only builtins are global.
+    pub fn with_prelude(root: &SyntaxNode, prelude: bool) -> Self {
+        let mut resolver = Resolver {
+            analysis: Analysis::default(),
+            scopes: Vec::new(),
+            base: 0,
+        };
+        resolver.walk(root);
+        Analysis {
+            prelude,
+            ..resolver.analysis
+        }
+    }
+
+    // The definition whose name, or one of whose uses, is at `offset`.
+    pub fn definition_at(&self, offset: usize) -> Option<usize> {
+        if let Some(i) = self
+            .definitions
+            .iter()
+            .position(|d| touches(&d.range, offset))
+        {
+            return Some(i);
+        }
+        self.reference_at(offset).and_then(|r| r.definition)
+    }
+
+    // The uses of a definition, not counting the definition itself.
+    pub fn references_to(&self, definition: usize) -> Vec<Range<usize>> {
+        self.references
+            .iter()
+            .filter(|r| r.definition == Some(definition))
+            .map(|r| r.range.clone())
+            .collect()
+    }
+
+    // Describes the top-level lets by the types typeck inferred for them,
+    // given in order as in typeck::Typing::globals. Functions it could
+    // only call any keep their parameter list.
+    pub fn set_types(&mut self, globals: &[(String, String)]) {
+        let mut globals = globals.iter();
+        let lets = self.definitions.iter_mut().filter(|d| {
+            d.top_level && matches!(d.kind, DefinitionKind::Let | DefinitionKind::Function)
+        });
+        for d in lets {
+            let Some((name, ty)) = globals.next() else {
+                break;
+            };
+            if *name != d.name {
+                // the two parsers disagree; trust neither
+                break;
+            }
+            if ty != "any" {
+                d.detail = format!("let {}: {}", name, ty);
+            }
+        }
+    }
+
+    pub fn hover(&self, offset: usize) -> Option<(Range<usize>, String)> {
+        if let Some(d) = self.definitions.iter().find(|d| touches(&d.range, offset)) {
+            return Some((d.range.clone(), d.detail.clone()));
+        }
+        let reference = self.reference_at(offset)?;
+        let detail = match reference.definition {
+            Some(i) => self.definitions[i].detail.clone(),
+            None => describe_global(&reference.name, self.prelude)?,
+        };
+        Some((reference.range.clone(), detail))
+    }
+
+    // Everything that can be typed at `offset`: names in scope there,
+    // builtins, prelude functions and keywords.
+    pub fn completions(&self, offset: usize) -> Vec<Completion> {
+        let mut visible: HashMap<&str, &Definition> = HashMap::new();
+        for d in &self.definitions {
+            if d.scope.start <= offset && offset <= d.scope.end {
+                // the innermost binding of a name is the one bound last
+                let shadowed = visible
+                    .get(d.name.as_str())
+                    .is_some_and(|other| other.scope.start > d.scope.start);
+                if !shadowed {
+                    visible.insert(&d.name, d);
+                }
+            }
+        }
+
+        let mut completions: Vec<Completion> = visible
+            .values()
+            .map(|d| Completion {
+                label: d.name.clone(),
+                kind: match d.kind {
+                    DefinitionKind::Function => CompletionKind::Function,
+                    DefinitionKind::Import => CompletionKind::Module,
+                    _ => CompletionKind::Variable,
+                },
+                detail: d.detail.clone(),
+            })
+            .collect();
+        completions.sort_by(|a, b| a.label.cmp(&b.label));
+
+        for (name, detail) in globals(self.prelude) {
+            if !visible.contains_key(name.as_str()) {
+                completions.push(Completion {
+                    label: name,
+                    kind: CompletionKind::Function,
+                    detail,
+                });
+            }
+        }
+        completions.extend(KEYWORDS.iter().map(|k| Completion {
+            label: k.to_string(),
+            kind: CompletionKind::Keyword,
+            detail: "keyword".to_string(),
+        }));
+        completions
+    }
+
+    fn reference_at(&self, offset: usize) -> Option<&Reference> {
+        self.references.iter().find(|r| touches(&r.range, offset))
+    }
+}
+
+// A cursor right after a name still counts as on it.
+fn touches(range: &Range<usize>, offset: usize) -> bool {
+    range.start <= offset && offset <= range.end
+}
+
+// Builtins and, unless left out, prelude functions with their signatures.
+pub(crate) fn globals(prelude: bool) -> Vec<(String, String)> {
+    let mut globals: Vec<(String, String)> = signatures::BUILTINS
+        .iter()
+        .map(|s| (s.name.to_string(), s.to_string()))
+        .collect();
+    if !prelude {
+        return globals;
+    }
+    let prelude = Analysis::new(&parser::parse(prelude::SOURCE));
+    for d in prelude.definitions {
+        if d.kind == DefinitionKind::Function
+            && !d.name.starts_with('_')
+            && d.scope.end == prelude::SOURCE.len()
+        {
+            let detail = d.detail.replacen("let ", "", 1).replacen(" = fn", "", 1);
+            globals.push((d.name, detail));
+        }
+    }
+    globals
+}
+
+fn describe_global(name: &str, prelude: bool) -> Option<String> {
+    if let Some(signature) = signatures::lookup(name) {
+        return Some(format!("{} (builtin)\n\n{}", signature, signature.doc));
+    }
+    globals(prelude)
+        .into_iter()
+        .find(|(global, _)| global == name)
+        .map(|(_, detail)| format!("{} (prelude)", detail))
+}
+
+struct Resolver {
+    analysis: Analysis,
+    // the names bound in each open scope, and where the scope ends
+    scopes: Vec<(HashMap<String, usize>, usize)>,
+    // where the tree being walked starts in the document: the code inside
+    // `${}` is parsed on its own, see interpolation()
+    base: usize,
+}
+
+impl Resolver {
+    fn walk(&mut self, node: &SyntaxNode) {
+        match node.kind() {
+            SyntaxKind::Program | SyntaxKind::BlockStatement => {
+                self.scoped(node, |r| r.walk_children(node));
+            }
+            SyntaxKind::FunctionLiteral | SyntaxKind::MacroLiteral => {
+                self.scoped(node, |r| {
+                    for child in node.children() {
+                        if child.kind() == SyntaxKind::ParameterList {
+                            let names = child
+                                .children()
+                                .into_iter()
+                                .filter(|c| c.kind() == SyntaxKind::Name);
+                            for param in names.flat_map(|name| name.tokens()) {
+                                let detail = format!("{} (parameter)", param.text());
+                                r.bind(
+                                    &param,
+                                    DefinitionKind::Parameter,
+                                    param.range().start,
+                                    detail,
+                                );
+                            }
+                        } else {
+                            r.walk(&child);
+                        }
+                    }
+                });
+            }
+            SyntaxKind::ForStatement => {
+                // the iterable is evaluated outside the loop's scope
+                let mut children = node.children().into_iter();
+                if let Some(iterable) = children.next() {
+                    self.walk(&iterable);
+                }
+                self.scoped(node, |r| {
+                    if let Some(var) = token_child(node, TokenType::Ident) {
+                        r.bind(
+                            &var,
+                            DefinitionKind::LoopVariable,
+                            var.range().start,
+                            format!("{} (loop variable)", var.text()),
+                        );
+                    }
+                    children.for_each(|child| r.walk(&child));
+                });
+            }
+            SyntaxKind::CatchClause => {
+                self.scoped(node, |r| {
+                    if let Some(var) = token_child(node, TokenType::Ident) {
+                        r.bind(
+                            &var,
+                            DefinitionKind::CatchParameter,
+                            var.range().start,
+                            format!("{} (caught error)", var.text()),
+                        );
+                    }
+                    r.walk_children(node);
+                });
+            }
+            SyntaxKind::LetStatement => self.let_statement(node),
+            SyntaxKind::ImportStatement => {
+                if let Some(alias) = token_child(node, TokenType::Ident) {
+                    let detail = node.to_string().trim_end_matches(';').to_string();
+                    self.bind(&alias, DefinitionKind::Import, node.range().end, detail);
+                }
+            }
+            SyntaxKind::Literal => {
+                if let Some(string) = token_child(node, TokenType::InterpolatedString) {
+                    self.interpolation(&string);
+                }
+            }
+            SyntaxKind::Name => {
+                let name = node.to_string();
+                let definition = self
+                    .scopes
+                    .iter()
+                    .rev()
+                    .find_map(|(names, _)| names.get(&name).copied());
+                self.analysis.references.push(Reference {
+                    name,
+                    range: self.shift(node.range()),
+                    definition,
+                });
+            }
+            _ => self.walk_children(node),
+        }
+    }
+
+    fn walk_children(&mut self, node: &SyntaxNode) {
+        for child in node.children() {
+            self.walk(&child);
+        }
+    }
+
+    // The syntax tree keeps an interpolated string as a single token, so
+    // the code inside each `${}` is lexed again from its text, parsed on its
+    // own and resolved where it sits in the document.
+    fn interpolation(&mut self, string: &SyntaxToken) {
+        let token = Lexer::new(string.text()).next_token();
+        let Some(TokenValue::Template(parts)) = token.value else {
+            return;
+        };
+        for part in parts {
+            let TemplatePart::Expr(tokens) = part else {
+                continue;
+            };
+            let (Some(first), Some(last)) = (tokens.first(), tokens.last()) else {
+                continue;
+            };
+            let code = first.span.start..last.span.end;
+            let outer = self.base;
+            self.base = outer + string.range().start + code.start;
+            self.walk(&parser::parse(&string.text()[code]));
+            self.base = outer;
+        }
+    }
+
+    fn shift(&self, range: Range<usize>) -> Range<usize> {
+        range.start + self.base..range.end + self.base
+    }
+
+    fn scoped(&mut self, node: &SyntaxNode, f: impl FnOnce(&mut Self)) {
+        self.scopes
+            .push((HashMap::new(), node.range().end + self.base));
+        f(self);
+        self.scopes.pop();
+    }
+
+    fn let_statement(&mut self, node: &SyntaxNode) {
+        let Some(name) = token_child(node, TokenType::Ident) else {
+            return self.walk_children(node);
+        };
+        let (annotation, value): (Vec<_>, Vec<_>) = node
+            .children()
+            .into_iter()
+            .partition(|c| c.kind() == SyntaxKind::TypeAnnotation);
+        let annotation = annotation.first().map(|a| a.to_string());
+        let value = value.into_iter().next();
+        let params = value.as_ref().and_then(|v| match v.kind() {
+            SyntaxKind::FunctionLiteral => Some("fn"),
+            SyntaxKind::MacroLiteral => Some("macro"),
+            _ => None,
+        });
+
+        match (&value, params) {
+            (Some(value), Some(keyword)) => {
+                let mut detail = format!(
+                    "let {} = {}({})",
+                    name.text(),
+                    keyword,
+                    parameters(value).join(", ")
+                );
+                if let Some(result) = value
+                    .children()
+                    .iter()
+                    .find(|c| c.kind() == SyntaxKind::TypeAnnotation)
+                {
+                    detail = format!("{} {}", detail, result);
+                }
+                self.bind(&name, DefinitionKind::Function, name.range().start, detail);
+                self.walk(value);
+            }
+            _ => {
+                if let Some(value) = &value {
+                    self.walk(value);
+                }
+                let detail = match (annotation, value.as_ref().and_then(literal_type)) {
+                    (Some(annotation), _) => format!("let {}{}", name.text(), annotation),
+                    (None, Some(type_)) => format!("let {}: {}", name.text(), type_),
+                    (None, None) => format!("let {}", name.text()),
+                };
+                self.bind(&name, DefinitionKind::Let, node.range().end, detail);
+            }
+        }
+    }
+
+    fn bind(
+        &mut self,
+        token: &SyntaxToken,
+        kind: DefinitionKind,
+        visible_from: usize,
+        detail: String,
+    ) {
+        let top_level = self.scopes.len() == 1;
+        let Some((names, end)) = self.scopes.last_mut() else {
+            return;
+        };
+        names.insert(token.text().to_string(), self.analysis.definitions.len());
+        self.analysis.definitions.push(Definition {
+            name: token.text().to_string(),
+            kind,
+            range: token.range().start + self.base..token.range().end + self.base,
+            scope: visible_from + self.base..*end,
+            detail,
+            top_level,
+        });
+    }
+}
+
+fn token_child(node: &SyntaxNode, kind: TokenType) -> Option<SyntaxToken> {
+    node.children_with_tokens()
+        .into_iter()
+        .find_map(|child| match child {
+            SyntaxElement::Token(token) if token.kind() == SyntaxKind::Token(kind) => Some(token),
+            _ => None,
+        })
+}
+
+// Each parameter with its annotation, if it has one: `n: int`.
+fn parameters(function: &SyntaxNode) -> Vec<String> {
+    let mut params: Vec<String> = Vec::new();
+    let lists = function
+        .children()
+        .into_iter()
+        .filter(|c| c.kind() == SyntaxKind::ParameterList);
+    for node in lists.flat_map(|list| list.children()) {
+        match (node.kind(), params.last_mut()) {
+            (SyntaxKind::TypeAnnotation, Some(param)) => param.push_str(&node.to_string()),
+            _ => params.push(node.to_string()),
+        }
+    }
+    params
+}
+
+// The type of a value that is plainly a literal; anything else needs real
+// inference.
+fn literal_type(value: &SyntaxNode) -> Option<&'static str> {
+    match value.kind() {
+        SyntaxKind::ArrayLiteral => return Some("array"),
+        SyntaxKind::Literal => {}
+        _ => return None,
+    }
+    let token = value.tokens().into_iter().next()?;
+    match token.kind() {
+        SyntaxKind::Token(TokenType::Int) => Some("int"),
+        SyntaxKind::Token(TokenType::Float) => Some("float"),
+        SyntaxKind::Token(TokenType::String | TokenType::InterpolatedString) => Some("string"),
+        SyntaxKind::Token(TokenType::True | TokenType::False) => Some("bool"),
+        _ => None,
+    }
+}
+
+// The let bindings and imports of a file as an outline. Bindings inside a
+// function's body are its children.
+pub fn symbols(root: &SyntaxNode) -> Vec<Symbol> {
+    let mut symbols = Vec::new();
+    for child in root.children() {
+        collect_symbols(&child, &mut symbols);
+    }
+    symbols
+}
+
+fn collect_symbols(node: &SyntaxNode, out: &mut Vec<Symbol>) {
+    let kind = match node.kind() {
+        SyntaxKind::LetStatement => {
+            let value = node.children().into_iter().next();
+            match value.map(|v| v.kind()) {
+                Some(SyntaxKind::FunctionLiteral | SyntaxKind::MacroLiteral) => {
+                    SymbolKind::Function
+                }
+                _ => SymbolKind::Variable,
+            }
+        }
+        SyntaxKind::ImportStatement => SymbolKind::Module,
+        _ => {
+            for child in node.children() {
+                collect_symbols(&child, out);
+            }
+            return;
+        }
+    };
+    let Some(name) = token_child(node, TokenType::Ident) else {
+        return;
+    };
+
+    let mut children = Vec::new();
+    for child in node.children() {
+        collect_symbols(&child, &mut children);
+    }
+    out.push(Symbol {
+        name: name.text().to_string(),
+        kind,
+        range: node.range(),
+        selection: name.range(),
+        children,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "let x = 1;
+let double = fn(n) { n * 2 };
+let f = fn(x) {
+    let y = x + 1;
+    for (i in [1, 2]) { puts(i + y); }
+    f(double(y))
+};
+puts(x);
+";
+
+    fn analysis() -> Analysis {
+        Analysis::new(&parser::parse(SOURCE))
+    }
+
+    fn at(needle: &str, nth: usize) -> usize {
+        SOURCE.match_indices(needle).nth(nth).unwrap().0
+    }
+
+    #[test]
+    fn test_resolution() {
+        let a = analysis();
+
+        // the parameter x shadows the top-level x inside f
+        let param = a.definition_at(at("x", 2)).unwrap();
+        assert_eq!(a.definitions[param].kind, DefinitionKind::Parameter);
+        assert_eq!(a.definition_at(at("x + 1", 0)), Some(param));
+
+        let top = a.definition_at(at("x", 0)).unwrap();
+        assert_eq!(a.references_to(top), vec![at("x);", 0)..at("x);", 0) + 1]);
+
+        // f sees itself, y is visible in the nested loop
+        let f = a.definition_at(at("f(double", 0)).unwrap();
+        assert_eq!(a.definitions[f].kind, DefinitionKind::Function);
+        assert_eq!(a.definitions[f].range.start, at("f =", 0));
+        let y = a.definition_at(at("y", 0)).unwrap();
+        assert_eq!(a.references_to(y).len(), 2);
+
+        // builtins resolve to nothing
+        let puts = a.references.iter().find(|r| r.name == "puts").unwrap();
+        assert_eq!(puts.definition, None);
+    }
+
+    #[test]
+    fn test_interpolated_strings() {
+        let source = "let myVar = 1;\nlet p = 2;\nputs(\"v=${myVar + 1}, ${\"in ${p}\"}${ fn(q) { q }(p) }\");";
+        let a = Analysis::new(&parser::parse(source));
+        let my_var = a.definition_at(source.find("myVar").unwrap()).unwrap();
+        let use_ = source.rfind("myVar").unwrap();
+        assert_eq!(a.references_to(my_var), vec![use_..use_ + 5]);
+        assert_eq!(a.definition_at(use_ + 2), Some(my_var));
+
+        // strings nested inside `${}` are resolved too
+        let p = a.definition_at(source.find("p =").unwrap()).unwrap();
+        let uses: Vec<usize> = a.references_to(p).iter().map(|r| r.start).collect();
+        assert_eq!(
+            uses,
+            vec![source.find("p}").unwrap(), source.find("p) }").unwrap()]
+        );
+
+        // and so are bindings made inside one
+        let q = a.definition_at(source.find("q)").unwrap()).unwrap();
+        assert_eq!(a.definitions[q].kind, DefinitionKind::Parameter);
+        let q_use = source.find("q }").unwrap();
+        assert_eq!(a.references_to(q), vec![q_use..q_use + 1]);
+    }
+
+    #[test]
+    fn test_let_is_visible_after_its_statement() {
+        let a = Analysis::new(&parser::parse("let x = 1; let x = x + 1;"));
+        let use_ = a.references.iter().find(|r| r.name == "x").unwrap();
+        assert_eq!(use_.definition, Some(0));
+    }
+
+    #[test]
+    fn test_hover() {
+        let a = analysis();
+        assert_eq!(a.hover(at("x", 0)).unwrap().1, "let x: int");
+        assert_eq!(a.hover(at("double(y)", 0)).unwrap().1, "let double = fn(n)");
+        assert_eq!(a.hover(at("i +", 0)).unwrap().1, "i (loop variable)");
+        let (range, text) = a.hover(at("puts(x)", 0)).unwrap();
+        assert_eq!(range, at("puts(x)", 0)..at("puts(x)", 0) + 4);
+        assert!(text.starts_with("puts(...values) (builtin)"), "{}", text);
+        assert_eq!(a.hover(at("= 1", 0)), None);
+
+        let a = Analysis::new(&parser::parse("map([1], fn(v) { v })"));
+        assert_eq!(a.hover(1).unwrap().1, "map(arr, f) (prelude)");
+
+        let source = "let n: [int] = [];\nlet f = fn(a: int, b) -> int { a };\nf(n, 1)";
+        let a = Analysis::new(&parser::parse(source));
+        let hover = |name: &str| a.hover(source.rfind(name).unwrap()).unwrap().1;
+        assert_eq!(hover("n,"), "let n: [int]");
+        assert_eq!(hover("f("), "let f = fn(a: int, b) -> int");
+        assert_eq!(hover("a }"), "a (parameter)");
+    }
+
+    #[test]
+    fn test_set_types() {
+        let source = "let n = len(\"ab\");
+let f = fn(a) { let inner = a; a + n };
+let m = macro(x) { x };
+puts(f(1), n);";
+        let mut a = Analysis::new(&parser::parse(source));
+        a.set_types(&[
+            ("n".to_string(), "int".to_string()),
+            ("f".to_string(), "fn(int) -> int".to_string()),
+            ("m".to_string(), "any".to_string()),
+        ]);
+        let hover = |name: &str| a.hover(source.rfind(name).unwrap()).unwrap().1;
+        assert_eq!(hover("n)"), "let n: int");
+        assert_eq!(hover("f("), "let f: fn(int) -> int");
+        assert_eq!(hover("m ="), "let m = macro(x)");
+        assert_eq!(hover("inner"), "let inner");
+
+        // names that don't line up leave the details alone
+        let mut a = Analysis::new(&parser::parse(source));
+        a.set_types(&[("x".to_string(), "int".to_string())]);
+        assert_eq!(a.hover(4).unwrap().1, "let n");
+    }
+
+    #[test]
+    fn test_completions() {
+        let a = analysis();
+        let labels = |offset| -> Vec<String> {
+            a.completions(offset).into_iter().map(|c| c.label).collect()
+        };
+
+        let in_loop = labels(at("puts(i", 0));
+        for name in ["double", "f", "i", "x", "y", "puts", "map", "let"] {
+            assert!(in_loop.contains(&name.to_string()), "{} missing", name);
+        }
+        assert_eq!(in_loop.iter().filter(|l| *l == "x").count(), 1);
+        assert!(!in_loop.contains(&"_slice".to_string()));
+
+        let at_start = labels(0);
+        assert!(!at_start.contains(&"x".to_string()));
+        assert!(!labels(at("puts(x)", 0)).contains(&"y".to_string()));
+    }
+
+    #[test]
+    fn test_without_prelude() {
+        let a = Analysis::with_prelude(&parser::parse("map([1], fn(v) { v })"), false);
+        assert_eq!(a.hover(1), None);
+        let labels: Vec<String> = a.completions(0).into_iter().map(|c| c.label).collect();
+        assert!(labels.contains(&"puts".to_string()));
+        assert!(!labels.contains(&"map".to_string()));
+    }
+
+    #[test]
+    fn test_symbols() {
+        let source = "import \"m.mk\" as m;\nlet x = 1;\nlet f = fn() {\n    let inner = 2;\n    inner\n};\n";
+        let symbols = symbols(&parser::parse(source));
+        let outline: Vec<(&str, SymbolKind, usize)> = symbols
+            .iter()
+            .map(|s| (s.name.as_str(), s.kind, s.children.len()))
+            .collect();
+        assert_eq!(
+            outline,
+            vec![
+                ("m", SymbolKind::Module, 0),
+                ("x", SymbolKind::Variable, 0),
+                ("f", SymbolKind::Function, 1)
+            ]
+        );
+        assert_eq!(symbols[2].children[0].name, "inner");
+        assert_eq!(&source[symbols[1].range.clone()], "let x = 1;");
+    }
+}
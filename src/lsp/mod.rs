@@ -0,0 +1,4 @@
+pub mod analysis;
+pub mod position;
+#[cfg(feature = "serde")]
+pub mod server;
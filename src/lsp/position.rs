@@ -0,0 +1,77 @@
+// Converts between byte offsets and LSP positions: a zero-based line and a
+// column counted in UTF-16 code units, which is what editors send unless
+// told otherwise.
+pub struct LineIndex {
+    line_starts: Vec<usize>,
+}
+
+impl LineIndex {
+    pub fn new(text: &str) -> Self {
+        let mut line_starts = vec![0];
+        line_starts.extend(text.match_indices('\n').map(|(i, _)| i + 1));
+        LineIndex { line_starts }
+    }
+
+    // An offset inside a character counts as the start of it: the lexer
+    // makes one-byte Illegal tokens of characters it does not know, and
+    // error spans can end in the middle of one.
+    pub fn position(&self, text: &str, offset: usize) -> (u32, u32) {
+        let offset = floor_char_boundary(text, offset);
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let start = self.line_starts[line];
+        let column: usize = text[start..offset].chars().map(char::len_utf16).sum();
+        (line as u32, column as u32)
+    }
+
+    // Positions past the end of a line clamp to its end, and lines past
+    // the end of the text to the end of the text.
+    pub fn offset(&self, text: &str, line: u32, character: u32) -> usize {
+        let Some(&start) = self.line_starts.get(line as usize) else {
+            return text.len();
+        };
+        let start = floor_char_boundary(text, start);
+        let end = self
+            .line_starts
+            .get(line as usize + 1)
+            .map_or(text.len(), |next| floor_char_boundary(text, next - 1));
+        let mut units = 0;
+        for (i, c) in text[start..end].char_indices() {
+            if units >= character as usize {
+                return start + i;
+            }
+            units += c.len_utf16();
+        }
+        end
+    }
+}
+
+fn floor_char_boundary(text: &str, offset: usize) -> usize {
+    let mut offset = offset.min(text.len());
+    while !text.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    offset
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_positions() {
+        let text = "let a = 1;\nlet é😀 = 2;\n";
+        let index = LineIndex::new(text);
+        assert_eq!(index.position(text, 0), (0, 0));
+        assert_eq!(index.position(text, 4), (0, 4));
+        let after_emoji = text.find(" = 2").unwrap();
+        assert_eq!(index.position(text, after_emoji), (1, 7));
+        assert_eq!(index.offset(text, 1, 7), after_emoji);
+        assert_eq!(index.offset(text, 0, 99), 10);
+        assert_eq!(index.offset(text, 9, 0), text.len());
+        assert_eq!(index.position(text, text.len()), (2, 0));
+
+        // the middle of é is the start of it
+        let e = text.find('é').unwrap();
+        assert_eq!(index.position(text, e + 1), index.position(text, e));
+    }
+}
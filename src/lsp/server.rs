@@ -0,0 +1,715 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::ops::Range;
+
+use serde_json::{json, Value};
+
+use crate::cst::cst::SyntaxNode;
+use crate::cst::parser;
+use crate::format::format::format_source;
+use crate::lexer::lexer::{Lexer, LexerTrait};
+use crate::lexer::token::Span;
+use crate::lsp::analysis::{self, Analysis, CompletionKind, Symbol, SymbolKind};
+use crate::lsp::position::LineIndex;
+use crate::parser::parser::Parser;
+use crate::resolver::diagnostics::Severity;
+use crate::resolver::resolver::resolve_with_prelude;
+use crate::typeck::typeck;
+
+// A language server speaking JSON-RPC over a pair of streams, normally
+// stdin and stdout (`monkey lsp`). Documents are synced in full on every
+// change, which is plenty for script-sized files.
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+// Serves requests until the client sends `exit`. Returns the exit code the
+// protocol asks for: 0 if `shutdown` came first, 1 otherwise. Without the
+// prelude (--no-prelude) its functions are not defined in the documents.
+pub fn run<R: BufRead, W: Write>(mut input: R, output: W, prelude: bool) -> io::Result<i32> {
+    let mut server = Server {
+        out: output,
+        documents: HashMap::new(),
+        shut_down: false,
+        prelude,
+    };
+    while let Some(message) = read_message(&mut input)? {
+        match serde_json::from_slice::<Value>(&message) {
+            Ok(message) => {
+                if message["method"] == "exit" {
+                    break;
+                }
+                server.handle(message)?;
+            }
+            Err(e) => server.reply_error(Value::Null, PARSE_ERROR, e.to_string())?,
+        }
+    }
+    Ok(if server.shut_down { 0 } else { 1 })
+}
+
+// One message body, or None at the end of the input.
+pub fn read_message<R: BufRead>(input: &mut R) -> io::Result<Option<Vec<u8>>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let length = length.ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidData, "message without Content-Length")
+    })?;
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    Ok(Some(body))
+}
+
+pub fn write_message<W: Write>(out: &mut W, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(out, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    out.flush()
+}
+
+struct Document {
+    text: String,
+    lines: LineIndex,
+    tree: SyntaxNode,
+    analysis: Analysis,
+}
+
+impl Document {
+    fn new(text: String, prelude: bool) -> Self {
+        let tree = parser::parse(&text);
+        let mut analysis = Analysis::with_prelude(&tree, prelude);
+        // types are only inferred once the code parses
+        let mut p = Parser::new(Lexer::new(&text));
+        let program = p.parse_program();
+        if p.errors().is_empty() {
+            analysis.set_types(&typeck::check_with_prelude(&program, prelude).globals);
+        }
+        Document {
+            lines: LineIndex::new(&text),
+            analysis,
+            tree,
+            text,
+        }
+    }
+
+    fn range(&self, range: &Range<usize>) -> Value {
+        let (start_line, start_char) = self.lines.position(&self.text, range.start);
+        let (end_line, end_char) = self.lines.position(&self.text, range.end);
+        json!({
+            "start": { "line": start_line, "character": start_char },
+            "end": { "line": end_line, "character": end_char },
+        })
+    }
+
+    fn offset(&self, position: &Value) -> Option<usize> {
+        let line = position["line"].as_u64()?;
+        let character = position["character"].as_u64()?;
+        Some(self.lines.offset(&self.text, line as u32, character as u32))
+    }
+}
+
+struct Server<W> {
+    out: W,
+    documents: HashMap<String, Document>,
+    shut_down: bool,
+    prelude: bool,
+}
+
+// A failed request: a JSON-RPC error code and message.
+type Failure = (i64, String);
+
+impl<W: Write> Server<W> {
+    fn handle(&mut self, message: Value) -> io::Result<()> {
+        let Some(method) = message["method"].as_str() else {
+            // a response to a request we never make
+            if message.get("id").is_some() {
+                return Ok(());
+            }
+            return self.reply_error(Value::Null, INVALID_REQUEST, "missing method".to_string());
+        };
+        let params = &message["params"];
+
+        let Some(id) = message.get("id").cloned() else {
+            return self.notification(method, params);
+        };
+        let result = match method {
+            "initialize" => Ok(capabilities()),
+            "shutdown" => {
+                self.shut_down = true;
+                Ok(Value::Null)
+            }
+            "textDocument/definition" => self.definition(params),
+            "textDocument/references" => self.references(params),
+            "textDocument/hover" => self.hover(params),
+            "textDocument/completion" => self.completion(params),
+            "textDocument/documentSymbol" => self.document_symbols(params),
+            "textDocument/formatting" => self.formatting(params),
+            _ => Err((METHOD_NOT_FOUND, format!("unknown method {}", method))),
+        };
+        match result {
+            Ok(result) => write_message(
+                &mut self.out,
+                &json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            ),
+            Err((code, msg)) => self.reply_error(id, code, msg),
+        }
+    }
+
+    fn reply_error(&mut self, id: Value, code: i64, message: String) -> io::Result<()> {
+        write_message(
+            &mut self.out,
+            &json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": code, "message": message },
+            }),
+        )
+    }
+
+    fn notification(&mut self, method: &str, params: &Value) -> io::Result<()> {
+        let uri = params["textDocument"]["uri"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+        match method {
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                self.documents
+                    .insert(uri.clone(), Document::new(text.to_string(), self.prelude));
+                self.publish_diagnostics(&uri)
+            }
+            "textDocument/didChange" => {
+                // full sync: the last change holds the whole text
+                let changes = params["contentChanges"].as_array();
+                let Some(text) = changes
+                    .and_then(|c| c.last())
+                    .and_then(|c| c["text"].as_str())
+                else {
+                    return Ok(());
+                };
+                self.documents
+                    .insert(uri.clone(), Document::new(text.to_string(), self.prelude));
+                self.publish_diagnostics(&uri)
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                self.send_diagnostics(&uri, Vec::new())
+            }
+            // initialized, $/cancelRequest, didSave and the like need nothing
+            _ => Ok(()),
+        }
+    }
+
+    fn publish_diagnostics(&mut self, uri: &str) -> io::Result<()> {
+        let Some(doc) = self.documents.get(uri) else {
+            return Ok(());
+        };
+        let mut p = Parser::new(Lexer::new(&doc.text));
+        let program = p.parse_program();
+        let errors = p.errors();
+        let diagnostic = |span: Span, severity: u8, message: String| {
+            json!({
+                "range": doc.range(&(span.start..span.end)),
+                "severity": severity,
+                "source": "monkey",
+                "message": message,
+            })
+        };
+        // names are only checked once the code parses
+        let diagnostics = if errors.is_empty() {
+            resolve_with_prelude(&program, self.prelude)
+                .diagnostics
+                .iter()
+                .map(|d| {
+                    let severity = match d.severity {
+                        Severity::Error => 1,
+                        Severity::Warning => 2,
+                    };
+                    diagnostic(d.span, severity, d.to_string())
+                })
+                .collect()
+        } else {
+            errors
+                .iter()
+                .map(|e| diagnostic(e.span, 1, e.to_string()))
+                .collect()
+        };
+        self.send_diagnostics(uri, diagnostics)
+    }
+
+    fn send_diagnostics(&mut self, uri: &str, diagnostics: Vec<Value>) -> io::Result<()> {
+        write_message(
+            &mut self.out,
+            &json!({
+                "jsonrpc": "2.0",
+                "method": "textDocument/publishDiagnostics",
+                "params": { "uri": uri, "diagnostics": diagnostics },
+            }),
+        )
+    }
+
+    // The open document a request is about, and the offset of its position.
+    fn document<'a>(
+        &'a self,
+        params: &'a Value,
+    ) -> Result<(&'a str, &'a Document, usize), Failure> {
+        let uri = params["textDocument"]["uri"]
+            .as_str()
+            .ok_or((INVALID_PARAMS, "missing textDocument.uri".to_string()))?;
+        let doc = self
+            .documents
+            .get(uri)
+            .ok_or((INVALID_PARAMS, format!("{} is not open", uri)))?;
+        let offset = doc.offset(&params["position"]).unwrap_or(0);
+        Ok((uri, doc, offset))
+    }
+
+    fn definition(&self, params: &Value) -> Result<Value, Failure> {
+        let (uri, doc, offset) = self.document(params)?;
+        Ok(match doc.analysis.definition_at(offset) {
+            Some(i) => {
+                let range = &doc.analysis.definitions[i].range;
+                json!({ "uri": uri, "range": doc.range(range) })
+            }
+            None => Value::Null,
+        })
+    }
+
+    fn references(&self, params: &Value) -> Result<Value, Failure> {
+        let (uri, doc, offset) = self.document(params)?;
+        let Some(i) = doc.analysis.definition_at(offset) else {
+            return Ok(json!([]));
+        };
+        let mut ranges = Vec::new();
+        if params["context"]["includeDeclaration"] == true {
+            ranges.push(doc.analysis.definitions[i].range.clone());
+        }
+        ranges.extend(doc.analysis.references_to(i));
+        let locations: Vec<Value> = ranges
+            .iter()
+            .map(|r| json!({ "uri": uri, "range": doc.range(r) }))
+            .collect();
+        Ok(json!(locations))
+    }
+
+    fn hover(&self, params: &Value) -> Result<Value, Failure> {
+        let (_, doc, offset) = self.document(params)?;
+        Ok(match doc.analysis.hover(offset) {
+            Some((range, text)) => json!({
+                "contents": { "kind": "markdown", "value": hover_markdown(&text) },
+                "range": doc.range(&range),
+            }),
+            None => Value::Null,
+        })
+    }
+
+    fn completion(&self, params: &Value) -> Result<Value, Failure> {
+        let (_, doc, offset) = self.document(params)?;
+        let items: Vec<Value> = doc
+            .analysis
+            .completions(offset)
+            .into_iter()
+            .map(|c| {
+                // LSP CompletionItemKind numbers
+                let kind = match c.kind {
+                    CompletionKind::Function => 3,
+                    CompletionKind::Variable => 6,
+                    CompletionKind::Module => 9,
+                    CompletionKind::Keyword => 14,
+                };
+                json!({ "label": c.label, "kind": kind, "detail": c.detail })
+            })
+            .collect();
+        Ok(json!(items))
+    }
+
+    fn document_symbols(&self, params: &Value) -> Result<Value, Failure> {
+        let (_, doc, _) = self.document(params)?;
+        Ok(symbols_json(doc, &analysis::symbols(&doc.tree)))
+    }
+
+    fn formatting(&self, params: &Value) -> Result<Value, Failure> {
+        let (_, doc, _) = self.document(params)?;
+        // code with syntax errors is left alone; the diagnostics say why
+        let Ok(formatted) = format_source(&doc.text) else {
+            return Ok(json!([]));
+        };
+        if formatted == doc.text {
+            return Ok(json!([]));
+        }
+        Ok(json!([{
+            "range": doc.range(&(0..doc.text.len())),
+            "newText": formatted,
+        }]))
+    }
+}
+
+fn capabilities() -> Value {
+    json!({
+        "capabilities": {
+            "textDocumentSync": 1,
+            "definitionProvider": true,
+            "referencesProvider": true,
+            "hoverProvider": true,
+            "completionProvider": {},
+            "documentSymbolProvider": true,
+            "documentFormattingProvider": true,
+        },
+        "serverInfo": { "name": "monkey", "version": env!("CARGO_PKG_VERSION") },
+    })
+}
+
+// The first line is code; builtins add a line of documentation after it.
+fn hover_markdown(text: &str) -> String {
+    let (code, doc) = text.split_once("\n\n").unwrap_or((text, ""));
+    let mut markdown = format!("```monkey\n{}\n```", code);
+    if !doc.is_empty() {
+        markdown.push_str("\n\n");
+        markdown.push_str(doc);
+    }
+    markdown
+}
+
+fn symbols_json(doc: &Document, symbols: &[Symbol]) -> Value {
+    let symbols: Vec<Value> = symbols
+        .iter()
+        .map(|s| {
+            // LSP SymbolKind numbers
+            let kind = match s.kind {
+                SymbolKind::Module => 2,
+                SymbolKind::Function => 12,
+                SymbolKind::Variable => 13,
+            };
+            json!({
+                "name": s.name,
+                "kind": kind,
+                "range": doc.range(&s.range),
+                "selectionRange": doc.range(&s.selection),
+                "children": symbols_json(doc, &s.children),
+            })
+        })
+        .collect();
+    json!(symbols)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const URI: &str = "file:///tmp/test.mk";
+
+    fn frame(message: Value) -> Vec<u8> {
+        let mut out = Vec::new();
+        write_message(&mut out, &message).unwrap();
+        out
+    }
+
+    fn request(id: u64, method: &str, params: Value) -> Vec<u8> {
+        frame(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }))
+    }
+
+    fn notify(method: &str, params: Value) -> Vec<u8> {
+        frame(json!({ "jsonrpc": "2.0", "method": method, "params": params }))
+    }
+
+    fn at(line: u32, character: u32) -> Value {
+        json!({ "textDocument": { "uri": URI }, "position": { "line": line, "character": character } })
+    }
+
+    // Runs the server over the scripted messages and returns its exit code
+    // and everything it sent back, in order.
+    fn exchange(messages: Vec<Vec<u8>>) -> (i32, Vec<Value>) {
+        let input = messages.concat();
+        let mut output = Vec::new();
+        let code = run(&input[..], &mut output, true).unwrap();
+
+        let mut replies = Vec::new();
+        let mut reader = &output[..];
+        while let Some(body) = read_message(&mut reader).unwrap() {
+            replies.push(serde_json::from_slice(&body).unwrap());
+        }
+        (code, replies)
+    }
+
+    fn response(replies: &[Value], id: u64) -> &Value {
+        replies
+            .iter()
+            .find(|r| r["id"] == id)
+            .unwrap_or_else(|| panic!("no response to {}", id))
+    }
+
+    fn open(text: &str) -> Vec<u8> {
+        notify(
+            "textDocument/didOpen",
+            json!({ "textDocument": { "uri": URI, "languageId": "monkey", "version": 1, "text": text } }),
+        )
+    }
+
+    #[test]
+    fn test_session() {
+        let source = "let x = 1;\nlet f = fn(n) { n + x };\nputs(f(x))\n";
+        let (code, replies) = exchange(vec![
+            request(1, "initialize", json!({ "capabilities": {} })),
+            notify("initialized", json!({})),
+            open(source),
+            request(2, "textDocument/definition", at(2, 5)),
+            request(
+                3,
+                "textDocument/references",
+                json!({
+                    "textDocument": { "uri": URI },
+                    "position": { "line": 0, "character": 4 },
+                    "context": { "includeDeclaration": true },
+                }),
+            ),
+            request(4, "textDocument/hover", at(1, 5)),
+            request(5, "textDocument/hover", at(2, 1)),
+            request(6, "textDocument/completion", at(1, 17)),
+            request(7, "textDocument/documentSymbol", at(0, 0)),
+            request(8, "textDocument/formatting", at(0, 0)),
+            request(9, "shutdown", Value::Null),
+            notify("exit", Value::Null),
+        ]);
+        assert_eq!(code, 0);
+
+        let init = response(&replies, 1);
+        assert_eq!(init["result"]["capabilities"]["hoverProvider"], true);
+
+        let diagnostics = &replies[1];
+        assert_eq!(diagnostics["method"], "textDocument/publishDiagnostics");
+        assert_eq!(diagnostics["params"]["diagnostics"], json!([]));
+
+        // `f` in puts(f(x)) is defined on line 1
+        let definition = &response(&replies, 2)["result"];
+        assert_eq!(definition["uri"], URI);
+        assert_eq!(
+            definition["range"],
+            json!({ "start": { "line": 1, "character": 4 }, "end": { "line": 1, "character": 5 } })
+        );
+
+        let references = response(&replies, 3)["result"].as_array().unwrap();
+        let lines: Vec<(u64, u64)> = references
+            .iter()
+            .map(|r| {
+                let start = &r["range"]["start"];
+                (
+                    start["line"].as_u64().unwrap(),
+                    start["character"].as_u64().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(lines, vec![(0, 4), (1, 20), (2, 7)]);
+
+        let hover = &response(&replies, 4)["result"]["contents"]["value"];
+        assert_eq!(hover, "```monkey\nlet f: fn(int) -> int\n```");
+        let hover = response(&replies, 5)["result"]["contents"]["value"]
+            .as_str()
+            .unwrap();
+        assert!(
+            hover.starts_with("```monkey\nputs(...values) (builtin)\n```\n\n"),
+            "{}",
+            hover
+        );
+
+        let completions = response(&replies, 6)["result"].as_array().unwrap();
+        let labels: Vec<&str> = completions
+            .iter()
+            .map(|c| c["label"].as_str().unwrap())
+            .collect();
+        for expected in ["n", "x", "f", "len", "map", "while"] {
+            assert!(
+                labels.contains(&expected),
+                "{} not in {:?}",
+                expected,
+                labels
+            );
+        }
+
+        let symbols = response(&replies, 7)["result"].as_array().unwrap();
+        let names: Vec<(&str, u64)> = symbols
+            .iter()
+            .map(|s| (s["name"].as_str().unwrap(), s["kind"].as_u64().unwrap()))
+            .collect();
+        assert_eq!(names, vec![("x", 13), ("f", 12)]);
+
+        let edits = response(&replies, 8)["result"].as_array().unwrap();
+        assert_eq!(
+            edits[0]["newText"],
+            "let x = 1;\nlet f = fn(n) { n + x };\nputs(f(x));\n"
+        );
+
+        assert_eq!(response(&replies, 9)["result"], Value::Null);
+    }
+
+    #[test]
+    fn test_diagnostics_follow_changes() {
+        let (_, replies) = exchange(vec![
+            open("let = 1;"),
+            notify(
+                "textDocument/didChange",
+                json!({
+                    "textDocument": { "uri": URI, "version": 2 },
+                    "contentChanges": [{ "text": "let y = 1; puts(z);" }],
+                }),
+            ),
+            notify(
+                "textDocument/didClose",
+                json!({ "textDocument": { "uri": URI } }),
+            ),
+        ]);
+        assert_eq!(replies.len(), 3);
+
+        let first = replies[0]["params"]["diagnostics"].as_array().unwrap();
+        assert!(!first.is_empty());
+        assert_eq!(
+            first[0]["range"]["start"],
+            json!({ "line": 0, "character": 4 })
+        );
+        assert_eq!(first[0]["severity"], 1);
+        assert!(first[0]["message"]
+            .as_str()
+            .unwrap()
+            .contains("Unexpected Token"));
+
+        // once it parses, names are checked too
+        let second = replies[1]["params"]["diagnostics"].as_array().unwrap();
+        let messages: Vec<_> = second
+            .iter()
+            .map(|d| {
+                (
+                    d["severity"].as_u64().unwrap(),
+                    d["message"].as_str().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                (2, "Unused Binding: y is never used"),
+                (1, "Undefined Name: z is not defined"),
+            ]
+        );
+        assert_eq!(replies[2]["params"]["diagnostics"], json!([]));
+    }
+
+    #[test]
+    fn test_names_inside_interpolations() {
+        let (_, replies) = exchange(vec![
+            open("let myVar = 1;\nputs(\"v=${myVar}\");"),
+            request(1, "textDocument/definition", at(1, 11)),
+            request(
+                2,
+                "textDocument/references",
+                json!({
+                    "textDocument": { "uri": URI },
+                    "position": { "line": 0, "character": 5 },
+                    "context": { "includeDeclaration": false },
+                }),
+            ),
+            request(3, "textDocument/hover", at(1, 12)),
+        ]);
+
+        // myVar is used, so nothing is reported
+        assert_eq!(replies[0]["params"]["diagnostics"], json!([]));
+        assert_eq!(
+            response(&replies, 1)["result"]["range"],
+            json!({ "start": { "line": 0, "character": 4 }, "end": { "line": 0, "character": 9 } })
+        );
+        assert_eq!(
+            response(&replies, 2)["result"],
+            json!([{
+                "uri": URI,
+                "range": { "start": { "line": 1, "character": 10 }, "end": { "line": 1, "character": 15 } },
+            }])
+        );
+        assert!(response(&replies, 3)["result"]["contents"]["value"]
+            .as_str()
+            .unwrap()
+            .contains("let myVar"));
+    }
+
+    #[test]
+    fn test_non_ascii_source() {
+        let (code, replies) = exchange(vec![
+            open("let é = 1;"),
+            request(1, "textDocument/hover", at(0, 4)),
+            request(2, "textDocument/formatting", at(0, 0)),
+            notify(
+                "textDocument/didChange",
+                json!({
+                    "textDocument": { "uri": URI, "version": 2 },
+                    "contentChanges": [{ "text": "let s = \"naïve 😀\";\nputs(s, ü);" }],
+                }),
+            ),
+            request(3, "shutdown", Value::Null),
+            notify("exit", Value::Null),
+        ]);
+        assert_eq!(code, 0);
+
+        // the lexer makes a one-byte Illegal token of the first byte of é
+        let first = replies[0]["params"]["diagnostics"].as_array().unwrap();
+        assert_eq!(
+            first[0]["range"]["start"],
+            json!({ "line": 0, "character": 4 })
+        );
+        // the second byte of é maps back to the start of it
+        let illegal: Vec<&Value> = first
+            .iter()
+            .filter(|d| d["message"].as_str().unwrap().starts_with("Illegal Token"))
+            .map(|d| &d["range"])
+            .collect();
+        assert_eq!(
+            illegal,
+            vec![
+                &json!({ "start": { "line": 0, "character": 4 }, "end": { "line": 0, "character": 4 } }),
+                &json!({ "start": { "line": 0, "character": 4 }, "end": { "line": 0, "character": 5 } }),
+            ]
+        );
+        assert_eq!(response(&replies, 1)["result"], Value::Null);
+        // broken code is left as it is
+        assert_eq!(response(&replies, 2)["result"], json!([]));
+
+        let second = replies
+            .iter()
+            .rev()
+            .find(|r| r["method"] == "textDocument/publishDiagnostics")
+            .unwrap();
+        let ranges: Vec<&Value> = second["params"]["diagnostics"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|d| &d["range"]["start"])
+            .collect();
+        // columns count UTF-16 units, so ü in `puts(s, ü)` is at 8
+        assert_eq!(ranges[0], &json!({ "line": 1, "character": 8 }));
+    }
+
+    #[test]
+    fn test_errors_and_exit_code() {
+        let (code, replies) = exchange(vec![
+            request(1, "textDocument/rename", at(0, 0)),
+            request(2, "textDocument/hover", at(0, 0)),
+            frame(json!("not an object")),
+            notify("exit", Value::Null),
+        ]);
+        // exit without shutdown
+        assert_eq!(code, 1);
+        assert_eq!(response(&replies, 1)["error"]["code"], METHOD_NOT_FOUND);
+        assert_eq!(response(&replies, 2)["error"]["code"], INVALID_PARAMS);
+        assert_eq!(replies[2]["error"]["code"], INVALID_REQUEST);
+    }
+}
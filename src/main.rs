@@ -1,11 +1,13 @@
 use std::{env, io, process::exit};
 
-pub mod ast;
-pub mod lexer;
-pub mod parser;
-pub mod repl;
+use monkeysinterust::{cli, repl};
 
 fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if !args.is_empty() {
+        exit(cli::cli::run(&args, &mut io::stdout(), &mut io::stderr()));
+    }
+
     let username = match env::var("USER") {
         Ok(user) => user,
         Err(_) => {
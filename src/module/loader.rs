@@ -0,0 +1,245 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::ast::ast::{ExportStatement, ImportStatement, Program};
+use crate::lexer::lexer::{Lexer, LexerTrait};
+use crate::parser::errors::ParseErrors;
+use crate::parser::parser::Parser;
+
+// A parsed source file together with what it exposes to importers.
+#[derive(Debug)]
+pub struct Module {
+    // canonical path, which is also the cache key
+    pub path: PathBuf,
+    pub program: Program,
+    // names declared with `export let`, in source order
+    pub exports: Vec<String>,
+    // modules this one imports, keyed by their alias
+    pub imports: Vec<(String, Rc<Module>)>,
+}
+
+#[derive(Debug)]
+pub enum ModuleError {
+    Io(PathBuf, io::Error),
+    Parse(PathBuf, ParseErrors),
+    // the import chain that leads back to its first entry
+    Cycle(Vec<PathBuf>),
+}
+
+impl Display for ModuleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ModuleError::Io(path, err) => write!(f, "cannot read {}: {}", path.display(), err),
+            ModuleError::Parse(path, errors) => {
+                write!(f, "cannot parse {}:", path.display())?;
+                for err in errors {
+                    write!(f, "\n\t{} at {}", err, err.span)?;
+                }
+                Ok(())
+            }
+            ModuleError::Cycle(chain) => {
+                let chain: Vec<String> = chain.iter().map(|p| p.display().to_string()).collect();
+                write!(f, "circular import: {}", chain.join(" -> "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for ModuleError {}
+
+// Loads a file and everything it imports. Each file is read and parsed once
+// per loader, however many modules import it; imports are resolved relative
+// to the directory of the importing file.
+#[derive(Default)]
+pub struct ModuleLoader {
+    cache: HashMap<PathBuf, Rc<Module>>,
+    // files whose imports are being resolved, outermost first
+    loading: Vec<PathBuf>,
+}
+
+impl ModuleLoader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn load(&mut self, path: impl AsRef<Path>) -> Result<Rc<Module>, ModuleError> {
+        let path = path.as_ref();
+        let path =
+            fs::canonicalize(path).map_err(|err| ModuleError::Io(path.to_path_buf(), err))?;
+
+        if let Some(module) = self.cache.get(&path) {
+            return Ok(Rc::clone(module));
+        }
+        if let Some(start) = self.loading.iter().position(|p| *p == path) {
+            let mut chain = self.loading[start..].to_vec();
+            chain.push(path);
+            return Err(ModuleError::Cycle(chain));
+        }
+
+        let source = fs::read_to_string(&path).map_err(|err| ModuleError::Io(path.clone(), err))?;
+        let mut p = Parser::new(Lexer::new(&source));
+        let program = p.parse_program();
+        let errors = p.errors();
+        if !errors.is_empty() {
+            return Err(ModuleError::Parse(path, errors));
+        }
+
+        self.loading.push(path.clone());
+        let imports = self.load_imports(&path, &program);
+        self.loading.pop();
+
+        let module = Rc::new(Module {
+            exports: exports_of(&program),
+            imports: imports?,
+            program,
+            path: path.clone(),
+        });
+        self.cache.insert(path, Rc::clone(&module));
+        Ok(module)
+    }
+
+    fn load_imports(
+        &mut self,
+        path: &Path,
+        program: &Program,
+    ) -> Result<Vec<(String, Rc<Module>)>, ModuleError> {
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let mut imports = Vec::new();
+        for stmt in &program.statements {
+            if let Some(import) = stmt.as_any().downcast_ref::<ImportStatement>() {
+                let module = self.load(dir.join(&import.path))?;
+                imports.push((import.alias.value.clone(), module));
+            }
+        }
+        Ok(imports)
+    }
+}
+
+fn exports_of(program: &Program) -> Vec<String> {
+    program
+        .statements
+        .iter()
+        .filter_map(|stmt| stmt.as_any().downcast_ref::<ExportStatement>())
+        .map(|export| export.statement.name.value.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A fresh directory under the system temp dir holding the given files.
+    fn write_files(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("monkey-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        for (file, source) in files {
+            let path = dir.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, source).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn test_load_caches_shared_imports() {
+        let dir = write_files(
+            "diamond",
+            &[
+                (
+                    "main.mk",
+                    r#"import "lib/a.mk" as a; import "lib/b.mk" as b; a.x + b.y;"#,
+                ),
+                (
+                    "lib/a.mk",
+                    r#"import "util.mk" as u; export let x = u.one;"#,
+                ),
+                (
+                    "lib/b.mk",
+                    r#"import "./util.mk" as u; export let y = u.one;"#,
+                ),
+                ("lib/util.mk", "export let one = 1; let hidden = 2;"),
+            ],
+        );
+
+        let mut loader = ModuleLoader::new();
+        let main = loader.load(dir.join("main.mk")).unwrap();
+        assert_eq!(main.imports.len(), 2);
+        assert!(main.exports.is_empty());
+
+        let (alias_a, a) = &main.imports[0];
+        let (alias_b, b) = &main.imports[1];
+        assert_eq!(alias_a, "a");
+        assert_eq!(alias_b, "b");
+        assert_eq!(a.exports, vec!["x"]);
+        assert_eq!(b.exports, vec!["y"]);
+        assert!(Rc::ptr_eq(&a.imports[0].1, &b.imports[0].1));
+        assert_eq!(a.imports[0].1.exports, vec!["one"]);
+
+        let again = loader.load(dir.join("lib/../main.mk")).unwrap();
+        assert!(Rc::ptr_eq(&main, &again));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_load_detects_cycles() {
+        let dir = write_files(
+            "cycle",
+            &[
+                ("main.mk", r#"import "a.mk" as a;"#),
+                ("a.mk", r#"import "b.mk" as b; export let x = 1;"#),
+                ("b.mk", r#"import "a.mk" as a; export let y = 2;"#),
+            ],
+        );
+
+        let err = ModuleLoader::new().load(dir.join("main.mk")).unwrap_err();
+        let dir = fs::canonicalize(&dir).unwrap();
+        match &err {
+            ModuleError::Cycle(chain) => assert_eq!(
+                chain,
+                &vec![dir.join("a.mk"), dir.join("b.mk"), dir.join("a.mk")]
+            ),
+            _ => panic!("expected a cycle, got {}", err),
+        }
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "circular import: {a} -> {b} -> {a}",
+                a = dir.join("a.mk").display(),
+                b = dir.join("b.mk").display()
+            )
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_load_reports_missing_and_broken_files() {
+        let dir = write_files(
+            "errors",
+            &[
+                ("main.mk", r#"import "missing.mk" as m;"#),
+                ("broken.mk", "let = 1;"),
+            ],
+        );
+
+        let mut loader = ModuleLoader::new();
+        match loader.load(dir.join("main.mk")).unwrap_err() {
+            ModuleError::Io(path, _) => assert!(path.ends_with("missing.mk")),
+            err => panic!("expected an io error, got {}", err),
+        }
+        match loader.load(dir.join("broken.mk")).unwrap_err() {
+            ModuleError::Parse(path, errors) => {
+                assert!(path.ends_with("broken.mk"));
+                assert!(!errors.is_empty());
+            }
+            err => panic!("expected a parse error, got {}", err),
+        }
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,327 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+use num_bigint::BigInt;
+
+/// A Monkey integer. Values that fit in an i64 stay `Small`; arithmetic
+/// that overflows promotes to `Big`, and a `Big` result that fits again is
+/// demoted, so equal values always have the same representation.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Integer {
+    Small(i64),
+    Big(BigInt),
+}
+
+impl Integer {
+    /// Parses an optionally signed run of decimal digits of any length.
+    pub fn parse(literal: &str) -> Option<Self> {
+        if let Ok(value) = literal.parse::<i64>() {
+            return Some(Integer::Small(value));
+        }
+        literal.parse::<BigInt>().ok().map(Integer::from)
+    }
+
+    pub fn add(&self, other: &Integer) -> Integer {
+        match (self, other) {
+            (Integer::Small(a), Integer::Small(b)) => match a.checked_add(*b) {
+                Some(sum) => Integer::Small(sum),
+                None => Integer::from(BigInt::from(*a) + BigInt::from(*b)),
+            },
+            _ => Integer::from(self.to_big() + other.to_big()),
+        }
+    }
+
+    pub fn sub(&self, other: &Integer) -> Integer {
+        match (self, other) {
+            (Integer::Small(a), Integer::Small(b)) => match a.checked_sub(*b) {
+                Some(diff) => Integer::Small(diff),
+                None => Integer::from(BigInt::from(*a) - BigInt::from(*b)),
+            },
+            _ => Integer::from(self.to_big() - other.to_big()),
+        }
+    }
+
+    pub fn mul(&self, other: &Integer) -> Integer {
+        match (self, other) {
+            (Integer::Small(a), Integer::Small(b)) => match a.checked_mul(*b) {
+                Some(product) => Integer::Small(product),
+                None => Integer::from(BigInt::from(*a) * BigInt::from(*b)),
+            },
+            _ => Integer::from(self.to_big() * other.to_big()),
+        }
+    }
+
+    /// Truncating division. `None` when dividing by zero.
+    pub fn div(&self, other: &Integer) -> Option<Integer> {
+        if other.is_zero() {
+            return None;
+        }
+        Some(match (self, other) {
+            // i64::MIN / -1 is the only overflowing case
+            (Integer::Small(a), Integer::Small(b)) => match a.checked_div(*b) {
+                Some(quotient) => Integer::Small(quotient),
+                None => Integer::from(BigInt::from(*a) / BigInt::from(*b)),
+            },
+            _ => Integer::from(self.to_big() / other.to_big()),
+        })
+    }
+
+    /// Remainder with the sign of the dividend. `None` when dividing by zero.
+    pub fn rem(&self, other: &Integer) -> Option<Integer> {
+        if other.is_zero() {
+            return None;
+        }
+        Some(match (self, other) {
+            (Integer::Small(a), Integer::Small(b)) => {
+                Integer::Small(a.checked_rem(*b).unwrap_or(0))
+            }
+            _ => Integer::from(self.to_big() % other.to_big()),
+        })
+    }
+
+    pub fn neg(&self) -> Integer {
+        match self {
+            Integer::Small(a) => match a.checked_neg() {
+                Some(negated) => Integer::Small(negated),
+                None => Integer::from(-BigInt::from(*a)),
+            },
+            Integer::Big(a) => Integer::from(-a),
+        }
+    }
+
+    pub fn bitand(&self, other: &Integer) -> Integer {
+        match (self, other) {
+            (Integer::Small(a), Integer::Small(b)) => Integer::Small(a & b),
+            _ => Integer::from(self.to_big() & other.to_big()),
+        }
+    }
+
+    pub fn bitor(&self, other: &Integer) -> Integer {
+        match (self, other) {
+            (Integer::Small(a), Integer::Small(b)) => Integer::Small(a | b),
+            _ => Integer::from(self.to_big() | other.to_big()),
+        }
+    }
+
+    pub fn bitxor(&self, other: &Integer) -> Integer {
+        match (self, other) {
+            (Integer::Small(a), Integer::Small(b)) => Integer::Small(a ^ b),
+            _ => Integer::from(self.to_big() ^ other.to_big()),
+        }
+    }
+
+    /// Shifts left, growing past i64 like the other operators.
+    pub fn shl(&self, by: u32) -> Integer {
+        Integer::from(self.to_big() << by)
+    }
+
+    /// Arithmetic shift right: negative values round down, towards -1.
+    pub fn shr(&self, by: u32) -> Integer {
+        match self {
+            Integer::Small(a) => Integer::Small(a >> by.min(63)),
+            Integer::Big(a) => Integer::from(a >> by),
+        }
+    }
+
+    pub fn pow(&self, exponent: u32) -> Integer {
+        match self {
+            Integer::Small(a) => match a.checked_pow(exponent) {
+                Some(power) => Integer::Small(power),
+                None => Integer::from(BigInt::from(*a).pow(exponent)),
+            },
+            Integer::Big(a) => Integer::from(a.pow(exponent)),
+        }
+    }
+
+    /// The number of bits in the magnitude, 0 for zero.
+    pub fn bits(&self) -> u64 {
+        match self {
+            Integer::Small(a) => u64::from(64 - a.unsigned_abs().leading_zeros()),
+            Integer::Big(a) => a.bits(),
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        matches!(self, Integer::Small(0))
+    }
+
+    fn to_big(&self) -> BigInt {
+        match self {
+            Integer::Small(a) => BigInt::from(*a),
+            Integer::Big(a) => a.clone(),
+        }
+    }
+}
+
+impl From<i64> for Integer {
+    fn from(value: i64) -> Self {
+        Integer::Small(value)
+    }
+}
+
+impl From<BigInt> for Integer {
+    fn from(value: BigInt) -> Self {
+        match i64::try_from(&value) {
+            Ok(small) => Integer::Small(small),
+            Err(_) => Integer::Big(value),
+        }
+    }
+}
+
+impl PartialOrd for Integer {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Integer {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Integer::Small(a), Integer::Small(b)) => a.cmp(b),
+            _ => self.to_big().cmp(&other.to_big()),
+        }
+    }
+}
+
+impl fmt::Display for Integer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Integer::Small(a) => write!(f, "{}", a),
+            Integer::Big(a) => write!(f, "{}", a),
+        }
+    }
+}
+
+/// Serialized as a decimal string, since JSON numbers lose precision past
+/// 2^53 and a `Big` has no upper bound at all.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Integer {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Integer {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let literal = String::deserialize(deserializer)?;
+        Integer::parse(&literal)
+            .ok_or_else(|| serde::de::Error::custom(format!("invalid integer {:?}", literal)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    fn int(literal: &str) -> Integer {
+        Integer::parse(literal).unwrap_or_else(|| panic!("could not parse {}", literal))
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(int("42"), Integer::Small(42));
+        assert_eq!(int("9223372036854775807"), Integer::Small(i64::MAX));
+        assert!(matches!(int("9223372036854775808"), Integer::Big(_)));
+        assert_eq!(
+            int("99999999999999999999").to_string(),
+            "99999999999999999999"
+        );
+        assert!(Integer::parse("12a").is_none());
+    }
+
+    #[test]
+    fn test_promotion_and_demotion() {
+        let max = Integer::from(i64::MAX);
+        let one = Integer::from(1);
+
+        let big = max.add(&one);
+        assert_eq!(big, int("9223372036854775808"));
+        assert!(matches!(big, Integer::Big(_)));
+
+        // coming back into range gives a Small again
+        assert_eq!(big.sub(&one), Integer::Small(i64::MAX));
+
+        assert_eq!(
+            max.mul(&Integer::from(2)).to_string(),
+            "18446744073709551614"
+        );
+        assert_eq!(Integer::from(i64::MIN).neg(), int("9223372036854775808"));
+        assert_eq!(
+            Integer::from(i64::MIN).div(&Integer::from(-1)),
+            Some(int("9223372036854775808"))
+        );
+        assert_eq!(
+            Integer::from(i64::MIN).rem(&Integer::from(-1)),
+            Some(Integer::Small(0))
+        );
+    }
+
+    #[test]
+    fn test_division_by_zero() {
+        assert_eq!(Integer::from(1).div(&Integer::from(0)), None);
+        assert_eq!(int("99999999999999999999").rem(&Integer::from(0)), None);
+        assert_eq!(
+            int("99999999999999999999").div(&int("33333333333333333333")),
+            Some(Integer::Small(3))
+        );
+    }
+
+    #[test]
+    fn test_bits_shifts_and_powers() {
+        let big = int("99999999999999999999");
+        assert_eq!(
+            Integer::from(6).bitand(&Integer::from(3)),
+            Integer::Small(2)
+        );
+        assert_eq!(
+            Integer::from(-6).bitand(&Integer::from(3)),
+            Integer::Small(2)
+        );
+        assert_eq!(Integer::from(6).bitor(&Integer::from(3)), Integer::Small(7));
+        assert_eq!(
+            Integer::from(6).bitxor(&Integer::from(3)),
+            Integer::Small(5)
+        );
+        assert_eq!(big.bitand(&Integer::from(0xff)), Integer::Small(0xff));
+        assert_eq!(big.bitxor(&big), Integer::Small(0));
+
+        assert_eq!(Integer::from(1).shl(64), int("18446744073709551616"));
+        assert_eq!(Integer::from(1).shl(64).shr(60), Integer::Small(16));
+        assert_eq!(Integer::from(-9).shr(1), Integer::Small(-5));
+        assert_eq!(Integer::from(-9).shr(100), Integer::Small(-1));
+        assert_eq!(big.neg().shr(200), Integer::Small(-1));
+
+        assert_eq!(Integer::from(-3).pow(3), Integer::Small(-27));
+        assert_eq!(Integer::from(7).pow(0), Integer::Small(1));
+        assert_eq!(Integer::from(2).pow(64), int("18446744073709551616"));
+        assert_eq!(
+            big.pow(2).to_string(),
+            "9999999999999999999800000000000000000001"
+        );
+
+        assert_eq!(Integer::from(0).bits(), 0);
+        assert_eq!(Integer::from(-8).bits(), 4);
+        assert_eq!(Integer::from(i64::MIN).bits(), 64);
+        assert_eq!(Integer::from(1).shl(64).bits(), 65);
+    }
+
+    #[test]
+    fn test_compare_and_hash() {
+        let a = int("99999999999999999999");
+        let b = int("100000000000000000000");
+
+        assert!(a < b);
+        assert!(Integer::from(i64::MAX) < a);
+        assert!(a.neg() < Integer::from(i64::MIN));
+
+        let mut set = HashSet::new();
+        set.insert(a.clone());
+        set.insert(Integer::from(i64::MAX).add(&Integer::from(1)));
+        assert!(set.contains(&b.sub(&Integer::from(1))));
+        assert!(set.contains(&int("9223372036854775808")));
+        assert_eq!(set.len(), 2);
+    }
+}
@@ -1,14 +1,28 @@
 use std::fmt::Display;
 
+use crate::lexer::token::Span;
+
 #[derive(Debug, Clone)]
 pub enum ParseErrorKind {
     UnexpectedToken,
+    NoPrefixParseFn,
+    InvalidLiteral,
+    OutsideLoop,
+    InvalidAssignment,
+    IllegalToken,
+    NotTopLevel,
 }
 
 impl Display for ParseErrorKind {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match *self {
             ParseErrorKind::UnexpectedToken => write!(f, "Unexpected Token"),
+            ParseErrorKind::NoPrefixParseFn => write!(f, "No Prefix Parse Function"),
+            ParseErrorKind::InvalidLiteral => write!(f, "Invalid Literal"),
+            ParseErrorKind::OutsideLoop => write!(f, "Outside Loop"),
+            ParseErrorKind::InvalidAssignment => write!(f, "Invalid Assignment"),
+            ParseErrorKind::IllegalToken => write!(f, "Illegal Token"),
+            ParseErrorKind::NotTopLevel => write!(f, "Not Top Level"),
         }
     }
 }
@@ -17,12 +31,14 @@ impl Display for ParseErrorKind {
 pub struct ParseError {
     pub kind: ParseErrorKind,
     pub msg: String,
+    // the token the error is about
+    pub span: Span,
 }
 
 
 impl ParseError {
-    fn new(kind: ParseErrorKind, msg: String) -> Self {
-        ParseError { kind, msg }
+    pub fn new(kind: ParseErrorKind, msg: String, span: Span) -> Self {
+        ParseError { kind, msg, span }
     }
 }
 
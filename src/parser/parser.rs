@@ -2,10 +2,16 @@ use std::collections::HashMap;
 use std::rc::Rc;
 
 use crate::ast::ast::{
-    self, ExpressionStatement, Identifier, LetStatement, ReturnStatements, Statement,
+    self, ArrayLiteral, AssignExpression, BlockStatement, Boolean, BreakStatement, CallExpression,
+    ContinueStatement, ExportStatement, ExpressionStatement, FloatLiteral, ForStatement,
+    FunctionLiteral, Identifier, IfExpression, ImportStatement, IndexExpression, InfixExpression,
+    IntegerLiteral, InterpolatedString, InterpolationPart, LetStatement, MacroLiteral,
+    MemberExpression, PrefixExpression, ReturnStatements, Statement, StringLiteral, ThrowStatement,
+    TryStatement, TypeExpr, WhileStatement,
 };
+use crate::lexer::errors::LexError;
 use crate::lexer::lexer::Lexer;
-use crate::lexer::token::TokenType;
+use crate::lexer::token::{Span, TemplatePart, TokenType, TokenValue};
 use crate::{
     ast::ast::Program,
     lexer::{lexer::LexerTrait, token::Token},
@@ -13,15 +19,60 @@ use crate::{
 
 use super::errors::{ParseError, ParseErrorKind, ParseErrors};
 
-type PrefixParseFn = fn() -> Box<dyn ast::Expression>;
-type InfixParseFn = fn(Box<dyn ast::Expression>) -> Box<dyn ast::Expression>;
+type PrefixParseFn = fn(&mut Parser) -> Option<Rc<dyn ast::Expression>>;
+type InfixParseFn = fn(&mut Parser, Rc<dyn ast::Expression>) -> Option<Rc<dyn ast::Expression>>;
+// a parameter list's names, and each one's annotation if it has one
+type Parameters = (Vec<Rc<Identifier>>, Vec<Option<TypeExpr>>);
 
-enum Predecessor {
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Predecessor {
     LOWEST,
-    EQUALS,  // == LESSGREATER // > or <
-    SUM,     // +
-    PRODUCT, // *
-    PREFIX,  // -X or !X CALL // myFunction(X)
+    ASSIGN,      // = += -= *= /=
+    LOGICALOR,   // ||
+    LOGICALAND,  // &&
+    EQUALS,      // ==
+    LESSGREATER, // > or <
+    BITOR,       // |
+    BITXOR,      // ^
+    BITAND,      // &
+    SHIFT,       // << or >>
+    SUM,         // +
+    PRODUCT,     // *
+    PREFIX,      // -X or !X
+    POWER,       // ** (right-associative, binds tighter than prefix -)
+    CALL,        // myFunction(X)
+    INDEX,       // array[index]
+}
+
+pub(crate) fn precedence_of(t: &TokenType) -> Predecessor {
+    match t {
+        TokenType::Or => Predecessor::LOGICALOR,
+        TokenType::And => Predecessor::LOGICALAND,
+        TokenType::EQ | TokenType::NotEq => Predecessor::EQUALS,
+        TokenType::LT | TokenType::GT | TokenType::LtEq | TokenType::GtEq => {
+            Predecessor::LESSGREATER
+        }
+        TokenType::BitOr => Predecessor::BITOR,
+        TokenType::BitXor => Predecessor::BITXOR,
+        TokenType::BitAnd => Predecessor::BITAND,
+        TokenType::ShiftLeft | TokenType::ShiftRight => Predecessor::SHIFT,
+        TokenType::Plus | TokenType::MINUS => Predecessor::SUM,
+        TokenType::ASTERISK | TokenType::SLASH | TokenType::Percent => Predecessor::PRODUCT,
+        TokenType::Power => Predecessor::POWER,
+        TokenType::LParen => Predecessor::CALL,
+        TokenType::LBracket | TokenType::Dot => Predecessor::INDEX,
+        TokenType::Assign
+        | TokenType::PlusAssign
+        | TokenType::MinusAssign
+        | TokenType::AsteriskAssign
+        | TokenType::SlashAssign => Predecessor::ASSIGN,
+        _ => Predecessor::LOWEST,
+    }
+}
+
+pub(crate) fn right_associative(t: &TokenType) -> bool {
+    *t == TokenType::Power
 }
 
 pub struct Parser {
@@ -31,6 +82,14 @@ pub struct Parser {
     errors: ParseErrors,
     prefix_parse_fns: HashMap<TokenType, PrefixParseFn>,
     infix_parse_fns: HashMap<TokenType, InfixParseFn>,
+    // how many loops enclose the current token; break/continue need one
+    loop_depth: usize,
+    // how many blocks enclose the current token; import/export need none
+    block_depth: usize,
+    // tokens to read instead of `l`, for the `${...}` parts of a string
+    replay: Option<std::vec::IntoIter<Token>>,
+    // lexer errors of the outer source when parsing replayed tokens
+    outer_lex_errors: Vec<LexError>,
 }
 
 impl Parser {
@@ -43,11 +102,9 @@ impl Parser {
     }
 
     fn parse_let_statement(&mut self) -> Option<Rc<LetStatement>> {
-        println!("parsing let statement");
         let token = self.cur_token.clone();
 
         if !self.expect_peek(TokenType::Ident) {
-            println!("expected ident , got {:?}", self.peek_token);
             return None;
         }
 
@@ -55,44 +112,300 @@ impl Parser {
             value: self.cur_token.literal.clone(),
             token: self.cur_token.clone(),
         });
+        let annotation = self.parse_annotation(TokenType::Colon)?;
 
         if !self.expect_peek(TokenType::Assign) {
             return None;
         }
         self.next_token();
-        while !self.cur_token_is(TokenType::Semicolon) {
-            if self.cur_token_is(TokenType::Eof) {
-                return None;
-            }
-            self.next_token()
-        }
 
-        // self.next_token();
+        let value = self.parse_expression(Predecessor::LOWEST)?;
+
+        if self.peek_token_is(TokenType::Semicolon) {
+            self.next_token();
+        }
 
         Some(Rc::new(LetStatement {
             token,
             name,
-            value: None,
+            annotation,
+            value: Some(value),
         }))
     }
 
     fn parse_ret_statement(&mut self) -> Option<Rc<ReturnStatements>> {
-        println!("parsing return statement");
         let token = self.cur_token.clone();
         self.next_token();
 
-        if !self.expect_peek(TokenType::Semicolon) {
-            println!("expected semicolon , got {:?}", self.peek_token);
-            return None;
+        let value = self.parse_expression(Predecessor::LOWEST)?;
+
+        if self.peek_token_is(TokenType::Semicolon) {
+            self.next_token();
         }
 
         Some(Rc::new(ReturnStatements {
             token,
-            // name,
-            value: None,
+            value: Some(value),
+        }))
+    }
+
+    fn parse_throw_statement(&mut self) -> Option<Rc<ThrowStatement>> {
+        let token = self.cur_token.clone();
+        self.next_token();
+
+        let value = self.parse_expression(Predecessor::LOWEST)?;
+
+        if self.peek_token_is(TokenType::Semicolon) {
+            self.next_token();
+        }
+
+        Some(Rc::new(ThrowStatement {
+            token,
+            value: Some(value),
+        }))
+    }
+
+    fn parse_try_statement(&mut self) -> Option<Rc<TryStatement>> {
+        let token = self.cur_token.clone();
+
+        if !self.expect_peek(TokenType::LBrace) {
+            return None;
+        }
+        let block = self.parse_block_statement();
+
+        let mut catch_param = None;
+        let mut catch_block = None;
+        if self.peek_token_is(TokenType::Catch) {
+            self.next_token();
+            // the binding is optional: `catch { }` just swallows the error
+            if self.peek_token_is(TokenType::LParen) {
+                self.next_token();
+                if !self.expect_peek(TokenType::Ident) {
+                    return None;
+                }
+                catch_param = Some(Rc::new(Identifier {
+                    token: self.cur_token.clone(),
+                    value: self.cur_token.literal.clone(),
+                }));
+                if !self.expect_peek(TokenType::RParen) {
+                    return None;
+                }
+            }
+            if !self.expect_peek(TokenType::LBrace) {
+                return None;
+            }
+            catch_block = Some(self.parse_block_statement());
+        }
+
+        let mut finally_block = None;
+        if self.peek_token_is(TokenType::Finally) {
+            self.next_token();
+            if !self.expect_peek(TokenType::LBrace) {
+                return None;
+            }
+            finally_block = Some(self.parse_block_statement());
+        }
+
+        if catch_block.is_none() && finally_block.is_none() {
+            self.peek_errors(TokenType::Catch);
+            return None;
+        }
+
+        if self.peek_token_is(TokenType::Semicolon) {
+            self.next_token();
+        }
+
+        Some(Rc::new(TryStatement {
+            token,
+            block,
+            catch_param,
+            catch_block,
+            finally_block,
+        }))
+    }
+
+    fn parse_while_statement(&mut self) -> Option<Rc<WhileStatement>> {
+        let token = self.cur_token.clone();
+
+        if !self.expect_peek(TokenType::LParen) {
+            return None;
+        }
+        self.next_token();
+        let condition = self.parse_expression(Predecessor::LOWEST)?;
+
+        if !self.expect_peek(TokenType::RParen) {
+            return None;
+        }
+        if !self.expect_peek(TokenType::LBrace) {
+            return None;
+        }
+        let body = self.parse_loop_body();
+
+        if self.peek_token_is(TokenType::Semicolon) {
+            self.next_token();
+        }
+
+        Some(Rc::new(WhileStatement {
+            token,
+            condition,
+            body,
+        }))
+    }
+
+    fn parse_for_statement(&mut self) -> Option<Rc<ForStatement>> {
+        let token = self.cur_token.clone();
+
+        if !self.expect_peek(TokenType::LParen) {
+            return None;
+        }
+        if !self.expect_peek(TokenType::Ident) {
+            return None;
+        }
+        let variable = Rc::new(Identifier {
+            token: self.cur_token.clone(),
+            value: self.cur_token.literal.clone(),
+        });
+
+        if !self.expect_peek(TokenType::In) {
+            return None;
+        }
+        self.next_token();
+        let iterable = self.parse_expression(Predecessor::LOWEST)?;
+
+        if !self.expect_peek(TokenType::RParen) {
+            return None;
+        }
+        if !self.expect_peek(TokenType::LBrace) {
+            return None;
+        }
+        let body = self.parse_loop_body();
+
+        if self.peek_token_is(TokenType::Semicolon) {
+            self.next_token();
+        }
+
+        Some(Rc::new(ForStatement {
+            token,
+            variable,
+            iterable,
+            body,
         }))
     }
 
+    fn parse_loop_body(&mut self) -> Rc<BlockStatement> {
+        self.loop_depth += 1;
+        let body = self.parse_block_statement();
+        self.loop_depth -= 1;
+        body
+    }
+
+    fn parse_break_statement(&mut self) -> Option<Rc<BreakStatement>> {
+        let token = self.cur_token.clone();
+        if !self.finish_loop_jump() {
+            return None;
+        }
+        Some(Rc::new(BreakStatement { token }))
+    }
+
+    fn parse_continue_statement(&mut self) -> Option<Rc<ContinueStatement>> {
+        let token = self.cur_token.clone();
+        if !self.finish_loop_jump() {
+            return None;
+        }
+        Some(Rc::new(ContinueStatement { token }))
+    }
+
+    // Eats the optional `;` after break/continue and reports one that is
+    // not inside a loop body.
+    fn finish_loop_jump(&mut self) -> bool {
+        let keyword = self.cur_token.clone();
+        if self.peek_token_is(TokenType::Semicolon) {
+            self.next_token();
+        }
+        if self.loop_depth > 0 {
+            return true;
+        }
+        let msg = format!("{} outside of a loop", keyword.literal);
+        self.errors.push(ParseError::new(
+            ParseErrorKind::OutsideLoop,
+            msg,
+            keyword.span,
+        ));
+        false
+    }
+
+    fn parse_import_statement(&mut self) -> Option<Rc<ImportStatement>> {
+        let token = self.cur_token.clone();
+        self.check_top_level();
+
+        if !self.expect_peek(TokenType::String) {
+            return None;
+        }
+        let path = self.cur_token.literal.clone();
+
+        if !self.expect_peek(TokenType::As) {
+            return None;
+        }
+        if !self.expect_peek(TokenType::Ident) {
+            return None;
+        }
+        let alias = Rc::new(Identifier {
+            token: self.cur_token.clone(),
+            value: self.cur_token.literal.clone(),
+        });
+
+        if self.peek_token_is(TokenType::Semicolon) {
+            self.next_token();
+        }
+
+        Some(Rc::new(ImportStatement { token, path, alias }))
+    }
+
+    fn parse_export_statement(&mut self) -> Option<Rc<ExportStatement>> {
+        let token = self.cur_token.clone();
+        self.check_top_level();
+
+        if !self.expect_peek(TokenType::Let) {
+            return None;
+        }
+        let statement = self.parse_let_statement()?;
+
+        Some(Rc::new(ExportStatement { token, statement }))
+    }
+
+    // Modules are resolved before anything runs, so import and export only
+    // make sense outside every block and function body.
+    fn check_top_level(&mut self) {
+        if self.block_depth > 0 {
+            let msg = format!(
+                "{} is only allowed at the top level",
+                self.cur_token.literal
+            );
+            self.errors.push(ParseError::new(
+                ParseErrorKind::NotTopLevel,
+                msg,
+                self.cur_token.span,
+            ));
+        }
+    }
+
+    fn parse_block_statement(&mut self) -> Rc<BlockStatement> {
+        let token = self.cur_token.clone();
+        let mut statements = Vec::new();
+        self.next_token();
+
+        self.block_depth += 1;
+        while !self.cur_token_is(TokenType::RBrace) && !self.cur_token_is(TokenType::Eof) {
+            if let Some(stmt) = self.parse_statement() {
+                statements.push(stmt);
+            }
+            self.next_token();
+        }
+        self.block_depth -= 1;
+
+        Rc::new(BlockStatement { token, statements })
+    }
+
     fn expect_peek(&mut self, t: TokenType) -> bool {
         if self.peek_token.type_ == t {
             self.next_token();
@@ -107,21 +420,97 @@ impl Parser {
         self.cur_token.type_ == t
     }
 
-    fn new(l: Lexer) -> Self {
+    fn peek_token_is(&self, t: TokenType) -> bool {
+        self.peek_token.type_ == t
+    }
+
+    fn peek_precedence(&self) -> Predecessor {
+        precedence_of(&self.peek_token.type_)
+    }
+
+    fn cur_precedence(&self) -> Predecessor {
+        precedence_of(&self.cur_token.type_)
+    }
+
+    pub fn new(l: Lexer) -> Self {
         let mut p = Parser {
             l,
-            cur_token: Token {
-                type_: TokenType::Eof,
-                literal: "".to_string(),
-            },
-            peek_token: Token {
-                type_: TokenType::Eof,
-                literal: "".to_string(),
-            },
+            cur_token: Token::new(TokenType::Eof, ""),
+            peek_token: Token::new(TokenType::Eof, ""),
             errors: vec![],
             prefix_parse_fns: HashMap::new(),
             infix_parse_fns: HashMap::new(),
+            loop_depth: 0,
+            block_depth: 0,
+            replay: None,
+            outer_lex_errors: vec![],
         };
+
+        p.register_prefix(TokenType::Ident, Parser::parse_identifier);
+        p.register_prefix(TokenType::Int, Parser::parse_integer_literal);
+        p.register_prefix(TokenType::Float, Parser::parse_float_literal);
+        p.register_prefix(TokenType::Illegal, Parser::parse_illegal);
+        p.register_prefix(TokenType::String, Parser::parse_string_literal);
+        p.register_prefix(
+            TokenType::InterpolatedString,
+            Parser::parse_interpolated_string,
+        );
+        p.register_prefix(TokenType::True, Parser::parse_boolean);
+        p.register_prefix(TokenType::False, Parser::parse_boolean);
+        p.register_prefix(TokenType::BANG, Parser::parse_prefix_expression);
+        p.register_prefix(TokenType::MINUS, Parser::parse_prefix_expression);
+        p.register_prefix(TokenType::LParen, Parser::parse_grouped_expression);
+        p.register_prefix(TokenType::If, Parser::parse_if_expression);
+        p.register_prefix(TokenType::Function, Parser::parse_function_literal);
+        p.register_prefix(TokenType::Macro, Parser::parse_macro_literal);
+        p.register_prefix(TokenType::LBracket, Parser::parse_array_literal);
+
+        for t in [
+            TokenType::Plus,
+            TokenType::MINUS,
+            TokenType::ASTERISK,
+            TokenType::SLASH,
+            TokenType::EQ,
+            TokenType::NotEq,
+            TokenType::LT,
+            TokenType::GT,
+            TokenType::And,
+            TokenType::Or,
+            TokenType::LtEq,
+            TokenType::GtEq,
+            TokenType::Percent,
+            TokenType::Power,
+            TokenType::BitAnd,
+            TokenType::BitOr,
+            TokenType::BitXor,
+            TokenType::ShiftLeft,
+            TokenType::ShiftRight,
+        ] {
+            p.register_infix(t, Parser::parse_infix_expression);
+        }
+        p.register_infix(TokenType::LParen, Parser::parse_call_expression);
+        p.register_infix(TokenType::LBracket, Parser::parse_index_expression);
+        p.register_infix(TokenType::Dot, Parser::parse_member_expression);
+        for t in [
+            TokenType::Assign,
+            TokenType::PlusAssign,
+            TokenType::MinusAssign,
+            TokenType::AsteriskAssign,
+            TokenType::SlashAssign,
+        ] {
+            p.register_infix(t, Parser::parse_assign_expression);
+        }
+
+        p.next_token();
+        p.next_token();
+        p
+    }
+
+    // A parser over tokens the lexer has already produced.
+    fn from_tokens(tokens: Vec<Token>, outer_lex_errors: Vec<LexError>) -> Self {
+        let mut p = Parser::new(Lexer::new(""));
+        p.replay = Some(tokens.into_iter());
+        p.outer_lex_errors = outer_lex_errors;
         p.next_token();
         p.next_token();
         p
@@ -129,26 +518,26 @@ impl Parser {
 
     fn next_token(&mut self) {
         self.cur_token = self.peek_token.clone();
-        self.peek_token = self.l.next_token();
-        println!("Advanced to token: {:?}", self.cur_token);
+        self.peek_token = match &mut self.replay {
+            Some(tokens) => tokens.next().unwrap_or_else(|| {
+                let end = self.cur_token.span;
+                let span = Span::new(end.end, end.end, end.line, end.column);
+                Token::new(TokenType::Eof, "").with_span(span)
+            }),
+            None => self.l.next_token(),
+        };
     }
 
-    fn parse_program(&mut self) -> Program {
+    pub fn parse_program(&mut self) -> Program {
         let mut program = Program {
             statements: Vec::new(),
         };
         while self.cur_token.type_ != TokenType::Eof {
             if let Some(stmt) = self.parse_statement() {
-                println!("{:?}", stmt);
                 program.statements.push(stmt);
-                println!(
-                    "Parsed statement. Total statements: {}",
-                    program.statements.len()
-                );
             }
             self.next_token();
         }
-        println!("{:?}", program);
         program
     }
 
@@ -156,40 +545,566 @@ impl Parser {
         match self.cur_token.type_ {
             TokenType::Let => self.parse_let_statement().map(|s| s as Rc<dyn Statement>),
             TokenType::Return => self.parse_ret_statement().map(|s| s as Rc<dyn Statement>),
-            _ => self.parse_expression_statement(),
+            TokenType::Throw => self.parse_throw_statement().map(|s| s as Rc<dyn Statement>),
+            TokenType::Try => self.parse_try_statement().map(|s| s as Rc<dyn Statement>),
+            TokenType::While => self.parse_while_statement().map(|s| s as Rc<dyn Statement>),
+            TokenType::For => self.parse_for_statement().map(|s| s as Rc<dyn Statement>),
+            TokenType::Break => self.parse_break_statement().map(|s| s as Rc<dyn Statement>),
+            TokenType::Import => self
+                .parse_import_statement()
+                .map(|s| s as Rc<dyn Statement>),
+            TokenType::Export => self
+                .parse_export_statement()
+                .map(|s| s as Rc<dyn Statement>),
+            TokenType::Continue => self
+                .parse_continue_statement()
+                .map(|s| s as Rc<dyn Statement>),
+            _ => self
+                .parse_expression_statement()
+                .map(|s| s as Rc<dyn Statement>),
+        }
+    }
+
+    pub fn errors(self) -> ParseErrors {
+        self.errors.clone()
+    }
+
+    fn peek_errors(&mut self, t: TokenType) {
+        let msg = format!(
+            "expected next token to be {} , got {} instead",
+            t, self.peek_token.type_
+        );
+        let err_struct = ParseError {
+            msg,
+            kind: ParseErrorKind::UnexpectedToken,
+            span: self.peek_token.span,
+        };
+        self.errors.push(err_struct);
+    }
+
+    fn no_prefix_parse_fn_error(&mut self) {
+        let msg = format!(
+            "no prefix parse function for {} found",
+            self.cur_token.type_
+        );
+        self.errors.push(ParseError::new(
+            ParseErrorKind::NoPrefixParseFn,
+            msg,
+            self.cur_token.span,
+        ));
+    }
+
+    fn parse_expression_statement(&mut self) -> Option<Rc<ExpressionStatement>> {
+        let token = self.cur_token.clone();
+        let expr = self.parse_expression(Predecessor::LOWEST)?;
+
+        if self.peek_token_is(TokenType::Semicolon) {
+            self.next_token();
+        }
+        Some(Rc::new(ExpressionStatement {
+            token,
+            expr: Some(expr),
+        }))
+    }
+
+    fn parse_expression(&mut self, precedence: Predecessor) -> Option<Rc<dyn ast::Expression>> {
+        let prefix = match self.prefix_parse_fns.get(&self.cur_token.type_) {
+            Some(prefix) => *prefix,
+            None => {
+                self.no_prefix_parse_fn_error();
+                return None;
+            }
+        };
+        let mut left = prefix(self)?;
+
+        while !self.peek_token_is(TokenType::Semicolon) && precedence < self.peek_precedence() {
+            let infix = match self.infix_parse_fns.get(&self.peek_token.type_) {
+                Some(infix) => *infix,
+                None => return Some(left),
+            };
+            self.next_token();
+            left = infix(self, left)?;
+        }
+
+        Some(left)
+    }
+
+    fn parse_identifier(&mut self) -> Option<Rc<dyn ast::Expression>> {
+        Some(Rc::new(Identifier {
+            token: self.cur_token.clone(),
+            value: self.cur_token.literal.clone(),
+        }))
+    }
+
+    fn parse_integer_literal(&mut self) -> Option<Rc<dyn ast::Expression>> {
+        let token = self.cur_token.clone();
+        // the lexer has already parsed the value, whatever base it was written in
+        match token.int_value().cloned() {
+            Some(value) => Some(Rc::new(IntegerLiteral { token, value })),
+            None => {
+                let msg = format!("could not parse {} as integer", token.literal);
+                self.errors.push(ParseError::new(
+                    ParseErrorKind::InvalidLiteral,
+                    msg,
+                    token.span,
+                ));
+                None
+            }
+        }
+    }
+
+    fn parse_float_literal(&mut self) -> Option<Rc<dyn ast::Expression>> {
+        let token = self.cur_token.clone();
+        match token.literal.replace('_', "").parse::<f64>() {
+            Ok(value) if value.is_finite() => Some(Rc::new(FloatLiteral { token, value })),
+            // 1e400 parses as infinity
+            Ok(_) => {
+                let msg = format!("{} is out of range for a float", token.literal);
+                self.errors.push(ParseError::new(
+                    ParseErrorKind::InvalidLiteral,
+                    msg,
+                    token.span,
+                ));
+                None
+            }
+            Err(_) => {
+                let msg = format!("could not parse {} as float", token.literal);
+                self.errors.push(ParseError::new(
+                    ParseErrorKind::InvalidLiteral,
+                    msg,
+                    token.span,
+                ));
+                None
+            }
+        }
+    }
+
+    // Nothing can be built from an Illegal token; report why the lexer rejected it.
+    fn parse_illegal(&mut self) -> Option<Rc<dyn ast::Expression>> {
+        let span = self.cur_token.span;
+        let outer = self.outer_lex_errors.iter().find(|err| err.span == span);
+        let msg = match self.l.error_at(span).or(outer) {
+            Some(err) => err.msg.clone(),
+            None => format!("illegal token at {}", self.cur_token.span),
+        };
+        self.errors
+            .push(ParseError::new(ParseErrorKind::IllegalToken, msg, span));
+        None
+    }
+
+    fn parse_string_literal(&mut self) -> Option<Rc<dyn ast::Expression>> {
+        Some(Rc::new(StringLiteral {
+            token: self.cur_token.clone(),
+            value: self.cur_token.literal.clone(),
+        }))
+    }
+
+    fn parse_interpolated_string(&mut self) -> Option<Rc<dyn ast::Expression>> {
+        let token = self.cur_token.clone();
+        let Some(TokenValue::Template(pieces)) = token.value.clone() else {
+            return None;
+        };
+
+        let mut parts = Vec::new();
+        for piece in pieces {
+            match piece {
+                TemplatePart::Text(text) => parts.push(InterpolationPart::Text(text)),
+                TemplatePart::Expr(tokens) => {
+                    parts.push(InterpolationPart::Expr(self.parse_interpolation(tokens)?))
+                }
+            }
+        }
+
+        Some(Rc::new(InterpolatedString { token, parts }))
+    }
+
+    // Parses the tokens of one `${...}` as a single expression.
+    fn parse_interpolation(&mut self, tokens: Vec<Token>) -> Option<Rc<dyn ast::Expression>> {
+        if tokens.is_empty() {
+            let msg = "empty ${} in string".to_string();
+            self.errors.push(ParseError::new(
+                ParseErrorKind::InvalidLiteral,
+                msg,
+                self.cur_token.span,
+            ));
+            return None;
+        }
+
+        let lex_errors = [self.l.errors(), self.outer_lex_errors.as_slice()].concat();
+        let mut sub = Parser::from_tokens(tokens, lex_errors);
+        let expr = sub.parse_expression(Predecessor::LOWEST);
+        if expr.is_some() && !sub.peek_token_is(TokenType::Eof) {
+            sub.peek_errors(TokenType::Eof);
+        }
+        let errors = sub.errors();
+        if !errors.is_empty() {
+            self.errors.extend(errors);
+            return None;
+        }
+        expr
+    }
+
+    fn parse_boolean(&mut self) -> Option<Rc<dyn ast::Expression>> {
+        Some(Rc::new(Boolean {
+            token: self.cur_token.clone(),
+            value: self.cur_token_is(TokenType::True),
+        }))
+    }
+
+    fn parse_prefix_expression(&mut self) -> Option<Rc<dyn ast::Expression>> {
+        let token = self.cur_token.clone();
+        let operator = token.literal.clone();
+        self.next_token();
+        let right = self.parse_expression(Predecessor::PREFIX)?;
+
+        Some(Rc::new(PrefixExpression {
+            token,
+            operator,
+            right,
+        }))
+    }
+
+    fn parse_infix_expression(
+        &mut self,
+        left: Rc<dyn ast::Expression>,
+    ) -> Option<Rc<dyn ast::Expression>> {
+        let token = self.cur_token.clone();
+        let operator = token.literal.clone();
+        let mut precedence = self.cur_precedence();
+        if right_associative(&token.type_) {
+            // parse the right side one level looser so a following ** nests into it
+            precedence = Predecessor::PREFIX;
+        }
+        self.next_token();
+        let right = self.parse_expression(precedence)?;
+
+        Some(Rc::new(InfixExpression {
+            token,
+            left,
+            operator,
+            right,
+        }))
+    }
+
+    fn parse_index_expression(
+        &mut self,
+        left: Rc<dyn ast::Expression>,
+    ) -> Option<Rc<dyn ast::Expression>> {
+        let token = self.cur_token.clone();
+        self.next_token();
+        let index = self.parse_expression(Predecessor::LOWEST)?;
+
+        if !self.expect_peek(TokenType::RBracket) {
+            return None;
+        }
+        Some(Rc::new(IndexExpression { token, left, index }))
+    }
+
+    fn parse_member_expression(
+        &mut self,
+        object: Rc<dyn ast::Expression>,
+    ) -> Option<Rc<dyn ast::Expression>> {
+        let token = self.cur_token.clone();
+
+        if !self.expect_peek(TokenType::Ident) {
+            return None;
+        }
+        let property = Rc::new(Identifier {
+            token: self.cur_token.clone(),
+            value: self.cur_token.literal.clone(),
+        });
+
+        Some(Rc::new(MemberExpression {
+            token,
+            object,
+            property,
+        }))
+    }
+
+    fn parse_assign_expression(
+        &mut self,
+        target: Rc<dyn ast::Expression>,
+    ) -> Option<Rc<dyn ast::Expression>> {
+        let token = self.cur_token.clone();
+
+        if !target.as_any().is::<Identifier>() && !target.as_any().is::<IndexExpression>() {
+            let msg = format!("cannot assign to {}", target);
+            self.errors.push(ParseError::new(
+                ParseErrorKind::InvalidAssignment,
+                msg,
+                token.span,
+            ));
+            return None;
+        }
+
+        let operator = token.literal.clone();
+        self.next_token();
+        // right-associative: `a = b = c` assigns c to b first
+        let value = self.parse_expression(Predecessor::LOWEST)?;
+
+        Some(Rc::new(AssignExpression {
+            token,
+            target,
+            operator,
+            value,
+        }))
+    }
+
+    fn parse_grouped_expression(&mut self) -> Option<Rc<dyn ast::Expression>> {
+        self.next_token();
+        let expr = self.parse_expression(Predecessor::LOWEST)?;
+
+        if !self.expect_peek(TokenType::RParen) {
+            return None;
+        }
+        Some(expr)
+    }
+
+    fn parse_if_expression(&mut self) -> Option<Rc<dyn ast::Expression>> {
+        let token = self.cur_token.clone();
+
+        if !self.expect_peek(TokenType::LParen) {
+            return None;
+        }
+        self.next_token();
+        let condition = self.parse_expression(Predecessor::LOWEST)?;
+
+        if !self.expect_peek(TokenType::RParen) {
+            return None;
+        }
+        if !self.expect_peek(TokenType::LBrace) {
+            return None;
+        }
+        let consequence = self.parse_block_statement();
+
+        let mut alternative = None;
+        if self.peek_token_is(TokenType::Else) {
+            self.next_token();
+            if !self.expect_peek(TokenType::LBrace) {
+                return None;
+            }
+            alternative = Some(self.parse_block_statement());
+        }
+
+        Some(Rc::new(IfExpression {
+            token,
+            condition,
+            consequence,
+            alternative,
+        }))
+    }
+
+    fn parse_function_literal(&mut self) -> Option<Rc<dyn ast::Expression>> {
+        let token = self.cur_token.clone();
+
+        if !self.expect_peek(TokenType::LParen) {
+            return None;
+        }
+        let (parameters, parameter_types) = self.parse_function_parameters()?;
+        let return_type = self.parse_annotation(TokenType::Arrow)?;
+
+        if !self.expect_peek(TokenType::LBrace) {
+            return None;
+        }
+        // a function body starts a fresh loop context: `break` cannot
+        // jump out of the function into a loop around its definition
+        let loop_depth = std::mem::take(&mut self.loop_depth);
+        let body = self.parse_block_statement();
+        self.loop_depth = loop_depth;
+
+        Some(Rc::new(FunctionLiteral {
+            token,
+            parameters,
+            parameter_types,
+            return_type,
+            body,
+        }))
+    }
+
+    fn parse_macro_literal(&mut self) -> Option<Rc<dyn ast::Expression>> {
+        let token = self.cur_token.clone();
+
+        if !self.expect_peek(TokenType::LParen) {
+            return None;
+        }
+        let (parameters, parameter_types) = self.parse_function_parameters()?;
+        // macros work on unevaluated code, which has no types to check
+        if let Some(annotation) = parameter_types.into_iter().flatten().next() {
+            self.errors.push(ParseError::new(
+                ParseErrorKind::UnexpectedToken,
+                "macro parameters take no type annotations".to_string(),
+                annotation.token().span,
+            ));
+            return None;
+        }
+
+        if !self.expect_peek(TokenType::LBrace) {
+            return None;
+        }
+        let loop_depth = std::mem::take(&mut self.loop_depth);
+        let body = self.parse_block_statement();
+        self.loop_depth = loop_depth;
+
+        Some(Rc::new(MacroLiteral {
+            token,
+            parameters,
+            body,
+        }))
+    }
+
+    // The parameters and their annotations, up to and including the `)`.
+    fn parse_function_parameters(&mut self) -> Option<Parameters> {
+        let mut identifiers = Vec::new();
+        let mut types = Vec::new();
+
+        if self.peek_token_is(TokenType::RParen) {
+            self.next_token();
+            return Some((identifiers, types));
+        }
+
+        loop {
+            if !self.expect_peek(TokenType::Ident) {
+                return None;
+            }
+            identifiers.push(Rc::new(Identifier {
+                token: self.cur_token.clone(),
+                value: self.cur_token.literal.clone(),
+            }));
+            types.push(self.parse_annotation(TokenType::Colon)?);
+            if !self.peek_token_is(TokenType::Comma) {
+                break;
+            }
+            self.next_token();
+        }
+
+        if !self.expect_peek(TokenType::RParen) {
+            return None;
+        }
+        Some((identifiers, types))
+    }
+
+    // The type after `marker` (`:` or `->`) if the next token is one.
+    // Returns None on an error and Some(None) when there is no marker.
+    fn parse_annotation(&mut self, marker: TokenType) -> Option<Option<TypeExpr>> {
+        if !self.peek_token_is(marker) {
+            return Some(None);
+        }
+        self.next_token();
+        self.parse_type().map(Some)
+    }
+
+    // A type starting at the next token, leaving the parser on its last
+    // token like parse_expression does.
+    fn parse_type(&mut self) -> Option<TypeExpr> {
+        self.next_token();
+        let token = self.cur_token.clone();
+        match token.type_ {
+            TokenType::Ident => Some(TypeExpr::Named(token)),
+            TokenType::LBracket => {
+                let element = self.parse_type()?;
+                if !self.expect_peek(TokenType::RBracket) {
+                    return None;
+                }
+                Some(TypeExpr::Array(token, Box::new(element)))
+            }
+            TokenType::Function => {
+                if !self.expect_peek(TokenType::LParen) {
+                    return None;
+                }
+                let mut params = Vec::new();
+                if self.peek_token_is(TokenType::RParen) {
+                    self.next_token();
+                } else {
+                    loop {
+                        params.push(self.parse_type()?);
+                        if !self.peek_token_is(TokenType::Comma) {
+                            break;
+                        }
+                        self.next_token();
+                    }
+                    if !self.expect_peek(TokenType::RParen) {
+                        return None;
+                    }
+                }
+                if !self.expect_peek(TokenType::Arrow) {
+                    return None;
+                }
+                let result = self.parse_type()?;
+                Some(TypeExpr::Function(token, params, Box::new(result)))
+            }
+            _ => {
+                let msg = format!("expected a type, got {} instead", token.type_);
+                self.errors.push(ParseError::new(
+                    ParseErrorKind::UnexpectedToken,
+                    msg,
+                    token.span,
+                ));
+                None
+            }
         }
     }
 
-    fn errors(self) -> ParseErrors {
-        self.errors.clone()
-    }
+    fn parse_call_expression(
+        &mut self,
+        function: Rc<dyn ast::Expression>,
+    ) -> Option<Rc<dyn ast::Expression>> {
+        let token = self.cur_token.clone();
+        let arguments = self.parse_expression_list(TokenType::RParen)?;
 
-    fn peek_errors(&mut self, t: TokenType) {
-        let msg = format!(
-            "expected next token to be {} , got {} instead",
-            t, self.peek_token.type_
-        );
-        let err_struct = ParseError {
-            msg,
-            kind: ParseErrorKind::UnexpectedToken,
-        };
-        println!("error_struct : {err_struct}");
-        self.errors.push(err_struct);
+        Some(Rc::new(CallExpression {
+            token,
+            function,
+            arguments,
+        }))
     }
 
-    fn parse_expression_statement(&mut self) -> ast::ExpressionStatement {
-        let stmt = ExpressionStatement {
-            token: self.cur_token,
-            expr: Predecessor::LOWEST,
-        };
+    // Comma-separated expressions up to and including `end`.
+    fn parse_expression_list(&mut self, end: TokenType) -> Option<Vec<Rc<dyn ast::Expression>>> {
+        let mut args = Vec::new();
 
-        if self.peek_token_is(TokenType::Semicolon) {
+        if self.peek_token_is(end) {
+            self.next_token();
+            return Some(args);
+        }
+
+        self.next_token();
+        args.push(self.parse_expression(Predecessor::LOWEST)?);
+
+        while self.peek_token_is(TokenType::Comma) {
+            self.next_token();
             self.next_token();
+            args.push(self.parse_expression(Predecessor::LOWEST)?);
         }
-        return stmt;
+
+        if !self.expect_peek(end) {
+            return None;
+        }
+        Some(args)
+    }
+
+    fn parse_array_literal(&mut self) -> Option<Rc<dyn ast::Expression>> {
+        let token = self.cur_token.clone();
+        let elements = self.parse_expression_list(TokenType::RBracket)?;
+
+        Some(Rc::new(ArrayLiteral { token, elements }))
     }
 }
 
+// Parses a program for a test, failing it on any parse error.
+#[cfg(test)]
+pub(crate) fn parse(input: &str) -> Program {
+    let mut p = Parser::new(Lexer::new(input));
+    let program = p.parse_program();
+    let errors = p.errors();
+    assert!(
+        errors.is_empty(),
+        "parse errors for {}: {:?}",
+        input,
+        errors
+    );
+    program
+}
+
 #[cfg(test)]
 mod tests {
     use core::panic;
@@ -197,8 +1112,13 @@ mod tests {
     use ast::ExpressionStatement;
 
     use super::*;
-    use crate::ast::ast::{Node, ReturnStatements};
+    use crate::ast::ast::{
+        ExportStatement, ImportStatement, InterpolatedString, InterpolationPart, MemberExpression,
+        Node, ReturnStatements,
+    };
+    use crate::ast::printer::parenthesize;
     use crate::lexer;
+    use crate::object::integer::Integer;
     use crate::{
         ast::ast::{LetStatement, Statement},
         lexer::lexer::Lexer,
@@ -225,6 +1145,9 @@ mod tests {
             .expect("Program statement is not expresssion statement");
 
         let ident = stmt
+            .expr
+            .as_ref()
+            .expect("expression statement has no expression")
             .as_any()
             .downcast_ref::<Identifier>()
             .expect("expression not identifier ");
@@ -317,6 +1240,693 @@ let x = 5 ; let y = 10 ; let foobar = 838383 ;
         }
     }
 
+    #[test]
+    fn test_integer_literal_expression() {
+        let tests = [
+            ("5;", Integer::Small(5)),
+            ("0xFF;", Integer::Small(255)),
+            ("0o755;", Integer::Small(493)),
+            ("0b1010;", Integer::Small(10)),
+            ("1_000_000;", Integer::Small(1_000_000)),
+            ("9223372036854775807;", Integer::Small(i64::MAX)),
+            (
+                "99999999999999999999;",
+                Integer::parse("99999999999999999999").unwrap(),
+            ),
+        ];
+
+        for (input, expected) in tests {
+            let l = Lexer::new(input);
+            let mut p = Parser::new(l);
+            let program = p.parse_program();
+            check_parser_errors(p);
+
+            let stmt = program.statements[0]
+                .as_any()
+                .downcast_ref::<ExpressionStatement>()
+                .expect("statement not ExpressionStatement");
+            let int = stmt
+                .expr
+                .as_ref()
+                .unwrap()
+                .as_any()
+                .downcast_ref::<IntegerLiteral>()
+                .unwrap_or_else(|| panic!("expression not IntegerLiteral. input={}", input));
+
+            assert_eq!(int.value, expected, "input={}", input);
+            assert_eq!(program.to_string(), &input[..input.len() - 1]);
+        }
+    }
+
+    #[test]
+    fn test_malformed_number_literals() {
+        let tests = [
+            ("0b102;", "invalid digit '2' in binary literal 0b102"),
+            ("0o78;", "invalid digit '8' in octal literal 0o78"),
+            ("0xFG;", "invalid digit 'G' in hexadecimal literal 0xFG"),
+            ("0x;", "missing digits in hexadecimal literal 0x"),
+            ("1_000_;", "trailing underscore in number literal 1_000_"),
+            ("0xFF_;", "trailing underscore in number literal 0xFF_"),
+            (
+                "let x = 1 + 3_.5;",
+                "trailing underscore in number literal 3_.5",
+            ),
+        ];
+
+        for (input, expected) in tests {
+            let l = Lexer::new(input);
+            let mut p = Parser::new(l);
+            p.parse_program();
+            let errs = p.errors();
+
+            assert!(!errs.is_empty(), "input={}", input);
+            assert_eq!(errs[0].msg, expected, "input={}", input);
+        }
+    }
+
+    #[test]
+    fn test_string_literal_expression() {
+        let input = r#""hello \"world\"";"#;
+        let l = Lexer::new(input);
+        let mut p = Parser::new(l);
+        let program = p.parse_program();
+        check_parser_errors(p);
+
+        let stmt = program.statements[0]
+            .as_any()
+            .downcast_ref::<ExpressionStatement>()
+            .expect("statement not ExpressionStatement");
+        let literal = stmt
+            .expr
+            .as_ref()
+            .unwrap()
+            .as_any()
+            .downcast_ref::<StringLiteral>()
+            .expect("expression not StringLiteral");
+
+        assert_eq!(literal.value, r#"hello "world""#);
+        assert_eq!(program.to_string(), r#""hello \"world\"""#);
+    }
+
+    #[test]
+    fn test_interpolated_string_expression() {
+        let input = r#"let msg = "Hello ${name}, you are ${age + 1} ${f("x")}";"#;
+        let l = Lexer::new(input);
+        let mut p = Parser::new(l);
+        let program = p.parse_program();
+        check_parser_errors(p);
+
+        let let_stmt = program.statements[0]
+            .as_any()
+            .downcast_ref::<LetStatement>()
+            .expect("statement not LetStatement");
+        let interpolated = let_stmt
+            .value
+            .as_ref()
+            .unwrap()
+            .as_any()
+            .downcast_ref::<InterpolatedString>()
+            .expect("expression not InterpolatedString");
+
+        let rendered: Vec<String> = interpolated
+            .parts
+            .iter()
+            .map(|part| match part {
+                InterpolationPart::Text(text) => format!("text({})", text),
+                InterpolationPart::Expr(expr) => format!("expr({})", expr),
+            })
+            .collect();
+        assert_eq!(
+            rendered,
+            vec![
+                "text(Hello )",
+                "expr(name)",
+                "text(, you are )",
+                "expr(age + 1)",
+                "text( )",
+                "expr(f(\"x\"))",
+            ]
+        );
+        assert_eq!(
+            program.to_string(),
+            r#"let msg = "Hello ${name}, you are ${age + 1} ${f("x")}";"#
+        );
+    }
+
+    #[test]
+    fn test_interpolation_errors() {
+        // spans of errors inside `${}` point into the string
+        let tests = [
+            (r#""a ${}";"#, "empty ${} in string", 0),
+            (
+                r#""a ${1 2}";"#,
+                "expected next token to be EOF , got int instead",
+                7,
+            ),
+            (
+                r#""a ${0b12}";"#,
+                "invalid digit '2' in binary literal 0b12",
+                5,
+            ),
+            (r#""a ${x";"#, "unterminated ${ in string", 0),
+        ];
+
+        for (input, expected, start) in tests {
+            let l = Lexer::new(input);
+            let mut p = Parser::new(l);
+            p.parse_program();
+            let errs = p.errors();
+
+            assert!(!errs.is_empty(), "input={}", input);
+            assert_eq!(errs[0].msg, expected, "input={}", input);
+            assert_eq!(errs[0].span.start, start, "input={}", input);
+        }
+    }
+
+    #[test]
+    fn test_array_literal() {
+        let input = "[1, 2 * 2, f(x)][0]; [];";
+        let l = Lexer::new(input);
+        let mut p = Parser::new(l);
+        let program = p.parse_program();
+        check_parser_errors(p);
+
+        assert_eq!(program.statements.len(), 2);
+        let stmt = program.statements[0]
+            .as_any()
+            .downcast_ref::<ExpressionStatement>()
+            .expect("statement not ExpressionStatement");
+        let index = stmt
+            .expr
+            .as_ref()
+            .unwrap()
+            .as_any()
+            .downcast_ref::<IndexExpression>()
+            .expect("expression not IndexExpression");
+        let array = index
+            .left
+            .as_any()
+            .downcast_ref::<ArrayLiteral>()
+            .expect("expression not ArrayLiteral");
+
+        assert_eq!(array.elements.len(), 3);
+        assert_eq!(array.elements[1].to_string(), "2 * 2");
+        assert_eq!(program.to_string(), "[1, 2 * 2, f(x)][0][]");
+    }
+
+    #[test]
+    fn test_macro_literal_parsing() {
+        let input = "macro(x, y) { x + y; }";
+        let l = Lexer::new(input);
+        let mut p = Parser::new(l);
+        let program = p.parse_program();
+        check_parser_errors(p);
+
+        assert_eq!(program.statements.len(), 1);
+        let stmt = program.statements[0]
+            .as_any()
+            .downcast_ref::<ExpressionStatement>()
+            .expect("statement not ExpressionStatement");
+        let macro_lit = stmt
+            .expr
+            .as_ref()
+            .unwrap()
+            .as_any()
+            .downcast_ref::<MacroLiteral>()
+            .expect("expression not MacroLiteral");
+
+        let params: Vec<&str> = macro_lit
+            .parameters
+            .iter()
+            .map(|p| p.value.as_str())
+            .collect();
+        assert_eq!(params, ["x", "y"]);
+        assert_eq!(macro_lit.body.statements.len(), 1);
+        assert_eq!(macro_lit.body.to_string(), "x + y");
+    }
+
+    #[test]
+    fn test_import_export_statements() {
+        let input = r#"import "lib/math.mk" as math;
+export let area = fn(r) { math.pi * r * r };
+math.sq(2).x;"#;
+        let l = Lexer::new(input);
+        let mut p = Parser::new(l);
+        let program = p.parse_program();
+        check_parser_errors(p);
+
+        assert_eq!(program.statements.len(), 3);
+        let import = program.statements[0]
+            .as_any()
+            .downcast_ref::<ImportStatement>()
+            .expect("statement not ImportStatement");
+        assert_eq!(import.path, "lib/math.mk");
+        assert_eq!(import.alias.value, "math");
+
+        let export = program.statements[1]
+            .as_any()
+            .downcast_ref::<ExportStatement>()
+            .expect("statement not ExportStatement");
+        assert_eq!(export.statement.name.value, "area");
+
+        let stmt = program.statements[2]
+            .as_any()
+            .downcast_ref::<ExpressionStatement>()
+            .expect("statement not ExpressionStatement");
+        let member = stmt
+            .expr
+            .as_ref()
+            .unwrap()
+            .as_any()
+            .downcast_ref::<MemberExpression>()
+            .expect("expression not MemberExpression");
+        assert_eq!(member.property.value, "x");
+        assert_eq!(member.object.to_string(), "math.sq(2)");
+
+        assert_eq!(
+            program.to_string(),
+            r#"import "lib/math.mk" as math;export let area = fn(r) { math.pi * r * r };math.sq(2).x"#
+        );
+    }
+
+    #[test]
+    fn test_import_export_errors() {
+        let tests = [
+            (
+                r#"fn() { import "a.mk" as a; };"#,
+                "import is only allowed at the top level",
+            ),
+            (
+                "if (x) { export let y = 1; }",
+                "export is only allowed at the top level",
+            ),
+            (
+                "export x;",
+                "expected next token to be LET , got Ident instead",
+            ),
+            (
+                "import a as b;",
+                "expected next token to be string , got Ident instead",
+            ),
+        ];
+
+        for (input, expected) in tests {
+            let l = Lexer::new(input);
+            let mut p = Parser::new(l);
+            p.parse_program();
+            let errs = p.errors();
+
+            assert!(!errs.is_empty(), "input={}", input);
+            assert_eq!(errs[0].msg, expected, "input={}", input);
+        }
+    }
+
+    #[test]
+    fn test_float_literal_expression() {
+        let tests = [
+            ("2.75;", 2.75),
+            ("1e-3;", 0.001),
+            ("2.5E2;", 250.0),
+            ("1_000.5;", 1000.5),
+        ];
+
+        for (input, expected) in tests {
+            let l = Lexer::new(input);
+            let mut p = Parser::new(l);
+            let program = p.parse_program();
+            check_parser_errors(p);
+
+            let stmt = program.statements[0]
+                .as_any()
+                .downcast_ref::<ExpressionStatement>()
+                .expect("statement not ExpressionStatement");
+            let float = stmt
+                .expr
+                .as_ref()
+                .unwrap()
+                .as_any()
+                .downcast_ref::<FloatLiteral>()
+                .unwrap_or_else(|| panic!("expression not FloatLiteral. input={}", input));
+
+            assert_eq!(float.value, expected, "input={}", input);
+            assert_eq!(program.to_string(), &input[..input.len() - 1]);
+        }
+
+        for input in ["1e400;", "-1.5e309;"] {
+            let mut p = Parser::new(Lexer::new(input));
+            p.parse_program();
+            let errs = p.errors();
+            assert!(!errs.is_empty(), "input={}", input);
+            assert!(matches!(errs[0].kind, ParseErrorKind::InvalidLiteral));
+            let literal = input.trim_start_matches('-').trim_end_matches(';');
+            assert_eq!(
+                errs[0].msg,
+                format!("{} is out of range for a float", literal)
+            );
+        }
+    }
+
+    #[test]
+    fn test_operator_precedence_parsing() {
+        // The parenthesized printer shows the grouping the parser chose.
+        // Display only brackets where needed, so it must read back to the
+        // same grouping.
+        let tests = [
+            ("-a * b", "((-a) * b)"),
+            ("!-a", "(!(-a))"),
+            ("a + b * c", "(a + (b * c))"),
+            ("a * b + c", "((a * b) + c)"),
+            ("a - b - c", "((a - b) - c)"),
+            ("a - (b - c)", "(a - (b - c))"),
+            ("(a + b) * c", "((a + b) * c)"),
+            ("-(5 + 5)", "(-(5 + 5))"),
+            ("5 < 4 != 3 > 4", "((5 < 4) != (3 > 4))"),
+            ("a || b && c", "(a || (b && c))"),
+            ("(a || b) && c", "((a || b) && c)"),
+            ("a && b || c && d", "((a && b) || (c && d))"),
+            ("a == b && c != d", "((a == b) && (c != d))"),
+            ("!a && (b || c)", "((!a) && (b || c))"),
+            ("x = a || b", "(x = (a || b))"),
+            ("a <= b == c >= d", "((a <= b) == (c >= d))"),
+            ("a % b * c", "((a % b) * c)"),
+            ("a * (b % c)", "(a * (b % c))"),
+            ("a | b ^ c & d", "(a | (b ^ (c & d)))"),
+            ("(a | b) & c", "((a | b) & c)"),
+            ("a & b == c", "((a & b) == c)"),
+            ("1 << 2 + 3", "(1 << (2 + 3))"),
+            ("(1 << 2) + 3", "((1 << 2) + 3)"),
+            ("a ** b ** c", "(a ** (b ** c))"),
+            ("(a ** b) ** c", "((a ** b) ** c)"),
+            ("-a ** b", "(-(a ** b))"),
+            ("(-a) ** b", "((-a) ** b)"),
+            ("a ** -b", "(a ** (-b))"),
+            ("a * b ** c", "(a * (b ** c))"),
+            ("(5 < 4) == true", "((5 < 4) == true)"),
+            ("a + add(b * c) + d", "((a + add((b * c))) + d)"),
+            (
+                "add(a, b, 1, 2 * 3, 4 + 5, add(6, 7 * 8))",
+                "add(a, b, 1, (2 * 3), (4 + 5), add(6, (7 * 8)))",
+            ),
+        ];
+
+        for (input, expected) in tests {
+            let l = Lexer::new(input);
+            let mut p = Parser::new(l);
+            let program = p.parse_program();
+            check_parser_errors(p);
+            assert_eq!(parenthesize(&program), expected, "input={}", input);
+
+            let l = Lexer::new(&program.to_string());
+            let mut p = Parser::new(l);
+            let reparsed = p.parse_program();
+            check_parser_errors(p);
+            assert_eq!(parenthesize(&reparsed), expected, "display={}", program);
+        }
+    }
+
+    #[test]
+    fn test_throw_statement() {
+        let input = "throw 1 + 2;";
+        let l = Lexer::new(input);
+        let mut p = Parser::new(l);
+        let program = p.parse_program();
+        check_parser_errors(p);
+
+        assert_eq!(program.statements.len(), 1);
+        let throw_stmt = program.statements[0]
+            .as_any()
+            .downcast_ref::<ThrowStatement>()
+            .expect("statement not ThrowStatement");
+        assert_eq!(throw_stmt.token_literal(), "throw");
+        assert_eq!(throw_stmt.to_string(), "throw 1 + 2;");
+    }
+
+    #[test]
+    fn test_try_statement() {
+        let tests = [
+            (
+                "try { throw x; } catch (e) { e; } finally { done(); }",
+                Some("e"),
+                true,
+                true,
+            ),
+            ("try { f(); } catch (err) { 1; }", Some("err"), true, false),
+            ("try { f(); } catch { 1; }", None, true, false),
+            ("try { f(); } finally { g(); }", None, false, true),
+        ];
+
+        for (input, param, has_catch, has_finally) in tests {
+            let l = Lexer::new(input);
+            let mut p = Parser::new(l);
+            let program = p.parse_program();
+            check_parser_errors(p);
+
+            assert_eq!(program.statements.len(), 1, "input={}", input);
+            let try_stmt = program.statements[0]
+                .as_any()
+                .downcast_ref::<TryStatement>()
+                .unwrap_or_else(|| panic!("statement not TryStatement. input={}", input));
+
+            assert_eq!(try_stmt.block.statements.len(), 1);
+            assert_eq!(
+                try_stmt.catch_param.as_ref().map(|p| p.value.as_str()),
+                param
+            );
+            assert_eq!(try_stmt.catch_block.is_some(), has_catch);
+            assert_eq!(try_stmt.finally_block.is_some(), has_finally);
+        }
+    }
+
+    #[test]
+    fn test_try_requires_catch_or_finally() {
+        let l = Lexer::new("try { f(); } g();");
+        let mut p = Parser::new(l);
+        p.parse_program();
+        let errs = p.errors();
+
+        assert_eq!(errs.len(), 1);
+        assert_eq!(
+            errs[0].msg,
+            "expected next token to be catch , got Ident instead"
+        );
+    }
+
+    #[test]
+    fn test_while_statement() {
+        let input = "while (x < 10) { let x = x + 1; if (x == 5) { continue; } break; }";
+        let l = Lexer::new(input);
+        let mut p = Parser::new(l);
+        let program = p.parse_program();
+        check_parser_errors(p);
+
+        assert_eq!(program.statements.len(), 1);
+        let while_stmt = program.statements[0]
+            .as_any()
+            .downcast_ref::<WhileStatement>()
+            .expect("statement not WhileStatement");
+
+        assert_eq!(while_stmt.condition.to_string(), "x < 10");
+        assert_eq!(while_stmt.body.statements.len(), 3);
+        assert!(while_stmt.body.statements[2]
+            .as_any()
+            .is::<BreakStatement>());
+    }
+
+    #[test]
+    fn test_loop_and_try_take_a_semicolon() {
+        let input = "while (x) { 1 }; for (a in b) { a; }; try { f(); } finally { g(); }; h();";
+        let l = Lexer::new(input);
+        let mut p = Parser::new(l);
+        let program = p.parse_program();
+        check_parser_errors(p);
+
+        assert_eq!(program.statements.len(), 4);
+        assert!(program.statements[0].as_any().is::<WhileStatement>());
+        assert!(program.statements[1].as_any().is::<ForStatement>());
+        assert!(program.statements[2].as_any().is::<TryStatement>());
+    }
+
+    #[test]
+    fn test_for_statement() {
+        let input = "for (item in items) { if (item) { continue; } }";
+        let l = Lexer::new(input);
+        let mut p = Parser::new(l);
+        let program = p.parse_program();
+        check_parser_errors(p);
+
+        assert_eq!(program.statements.len(), 1);
+        let for_stmt = program.statements[0]
+            .as_any()
+            .downcast_ref::<ForStatement>()
+            .expect("statement not ForStatement");
+
+        assert_eq!(for_stmt.variable.value, "item");
+        assert_eq!(for_stmt.iterable.to_string(), "items");
+        assert_eq!(for_stmt.body.statements.len(), 1);
+        assert_eq!(
+            program.to_string(),
+            "for (item in items) { if (item) { continue; } }"
+        );
+    }
+
+    #[test]
+    fn test_break_continue_outside_loop() {
+        let tests = [
+            ("break;", "break outside of a loop"),
+            ("continue;", "continue outside of a loop"),
+            (
+                "while (true) { let f = fn() { break; }; }",
+                "break outside of a loop",
+            ),
+        ];
+
+        for (input, expected) in tests {
+            let l = Lexer::new(input);
+            let mut p = Parser::new(l);
+            p.parse_program();
+            let errs = p.errors();
+
+            assert!(!errs.is_empty(), "input={}", input);
+            assert_eq!(errs[0].msg, expected, "input={}", input);
+        }
+    }
+
+    #[test]
+    fn test_assign_expressions() {
+        let tests = [
+            ("x = 5;", "x", "=", "5"),
+            ("x += y * 2;", "x", "+=", "y * 2"),
+            ("x -= 1;", "x", "-=", "1"),
+            ("x *= 3;", "x", "*=", "3"),
+            ("x /= 4;", "x", "/=", "4"),
+            ("arr[i + 1] = v;", "arr[i + 1]", "=", "v"),
+            ("h[key] += 1;", "h[key]", "+=", "1"),
+            ("a = b = c;", "a", "=", "b = c"),
+        ];
+
+        for (input, target, operator, value) in tests {
+            let l = Lexer::new(input);
+            let mut p = Parser::new(l);
+            let program = p.parse_program();
+            check_parser_errors(p);
+
+            assert_eq!(program.statements.len(), 1, "input={}", input);
+            let stmt = program.statements[0]
+                .as_any()
+                .downcast_ref::<ExpressionStatement>()
+                .expect("statement not ExpressionStatement");
+            let assign = stmt
+                .expr
+                .as_ref()
+                .unwrap()
+                .as_any()
+                .downcast_ref::<AssignExpression>()
+                .unwrap_or_else(|| panic!("expression not AssignExpression. input={}", input));
+
+            assert_eq!(assign.target.to_string(), target, "input={}", input);
+            assert_eq!(assign.operator, operator, "input={}", input);
+            assert_eq!(assign.value.to_string(), value, "input={}", input);
+        }
+    }
+
+    #[test]
+    fn test_invalid_assign_target() {
+        let tests = [
+            ("5 = x;", "cannot assign to 5"),
+            ("f(x) += 1;", "cannot assign to f(x)"),
+        ];
+
+        for (input, expected) in tests {
+            let l = Lexer::new(input);
+            let mut p = Parser::new(l);
+            p.parse_program();
+            let errs = p.errors();
+
+            assert!(!errs.is_empty(), "input={}", input);
+            assert_eq!(errs[0].msg, expected, "input={}", input);
+        }
+    }
+
+    #[test]
+    fn test_type_annotations() {
+        let tests = [
+            ("let x: int = 5;", "let x: int = 5;"),
+            (
+                "let f = fn(a: int, b, c: [string]) -> bool { true };",
+                "let f = fn(a: int, b, c: [string]) -> bool { true };",
+            ),
+            (
+                "let apply: fn(fn(T) -> U, T) -> U = fn(f, x) { f(x) };",
+                "let apply: fn(fn(T) -> U, T) -> U = fn(f, x) { f(x) };",
+            ),
+            (
+                "fn() -> fn() -> null { fn() {} }",
+                "fn() -> fn() -> null { fn() {  } }",
+            ),
+        ];
+
+        for (input, expected) in tests {
+            let l = Lexer::new(input);
+            let mut p = Parser::new(l);
+            let program = p.parse_program();
+            check_parser_errors(p);
+            assert_eq!(program.to_string(), expected);
+        }
+
+        let l = Lexer::new("fn(a: int, b) -> [int] { [a] }");
+        let mut p = Parser::new(l);
+        let program = p.parse_program();
+        check_parser_errors(p);
+        let stmt = program.statements[0]
+            .as_any()
+            .downcast_ref::<ExpressionStatement>()
+            .unwrap();
+        let function = stmt
+            .expr
+            .as_ref()
+            .unwrap()
+            .as_any()
+            .downcast_ref::<FunctionLiteral>()
+            .unwrap();
+        let types: Vec<Option<String>> = function
+            .parameter_types
+            .iter()
+            .map(|t| t.as_ref().map(|t| t.to_string()))
+            .collect();
+        assert_eq!(types, vec![Some("int".to_string()), None]);
+        assert_eq!(function.return_type.as_ref().unwrap().to_string(), "[int]");
+    }
+
+    #[test]
+    fn test_type_annotation_errors() {
+        let tests = [
+            ("let x: = 5;", "expected a type, got = instead"),
+            (
+                "let x: [int = 5;",
+                "expected next token to be ] , got = instead",
+            ),
+            (
+                "let f: fn(int) = 5;",
+                "expected next token to be -> , got = instead",
+            ),
+            ("fn(a) -> { a }", "expected a type, got { instead"),
+            (
+                "let m = macro(a: int) { a };",
+                "macro parameters take no type annotations",
+            ),
+        ];
+
+        for (input, expected) in tests {
+            let l = Lexer::new(input);
+            let mut p = Parser::new(l);
+            p.parse_program();
+            let errs = p.errors();
+
+            assert!(!errs.is_empty(), "input={}", input);
+            assert_eq!(errs[0].msg, expected, "input={}", input);
+        }
+    }
+
     fn check_parser_errors(p: Parser) {
         let errs = p.errors();
         if errs.is_empty() {
@@ -0,0 +1,110 @@
+let each = fn(arr, f) {
+    for (x in arr) {
+        f(x);
+    }
+    arr
+};
+
+let map = fn(arr, f) {
+    let out = [];
+    for (x in arr) {
+        out = push(out, f(x));
+    }
+    out
+};
+
+let filter = fn(arr, pred) {
+    let out = [];
+    for (x in arr) {
+        if (pred(x)) {
+            out = push(out, x);
+        }
+    }
+    out
+};
+
+let reduce = fn(arr, initial, f) {
+    let acc = initial;
+    for (x in arr) {
+        acc = f(acc, x);
+    }
+    acc
+};
+
+let range = fn(start, end) {
+    let out = [];
+    let i = start;
+    while (i < end) {
+        out = push(out, i);
+        i += 1;
+    }
+    out
+};
+
+let zip = fn(a, b) {
+    let out = [];
+    let i = 0;
+    while (i < len(a) && i < len(b)) {
+        out = push(out, [a[i], b[i]]);
+        i += 1;
+    }
+    out
+};
+
+let any = fn(arr, pred) {
+    for (x in arr) {
+        if (pred(x)) {
+            return true;
+        }
+    }
+    false
+};
+
+let all = fn(arr, pred) {
+    for (x in arr) {
+        if (!pred(x)) {
+            return false;
+        }
+    }
+    true
+};
+
+let sort_by = fn(arr, key) {
+    let n = len(arr);
+    if (n < 2) {
+        return arr;
+    }
+    let slice = fn(start, end) {
+        let out = [];
+        let i = start;
+        while (i < end) {
+            out = push(out, arr[i]);
+            i += 1;
+        }
+        out
+    };
+    let left = sort_by(slice(0, n / 2), key);
+    let right = sort_by(slice(n / 2, n), key);
+
+    let out = [];
+    let i = 0;
+    let j = 0;
+    while (i < len(left) && j < len(right)) {
+        if (key(right[j]) < key(left[i])) {
+            out = push(out, right[j]);
+            j += 1;
+        } else {
+            out = push(out, left[i]);
+            i += 1;
+        }
+    }
+    while (i < len(left)) {
+        out = push(out, left[i]);
+        i += 1;
+    }
+    while (j < len(right)) {
+        out = push(out, right[j]);
+        j += 1;
+    }
+    out
+};
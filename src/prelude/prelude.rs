@@ -0,0 +1,65 @@
+use std::rc::Rc;
+
+use crate::ast::ast::Program;
+use crate::lexer::lexer::{Lexer, LexerTrait};
+use crate::parser::parser::Parser;
+
+// Collection helpers written in Monkey and bound before user code runs:
+// each, map, filter, reduce, range, zip, any, all and sort_by, and no
+// other names. They rely only on the `len` builtin and a `push` that
+// returns a new array. range(start, end) excludes `end`, zip stops at the
+// shorter array and sort_by is a stable merge sort on key(x). The tools
+// that know about it (check, lint and lsp) leave it out with --no-prelude.
+pub const SOURCE: &str = include_str!("prelude.mk");
+
+thread_local! {
+    static PROGRAM: Rc<Program> = parse();
+}
+
+// The parsed prelude. It is parsed once per thread and shared after that.
+pub fn program() -> Rc<Program> {
+    PROGRAM.with(Rc::clone)
+}
+
+fn parse() -> Rc<Program> {
+    let mut p = Parser::new(Lexer::new(SOURCE));
+    let program = p.parse_program();
+    let errors = p.errors();
+    // the source is compiled in, so this can only fail on a broken build
+    assert!(errors.is_empty(), "prelude does not parse: {:?}", errors);
+    Rc::new(program)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::ast::LetStatement;
+
+    #[test]
+    fn test_prelude_defines_collection_helpers() {
+        let program = program();
+        let names: Vec<&str> = program
+            .statements
+            .iter()
+            .map(|stmt| {
+                stmt.as_any()
+                    .downcast_ref::<LetStatement>()
+                    .expect("prelude statement not LetStatement")
+                    .name
+                    .value
+                    .as_str()
+            })
+            .collect();
+
+        // helpers stay inside the functions that use them
+        assert_eq!(
+            names,
+            ["each", "map", "filter", "reduce", "range", "zip", "any", "all", "sort_by"]
+        );
+    }
+
+    #[test]
+    fn test_prelude_is_parsed_once() {
+        assert!(Rc::ptr_eq(&program(), &program()));
+    }
+}
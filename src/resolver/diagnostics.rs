@@ -0,0 +1,70 @@
+use std::fmt::Display;
+
+use crate::lexer::token::Span;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticKind {
+    UndefinedName,
+    UnusedBinding,
+    ShadowedBinding,
+    UnreachableCode,
+}
+
+impl Display for DiagnosticKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            DiagnosticKind::UndefinedName => write!(f, "Undefined Name"),
+            DiagnosticKind::UnusedBinding => write!(f, "Unused Binding"),
+            DiagnosticKind::ShadowedBinding => write!(f, "Shadowed Binding"),
+            DiagnosticKind::UnreachableCode => write!(f, "Unreachable Code"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub kind: DiagnosticKind,
+    pub severity: Severity,
+    pub msg: String,
+    // the identifier or statement the diagnostic is about
+    pub span: Span,
+}
+
+impl Diagnostic {
+    pub fn new(kind: DiagnosticKind, msg: String, span: Span) -> Self {
+        // only a name that cannot be found is certain to fail at runtime
+        let severity = match kind {
+            DiagnosticKind::UndefinedName => Severity::Error,
+            _ => Severity::Warning,
+        };
+        Diagnostic {
+            kind,
+            severity,
+            msg,
+            span,
+        }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.kind, self.msg)
+    }
+}
+
+pub type Diagnostics = Vec<Diagnostic>;
@@ -0,0 +1,646 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::ast::ast::*;
+use crate::ast::visit::{self, Visitor};
+use crate::builtins::signatures;
+use crate::lexer::token::Span;
+use crate::prelude::prelude;
+use crate::resolver::diagnostics::{Diagnostic, DiagnosticKind, Diagnostics};
+
+// Static name resolution. Every identifier, bound or used, gets the slot
+// its value lives in, so a runtime can index arrays instead of looking
+// names up in environments:
+//
+// - Global: bindings outside any function, the prelude's first.
+// - Local: bindings inside a function, parameters first. Blocks inside the
+//   function get fresh slots in the same frame rather than a frame each.
+// - Free: a binding of an enclosing function, captured when the closure
+//   is created; the function's Frame says where each one comes from.
+// - Builtin: an index into builtins::signatures::BUILTINS.
+//
+// Along the way it reports names that are never defined, bindings that are
+// never used or that shadow another one, and statements that can never run.
+// Names starting with `_` are exempt from the unused and shadowing checks.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Slot {
+    Global(usize),
+    Local(usize),
+    Free(usize),
+    Builtin(usize),
+}
+
+// What a function literal needs at runtime: room for its locals, and for
+// each of its free variables the slot to capture it from, as seen from the
+// function that creates the closure.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Frame {
+    pub locals: usize,
+    pub captures: Vec<Slot>,
+}
+
+// A node of the resolved program, by address. Spans can't tell nodes
+// apart: macro expansion and the optimizer copy them, and the prelude's
+// overlap the user's. So a Resolution only answers for the nodes of the
+// program it was made from, while that program is alive.
+type NodeId = *const ();
+
+fn id<T>(node: &T) -> NodeId {
+    (node as *const T).cast()
+}
+
+#[derive(Debug, Default)]
+pub struct Resolution {
+    // slots by identifier
+    slots: HashMap<NodeId, Slot>,
+    // frames by function or macro literal
+    frames: HashMap<NodeId, Frame>,
+    pub globals: usize,
+    pub diagnostics: Diagnostics,
+}
+
+impl Resolution {
+    pub fn slot(&self, ident: &Identifier) -> Option<Slot> {
+        self.slots.get(&id(ident)).copied()
+    }
+
+    pub fn function_frame(&self, function: &FunctionLiteral) -> Option<&Frame> {
+        self.frames.get(&id(function))
+    }
+
+    pub fn macro_frame(&self, function: &MacroLiteral) -> Option<&Frame> {
+        self.frames.get(&id(function))
+    }
+}
+
+pub fn resolve(program: &Program) -> Resolution {
+    resolve_with_prelude(program, true)
+}
+
+// Without the prelude (--no-prelude) its names are undefined like any other.
+pub fn resolve_with_prelude(program: &Program, prelude: bool) -> Resolution {
+    let mut resolver = Resolver::new(prelude);
+    resolver.visit_program(program);
+    resolver.finish()
+}
+
+struct Binding {
+    name: String,
+    span: Span,
+    slot: Slot,
+    used: bool,
+    // prelude bindings and exports are used from elsewhere
+    exempt: bool,
+}
+
+struct Function {
+    // innermost last; each maps names to indexes into Resolver::bindings
+    scopes: Vec<HashMap<String, usize>>,
+    frame: Frame,
+    // binding index -> free slot, for bindings of enclosing functions
+    free: HashMap<usize, usize>,
+    // the function or macro literal, None for the top level
+    literal: Option<NodeId>,
+}
+
+impl Function {
+    fn new(literal: Option<NodeId>) -> Self {
+        Function {
+            scopes: vec![HashMap::new()],
+            frame: Frame::default(),
+            free: HashMap::new(),
+            literal,
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<usize> {
+        self.scopes.iter().rev().find_map(|s| s.get(name).copied())
+    }
+}
+
+struct Resolver {
+    bindings: Vec<Binding>,
+    // the top level first, then each function being resolved
+    functions: Vec<Function>,
+    resolution: Resolution,
+    // how many `quote(...)` calls enclose the current node, minus the
+    // `unquote(...)` calls inside them; quoted code is not resolved
+    quoting: usize,
+}
+
+impl Resolver {
+    fn new(prelude: bool) -> Self {
+        let mut resolver = Resolver {
+            bindings: Vec::new(),
+            functions: vec![Function::new(None)],
+            resolution: Resolution::default(),
+            quoting: 0,
+        };
+        if !prelude {
+            return resolver;
+        }
+        for stmt in &prelude::program().statements {
+            if let Some(stmt) = stmt.as_any().downcast_ref::<LetStatement>() {
+                let index = resolver.declare(&stmt.name);
+                resolver.bindings[index].exempt = true;
+            }
+        }
+        // the prelude's nodes are gone once it is declared
+        resolver.resolution.slots.clear();
+        resolver
+    }
+
+    fn finish(mut self) -> Resolution {
+        let globals = self.functions.pop().expect("top level").frame.locals;
+        for b in &self.bindings {
+            if !b.used && !b.exempt && !b.name.starts_with('_') {
+                self.resolution.diagnostics.push(Diagnostic::new(
+                    DiagnosticKind::UnusedBinding,
+                    format!("{} is never used", b.name),
+                    b.span,
+                ));
+            }
+        }
+        self.resolution.globals = globals;
+        self.resolution
+            .diagnostics
+            .sort_by_key(|d| (d.span.start, d.kind as u8));
+        self.resolution
+    }
+
+    fn current(&mut self) -> &mut Function {
+        self.functions.last_mut().expect("top level")
+    }
+
+    // Binds the identifier in the innermost scope and gives it a new slot.
+    fn declare(&mut self, ident: &Identifier) -> usize {
+        let name = &ident.value;
+        if !name.starts_with('_') {
+            if let Some(earlier) = self.visible(name) {
+                if !self.bindings[earlier].exempt {
+                    let span = self.bindings[earlier].span;
+                    self.resolution.diagnostics.push(Diagnostic::new(
+                        DiagnosticKind::ShadowedBinding,
+                        format!("{} shadows the binding at {}", name, span),
+                        ident.token.span,
+                    ));
+                }
+            }
+        }
+
+        let top_level = self.functions.len() == 1;
+        let function = self.current();
+        let slot = if top_level {
+            Slot::Global(function.frame.locals)
+        } else {
+            Slot::Local(function.frame.locals)
+        };
+        function.frame.locals += 1;
+        let index = self.bindings.len();
+        self.current()
+            .scopes
+            .last_mut()
+            .expect("open scope")
+            .insert(name.clone(), index);
+        self.bindings.push(Binding {
+            name: name.clone(),
+            span: ident.token.span,
+            slot,
+            used: false,
+            exempt: false,
+        });
+        self.resolution.slots.insert(id(ident), slot);
+        index
+    }
+
+    fn visible(&self, name: &str) -> Option<usize> {
+        self.functions.iter().rev().find_map(|f| f.lookup(name))
+    }
+
+    // The slot of a binding as seen from function `depth`, capturing it
+    // into every function between its own and that one.
+    fn slot_from(&mut self, depth: usize, binding: usize) -> Slot {
+        if self.functions[depth].lookup(&self.bindings[binding].name) == Some(binding) {
+            return self.bindings[binding].slot;
+        }
+        if let Slot::Global(_) = self.bindings[binding].slot {
+            return self.bindings[binding].slot;
+        }
+        if let Some(&free) = self.functions[depth].free.get(&binding) {
+            return Slot::Free(free);
+        }
+        let outer = self.slot_from(depth - 1, binding);
+        let function = &mut self.functions[depth];
+        let free = function.frame.captures.len();
+        function.frame.captures.push(outer);
+        function.free.insert(binding, free);
+        Slot::Free(free)
+    }
+
+    fn scoped(&mut self, f: impl FnOnce(&mut Self)) {
+        self.current().scopes.push(HashMap::new());
+        f(self);
+        self.current().scopes.pop();
+    }
+
+    fn function(&mut self, literal: NodeId, parameters: &[Rc<Identifier>], body: &BlockStatement) {
+        self.functions.push(Function::new(Some(literal)));
+        for param in parameters {
+            self.declare(param);
+        }
+        self.visit_block_statement(body);
+        let function = self.functions.pop().expect("function scope");
+        let literal = function.literal.expect("function literal");
+        self.resolution.frames.insert(literal, function.frame);
+    }
+
+    // Resolves the statements of a block in order, reporting the first one
+    // after a statement that always jumps away.
+    fn statements(&mut self, statements: &[Rc<dyn Statement>]) {
+        let mut jumped = false;
+        let mut reported = false;
+        for stmt in statements {
+            if jumped && !reported {
+                self.resolution.diagnostics.push(Diagnostic::new(
+                    DiagnosticKind::UnreachableCode,
+                    "this code never runs".to_string(),
+                    statement_span(stmt.as_ref()),
+                ));
+                reported = true;
+            }
+            self.visit_statement(stmt.as_ref());
+            let any = stmt.as_any();
+            jumped |= any.is::<ReturnStatements>()
+                || any.is::<ThrowStatement>()
+                || any.is::<BreakStatement>()
+                || any.is::<ContinueStatement>();
+        }
+    }
+}
+
+fn statement_span(stmt: &dyn Statement) -> Span {
+    crate::ast::printer::token_of(stmt)
+        .map(|t| t.span)
+        .unwrap_or_default()
+}
+
+impl Visitor for Resolver {
+    fn visit_program(&mut self, program: &Program) {
+        self.statements(&program.statements);
+    }
+
+    fn visit_block_statement(&mut self, block: &BlockStatement) {
+        self.scoped(|r| r.statements(&block.statements));
+    }
+
+    fn visit_let_statement(&mut self, stmt: &LetStatement) {
+        // a function may call itself; any other value is evaluated before
+        // its name is bound
+        let recursive = stmt
+            .value
+            .as_ref()
+            .is_some_and(|v| v.as_any().is::<FunctionLiteral>() || v.as_any().is::<MacroLiteral>());
+        if recursive {
+            self.declare(&stmt.name);
+            visit::walk_let_statement(self, stmt);
+        } else {
+            visit::walk_let_statement(self, stmt);
+            self.declare(&stmt.name);
+        }
+    }
+
+    fn visit_export_statement(&mut self, stmt: &ExportStatement) {
+        visit::walk_export_statement(self, stmt);
+        if let Some(binding) = self.current().lookup(&stmt.statement.name.value) {
+            self.bindings[binding].exempt = true;
+        }
+    }
+
+    fn visit_import_statement(&mut self, stmt: &ImportStatement) {
+        self.declare(&stmt.alias);
+    }
+
+    fn visit_for_statement(&mut self, stmt: &ForStatement) {
+        self.visit_expression(stmt.iterable.as_ref());
+        self.scoped(|r| {
+            r.declare(&stmt.variable);
+            r.visit_block_statement(&stmt.body);
+        });
+    }
+
+    fn visit_try_statement(&mut self, stmt: &TryStatement) {
+        self.visit_block_statement(&stmt.block);
+        if let Some(block) = &stmt.catch_block {
+            self.scoped(|r| {
+                if let Some(param) = &stmt.catch_param {
+                    r.declare(param);
+                }
+                r.visit_block_statement(block);
+            });
+        }
+        if let Some(block) = &stmt.finally_block {
+            self.visit_block_statement(block);
+        }
+    }
+
+    fn visit_function_literal(&mut self, expr: &FunctionLiteral) {
+        self.function(id(expr), &expr.parameters, &expr.body);
+    }
+
+    fn visit_macro_literal(&mut self, expr: &MacroLiteral) {
+        self.function(id(expr), &expr.parameters, &expr.body);
+    }
+
+    fn visit_call_expression(&mut self, expr: &CallExpression) {
+        let callee = expr
+            .function
+            .as_any()
+            .downcast_ref::<Identifier>()
+            .map(|i| i.value.as_str());
+        match callee {
+            // quote and unquote are expanded away before anything runs
+            Some("quote") => {
+                self.quoting += 1;
+                visit::walk_call_expression(self, expr);
+                self.quoting -= 1;
+            }
+            Some("unquote") if self.quoting > 0 => {
+                let quoting = std::mem::replace(&mut self.quoting, 0);
+                for arg in &expr.arguments {
+                    self.visit_expression(arg.as_ref());
+                }
+                self.quoting = quoting;
+            }
+            _ => visit::walk_call_expression(self, expr),
+        }
+    }
+
+    fn visit_identifier(&mut self, ident: &Identifier) {
+        if self.quoting > 0 || ident.value == "quote" || ident.value == "unquote" {
+            return;
+        }
+        let slot = match self.visible(&ident.value) {
+            Some(binding) => {
+                self.bindings[binding].used = true;
+                let depth = self.functions.len() - 1;
+                Some(self.slot_from(depth, binding))
+            }
+            None => signatures::BUILTINS
+                .iter()
+                .position(|s| s.name == ident.value)
+                .map(Slot::Builtin),
+        };
+        match slot {
+            Some(slot) => {
+                self.resolution.slots.insert(id(ident), slot);
+            }
+            None => self.resolution.diagnostics.push(Diagnostic::new(
+                DiagnosticKind::UndefinedName,
+                format!("{} is not defined", ident.value),
+                ident.token.span,
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parser::parse;
+    use crate::resolver::diagnostics::Severity;
+
+    fn diagnostics(input: &str) -> Vec<String> {
+        resolve(&parse(input))
+            .diagnostics
+            .iter()
+            .map(|d| format!("{} {}: {}", d.span, d.severity, d))
+            .collect()
+    }
+
+    // Every identifier visited, with the slot it resolved to.
+    struct Collect<'a>(&'a Resolution, Vec<(String, Option<Slot>)>);
+
+    impl Visitor for Collect<'_> {
+        fn visit_identifier(&mut self, ident: &Identifier) {
+            self.1.push((ident.value.clone(), self.0.slot(ident)));
+        }
+        fn visit_let_statement(&mut self, stmt: &LetStatement) {
+            self.1
+                .push((stmt.name.value.clone(), self.0.slot(&stmt.name)));
+            visit::walk_let_statement(self, stmt);
+        }
+    }
+
+    fn slots(input: &str) -> Vec<(String, Option<Slot>)> {
+        let program = parse(input);
+        let resolution = resolve(&program);
+        let mut collect = Collect(&resolution, Vec::new());
+        collect.visit_program(&program);
+        collect.1
+    }
+
+    #[test]
+    fn test_clean_program() {
+        let input = "let total = reduce([1, 2], 0, fn(acc, x) { acc + x });
+let count = fn(n) { if (n > 0) { count(n - 1) } else { 0 } };
+for (x in [total]) { puts(count(x)); }
+let m = macro(a) { quote(unquote(a) + later) };
+m(1);";
+        assert_eq!(diagnostics(input), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let input = "let x = 1;
+let f = fn(x, _unused, y) {
+    return x + z;
+    puts(y);
+    puts(y);
+};
+let g = fn() { while (true) { break; puts(1); } };
+f(1, 2, 3);
+";
+        assert_eq!(
+            diagnostics(input),
+            vec![
+                "1:5 warning: Unused Binding: x is never used",
+                "2:12 warning: Shadowed Binding: x shadows the binding at 1:5",
+                "3:16 error: Undefined Name: z is not defined",
+                "4:5 warning: Unreachable Code: this code never runs",
+                "7:5 warning: Unused Binding: g is never used",
+                "7:38 warning: Unreachable Code: this code never runs",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scopes() {
+        // block bindings end with their block, a for variable with the loop
+        assert_eq!(
+            diagnostics("if (true) { let a = 1; puts(a); } puts(a);"),
+            vec!["1:40 error: Undefined Name: a is not defined"]
+        );
+        assert_eq!(
+            diagnostics("for (i in [1]) { puts(i); } puts(i);"),
+            vec!["1:34 error: Undefined Name: i is not defined"]
+        );
+        // a let's value sees the outer binding, not the one being made
+        assert_eq!(
+            diagnostics("let v = 1; if (true) { let v = v + 1; puts(v); }"),
+            vec!["1:28 warning: Shadowed Binding: v shadows the binding at 1:5"]
+        );
+        assert_eq!(
+            diagnostics("try { throw 1; } catch (e) { puts(e); } export let api = 1;"),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            diagnostics("let map = 1; puts(map); import \"m.mk\" as m;"),
+            vec!["1:42 warning: Unused Binding: m is never used"]
+        );
+    }
+
+    #[test]
+    fn test_slots() {
+        let input = "let a = 1;
+let make = fn(b) {
+    let c = b;
+    fn(d) { a + b + c + d + len(d) }
+};
+make(a);";
+        let prelude_globals = resolve(&parse("")).globals;
+        let g = |i| Some(Slot::Global(prelude_globals + i));
+        assert_eq!(
+            slots(input),
+            vec![
+                ("a".to_string(), g(0)),
+                ("make".to_string(), g(1)),
+                ("c".to_string(), Some(Slot::Local(1))),
+                ("b".to_string(), Some(Slot::Local(0))),
+                ("a".to_string(), g(0)),
+                ("b".to_string(), Some(Slot::Free(0))),
+                ("c".to_string(), Some(Slot::Free(1))),
+                ("d".to_string(), Some(Slot::Local(0))),
+                ("len".to_string(), Some(Slot::Builtin(0))),
+                ("d".to_string(), Some(Slot::Local(0))),
+                ("make".to_string(), g(1)),
+                ("a".to_string(), g(0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_slots_are_per_node() {
+        // the prelude's names are not at the user's offsets
+        for stmt in &prelude::program().statements {
+            let Some(stmt) = stmt.as_any().downcast_ref::<LetStatement>() else {
+                continue;
+            };
+            let offset = stmt.name.token.span.start;
+            if offset < "quote(".len() {
+                continue;
+            }
+            let input = format!("quote({}y);", " ".repeat(offset - "quote(".len()));
+            let expected = vec![("quote".to_string(), None), ("y".to_string(), None)];
+            assert_eq!(slots(&input), expected, "{}", input);
+        }
+
+        // neither are other nodes with the same span; the `a` and `b` uses
+        // both start at offset 11
+        let mut program = parse("let a = 1; a;");
+        program
+            .statements
+            .extend(parse("fn(b) { a; b };").statements);
+        let resolution = resolve(&program);
+        let mut collect = Collect(&resolution, Vec::new());
+        collect.visit_program(&program);
+        let g = Some(Slot::Global(resolve(&parse("")).globals));
+        assert_eq!(
+            collect.1,
+            vec![
+                ("a".to_string(), g),
+                ("a".to_string(), g),
+                ("a".to_string(), g),
+                ("b".to_string(), Some(Slot::Local(0))),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_without_prelude() {
+        let program = parse("let m = map([1], fn(x) { x }); puts(m);");
+        let resolution = resolve_with_prelude(&program, false);
+        assert_eq!(resolution.globals, 1);
+        let diagnostics: Vec<String> = resolution
+            .diagnostics
+            .iter()
+            .map(|d| format!("{} {}", d.span, d))
+            .collect();
+        assert_eq!(diagnostics, vec!["1:9 Undefined Name: map is not defined"]);
+    }
+
+    #[test]
+    fn test_prelude_resolves() {
+        let errors: Vec<_> = resolve(&prelude::program())
+            .diagnostics
+            .into_iter()
+            .filter(|d| d.severity == Severity::Error)
+            .collect();
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
+
+    #[test]
+    fn test_frames_capture_through_every_level() {
+        let program = parse("let f = fn(x) { fn() { fn() { x } } };");
+        let resolution = resolve(&program);
+
+        let let_f = program.statements[0]
+            .as_any()
+            .downcast_ref::<LetStatement>()
+            .unwrap();
+        let outer = let_f
+            .value
+            .as_ref()
+            .unwrap()
+            .as_any()
+            .downcast_ref::<FunctionLiteral>()
+            .unwrap();
+        let function = |stmt: &Rc<dyn Statement>| -> Rc<dyn Expression> {
+            let stmt = stmt.as_any().downcast_ref::<ExpressionStatement>().unwrap();
+            stmt.expr.clone().unwrap()
+        };
+        let middle_expr = function(&outer.body.statements[0]);
+        let middle = middle_expr
+            .as_any()
+            .downcast_ref::<FunctionLiteral>()
+            .unwrap();
+        let inner_expr = function(&middle.body.statements[0]);
+        let inner = inner_expr
+            .as_any()
+            .downcast_ref::<FunctionLiteral>()
+            .unwrap();
+
+        let frame = |f: &FunctionLiteral| resolution.function_frame(f).cloned().unwrap();
+        assert_eq!(
+            frame(outer),
+            Frame {
+                locals: 1,
+                captures: vec![]
+            }
+        );
+        assert_eq!(
+            frame(middle),
+            Frame {
+                locals: 0,
+                captures: vec![Slot::Local(0)]
+            }
+        );
+        assert_eq!(
+            frame(inner),
+            Frame {
+                locals: 0,
+                captures: vec![Slot::Free(0)]
+            }
+        );
+    }
+}
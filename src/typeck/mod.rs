@@ -0,0 +1,2 @@
+pub mod typeck;
+pub mod types;
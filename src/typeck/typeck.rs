@@ -0,0 +1,990 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use crate::ast::ast::*;
+use crate::ast::printer;
+use crate::lexer::lexer::{Lexer, LexerTrait};
+use crate::lexer::token::Span;
+use crate::parser::parser::Parser;
+use crate::prelude::prelude;
+use crate::typeck::types::{Class, Scheme, Type, Vars};
+
+// An optional static type checker: Hindley–Milner inference over the AST,
+// with let-polymorphism for functions. Nothing else depends on it; the
+// language stays dynamically typed and `monkey check` is how it is run.
+//
+// The types are int, float, bool, string, null, arrays of one element type
+// and functions. `+` and the comparisons take two ints, floats or strings,
+// the other arithmetic operators two ints or floats, and `%`, the bitwise
+// operators and shifts two ints. An int and a float mix as well, the int
+// being promoted: `1 / 2.0` is a float. `==`, `!=`, `&&`, `||` and `!` take
+// anything and give a bool, as do conditions, since every value has a
+// truthiness.
+//
+// Promotion is only seen where both sides are known. `x += 0.5` is fine
+// on an int x, but x keeps type int: a variable is given one type, and
+// the checker does not follow it changing to a float at run time.
+//
+// Annotations are optional and checked where they are written:
+//
+//     let n: int = 5;
+//     let twice = fn(f: fn(T) -> T, x: T) -> T { f(f(x)) };
+//
+// A capitalized name in an annotation is a type variable, shared by the
+// annotations of the let or function it is written in and anything inside
+// it. Inside the annotated code it only matches itself, so the function
+// has to work for any type; callers pick the type as they like. `any`
+// opts a value out of checking.
+//
+// Only lets whose value is a function literal are generalized. Any other
+// value can be assigned to later, so `let xs = [];` gets one element type
+// that its uses have to agree on. Things the checker cannot follow, like
+// imports, members, macros and quoted code, have type any. Names it cannot
+// find do too; the resolver is the one to report those.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeError {
+    pub msg: String,
+    // where the conflicting type was found
+    pub span: Span,
+    // the other side of the conflict, where the expected type came from
+    pub related: Option<(Span, String)>,
+}
+
+impl Display for TypeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.msg)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Typing {
+    // the type of each top-level binding of the program, in order
+    pub globals: Vec<(String, String)>,
+    pub errors: Vec<TypeError>,
+}
+
+pub fn check(program: &Program) -> Typing {
+    check_with_prelude(program, true)
+}
+
+// Without the prelude (--no-prelude) its names have type any, as unknown
+// names do.
+pub fn check_with_prelude(program: &Program, prelude: bool) -> Typing {
+    let mut checker = Checker::default();
+    checker.scopes.push(HashMap::new());
+    for (name, ty) in BUILTINS {
+        let scheme = checker.builtin(ty);
+        checker.scopes[0].insert(
+            name.to_string(),
+            Binding {
+                scheme,
+                span: Span::default(),
+            },
+        );
+    }
+    if prelude {
+        for stmt in &prelude::program().statements {
+            checker.statement(stmt.as_ref());
+        }
+    }
+
+    // the prelude checks cleanly; see the tests
+    checker.errors.clear();
+    // its spans are in its own source, so like the builtins its bindings
+    // get no "defined here" note
+    for binding in checker.scopes[0].values_mut() {
+        binding.span = Span::default();
+    }
+    checker.record = true;
+    for stmt in &program.statements {
+        checker.statement(stmt.as_ref());
+    }
+    Typing {
+        globals: checker.globals,
+        errors: checker.errors,
+    }
+}
+
+// The types of the builtins, in annotation syntax. The ones missing take
+// optional or any number of arguments and have type any.
+const BUILTINS: &[(&str, &str)] = &[
+    ("len", "fn(T) -> int"),
+    ("first", "fn([T]) -> T"),
+    ("last", "fn([T]) -> T"),
+    ("rest", "fn([T]) -> [T]"),
+    ("push", "fn([T], T) -> [T]"),
+    ("split", "fn(string, string) -> [string]"),
+    ("join", "fn([string], string) -> string"),
+    ("trim", "fn(string) -> string"),
+    ("upper", "fn(string) -> string"),
+    ("lower", "fn(string) -> string"),
+    ("contains", "fn(string, string) -> bool"),
+    ("starts_with", "fn(string, string) -> bool"),
+    ("ends_with", "fn(string, string) -> bool"),
+    ("replace", "fn(string, string, string) -> string"),
+    ("chars", "fn(string) -> [string]"),
+    ("ord", "fn(string) -> int"),
+    ("chr", "fn(int) -> string"),
+];
+
+struct Binding {
+    scheme: Scheme,
+    // the name in its let, parameter list, loop or catch clause
+    span: Span,
+}
+
+// The return type of a function being checked, and where it was first
+// fixed: its annotation or the first return.
+struct Return {
+    ty: Type,
+    site: Option<(Span, String)>,
+    returned: bool,
+}
+
+#[derive(Default)]
+struct Checker {
+    vars: Vars,
+    // lets deep; see types::Var
+    level: usize,
+    scopes: Vec<HashMap<String, Binding>>,
+    // the type variables named in annotations, per let and function
+    type_params: Vec<HashMap<String, Type>>,
+    returns: Vec<Return>,
+    // whether top-level lets go in globals, which the prelude's don't
+    record: bool,
+    globals: Vec<(String, String)>,
+    errors: Vec<TypeError>,
+}
+
+impl Checker {
+    fn builtin(&mut self, ty: &str) -> Scheme {
+        let source = format!("let _: {} = 0;", ty);
+        let program = Parser::new(Lexer::new(&source)).parse_program();
+        let annotation = program.statements[0]
+            .as_any()
+            .downcast_ref::<LetStatement>()
+            .and_then(|stmt| stmt.annotation.clone())
+            .expect("builtin types are valid annotations");
+        self.level += 1;
+        self.type_params.push(HashMap::new());
+        let ty = self.annotation(&annotation);
+        self.type_params.pop();
+        self.level -= 1;
+        self.vars.generalize(&ty, self.level)
+    }
+
+    fn fresh(&mut self) -> Type {
+        self.vars.fresh(Class::Any, self.level)
+    }
+
+    fn error(&mut self, msg: String, span: Span, related: Option<(Span, String)>) {
+        self.errors.push(TypeError { msg, span, related });
+    }
+
+    // Reports `found` where `expected` was needed, unless they unify.
+    fn expect(
+        &mut self,
+        found: &Type,
+        expected: &Type,
+        span: Span,
+        related: Option<(Span, String)>,
+    ) -> bool {
+        let msg = format!(
+            "expected {}, found {}",
+            self.vars.describe(expected),
+            self.vars.describe(found)
+        );
+        if self.vars.unify(found, expected) {
+            return true;
+        }
+        self.error(msg, span, related);
+        false
+    }
+
+    fn bind(&mut self, name: &str, scheme: Scheme, span: Span) {
+        let scope = self.scopes.last_mut().expect("a scope is open");
+        scope.insert(name.to_string(), Binding { scheme, span });
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Binding> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    // The type a name has here, and where it was bound, if it is known.
+    fn name(&mut self, name: &str) -> Option<(Type, Span)> {
+        let binding = self.lookup(name)?;
+        let (scheme, span) = (binding.scheme.clone(), binding.span);
+        Some((self.vars.instantiate(&scheme, self.level), span))
+    }
+
+    fn defined_here(&self, name: &str, ty: &Type, span: Span) -> Option<(Span, String)> {
+        // builtins have no place in the source
+        (span != Span::default()).then(|| {
+            (
+                span,
+                format!("{} is defined here as {}", name, self.vars.show(ty)),
+            )
+        })
+    }
+
+    fn annotation(&mut self, annotation: &TypeExpr) -> Type {
+        match annotation {
+            TypeExpr::Named(token) => match token.literal.as_str() {
+                "int" => Type::Int,
+                "float" => Type::Float,
+                "bool" => Type::Bool,
+                "string" => Type::String,
+                "null" => Type::Null,
+                "any" => Type::Any,
+                name if name.starts_with(char::is_uppercase) => {
+                    let known = self.type_params.iter().rev().find_map(|p| p.get(name));
+                    if let Some(ty) = known {
+                        return ty.clone();
+                    }
+                    let ty = self.vars.rigid(name, self.level);
+                    let params = self.type_params.last_mut().expect("a type scope is open");
+                    params.insert(name.to_string(), ty.clone());
+                    ty
+                }
+                name => {
+                    self.error(format!("unknown type {}", name), token.span, None);
+                    Type::Any
+                }
+            },
+            TypeExpr::Array(_, element) => Type::Array(Box::new(self.annotation(element))),
+            TypeExpr::Function(_, params, result) => Type::Function(
+                params.iter().map(|p| self.annotation(p)).collect(),
+                Box::new(self.annotation(result)),
+            ),
+        }
+    }
+
+    // The type of the statement's value when it ends a block: null for
+    // statements that have none, and for ones that jump away a fresh
+    // variable, since they give no value to agree with.
+    fn statement(&mut self, stmt: &dyn Statement) -> Type {
+        let any = stmt.as_any();
+        if let Some(s) = any.downcast_ref::<ExpressionStatement>() {
+            if let Some(expr) = &s.expr {
+                return self.expression(expr.as_ref());
+            }
+        } else if let Some(s) = any.downcast_ref::<LetStatement>() {
+            self.let_statement(s);
+        } else if let Some(s) = any.downcast_ref::<ReturnStatements>() {
+            self.return_statement(s);
+            return self.fresh();
+        } else if let Some(s) = any.downcast_ref::<BlockStatement>() {
+            self.block(s);
+        } else if let Some(s) = any.downcast_ref::<ThrowStatement>() {
+            if let Some(value) = &s.value {
+                self.expression(value.as_ref());
+            }
+            return self.fresh();
+        } else if let Some(s) = any.downcast_ref::<TryStatement>() {
+            self.block(&s.block);
+            if let Some(catch) = &s.catch_block {
+                self.scopes.push(HashMap::new());
+                if let Some(param) = &s.catch_param {
+                    self.bind(&param.value, Scheme::mono(Type::Any), param.token.span);
+                }
+                self.block(catch);
+                self.scopes.pop();
+            }
+            if let Some(finally) = &s.finally_block {
+                self.block(finally);
+            }
+        } else if let Some(s) = any.downcast_ref::<WhileStatement>() {
+            self.expression(s.condition.as_ref());
+            self.block(&s.body);
+        } else if let Some(s) = any.downcast_ref::<ForStatement>() {
+            self.for_statement(s);
+        } else if any.is::<BreakStatement>() || any.is::<ContinueStatement>() {
+            return self.fresh();
+        } else if let Some(s) = any.downcast_ref::<ImportStatement>() {
+            self.bind(&s.alias.value, Scheme::mono(Type::Any), s.alias.token.span);
+        } else if let Some(s) = any.downcast_ref::<ExportStatement>() {
+            self.let_statement(&s.statement);
+        }
+        Type::Null
+    }
+
+    fn let_statement(&mut self, stmt: &LetStatement) {
+        let name = &stmt.name;
+        let function = stmt
+            .value
+            .as_ref()
+            .is_some_and(|v| v.as_any().is::<FunctionLiteral>());
+
+        self.level += 1;
+        self.type_params.push(HashMap::new());
+        let annotated = stmt.annotation.as_ref().map(|a| {
+            let site = (a.token().span, format!("annotated as {} here", a));
+            (self.annotation(a), site)
+        });
+        // a function can call itself, at the one type it is being given
+        let own = annotated
+            .as_ref()
+            .map_or_else(|| self.fresh(), |(ty, _)| ty.clone());
+        if function {
+            self.bind(&name.value, Scheme::mono(own.clone()), name.token.span);
+        }
+        let ty = match &stmt.value {
+            Some(value) => self.expression(value.as_ref()),
+            None => Type::Any,
+        };
+        let span = stmt
+            .value
+            .as_ref()
+            .map_or(name.token.span, |v| printer::start_span(v.as_ref()));
+        match annotated {
+            Some((_, site)) => {
+                self.expect(&ty, &own, span, Some(site));
+            }
+            None => {
+                self.vars.unify(&own, &ty);
+            }
+        }
+        self.type_params.pop();
+        self.level -= 1;
+
+        let scheme = if function {
+            self.vars.generalize(&own, self.level)
+        } else {
+            self.vars.lower(&own, self.level);
+            Scheme::mono(own)
+        };
+        if self.record && self.scopes.len() == 1 {
+            let ty = self.vars.show(&scheme.ty);
+            self.globals.push((name.value.clone(), ty));
+        }
+        self.bind(&name.value, scheme, name.token.span);
+    }
+
+    fn return_statement(&mut self, stmt: &ReturnStatements) {
+        let (ty, span) = match &stmt.value {
+            Some(value) => (
+                self.expression(value.as_ref()),
+                printer::start_span(value.as_ref()),
+            ),
+            None => (Type::Null, stmt.token.span),
+        };
+        // a return outside any function leaves the program
+        let Some(ret) = self.returns.last() else {
+            return;
+        };
+        let (expected, site) = (ret.ty.clone(), ret.site.clone());
+        self.expect(&ty, &expected, span, site);
+        let ret = self.returns.last_mut().expect("checked above");
+        ret.returned = true;
+        if ret.site.is_none() {
+            let msg = format!("returns {} here", self.vars.describe(&expected));
+            ret.site = Some((span, msg));
+        }
+    }
+
+    fn for_statement(&mut self, stmt: &ForStatement) {
+        let iterable = self.expression(stmt.iterable.as_ref());
+        let element = match self.vars.shallow(&iterable) {
+            Type::String => Type::String,
+            Type::Any => Type::Any,
+            _ => {
+                let element = self.fresh();
+                let array = Type::Array(Box::new(element.clone()));
+                if self.vars.unify(&iterable, &array) {
+                    element
+                } else {
+                    let msg = format!("cannot loop over {}", self.vars.describe(&iterable));
+                    self.error(msg, printer::start_span(stmt.iterable.as_ref()), None);
+                    Type::Any
+                }
+            }
+        };
+        self.scopes.push(HashMap::new());
+        let var = &stmt.variable;
+        self.bind(&var.value, Scheme::mono(element), var.token.span);
+        self.block(&stmt.body);
+        self.scopes.pop();
+    }
+
+    // The type of the block's value: its last statement's.
+    fn block(&mut self, block: &BlockStatement) -> Type {
+        self.scopes.push(HashMap::new());
+        let mut ty = Type::Null;
+        for stmt in &block.statements {
+            ty = self.statement(stmt.as_ref());
+        }
+        self.scopes.pop();
+        ty
+    }
+
+    fn expression(&mut self, expr: &dyn Expression) -> Type {
+        let any = expr.as_any();
+        if let Some(e) = any.downcast_ref::<Identifier>() {
+            self.name(&e.value).map_or(Type::Any, |(ty, _)| ty)
+        } else if any.is::<IntegerLiteral>() {
+            Type::Int
+        } else if any.is::<FloatLiteral>() {
+            Type::Float
+        } else if any.is::<StringLiteral>() {
+            Type::String
+        } else if any.is::<Boolean>() {
+            Type::Bool
+        } else if let Some(e) = any.downcast_ref::<InterpolatedString>() {
+            for part in &e.parts {
+                if let InterpolationPart::Expr(expr) = part {
+                    self.expression(expr.as_ref());
+                }
+            }
+            Type::String
+        } else if let Some(e) = any.downcast_ref::<PrefixExpression>() {
+            let right = self.expression(e.right.as_ref());
+            if e.operator == "-" {
+                let number = self.vars.fresh(Class::Number, self.level);
+                let site = (e.token.span, "required by -".to_string());
+                if self.expect(
+                    &right,
+                    &number,
+                    printer::start_span(e.right.as_ref()),
+                    Some(site),
+                ) {
+                    number
+                } else {
+                    Type::Any
+                }
+            } else {
+                Type::Bool
+            }
+        } else if let Some(e) = any.downcast_ref::<InfixExpression>() {
+            self.infix(e)
+        } else if let Some(e) = any.downcast_ref::<ArrayLiteral>() {
+            let element = self.fresh();
+            let mut first = None;
+            for expr in &e.elements {
+                let ty = self.expression(expr.as_ref());
+                let span = printer::start_span(expr.as_ref());
+                let related = first.take();
+                self.expect(&ty, &element, span, related.clone());
+                first = related.or_else(|| {
+                    let msg = format!("the first element is {}", self.vars.describe(&ty));
+                    Some((span, msg))
+                });
+            }
+            Type::Array(Box::new(element))
+        } else if let Some(e) = any.downcast_ref::<IndexExpression>() {
+            self.index(e)
+        } else if let Some(e) = any.downcast_ref::<AssignExpression>() {
+            self.assign(e)
+        } else if let Some(e) = any.downcast_ref::<MemberExpression>() {
+            self.expression(e.object.as_ref());
+            Type::Any
+        } else if let Some(e) = any.downcast_ref::<IfExpression>() {
+            self.expression(e.condition.as_ref());
+            let consequence = self.block(&e.consequence);
+            let Some(alternative) = &e.alternative else {
+                // null when the condition is false
+                return Type::Any;
+            };
+            let other = self.block(alternative);
+            let site = (
+                tail_span(&e.consequence),
+                format!("the other branch is {}", self.vars.describe(&consequence)),
+            );
+            self.expect(&other, &consequence, tail_span(alternative), Some(site));
+            consequence
+        } else if let Some(e) = any.downcast_ref::<FunctionLiteral>() {
+            self.function(e)
+        } else if let Some(e) = any.downcast_ref::<CallExpression>() {
+            self.call(e)
+        } else {
+            // macros work on code, not values
+            Type::Any
+        }
+    }
+
+    fn infix(&mut self, e: &InfixExpression) -> Type {
+        let left = self.expression(e.left.as_ref());
+        let right = self.expression(e.right.as_ref());
+        let (class, result) = match e.operator.as_str() {
+            "==" | "!=" | "&&" | "||" => return Type::Bool,
+            "+" => (Class::Ordered, None),
+            "<" | ">" | "<=" | ">=" => (Class::Ordered, Some(Type::Bool)),
+            "-" | "*" | "/" | "**" => (Class::Number, None),
+            // %, the bitwise operators and shifts
+            _ => (Class::Number, Some(Type::Int)),
+        };
+        let operand = match result {
+            Some(Type::Int) => Type::Int,
+            _ if self.promotes(&left, &right) => return result.unwrap_or(Type::Float),
+            _ => self.vars.fresh(class, self.level),
+        };
+        let (left_span, right_span) = (
+            printer::start_span(e.left.as_ref()),
+            printer::start_span(e.right.as_ref()),
+        );
+        let site = (e.token.span, format!("required by {}", e.operator));
+        if !self.expect(&left, &operand, left_span, Some(site)) {
+            // without a type to go on, nothing more is checked
+            return result.unwrap_or(Type::Any);
+        }
+        let msg = format!("the left operand is {}", self.vars.describe(&left));
+        self.expect(&right, &operand, right_span, Some((left_span, msg)));
+        result.unwrap_or(operand)
+    }
+
+    // Whether an arithmetic operator or comparison mixes an int and a
+    // float, which makes the int a float.
+    fn promotes(&self, left: &Type, right: &Type) -> bool {
+        matches!(
+            (self.vars.shallow(left), self.vars.shallow(right)),
+            (Type::Int, Type::Float) | (Type::Float, Type::Int)
+        )
+    }
+
+    fn index(&mut self, e: &IndexExpression) -> Type {
+        let left = self.expression(e.left.as_ref());
+        let index = self.expression(e.index.as_ref());
+        let element = match self.vars.shallow(&left) {
+            Type::String => Type::String,
+            Type::Any => Type::Any,
+            _ => {
+                let element = self.fresh();
+                let array = Type::Array(Box::new(element.clone()));
+                if !self.vars.unify(&left, &array) {
+                    let msg = format!("cannot index {}", self.vars.describe(&left));
+                    self.error(msg, printer::start_span(e.left.as_ref()), None);
+                    return Type::Any;
+                }
+                element
+            }
+        };
+        self.expect(
+            &index,
+            &Type::Int,
+            printer::start_span(e.index.as_ref()),
+            None,
+        );
+        element
+    }
+
+    fn assign(&mut self, e: &AssignExpression) -> Type {
+        let value = self.expression(e.value.as_ref());
+        let (target, related) = match e.target.as_any().downcast_ref::<Identifier>() {
+            Some(ident) => match self.name(&ident.value) {
+                Some((ty, span)) => {
+                    let related = self.defined_here(&ident.value, &ty, span);
+                    (ty, related)
+                }
+                None => (Type::Any, None),
+            },
+            None => (self.expression(e.target.as_ref()), None),
+        };
+        let class = match e.operator.as_str() {
+            "=" => None,
+            "+=" => Some(Class::Ordered),
+            _ => Some(Class::Number),
+        };
+        if let Some(class) = class {
+            let operand = self.vars.fresh(class, self.level);
+            let site = (e.token.span, format!("required by {}", e.operator));
+            if !self.expect(
+                &target,
+                &operand,
+                printer::start_span(e.target.as_ref()),
+                Some(site),
+            ) {
+                return target;
+            }
+            if self.promotes(&target, &value) {
+                return target;
+            }
+        }
+        self.expect(
+            &value,
+            &target,
+            printer::start_span(e.value.as_ref()),
+            related,
+        );
+        target
+    }
+
+    fn function(&mut self, e: &FunctionLiteral) -> Type {
+        self.type_params.push(HashMap::new());
+        let params: Vec<Type> = e
+            .parameter_types
+            .iter()
+            .map(|t| match t {
+                Some(t) => self.annotation(t),
+                None => self.fresh(),
+            })
+            .collect();
+        let (result, site) = match &e.return_type {
+            Some(t) => {
+                let site = (t.token().span, format!("annotated as {} here", t));
+                (self.annotation(t), Some(site))
+            }
+            None => (self.fresh(), None),
+        };
+
+        self.scopes.push(HashMap::new());
+        for (param, ty) in e.parameters.iter().zip(&params) {
+            self.bind(&param.value, Scheme::mono(ty.clone()), param.token.span);
+        }
+        self.returns.push(Return {
+            ty: result.clone(),
+            site,
+            returned: false,
+        });
+        let tail = self.block(&e.body);
+        let ret = self.returns.pop().expect("pushed above");
+        self.scopes.pop();
+        self.type_params.pop();
+
+        // falling off the end after returning a value is left alone: the
+        // loop or if before it is likely to always return
+        let falls_off = !e
+            .body
+            .statements
+            .last()
+            .is_some_and(|s| s.as_any().is::<ExpressionStatement>());
+        if !(falls_off && ret.returned) {
+            self.expect(&tail, &result, tail_span(&e.body), ret.site);
+        }
+        Type::Function(params, Box::new(result))
+    }
+
+    fn call(&mut self, e: &CallExpression) -> Type {
+        let callee = e.function.as_any().downcast_ref::<Identifier>();
+        let name = callee.map(|ident| ident.value.as_str());
+        if matches!(name, Some("quote" | "unquote")) && self.lookup(name.unwrap()).is_none() {
+            // quoted code is not evaluated here
+            return Type::Any;
+        }
+
+        let function = self.expression(e.function.as_ref());
+        let args: Vec<(Type, Span)> = e
+            .arguments
+            .iter()
+            .map(|arg| {
+                (
+                    self.expression(arg.as_ref()),
+                    printer::start_span(arg.as_ref()),
+                )
+            })
+            .collect();
+        let related = callee
+            .and_then(|ident| self.lookup(&ident.value).map(|b| (ident, b.span)))
+            .and_then(|(ident, span)| self.defined_here(&ident.value, &function, span));
+
+        match self.vars.shallow(&function) {
+            Type::Any => Type::Any,
+            Type::Function(params, result) => {
+                if params.len() != args.len() {
+                    let msg = format!(
+                        "{} takes {} argument{} but {} {} given",
+                        name.unwrap_or("the function"),
+                        params.len(),
+                        if params.len() == 1 { "" } else { "s" },
+                        args.len(),
+                        if args.len() == 1 { "was" } else { "were" },
+                    );
+                    self.error(msg, printer::start_span(e.function.as_ref()), related);
+                } else {
+                    for ((arg, span), param) in args.iter().zip(&params) {
+                        self.expect(arg, param, *span, related.clone());
+                    }
+                }
+                *result
+            }
+            _ => {
+                let result = self.fresh();
+                let types = args.into_iter().map(|(ty, _)| ty).collect();
+                let expected = Type::Function(types, Box::new(result.clone()));
+                if !self.vars.unify(&function, &expected) {
+                    let msg = format!("{} is not a function", self.vars.describe(&function));
+                    self.error(msg, printer::start_span(e.function.as_ref()), related);
+                    return Type::Any;
+                }
+                result
+            }
+        }
+    }
+}
+
+// Where the block's value comes from: its last statement, or the block
+// itself when it is empty.
+fn tail_span(block: &BlockStatement) -> Span {
+    let Some(last) = block.statements.last() else {
+        return block.token.span;
+    };
+    match last.as_any().downcast_ref::<ExpressionStatement>() {
+        Some(ExpressionStatement {
+            expr: Some(expr), ..
+        }) => printer::start_span(expr.as_ref()),
+        _ => token_span(last.as_ref()),
+    }
+}
+
+fn token_span(node: &dyn Node) -> Span {
+    printer::token_of(node).map(|t| t.span).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parser::parse;
+
+    fn typing(source: &str) -> Typing {
+        check(&parse(source))
+    }
+
+    fn types(source: &str) -> Vec<String> {
+        let typing = typing(source);
+        assert!(typing.errors.is_empty(), "{}: {:?}", source, typing.errors);
+        typing
+            .globals
+            .iter()
+            .map(|(name, ty)| format!("{}: {}", name, ty))
+            .collect()
+    }
+
+    // Each error as `line:col msg`, with ` (line:col note)` when it has one.
+    fn errors(source: &str) -> Vec<String> {
+        typing(source)
+            .errors
+            .iter()
+            .map(|e| match &e.related {
+                Some((span, note)) => format!("{} {} ({} {})", e.span, e.msg, span, note),
+                None => format!("{} {}", e.span, e.msg),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_prelude_checks() {
+        let typing = typing("");
+        assert!(typing.errors.is_empty(), "{:?}", typing.errors);
+        assert_eq!(
+            types("let m = map; let r = reduce; let s = sort_by; let z = zip; let n = range;"),
+            vec![
+                "m: fn([A], fn(A) -> B) -> [B]",
+                "r: fn([A], B, fn(B, A) -> B) -> B",
+                "s: fn([A], fn(A) -> B) -> [A]",
+                "z: fn([A], [A]) -> [[A]]",
+                "n: fn(int, int) -> [int]",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_without_prelude() {
+        let typing = check_with_prelude(&parse("let m = map; let n = len;"), false);
+        assert!(typing.errors.is_empty(), "{:?}", typing.errors);
+        assert_eq!(
+            typing.globals,
+            vec![
+                ("m".to_string(), "any".to_string()),
+                ("n".to_string(), "fn(A) -> int".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_inference() {
+        assert_eq!(
+            types(
+                "let n = 1 + 2 * 3;
+let x = 1.5 / 2.0;
+let s = \"a\" + \"b\";
+let ok = n < 10 && !false;
+let xs = [n, len(s)];
+let first_char = s[0];
+let pairs = fn(a, b) { [a, b] };
+let adder = fn(x) { fn(y) { x + y } };
+let add_one = adder(1);
+let count = fn(xs) { let total = 0; for (x in xs) { total += 1; } total };
+let fact = fn(n) { if (n < 2) { return 1; } n * fact(n - 1) };
+let msg = \"${n} items\";
+let maybe = fn(x) { if (x) { 1 } };
+"
+            ),
+            vec![
+                "n: int",
+                "x: float",
+                "s: string",
+                "ok: bool",
+                "xs: [int]",
+                "first_char: string",
+                "pairs: fn(A, A) -> [A]",
+                "adder: fn(A) -> fn(A) -> A",
+                "add_one: fn(int) -> int",
+                "count: fn([A]) -> int",
+                "fact: fn(int) -> int",
+                "msg: string",
+                "maybe: fn(A) -> any",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_let_polymorphism() {
+        assert_eq!(
+            types(
+                "let id = fn(x) { x };
+let a = id(1);
+let b = id(\"s\");
+let c = map([1, 2], fn(n) { n > 1 });
+"
+            ),
+            vec!["id: fn(A) -> A", "a: int", "b: string", "c: [bool]"]
+        );
+        // values other than functions are not generalized
+        assert_eq!(
+            errors("let xs = [];\nxs = push(xs, 1);\nxs = [\"s\"];"),
+            vec!["3:6 expected [int], found [string] (1:5 xs is defined here as [int])"]
+        );
+    }
+
+    #[test]
+    fn test_annotations() {
+        assert_eq!(
+            types(
+                "let n: int = 5;
+let xs: [float] = [];
+let twice = fn(f: fn(T) -> T, x: T) -> T { f(f(x)) };
+let inc = fn(n: int) -> int { n + 1 };
+let d: any = 1;
+"
+            ),
+            vec![
+                "n: int",
+                "xs: [float]",
+                "twice: fn(fn(T) -> T, T) -> T",
+                "inc: fn(int) -> int",
+                "d: any",
+            ]
+        );
+        assert_eq!(
+            errors(
+                "let n: int = \"five\";
+let f = fn(x: T) -> T { x + 1 };
+let g = fn() -> [string] { [1] };
+let h: fn(int) -> int = fn(s: string) { s };
+let u: number = 1;
+"
+            ),
+            vec![
+                "1:14 expected int, found string (1:8 annotated as int here)",
+                "2:25 expected int, float or string, found T (2:27 required by +)",
+                "3:28 expected [string], found [int] (3:17 annotated as [string] here)",
+                "4:25 expected fn(int) -> int, found fn(string) -> string \
+                 (4:8 annotated as fn(int) -> int here)",
+                "5:8 unknown type number",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_errors_point_at_both_sites() {
+        assert_eq!(
+            errors(
+                "let n = 1;
+let s = n + \"a\";
+let xs = [1, 2, true];
+let f = fn(a, b) { a - b };
+f(1, \"b\");
+f(1);
+let g = fn(x) { if (x) { return 1; } \"one\" };
+let y = if (n > 0) { 1 } else { \"no\" };
+n = [];
+n();
+let z = n[0];
+for (c in 5) { puts(c) }
+"
+            ),
+            vec![
+                "2:13 expected int, found string (2:9 the left operand is int)",
+                "3:17 expected int, found bool (3:11 the first element is int)",
+                "5:6 expected int, found string (4:5 f is defined here as fn(A, A) -> A)",
+                "6:1 f takes 2 arguments but 1 was given (4:5 f is defined here as fn(A, A) -> A)",
+                "7:38 expected int, found string (7:33 returns int here)",
+                "8:33 expected int, found string (8:22 the other branch is int)",
+                "9:5 expected int, found [A] (1:5 n is defined here as int)",
+                "10:1 int is not a function (1:5 n is defined here as int)",
+                "11:9 cannot index int",
+                "12:11 cannot loop over int",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_int_and_float_mix() {
+        assert_eq!(
+            types(
+                "let a = 1 / 2.0;
+let b = 1 < 2.5;
+let c = 2.5 * 2 + 1;
+let d = 2 ** 0.5;
+let x = 1;
+x += 1.5;
+let y = 1.5;
+y -= 1;
+let half = fn(n: int) -> float { n / 2.0 };
+"
+            ),
+            vec![
+                "a: float",
+                "b: bool",
+                "c: float",
+                "d: float",
+                "x: int",
+                "y: float",
+                "half: fn(int) -> float",
+            ]
+        );
+        assert_eq!(
+            errors("1 % 2.0;\n\"a\" + 1.5;\nlet s = \"a\";\ns += 1.5;"),
+            vec![
+                "1:5 expected int, found float (1:1 the left operand is int)",
+                "2:7 expected string, found float (2:1 the left operand is string)",
+                "4:6 expected string, found float (3:5 s is defined here as string)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_library_functions_have_no_site() {
+        // the prelude's spans would point into the user's file
+        assert_eq!(
+            errors("len(1, 2);\nreduce([1, 2], \"\", fn(a, x) { a + x });"),
+            vec![
+                "1:1 len takes 1 argument but 2 were given",
+                "2:20 expected fn(string, int) -> string, found fn(A, A) -> A",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dynamic_parts_are_not_checked() {
+        assert_eq!(
+            types(
+                "import \"lib\" as lib;
+let a = lib.answer + 1;
+let q = quote(x + unquote(2));
+let m = macro(x) { x };
+let p = puts(1, \"two\");
+let e = undefined_name * 2;
+let t = fn() { try { throw 1; } catch (err) { err + 1 } };
+"
+            ),
+            vec![
+                "a: any",
+                "q: any",
+                "m: any",
+                "p: any",
+                "e: any",
+                "t: fn() -> null",
+            ]
+        );
+    }
+}
@@ -0,0 +1,379 @@
+use std::collections::HashMap;
+
+// The types the checker works with. A Var is a type variable whose state
+// lives in a Vars table. Any is the type of what cannot be known
+// statically, like imported members, macro results or what `puts` takes:
+// it matches every type and nothing is learned from the match.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Type {
+    Int,
+    Float,
+    Bool,
+    String,
+    Null,
+    Array(Box<Type>),
+    Function(Vec<Type>, Box<Type>),
+    Var(usize),
+    Any,
+}
+
+// What a flexible type variable may become. The arithmetic and comparison
+// operators work on more than one type, so `a + b` gives its operands a
+// variable of class Ordered rather than a type. The classes nest, so two
+// of them meet at the smaller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Class {
+    // int or float
+    Number,
+    // int, float or string
+    Ordered,
+    Any,
+}
+
+impl Class {
+    fn admits(self, ty: &Type) -> bool {
+        match self {
+            Class::Number => matches!(ty, Type::Int | Type::Float | Type::Any),
+            Class::Ordered => matches!(ty, Type::Int | Type::Float | Type::String | Type::Any),
+            Class::Any => true,
+        }
+    }
+}
+
+// A type with the variables in `vars` quantified: each use of a let-bound
+// function gets fresh ones.
+#[derive(Debug, Clone)]
+pub struct Scheme {
+    pub vars: Vec<usize>,
+    pub ty: Type,
+}
+
+impl Scheme {
+    pub fn mono(ty: Type) -> Scheme {
+        Scheme {
+            vars: Vec::new(),
+            ty,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Kind {
+    Flexible(Class),
+    // a variable named in an annotation, like T in fn(T) -> T; it stands
+    // for whatever the caller picks, so it only matches itself
+    Rigid(String),
+}
+
+#[derive(Debug, Clone)]
+struct Var {
+    kind: Kind,
+    // how many lets deep the variable was made; the ones deeper than a
+    // let are the ones it may generalize
+    level: usize,
+    bound: Option<Type>,
+}
+
+#[derive(Debug, Default)]
+pub struct Vars {
+    vars: Vec<Var>,
+}
+
+impl Vars {
+    pub fn fresh(&mut self, class: Class, level: usize) -> Type {
+        self.push(Kind::Flexible(class), level)
+    }
+
+    pub fn rigid(&mut self, name: &str, level: usize) -> Type {
+        self.push(Kind::Rigid(name.to_string()), level)
+    }
+
+    fn push(&mut self, kind: Kind, level: usize) -> Type {
+        self.vars.push(Var {
+            kind,
+            level,
+            bound: None,
+        });
+        Type::Var(self.vars.len() - 1)
+    }
+
+    // Follows bound variables until a type that is not one.
+    pub fn shallow(&self, ty: &Type) -> Type {
+        let mut ty = ty.clone();
+        while let Type::Var(v) = ty {
+            match &self.vars[v].bound {
+                Some(bound) => ty = bound.clone(),
+                None => break,
+            }
+        }
+        ty
+    }
+
+    // The type with every bound variable replaced, all the way down.
+    pub fn resolve(&self, ty: &Type) -> Type {
+        match self.shallow(ty) {
+            Type::Array(element) => Type::Array(Box::new(self.resolve(&element))),
+            Type::Function(params, result) => Type::Function(
+                params.iter().map(|p| self.resolve(p)).collect(),
+                Box::new(self.resolve(&result)),
+            ),
+            ty => ty,
+        }
+    }
+
+    // The class of an unbound flexible variable.
+    pub fn class(&self, ty: &Type) -> Option<Class> {
+        match self.shallow(ty) {
+            Type::Var(v) => match self.vars[v].kind {
+                Kind::Flexible(class) => Some(class),
+                Kind::Rigid(_) => None,
+            },
+            _ => None,
+        }
+    }
+
+    // Makes the two types equal by binding variables. On failure some of
+    // the variables may already be bound; the caller reports the mismatch
+    // and carries on.
+    pub fn unify(&mut self, a: &Type, b: &Type) -> bool {
+        let (a, b) = (self.shallow(a), self.shallow(b));
+        match (&a, &b) {
+            (Type::Var(x), Type::Var(y)) if x == y => true,
+            (Type::Var(x), _) if self.class(&a).is_some() => self.bind(*x, &b),
+            (_, Type::Var(y)) if self.class(&b).is_some() => self.bind(*y, &a),
+            (Type::Any, _) | (_, Type::Any) => true,
+            (Type::Array(x), Type::Array(y)) => self.unify(x, y),
+            (Type::Function(p, r), Type::Function(q, s)) => {
+                p.len() == q.len()
+                    && p.iter().zip(q).all(|(x, y)| self.unify(x, y))
+                    && self.unify(r, s)
+            }
+            _ => a == b,
+        }
+    }
+
+    fn bind(&mut self, v: usize, ty: &Type) -> bool {
+        let Kind::Flexible(class) = self.vars[v].kind else {
+            unreachable!("only flexible variables are bound");
+        };
+        let level = self.vars[v].level;
+        if let Type::Var(w) = *ty {
+            // both unbound: the other one takes the tighter class
+            match self.vars[w].kind {
+                Kind::Flexible(other) => self.vars[w].kind = Kind::Flexible(class.min(other)),
+                Kind::Rigid(_) if class != Class::Any => return false,
+                Kind::Rigid(_) => {}
+            }
+            self.vars[w].level = self.vars[w].level.min(level);
+        } else if !class.admits(ty) || self.occurs(v, level, ty) {
+            return false;
+        }
+        self.vars[v].bound = Some(ty.clone());
+        true
+    }
+
+    // Whether `v` occurs in `ty`, lowering the level of the variables in it
+    // to `level` on the way: they are now reachable from wherever `v` is.
+    fn occurs(&mut self, v: usize, level: usize, ty: &Type) -> bool {
+        match self.shallow(ty) {
+            Type::Var(w) => {
+                self.vars[w].level = self.vars[w].level.min(level);
+                w == v
+            }
+            Type::Array(element) => self.occurs(v, level, &element),
+            Type::Function(params, result) => {
+                params.iter().any(|p| self.occurs(v, level, p)) || self.occurs(v, level, &result)
+            }
+            _ => false,
+        }
+    }
+
+    // Keeps the variables in `ty` from being generalized by any let deeper
+    // than `level`.
+    pub fn lower(&mut self, ty: &Type, level: usize) {
+        self.occurs(usize::MAX, level, ty);
+    }
+
+    pub fn generalize(&self, ty: &Type, level: usize) -> Scheme {
+        let ty = self.resolve(ty);
+        let mut vars = Vec::new();
+        self.free(&ty, &mut vars);
+        vars.retain(|&v| self.vars[v].level > level);
+        Scheme { vars, ty }
+    }
+
+    // The variables in a resolved type, in the order they first appear.
+    fn free(&self, ty: &Type, vars: &mut Vec<usize>) {
+        match ty {
+            Type::Var(v) if !vars.contains(v) => vars.push(*v),
+            Type::Array(element) => self.free(element, vars),
+            Type::Function(params, result) => {
+                params.iter().for_each(|p| self.free(p, vars));
+                self.free(result, vars);
+            }
+            _ => {}
+        }
+    }
+
+    // The scheme's type with fresh flexible variables for the quantified
+    // ones; quantified rigid variables become flexible ones of any type.
+    pub fn instantiate(&mut self, scheme: &Scheme, level: usize) -> Type {
+        if scheme.vars.is_empty() {
+            return scheme.ty.clone();
+        }
+        let fresh: HashMap<usize, Type> = scheme
+            .vars
+            .iter()
+            .map(|&v| {
+                let class = match self.vars[v].kind {
+                    Kind::Flexible(class) => class,
+                    Kind::Rigid(_) => Class::Any,
+                };
+                (v, self.fresh(class, level))
+            })
+            .collect();
+        substitute(&self.resolve(&scheme.ty), &fresh)
+    }
+
+    // The type as it would be written in an annotation, with its variables
+    // named A, B, C... in the order they appear, or by their own name if
+    // they came from an annotation.
+    pub fn show(&self, ty: &Type) -> String {
+        let ty = self.resolve(ty);
+        let mut vars = Vec::new();
+        self.free(&ty, &mut vars);
+        let mut names = HashMap::new();
+        let mut letters = (0..).map(|i: usize| match i {
+            0..=25 => ((b'A' + i as u8) as char).to_string(),
+            _ => format!("T{}", i),
+        });
+        for v in vars {
+            let name = match &self.vars[v].kind {
+                Kind::Rigid(name) => name.clone(),
+                Kind::Flexible(_) => letters.next().unwrap(),
+            };
+            names.insert(v, name);
+        }
+        let mut out = String::new();
+        write_type(&mut out, &ty, &names);
+        out
+    }
+
+    // Like show, but a lone variable with a class says what it can be.
+    pub fn describe(&self, ty: &Type) -> String {
+        match self.class(ty) {
+            Some(Class::Number) => "int or float".to_string(),
+            Some(Class::Ordered) => "int, float or string".to_string(),
+            _ => self.show(ty),
+        }
+    }
+}
+
+fn substitute(ty: &Type, vars: &HashMap<usize, Type>) -> Type {
+    match ty {
+        Type::Var(v) => vars.get(v).cloned().unwrap_or(Type::Var(*v)),
+        Type::Array(element) => Type::Array(Box::new(substitute(element, vars))),
+        Type::Function(params, result) => Type::Function(
+            params.iter().map(|p| substitute(p, vars)).collect(),
+            Box::new(substitute(result, vars)),
+        ),
+        ty => ty.clone(),
+    }
+}
+
+fn write_type(out: &mut String, ty: &Type, names: &HashMap<usize, String>) {
+    match ty {
+        Type::Int => out.push_str("int"),
+        Type::Float => out.push_str("float"),
+        Type::Bool => out.push_str("bool"),
+        Type::String => out.push_str("string"),
+        Type::Null => out.push_str("null"),
+        Type::Any => out.push_str("any"),
+        Type::Var(v) => out.push_str(&names[v]),
+        Type::Array(element) => {
+            out.push('[');
+            write_type(out, element, names);
+            out.push(']');
+        }
+        Type::Function(params, result) => {
+            out.push_str("fn(");
+            for (i, param) in params.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                write_type(out, param, names);
+            }
+            out.push_str(") -> ");
+            write_type(out, result, names);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn function(params: Vec<Type>, result: Type) -> Type {
+        Type::Function(params, Box::new(result))
+    }
+
+    #[test]
+    fn test_unify() {
+        let mut vars = Vars::default();
+        let a = vars.fresh(Class::Any, 1);
+        let b = vars.fresh(Class::Any, 1);
+        let f = function(vec![a.clone(), Type::Array(Box::new(a.clone()))], b.clone());
+        let g = function(vec![Type::Int, b.clone()], Type::Array(Box::new(Type::Int)));
+        assert!(vars.unify(&f, &g));
+        assert_eq!(vars.show(&f), "fn(int, [int]) -> [int]");
+
+        assert!(!vars.unify(&a, &Type::String));
+        assert!(vars.unify(&a, &Type::Any));
+
+        // a variable cannot contain itself
+        let c = vars.fresh(Class::Any, 1);
+        assert!(!vars.unify(&c, &Type::Array(Box::new(c.clone()))));
+    }
+
+    #[test]
+    fn test_classes() {
+        let mut vars = Vars::default();
+        let number = vars.fresh(Class::Number, 1);
+        let ordered = vars.fresh(Class::Ordered, 1);
+        assert!(!vars.unify(&ordered, &Type::Bool));
+        assert!(vars.unify(&ordered, &number));
+        assert_eq!(vars.describe(&ordered), "int or float");
+        assert!(!vars.unify(&number, &Type::String));
+        assert!(vars.unify(&number, &Type::Float));
+        assert_eq!(vars.show(&ordered), "float");
+
+        let t = vars.rigid("T", 1);
+        let any = vars.fresh(Class::Any, 1);
+        assert!(!vars.unify(&t, &Type::Int));
+        let number = vars.fresh(Class::Number, 1);
+        assert!(!vars.unify(&number, &t));
+        assert!(vars.unify(&any, &t));
+        assert_eq!(vars.show(&function(vec![any], t)), "fn(T) -> T");
+    }
+
+    #[test]
+    fn test_generalize_and_instantiate() {
+        let mut vars = Vars::default();
+        let outer = vars.fresh(Class::Any, 0);
+        let inner = vars.fresh(Class::Number, 1);
+        let ty = function(vec![inner.clone(), outer.clone()], inner);
+        let scheme = vars.generalize(&ty, 0);
+        assert_eq!(scheme.vars.len(), 1);
+
+        let first = vars.instantiate(&scheme, 0);
+        let second = vars.instantiate(&scheme, 0);
+        let int = function(vec![Type::Int, Type::Bool], vars.fresh(Class::Any, 0));
+        let float = function(vec![Type::Float, Type::Bool], vars.fresh(Class::Any, 0));
+        assert!(vars.unify(&first, &int));
+        assert!(vars.unify(&second, &float));
+        assert_eq!(vars.show(&first), "fn(int, bool) -> int");
+        assert_eq!(vars.show(&second), "fn(float, bool) -> float");
+        // the variable from outside the let is shared by both uses
+        assert_eq!(vars.show(&outer), "bool");
+    }
+}